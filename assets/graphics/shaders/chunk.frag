@@ -0,0 +1,27 @@
+#version 330 core
+
+in vec3 vertexcolor;
+in vec2 UV;
+#ifdef SHADOWS
+in vec3 shadow_position;
+in float view_depth;
+#endif
+
+out vec4 color;
+
+#include "include/block_atlas.glsl"
+
+#ifdef SHADOWS
+#include "include/shadows.glsl"
+#endif
+
+void main() {
+    vec4 texel = block_texel(UV);
+    if (texel.a < alpha_cutoff) {
+        discard;
+    }
+    color = vec4(vertexcolor * texel.rgb, texel.a);
+#ifdef SHADOWS
+    color.rgb *= shade(shadow_position, view_depth);
+#endif
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+#version 330 core
+
+layout(location=0) in vec3 vertexPosition_modelspace;
+layout(location=1) in vec3 incolor;
+layout(location=2) in vec2 inUV;
+out vec3 vertexcolor;
+out vec2 UV;
+#ifdef SHADOWS
+// The position relative to the chunk the camera is in, where the shadow maps are rendered from.
+out vec3 shadow_position;
+out float view_depth;
+#endif
+
+
+uniform mat4 MVP;
+#ifdef SHADOWS
+// The position of the chunk relative to the chunk the camera is in.
+uniform vec3 chunk_offset;
+#endif
+
+#include "include/fog.glsl"
+
+void main() {
+	gl_Position = MVP * vec4(vertexPosition_modelspace, 1);
+	vertexcolor = apply_fog(incolor, gl_Position.w);
+	UV = inUV;
+#ifdef SHADOWS
+	shadow_position = vertexPosition_modelspace + chunk_offset;
+	view_depth = gl_Position.w;
+#endif
+}
\ No newline at end of file
@@ -0,0 +1,18 @@
+// Texturing voxels from the block atlas, for the shaders that draw chunks.
+
+uniform sampler2D block_atlas;
+// Larger than the size of any face in voxels plus one.
+uniform float tile_stride;
+// The number of tiles across and down the atlas.
+uniform vec2 atlas_tiles;
+// Fragments less opaque than this are discarded, so cutout voxels like leaves can be drawn without blending.
+uniform float alpha_cutoff;
+
+// The texel of the atlas at the UV of a chunk vertex.
+// The UV is the atlas tile times tile_stride, plus one, plus the position on the face in voxels.
+// Keeping the tile and the position in one value lets faces spanning several voxels repeat their tile.
+vec4 block_texel(vec2 uv) {
+    vec2 tile = floor(uv / tile_stride);
+    vec2 position = fract(uv - tile * tile_stride);
+    return texture(block_atlas, (tile + position) / atlas_tiles);
+}
@@ -0,0 +1,9 @@
+// The output image and work group layout of compute shaders that run once for every texel of their output.
+layout(local_size_x=1, local_size_y=1) in;
+layout(rgba8, binding=0) uniform image2D img_output;
+
+// Turns texel coordinates of the output into texture coordinates, since there is one work group per texel.
+vec2 texel_scale() {
+    uvec2 nwg = gl_NumWorkGroups.xy;
+    return vec2(1./int(nwg.x), 1./int(nwg.y));
+}
\ No newline at end of file
@@ -0,0 +1,13 @@
+// Fades colors into the fog by their distance from the camera.
+// Shaders including this define FOG_DISTANCE, the distance at which everything is fog.
+const vec3 FOG_COLOR = vec3(0.6, 0.6, 0.6);
+
+// How much of the color at the distance from the camera is fog, from 0 to 1.
+float fog_amount(float camera_distance) {
+	return clamp(abs(camera_distance / FOG_DISTANCE), 0, 1);
+}
+
+vec3 apply_fog(vec3 color, float camera_distance) {
+	float fog = fog_amount(camera_distance);
+	return color * (1 - fog) + FOG_COLOR * fog;
+}
\ No newline at end of file
@@ -0,0 +1,50 @@
+// Shadows of the sun, from the cascaded shadow maps rendered with the shadow shader.
+// The first cascade covers the view up to cascade_ends.x, the second up to cascade_ends.y, and the last up to cascade_ends.z.
+// Fragments further away than that are in the sun.
+#include "include/fog.glsl"
+
+uniform sampler2D shadow_map0;
+uniform sampler2D shadow_map1;
+uniform sampler2D shadow_map2;
+// The view projection matrices of the light for each cascade, relative to the chunk the camera is in.
+uniform mat4 shadow_VP0;
+uniform mat4 shadow_VP1;
+uniform mat4 shadow_VP2;
+// The view depth at which each cascade ends.
+uniform vec3 cascade_ends;
+// How much closer to the light than the shadow map depth a fragment has to be to be lit, for each cascade.
+uniform vec3 shadow_bias;
+// How dark shadows are. Without shadow uniforms it is zero, and nothing is shadowed.
+uniform float shadow_strength;
+
+// The fraction of the 3x3 shadow map texels around the position that the position is lit in.
+float filtered_sunlight(sampler2D shadow_map, mat4 shadow_VP, float bias, vec3 position) {
+	vec4 light_position = shadow_VP * vec4(position, 1);
+	vec3 coordinates = light_position.xyz / light_position.w * 0.5 + 0.5;
+	vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+	float lit = 0.0;
+	for (int x = -1; x <= 1; x++) {
+		for (int y = -1; y <= 1; y++) {
+			float depth = texture(shadow_map, coordinates.xy + vec2(x, y) * texel).r;
+			lit += coordinates.z - bias <= depth ? 1.0 : 0.0;
+		}
+	}
+	return lit / 9.0;
+}
+
+// The fraction of the sunlight that reaches the position, from the cascade the view depth is in.
+float sunlight(vec3 position, float view_depth) {
+	if (view_depth < cascade_ends.x) {
+		return filtered_sunlight(shadow_map0, shadow_VP0, shadow_bias.x, position);
+	} else if (view_depth < cascade_ends.y) {
+		return filtered_sunlight(shadow_map1, shadow_VP1, shadow_bias.y, position);
+	} else if (view_depth < cascade_ends.z) {
+		return filtered_sunlight(shadow_map2, shadow_VP2, shadow_bias.z, position);
+	}
+	return 1.0;
+}
+
+// How much of the color is left in the shade. Shadows fade into the fog like the colors they darken.
+float shade(vec3 position, float view_depth) {
+	return 1 - shadow_strength * (1 - sunlight(position, view_depth)) * (1 - fog_amount(view_depth));
+}
\ No newline at end of file
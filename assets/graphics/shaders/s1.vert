@@ -9,12 +9,10 @@ out vec2 UV;
 
 uniform mat4 MVP;
 
+#include "include/fog.glsl"
+
 void main() {
 	gl_Position = MVP * vec4(vertexPosition_modelspace, 1);
-	float dist = clamp(abs(gl_Position.w/50),0,1);
-
-	vec3 fogcolor = vec3(0.6, 0.6, 0.6);
-
-	vertexcolor = incolor * (1-dist) + fogcolor*dist;
+	vertexcolor = apply_fog(incolor, gl_Position.w);
 	UV = inUV;
 }
\ No newline at end of file
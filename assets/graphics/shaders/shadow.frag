@@ -0,0 +1,13 @@
+#version 330 core
+
+in vec2 UV;
+
+#include "include/block_atlas.glsl"
+
+// Only depth is written to a shadow map, so only whether the fragment is discarded matters here.
+// Discarding like the chunk shader lets light through the gaps in cutout voxels.
+void main() {
+    if (block_texel(UV).a < alpha_cutoff) {
+        discard;
+    }
+}
\ No newline at end of file
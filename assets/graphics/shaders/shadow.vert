@@ -0,0 +1,13 @@
+#version 330 core
+
+layout(location=0) in vec3 vertexPosition_modelspace;
+layout(location=2) in vec2 inUV;
+out vec2 UV;
+
+// The view projection matrix of the light times the model matrix.
+uniform mat4 MVP;
+
+void main() {
+	gl_Position = MVP * vec4(vertexPosition_modelspace, 1);
+	UV = inUV;
+}
\ No newline at end of file
@@ -0,0 +1,473 @@
+use crate::{Sound, SoundTemplate};
+
+/// Describes the surroundings of the listener as seen by the logic thread.
+/// The ambience director uses this to decide which bed should be playing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AmbienceContext {
+    /// The absolute height of the listener.
+    pub altitude: f32,
+    /// The number of solid voxels between the listener and open sky.
+    pub depth: f32,
+    /// How enclosed the listener is, from 0 (open air) to 1 (surrounded on all sides).
+    pub enclosure: f32,
+    /// The time of day, where 0 and 1 are midnight and 0.5 is noon.
+    pub time_of_day: f32,
+}
+
+impl Default for AmbienceContext {
+    fn default() -> Self {
+        AmbienceContext {
+            altitude: 0.,
+            depth: 0.,
+            enclosure: 0.,
+            time_of_day: 0.5,
+        }
+    }
+}
+
+/// A condition on the ambience context that decides whether a bed may play.
+#[derive(Clone, Debug)]
+pub enum AmbienceCondition {
+    Always,
+    AltitudeAbove(f32),
+    AltitudeBelow(f32),
+    DepthAtLeast(f32),
+    EnclosureAtLeast(f32),
+    EnclosureBelow(f32),
+    /// Holds if the time of day is between the two values.
+    /// If the first value is greater than the second, the interval wraps around midnight.
+    TimeOfDayBetween(f32, f32),
+    All(Vec<AmbienceCondition>),
+    Any(Vec<AmbienceCondition>),
+}
+
+impl AmbienceCondition {
+    /// Returns true if the condition holds in the given context.
+    pub fn holds(&self, context: &AmbienceContext) -> bool {
+        match self {
+            AmbienceCondition::Always => true,
+            AmbienceCondition::AltitudeAbove(altitude) => context.altitude > *altitude,
+            AmbienceCondition::AltitudeBelow(altitude) => context.altitude < *altitude,
+            AmbienceCondition::DepthAtLeast(depth) => context.depth >= *depth,
+            AmbienceCondition::EnclosureAtLeast(enclosure) => context.enclosure >= *enclosure,
+            AmbienceCondition::EnclosureBelow(enclosure) => context.enclosure < *enclosure,
+            AmbienceCondition::TimeOfDayBetween(start, end) => {
+                if start <= end {
+                    context.time_of_day >= *start && context.time_of_day < *end
+                } else {
+                    context.time_of_day >= *start || context.time_of_day < *end
+                }
+            }
+            AmbienceCondition::All(conditions) => {
+                conditions.iter().all(|condition| condition.holds(context))
+            }
+            AmbienceCondition::Any(conditions) => {
+                conditions.iter().any(|condition| condition.holds(context))
+            }
+        }
+    }
+}
+
+/// Specifies how a bed is brought in and taken out of the mix.
+#[derive(Clone, Copy, Debug)]
+pub struct AmbienceTransition {
+    /// Seconds to fade the bed in from silence.
+    pub fade_in: f32,
+    /// Seconds to fade the bed out when it is replaced.
+    pub fade_out: f32,
+    /// Seconds of silence between the previous bed starting to fade out and this bed starting.
+    pub silence_gap: f32,
+    /// Minimum number of seconds the bed plays before it can be replaced.
+    pub hold: f32,
+}
+
+impl Default for AmbienceTransition {
+    fn default() -> Self {
+        AmbienceTransition {
+            fade_in: 4.,
+            fade_out: 4.,
+            silence_gap: 0.,
+            hold: 8.,
+        }
+    }
+}
+
+/// A looping sound that the director can play as ambience.
+pub struct AmbienceBed {
+    sound: Box<dyn SoundTemplate>,
+    condition: AmbienceCondition,
+    priority: i32,
+    volume: f32,
+    transition: AmbienceTransition,
+}
+
+impl AmbienceBed {
+    /// Creates a new bed.
+    ///
+    /// # Arguments
+    ///
+    /// * `sound` - The sound to play. Should be looping or at least very long.
+    /// * `condition` - The bed is only played while this holds.
+    /// * `priority` - Of all beds whose conditions hold, the one with the highest priority is played.
+    /// * `volume` - The gain applied to the sound.
+    /// * `transition` - How to fade the bed in and out.
+    pub fn new(
+        sound: Box<dyn SoundTemplate>,
+        condition: AmbienceCondition,
+        priority: i32,
+        volume: f32,
+        transition: AmbienceTransition,
+    ) -> AmbienceBed {
+        AmbienceBed {
+            sound,
+            condition,
+            priority,
+            volume,
+            transition,
+        }
+    }
+}
+
+/// A bed that is currently audible.
+struct ActiveBed {
+    bed_index: usize,
+    sound: Box<dyn Sound>,
+    gain: f32,
+    /// Change in gain per sample.
+    gain_step: f32,
+    /// Samples played since the bed started.
+    age: u64,
+}
+
+impl ActiveBed {
+    fn is_silent(&self) -> bool {
+        self.gain <= 0. && self.gain_step <= 0.
+    }
+}
+
+/// Crossfades between ambience beds depending on the context sent from the logic thread.
+pub struct AmbienceDirector {
+    beds: Vec<AmbienceBed>,
+    context: AmbienceContext,
+    current: Option<ActiveBed>,
+    fading_out: Vec<ActiveBed>,
+    /// A bed waiting for a silence gap to pass before starting, and the number of samples left to wait.
+    pending: Option<(usize, u64)>,
+    buffer: Vec<f32>,
+    sample_rate: u32,
+}
+
+impl AmbienceDirector {
+    pub fn new(sample_rate: u32) -> AmbienceDirector {
+        AmbienceDirector {
+            beds: Vec::new(),
+            context: AmbienceContext::default(),
+            current: None,
+            fading_out: Vec::new(),
+            pending: None,
+            buffer: Vec::new(),
+            sample_rate,
+        }
+    }
+
+    pub fn add_bed(&mut self, bed: AmbienceBed) {
+        self.beds.push(bed);
+    }
+
+    pub fn set_context(&mut self, context: AmbienceContext) {
+        self.context = context;
+    }
+
+    /// Returns the index of the bed that should be playing in the current context.
+    fn target_bed(&self) -> Option<usize> {
+        self.beds
+            .iter()
+            .enumerate()
+            .filter(|(_, bed)| bed.condition.holds(&self.context))
+            .max_by_key(|(index, bed)| (bed.priority, std::cmp::Reverse(*index)))
+            .map(|(index, _)| index)
+    }
+
+    fn seconds_to_samples(&self, seconds: f32) -> u64 {
+        (seconds * self.sample_rate as f32) as u64
+    }
+
+    fn gain_step(&self, seconds: f32, volume: f32) -> f32 {
+        let samples = self.seconds_to_samples(seconds);
+        if samples == 0 {
+            volume
+        } else {
+            volume / samples as f32
+        }
+    }
+
+    fn start_bed(&mut self, bed_index: usize) {
+        let bed = &self.beds[bed_index];
+        self.current = Some(ActiveBed {
+            bed_index,
+            sound: bed.sound.create_instance(None),
+            gain: 0.,
+            gain_step: self.gain_step(bed.transition.fade_in, bed.volume),
+            age: 0,
+        });
+    }
+
+    /// Decides whether to switch beds.
+    fn update_target(&mut self) {
+        let target = self.target_bed();
+        let current_index = self.current.as_ref().map(|active| active.bed_index);
+        let pending_index = self.pending.map(|(bed_index, _)| bed_index);
+        if pending_index.is_some() && target != pending_index {
+            // The bed waiting behind the silence gap is no longer wanted.
+            self.pending = None;
+        }
+        if target == current_index || (target.is_some() && target == pending_index) {
+            return;
+        }
+        if let Some(active) = &self.current {
+            let hold = self.seconds_to_samples(self.beds[active.bed_index].transition.hold);
+            if active.age < hold {
+                return;
+            }
+        }
+        if let Some(mut active) = self.current.take() {
+            let bed = &self.beds[active.bed_index];
+            active.gain_step = -self.gain_step(bed.transition.fade_out, bed.volume);
+            self.fading_out.push(active);
+        }
+        self.pending = target.map(|bed_index| {
+            (
+                bed_index,
+                self.seconds_to_samples(self.beds[bed_index].transition.silence_gap),
+            )
+        });
+    }
+
+    /// Fills `samples` with the mono ambience mix.
+    pub fn next(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = 0.;
+        }
+        self.update_target();
+
+        if let Some((bed_index, remaining)) = self.pending {
+            let num_samples = samples.len() as u64;
+            if remaining <= num_samples {
+                self.pending = None;
+                self.start_bed(bed_index);
+            } else {
+                self.pending = Some((bed_index, remaining - num_samples));
+            }
+        }
+
+        if self.buffer.len() < samples.len() {
+            self.buffer.resize(samples.len(), 0.);
+        }
+        let buffer = &mut self.buffer[0..samples.len()];
+        for active in self.current.iter_mut().chain(self.fading_out.iter_mut()) {
+            let volume = self.beds[active.bed_index].volume;
+            active.sound.next(buffer);
+            for (sample, bed_sample) in samples.iter_mut().zip(buffer.iter()) {
+                active.gain = (active.gain + active.gain_step).clamp(0., volume);
+                *sample += bed_sample * active.gain;
+            }
+            active.age += samples.len() as u64;
+        }
+        self.fading_out
+            .retain(|active| !active.is_silent() && !active.sound.is_finished());
+        if let Some(active) = &self.current {
+            if active.sound.is_finished() {
+                self.current = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sound that plays a constant value forever.
+    struct ConstantSound(f32);
+
+    impl Sound for ConstantSound {
+        fn next(&mut self, samples: &mut [f32]) {
+            samples.fill(self.0);
+        }
+
+        fn is_finished(&self) -> bool {
+            false
+        }
+
+        fn location(&self) -> Option<world::Location> {
+            None
+        }
+    }
+
+    impl SoundTemplate for ConstantSound {
+        fn create_instance(&self, _location: Option<world::Location>) -> Box<dyn Sound> {
+            Box::new(ConstantSound(self.0))
+        }
+    }
+
+    const SAMPLE_RATE: u32 = 100;
+
+    /// A director with a surface bed that plays below an altitude of 100,
+    /// and a cave bed with a higher priority that plays at a depth of at least 1.
+    fn director(surface: AmbienceTransition, cave: AmbienceTransition) -> AmbienceDirector {
+        let mut director = AmbienceDirector::new(SAMPLE_RATE);
+        director.add_bed(AmbienceBed::new(
+            Box::new(ConstantSound(1.)),
+            AmbienceCondition::AltitudeBelow(100.),
+            0,
+            1.,
+            surface,
+        ));
+        director.add_bed(AmbienceBed::new(
+            Box::new(ConstantSound(1.)),
+            AmbienceCondition::DepthAtLeast(1.),
+            1,
+            1.,
+            cave,
+        ));
+        director
+    }
+
+    fn cave_context() -> AmbienceContext {
+        AmbienceContext {
+            depth: 2.,
+            ..Default::default()
+        }
+    }
+
+    /// Plays the given number of seconds in blocks of a tenth of a second, and returns the last sample.
+    fn play(director: &mut AmbienceDirector, seconds: f32) -> f32 {
+        let mut samples = [0.; (SAMPLE_RATE / 10) as usize];
+        for _ in 0..(seconds * 10.).round() as usize {
+            director.next(&mut samples);
+        }
+        samples[samples.len() - 1]
+    }
+
+    fn current_bed(director: &AmbienceDirector) -> Option<usize> {
+        director.current.as_ref().map(|active| active.bed_index)
+    }
+
+    #[test]
+    fn beds_crossfade() {
+        let transition = AmbienceTransition {
+            fade_in: 1.,
+            fade_out: 1.,
+            silence_gap: 0.,
+            hold: 0.,
+        };
+        let mut director = director(transition, transition);
+        assert!((play(&mut director, 2.) - 1.).abs() < 1e-4);
+        assert_eq!(current_bed(&director), Some(0));
+
+        director.set_context(cave_context());
+        let halfway = play(&mut director, 0.5);
+        assert_eq!(current_bed(&director), Some(1));
+        assert_eq!(director.fading_out.len(), 1);
+        // One bed fades in as the other fades out, so the mix stays at the same level.
+        assert!((halfway - 1.).abs() < 0.05, "{}", halfway);
+
+        assert!((play(&mut director, 1.) - 1.).abs() < 1e-4);
+        assert!(director.fading_out.is_empty());
+    }
+
+    #[test]
+    fn beds_are_held() {
+        let transition = AmbienceTransition {
+            fade_in: 0.,
+            fade_out: 0.,
+            silence_gap: 0.,
+            hold: 1.,
+        };
+        let mut director = director(transition, transition);
+        play(&mut director, 0.5);
+        director.set_context(cave_context());
+        play(&mut director, 0.3);
+        assert_eq!(current_bed(&director), Some(0));
+        play(&mut director, 0.3);
+        assert_eq!(current_bed(&director), Some(1));
+    }
+
+    #[test]
+    fn silence_gap_before_bed() {
+        let surface = AmbienceTransition {
+            fade_in: 0.,
+            fade_out: 0.,
+            silence_gap: 0.,
+            hold: 0.,
+        };
+        let cave = AmbienceTransition {
+            silence_gap: 1.,
+            ..surface
+        };
+        let mut director = director(surface, cave);
+        play(&mut director, 0.5);
+        director.set_context(cave_context());
+        assert_eq!(play(&mut director, 0.5), 0.);
+        assert_eq!(current_bed(&director), None);
+        assert_eq!(play(&mut director, 0.6), 1.);
+        assert_eq!(current_bed(&director), Some(1));
+    }
+
+    #[test]
+    fn pending_bed_is_dropped_when_no_bed_is_wanted() {
+        let surface = AmbienceTransition {
+            fade_in: 0.,
+            fade_out: 0.,
+            silence_gap: 0.,
+            hold: 0.,
+        };
+        let cave = AmbienceTransition {
+            silence_gap: 1.,
+            ..surface
+        };
+        let mut director = director(surface, cave);
+        play(&mut director, 0.5);
+        director.set_context(cave_context());
+        play(&mut director, 0.5);
+        director.set_context(AmbienceContext {
+            altitude: 200.,
+            ..Default::default()
+        });
+        // The cave bed must not start once the gap is over, not even for a moment.
+        for _ in 0..20 {
+            assert_eq!(play(&mut director, 0.1), 0.);
+            assert_eq!(current_bed(&director), None);
+        }
+    }
+
+    #[test]
+    fn time_of_day_wraps_around_midnight() {
+        let night = AmbienceCondition::TimeOfDayBetween(0.8, 0.2);
+        let mut context = AmbienceContext {
+            time_of_day: 0.9,
+            ..Default::default()
+        };
+        assert!(night.holds(&context));
+        context.time_of_day = 0.1;
+        assert!(night.holds(&context));
+        context.time_of_day = 0.5;
+        assert!(!night.holds(&context));
+    }
+
+    #[test]
+    fn combined_conditions() {
+        let cave = AmbienceCondition::All(vec![
+            AmbienceCondition::DepthAtLeast(4.),
+            AmbienceCondition::EnclosureAtLeast(0.5),
+        ]);
+        let mut context = AmbienceContext {
+            depth: 6.,
+            enclosure: 0.8,
+            ..Default::default()
+        };
+        assert!(cave.holds(&context));
+        context.enclosure = 0.2;
+        assert!(!cave.holds(&context));
+    }
+}
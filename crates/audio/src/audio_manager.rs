@@ -1,6 +1,9 @@
-use crate::{AudioHandle, Listener, Sound, SoundTemplate};
+use crate::{AmbienceContext, AmbienceDirector, AudioHandle, Listener, Sound, SoundTemplate};
 use cpal::traits::{DeviceTrait, HostTrait};
-use synth::{start_stream, SampleProvider};
+use synth::{
+    dynamics::{LimiterProcessor, LimiterSettings},
+    start_stream, SampleProvider,
+};
 use log::debug;
 use std::sync::mpsc;
 use world::Location;
@@ -9,14 +12,20 @@ use super::listener::ListenerInterpolation;
 
 const MONO_SAMPLES_SIZE: usize = 8192;
 
+pub const SAMPLE_RATE: u32 = 48000;
+
 pub enum AudioMessage {
     StartSound(usize, Option<Location>),
     Listener(Listener),
+    Ambience(AmbienceContext),
 }
 
 pub struct AudioManager {
     sound_templates: Vec<Box<dyn SoundTemplate>>,
     current_audio: Vec<Box<dyn Sound>>,
+    ambience_director: AmbienceDirector,
+    /// Keeps the sum of all sounds from clipping.
+    master_limiter: LimiterProcessor,
     mono_samples: [f32; MONO_SAMPLES_SIZE],
     audio_message_receiver: mpsc::Receiver<AudioMessage>,
     audio_message_sender: mpsc::Sender<AudioMessage>,
@@ -33,13 +42,15 @@ impl AudioManager {
         AudioManager {
             sound_templates: Vec::new(),
             current_audio: Vec::new(),
+            ambience_director: AmbienceDirector::new(SAMPLE_RATE),
+            master_limiter: LimiterProcessor::new(LimiterSettings::default(), 2, SAMPLE_RATE),
             mono_samples: [0.; MONO_SAMPLES_SIZE],
             audio_message_receiver: receiver,
             audio_message_sender: sender,
             next_listener: Listener::default(),
             listener_interpolation: ListenerInterpolation::default(),
             tick_sample: 0,
-            ticks_per_sample: tps as f32 / 48000.,
+            ticks_per_sample: tps as f32 / SAMPLE_RATE as f32,
         }
     }
 
@@ -58,6 +69,7 @@ impl AudioManager {
                 self.listener_interpolation = old_listener.interpolate_to(&self.next_listener);
                 self.tick_sample = 0;
             }
+            AudioMessage::Ambience(context) => self.ambience_director.set_context(context),
         };
     }
 
@@ -65,6 +77,10 @@ impl AudioManager {
         self.sound_templates.push(sound);
     }
 
+    pub fn ambience_director(&mut self) -> &mut AmbienceDirector {
+        &mut self.ambience_director
+    }
+
     pub fn start(self) -> AudioHandle {
         let (sender, receiver) = mpsc::channel();
         let audio_message_sender = self.audio_message_sender.clone();
@@ -140,6 +156,18 @@ impl SampleProvider for AudioManager {
                 tick_sample += 1;
             }
         }
+
+        // Ambience is not placed in the world, so it is split evenly between the ears.
+        self.ambience_director.next(mono_samples);
+        for (i, mono_sample) in mono_samples.iter().enumerate() {
+            samples[2 * i] += *mono_sample * 0.5;
+            samples[2 * i + 1] += *mono_sample * 0.5;
+        }
+
+        for frame in samples.chunks_exact_mut(2) {
+            self.master_limiter.process(frame);
+        }
+
         self.tick_sample += (samples.len() / 2) as u32;
         self.current_audio.retain(|sound| !sound.is_finished());
         loop {
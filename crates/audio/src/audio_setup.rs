@@ -1,4 +1,7 @@
-use crate::{synth_sound::SynthTemplate, AudioHandle, AudioManager};
+use crate::{
+    synth_sound::SynthTemplate, AmbienceBed, AmbienceCondition, AmbienceTransition, AudioHandle,
+    AudioManager, SAMPLE_RATE,
+};
 use synth::modules;
 use rand::{thread_rng, Rng, SeedableRng};
 use rand_chacha::{self, ChaCha20Rng};
@@ -7,10 +10,71 @@ pub fn setup_audio(tps: u32) -> AudioHandle {
     let rng: ChaCha20Rng = rand_chacha::ChaCha20Rng::seed_from_u64(thread_rng().gen());
 
     let mut audio_manager = AudioManager::new(tps);
-    let module = modules::SineOscillator::new((130.).into(), 48000);
-    let module = module + modules::NoiseOscillator::new(rng) * 0.2;
-    let sound = Box::new(SynthTemplate::new(module * 0.6, (48000. * 0.15) as u64));
+    let module = modules::SineOscillator::new((130.).into(), SAMPLE_RATE);
+    let module = module + modules::NoiseOscillator::new(rng.clone()) * 0.2;
+    let sound = Box::new(SynthTemplate::new(
+        module * 0.6,
+        (SAMPLE_RATE as f32 * 0.15) as u64,
+    ));
     audio_manager.add_sound(sound);
 
+    setup_ambience(&mut audio_manager, rng);
+
     audio_manager.start()
 }
+
+fn setup_ambience(audio_manager: &mut AudioManager, rng: ChaCha20Rng) {
+    let director = audio_manager.ambience_director();
+
+    // Wind: low-passed noise swelling slowly.
+    let gust = modules::SineOscillator::new((0.07).into(), SAMPLE_RATE) * 0.4 + 0.6;
+    let wind = modules::OnePoleFilter::new(
+        modules::NoiseOscillator::new(rng.clone()) * 0.02,
+        (0.98).into(),
+    ) * gust;
+    director.add_bed(AmbienceBed::new(
+        Box::new(SynthTemplate::new(wind, u64::MAX)),
+        AmbienceCondition::EnclosureBelow(0.5),
+        0,
+        0.3,
+        AmbienceTransition::default(),
+    ));
+
+    // High altitude: the same wind but harsher and louder.
+    let gust = modules::SineOscillator::new((0.13).into(), SAMPLE_RATE) * 0.5 + 0.5;
+    let high_wind = modules::OnePoleFilter::new(
+        modules::NoiseOscillator::new(rng.clone()) * 0.05,
+        (0.95).into(),
+    ) * gust;
+    director.add_bed(AmbienceBed::new(
+        Box::new(SynthTemplate::new(high_wind, u64::MAX)),
+        AmbienceCondition::All(vec![
+            AmbienceCondition::AltitudeAbove(64.),
+            AmbienceCondition::EnclosureBelow(0.5),
+        ]),
+        1,
+        0.4,
+        AmbienceTransition::default(),
+    ));
+
+    // Caves: a low drone with a little rumble, separated from the surface beds by a short silence.
+    let drone = modules::SineOscillator::new((55.).into(), SAMPLE_RATE)
+        + modules::SineOscillator::new((82.5).into(), SAMPLE_RATE) * 0.5;
+    let rumble =
+        modules::OnePoleFilter::new(modules::NoiseOscillator::new(rng) * 0.01, (0.99).into());
+    director.add_bed(AmbienceBed::new(
+        Box::new(SynthTemplate::new(drone * 0.3 + rumble, u64::MAX)),
+        AmbienceCondition::All(vec![
+            AmbienceCondition::DepthAtLeast(4.),
+            AmbienceCondition::EnclosureAtLeast(0.5),
+        ]),
+        2,
+        0.25,
+        AmbienceTransition {
+            fade_in: 6.,
+            fade_out: 3.,
+            silence_gap: 2.,
+            hold: 8.,
+        },
+    ));
+}
@@ -1,7 +1,11 @@
+mod ambience;
+pub use ambience::{
+    AmbienceBed, AmbienceCondition, AmbienceContext, AmbienceDirector, AmbienceTransition,
+};
 mod audio_handle;
 pub use audio_handle::{AudioHandle, AudioMessageHandle};
 mod audio_manager;
-pub use audio_manager::{AudioManager, AudioMessage};
+pub use audio_manager::{AudioManager, AudioMessage, SAMPLE_RATE};
 mod audio_setup;
 pub use audio_setup::setup_audio;
 mod sound;
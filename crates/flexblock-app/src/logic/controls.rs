@@ -31,6 +31,10 @@ pub struct ControlConfig {
     pub player_interact_1: Control,
     #[serde(default = "player_interact_2_default")]
     pub player_interact_2: Control,
+    #[serde(default = "increase_render_radius_default")]
+    pub increase_render_radius: Control,
+    #[serde(default = "decrease_render_radius_default")]
+    pub decrease_render_radius: Control,
 }
 
 impl Default for ControlConfig {
@@ -45,6 +49,8 @@ impl Default for ControlConfig {
             load: load_default(),
             player_interact_1: player_interact_1_default(),
             player_interact_2: player_interact_2_default(),
+            increase_render_radius: increase_render_radius_default(),
+            decrease_render_radius: decrease_render_radius_default(),
         }
     }
 }
@@ -99,6 +105,16 @@ fn player_interact_2_default() -> Control {
         mouse_button: MouseButton::Right,
     }
 }
+fn increase_render_radius_default() -> Control {
+    Control::Keyboard {
+        key_code: VirtualKeyCode::Equals,
+    }
+}
+fn decrease_render_radius_default() -> Control {
+    Control::Keyboard {
+        key_code: VirtualKeyCode::Minus,
+    }
+}
 
 pub fn save_control_config<P>(path: P, control_config: &ControlConfig)
 where
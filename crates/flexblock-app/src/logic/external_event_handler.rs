@@ -120,6 +120,14 @@ impl ExternalEventHandler {
             self.tick_state_events
                 .push(StateInputEvent::PlayerInteract2);
         }
+        if control == self.control_config.increase_render_radius {
+            self.tick_logic_events
+                .push(LogicEvent::IncreaseRenderRadius)
+        }
+        if control == self.control_config.decrease_render_radius {
+            self.tick_logic_events
+                .push(LogicEvent::DecreaseRenderRadius)
+        }
     }
 
     /// Returns and clears the current event buffer.
@@ -3,4 +3,6 @@
 pub enum LogicEvent {
     Save,
     LoadLatest,
+    IncreaseRenderRadius,
+    DecreaseRenderRadius,
 }
@@ -15,6 +15,25 @@ use std::{
     time::{Duration, Instant},
 };
 
+/// How many chunks the render radius changes by per key press.
+const RENDER_RADIUS_STEP: f32 = 1.;
+
+/// The largest render radius in chunks the player can choose.
+/// Chunks further away than the world buffers have room for aren't drawn anyway.
+const MAX_RENDER_RADIUS: f32 = 8.1;
+
+/// Reads the starting render radius from the `FLEXBLOCK_RENDER_RADIUS` environment variable.
+/// Like every other render radius, the value is given in chunks, e.g. `4.5`.
+fn render_radius_from_env() -> f32 {
+    std::env::var("FLEXBLOCK_RENDER_RADIUS")
+        .ok()
+        .and_then(|radius| radius.parse::<f32>().ok())
+        .filter(|radius| radius.is_finite())
+        .map_or(game::DEFAULT_RENDER_RADIUS, |radius| {
+            radius.clamp(0., MAX_RENDER_RADIUS)
+        })
+}
+
 #[derive(Serialize, Deserialize)]
 struct SaveData {
     pub state: State,
@@ -39,6 +58,7 @@ pub fn start_logic_thread(
             state: State::new(),
             event_history: InputEventHistory::new(),
         };
+        let mut render_radius = render_radius_from_env();
 
         let mut last_tick = Instant::now();
         loop {
@@ -46,7 +66,7 @@ pub fn start_logic_thread(
             external_event_handler.handle_inputs(&window_to_logic_receiver.channel_receiver);
             // Get tick events.
             let (state_events, logic_events) = external_event_handler.tick_events();
-            handle_logic_events(&logic_events, &mut save_data);
+            handle_logic_events(&logic_events, &mut save_data, &mut render_radius);
 
             let event_history = &mut save_data.event_history;
             let state = &mut save_data.state;
@@ -66,6 +86,7 @@ pub fn start_logic_thread(
             match gsm_mutex.try_lock() {
                 Ok(mut gsm) => {
                     state.update_graphics_state_model(&mut gsm);
+                    gsm.render_radius = render_radius;
                     if let Err(error) = gsm_channel.send(Update) {
                         panic!("Packing thread has deallocated the channel. {}", error);
                     }
@@ -87,15 +108,26 @@ pub fn start_logic_thread(
     })
 }
 
-fn handle_logic_events(events: &[LogicEvent], save_data: &mut SaveData) {
+fn handle_logic_events(events: &[LogicEvent], save_data: &mut SaveData, render_radius: &mut f32) {
     for event in events.iter() {
         match event {
             LogicEvent::Save => save(save_data),
             LogicEvent::LoadLatest => load(save_data),
+            LogicEvent::IncreaseRenderRadius => {
+                change_render_radius(render_radius, RENDER_RADIUS_STEP)
+            }
+            LogicEvent::DecreaseRenderRadius => {
+                change_render_radius(render_radius, -RENDER_RADIUS_STEP)
+            }
         }
     }
 }
 
+fn change_render_radius(render_radius: &mut f32, change: f32) {
+    *render_radius = (*render_radius + change).clamp(0., MAX_RENDER_RADIUS);
+    info!("Render radius set to {} chunks", render_radius);
+}
+
 fn save(save_data: &SaveData) {
     let save_path = Path::new("saves/save.flex");
     if let Err(error) = std::fs::create_dir_all(save_path.parent().unwrap()) {
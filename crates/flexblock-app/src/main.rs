@@ -6,6 +6,7 @@ mod logic;
 mod window;
 mod logging;
 mod pack;
+mod replay;
 
 extern crate nalgebra_glm as glm;
 
@@ -13,10 +14,28 @@ use std::sync::{mpsc, Arc, Mutex};
 
 use game::GraphicsStateModel;
 use graphics::RenderMessages;
+use log::error;
 
 fn main() {
     logging::log_init();
 
+    // `flexblock --replay <recording> [--window | --screenshots <directory>]` replays a render recording
+    // instead of running the game.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        let screenshots = args
+            .iter()
+            .position(|arg| arg == "--screenshots")
+            .map(|index| args.get(index + 1));
+        match (args.get(index + 1), screenshots) {
+            (Some(path), Some(Some(directory))) => replay::replay_to_screenshots(path, directory),
+            (Some(_), Some(None)) => error!("--screenshots needs the directory to save them in."),
+            (Some(path), None) => replay::replay(path, args.iter().any(|arg| arg == "--window")),
+            (None, _) => error!("--replay needs the path of a render recording."),
+        }
+        return;
+    }
+
     // Create game input event channel.
     let (game_event_sender, game_event_receiver) = mpsc::channel();
     let window_to_logic_sender = channels::WindowToLogicSender {
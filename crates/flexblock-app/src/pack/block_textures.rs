@@ -0,0 +1,173 @@
+use super::chunk_mesher::FaceDirection;
+use graphics::TextureAtlas;
+use log::error;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use world::chunk::CHUNK_SIZE;
+use world::VoxelType;
+
+/// The distance between atlas tiles in the texture coordinates of chunk meshes.
+/// Texture coordinates are the tile times this, plus one, plus the position on the face in voxels,
+/// so it has to be larger than the largest face plus one. The chunk shader splits them up again.
+pub const TILE_STRIDE: f32 = (CHUNK_SIZE + 2) as f32;
+
+/// The textures of one voxel type as written in the block texture config.
+/// A face uses the first texture given of its own (`top` or `bottom`), `sides` and `all`.
+#[derive(Deserialize, Debug)]
+struct BlockTextureEntry {
+    voxel_type: u16,
+    all: Option<String>,
+    sides: Option<String>,
+    top: Option<String>,
+    bottom: Option<String>,
+}
+
+impl BlockTextureEntry {
+    /// The name of the texture the face should have.
+    fn texture(&self, direction: FaceDirection) -> Option<&String> {
+        let own = match direction {
+            FaceDirection::Top => self.top.as_ref(),
+            FaceDirection::Bottom => self.bottom.as_ref(),
+            _ => None,
+        };
+        own.or(self.sides.as_ref().filter(|_| direction.axis() != 1))
+            .or(self.all.as_ref())
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct BlockTextureConfig {
+    #[serde(default)]
+    block: Vec<BlockTextureEntry>,
+}
+
+/// The atlas tile of every face of every voxel type.
+#[derive(Clone, Debug, Default)]
+pub struct BlockTextures {
+    tiles: HashMap<(u16, FaceDirection), [u32; 2]>,
+    /// The tile used for faces without a texture.
+    missing_tile: [u32; 2],
+    /// The number of tiles across and down the atlas.
+    grid_size: (u32, u32),
+}
+
+impl BlockTextures {
+    /// Finds the tile of each face in the block config from the atlas.
+    fn new(config: &BlockTextureConfig, atlas: &TextureAtlas) -> BlockTextures {
+        let missing_tile = atlas.tile(TextureAtlas::MISSING_TILE).unwrap_or([0, 0]);
+        let mut tiles = HashMap::new();
+        for entry in &config.block {
+            for direction in FaceDirection::ALL {
+                let tile = match entry.texture(direction) {
+                    Some(texture) => match atlas.tile(texture) {
+                        Some(tile) => tile,
+                        None => {
+                            error!(
+                                "Voxel type {} uses texture {}, which is not in the block atlas.",
+                                entry.voxel_type, texture
+                            );
+                            continue;
+                        }
+                    },
+                    None => continue,
+                };
+                tiles.insert((entry.voxel_type, direction), tile);
+            }
+        }
+        BlockTextures {
+            tiles,
+            missing_tile,
+            grid_size: atlas.grid_size(),
+        }
+    }
+
+    /// Loads the block texture config at the given path and finds the tile of each face from
+    /// the atlas. If the config can't be loaded, every face uses the missing tile.
+    pub fn load<P: AsRef<Path>>(path: P, atlas: &TextureAtlas) -> BlockTextures {
+        let config = match fs::read_to_string(path) {
+            Ok(config_string) => toml::from_str(&config_string).unwrap_or_else(|error| {
+                error!("Could not parse block texture config. Error: {:?}", error);
+                BlockTextureConfig::default()
+            }),
+            Err(error) => {
+                error!("Could not read block texture config. Error: {:?}", error);
+                BlockTextureConfig::default()
+            }
+        };
+        BlockTextures::new(&config, atlas)
+    }
+
+    /// The column and row in the atlas of the tile for the face.
+    pub fn tile(&self, voxel_type: VoxelType, direction: FaceDirection) -> [u32; 2] {
+        self.tiles
+            .get(&(voxel_type.0, direction))
+            .copied()
+            .unwrap_or(self.missing_tile)
+    }
+
+    /// The number of tiles across and down the atlas.
+    pub fn grid_size(&self) -> (u32, u32) {
+        self.grid_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::atlas::AtlasBuilder;
+    use utils::png_reader::PngData;
+    use utils::ColorFormat;
+
+    #[test]
+    fn faces_fall_back_to_sides_and_all() {
+        let mut builder = AtlasBuilder::new();
+        for name in ["dirt", "grass_side", "grass_top", "stone"] {
+            builder
+                .add(
+                    name,
+                    PngData {
+                        width: 1,
+                        height: 1,
+                        data: vec![0, 0, 0],
+                        format: ColorFormat::RGB,
+                    },
+                )
+                .unwrap();
+        }
+        let (_, atlas) = builder.build();
+        let config: BlockTextureConfig = toml::from_str(
+            r#"
+            [[block]]
+            voxel_type = 1
+            top = "grass_top"
+            sides = "grass_side"
+            all = "dirt"
+
+            [[block]]
+            voxel_type = 2
+            all = "water"
+            "#,
+        )
+        .unwrap();
+        let textures = BlockTextures::new(&config, &atlas);
+
+        let grass = VoxelType(1);
+        assert_eq!(
+            textures.tile(grass, FaceDirection::Top),
+            atlas.tile("grass_top").unwrap()
+        );
+        assert_eq!(
+            textures.tile(grass, FaceDirection::Left),
+            atlas.tile("grass_side").unwrap()
+        );
+        assert_eq!(
+            textures.tile(grass, FaceDirection::Bottom),
+            atlas.tile("dirt").unwrap()
+        );
+        let missing = atlas.tile(TextureAtlas::MISSING_TILE).unwrap();
+        assert_eq!(textures.tile(VoxelType(2), FaceDirection::Top), missing);
+        assert_eq!(textures.tile(VoxelType(3), FaceDirection::Top), missing);
+    }
+}
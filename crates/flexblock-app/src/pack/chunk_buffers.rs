@@ -0,0 +1,230 @@
+use world::Transparency;
+
+/// Keeps track of which chunk is packed into each world buffer.
+///
+/// Each chunk has a buffer for each transparency of voxel it contains, so they can be drawn in
+/// separate passes. The number of buffers follows the number of world buffers the window has
+/// available. When every buffer is taken, the chunk furthest from the view is evicted to make
+/// room for a closer one.
+pub struct ChunkBufferAllocator {
+    /// The chunk and transparency packed into each buffer, indexed by world buffer.
+    /// None means the buffer is free.
+    buffers: Vec<Option<(glm::IVec3, Transparency)>>,
+}
+
+/// Squared distance between two chunk locations.
+fn distance_squared(a: glm::IVec3, b: glm::IVec3) -> i32 {
+    let v = a - b;
+    v.x * v.x + v.y * v.y + v.z * v.z
+}
+
+impl ChunkBufferAllocator {
+    /// Creates an allocator with no buffers.
+    pub fn new() -> ChunkBufferAllocator {
+        ChunkBufferAllocator {
+            buffers: Vec::new(),
+        }
+    }
+
+    /// The number of buffers chunks can be packed into.
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// The number of buffers without a chunk in them.
+    pub fn free_count(&self) -> usize {
+        self.buffers.iter().filter(|chunk| chunk.is_none()).count()
+    }
+
+    /// Changes the number of buffers.
+    /// Returns the chunks that had a buffer removed. They have to be packed again.
+    pub fn resize(&mut self, buffer_count: usize) -> Vec<glm::IVec3> {
+        let mut removed = Vec::new();
+        if buffer_count < self.buffers.len() {
+            for (chunk, _) in self.buffers.drain(buffer_count..).flatten() {
+                if !removed.contains(&chunk) {
+                    removed.push(chunk);
+                }
+            }
+        }
+        self.buffers.resize(buffer_count, None);
+        removed
+    }
+
+    /// The buffer the chunk's voxels of the given transparency are packed into, if they are packed.
+    pub fn buffer_of(&self, location: glm::IVec3, transparency: Transparency) -> Option<usize> {
+        self.buffers
+            .iter()
+            .position(|chunk| *chunk == Some((location, transparency)))
+    }
+
+    /// Every buffer the chunk is packed into.
+    pub fn buffers_of(&self, location: glm::IVec3) -> Vec<usize> {
+        self.iter()
+            .filter(|(_, chunk, _)| *chunk == location)
+            .map(|(buffer, _, _)| buffer)
+            .collect()
+    }
+
+    /// Finds a buffer to pack the chunk's voxels of the given transparency into and registers
+    /// them as packed into it.
+    ///
+    /// If no buffer is free, a buffer of the chunk furthest from the center is taken, as long
+    /// as it is further away than the new chunk. Returns the buffer and the evicted chunk, if
+    /// any, or None if the new chunk is further away than every packed chunk.
+    /// The evicted chunk may still be packed into other buffers.
+    pub fn allocate(
+        &mut self,
+        location: glm::IVec3,
+        transparency: Transparency,
+        center: glm::IVec3,
+    ) -> Option<(usize, Option<glm::IVec3>)> {
+        if let Some(buffer) = self.buffers.iter().position(|chunk| chunk.is_none()) {
+            self.buffers[buffer] = Some((location, transparency));
+            return Some((buffer, None));
+        }
+        let (buffer, furthest, _) = self
+            .iter()
+            .max_by_key(|(_, chunk, _)| distance_squared(*chunk, center))?;
+        if distance_squared(furthest, center) <= distance_squared(location, center) {
+            return None;
+        }
+        self.buffers[buffer] = Some((location, transparency));
+        Some((buffer, Some(furthest)))
+    }
+
+    /// Frees the buffer. Returns the chunk that was packed into it.
+    pub fn free(&mut self, buffer: usize) -> Option<glm::IVec3> {
+        self.buffers
+            .get_mut(buffer)
+            .and_then(Option::take)
+            .map(|(chunk, _)| chunk)
+    }
+
+    /// Iterates over the buffers and the chunks and transparencies packed into them.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, glm::IVec3, Transparency)> + '_ {
+        self.buffers
+            .iter()
+            .enumerate()
+            .filter_map(|(buffer, chunk)| {
+                chunk.map(|(chunk, transparency)| (buffer, chunk, transparency))
+            })
+    }
+}
+
+impl Default for ChunkBufferAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_furthest_when_full() {
+        let center = glm::vec3(0, 0, 0);
+        let mut allocator = ChunkBufferAllocator::new();
+        allocator.resize(2);
+        assert_eq!(
+            allocator.allocate(glm::vec3(3, 0, 0), Transparency::Opaque, center),
+            Some((0, None))
+        );
+        assert_eq!(
+            allocator.allocate(glm::vec3(1, 0, 0), Transparency::Opaque, center),
+            Some((1, None))
+        );
+        assert_eq!(allocator.free_count(), 0);
+
+        // Further than everything packed, so there's no room.
+        assert_eq!(
+            allocator.allocate(glm::vec3(0, 5, 0), Transparency::Opaque, center),
+            None
+        );
+        assert_eq!(
+            allocator.allocate(glm::vec3(0, 0, 0), Transparency::Opaque, center),
+            Some((0, Some(glm::vec3(3, 0, 0))))
+        );
+        assert_eq!(
+            allocator.buffer_of(glm::vec3(0, 0, 0), Transparency::Opaque),
+            Some(0)
+        );
+        assert_eq!(
+            allocator.buffer_of(glm::vec3(3, 0, 0), Transparency::Opaque),
+            None
+        );
+    }
+
+    #[test]
+    fn resize_keeps_remaining_buffers() {
+        let center = glm::vec3(0, 0, 0);
+        let mut allocator = ChunkBufferAllocator::new();
+        allocator.resize(3);
+        for x in 0..3 {
+            allocator.allocate(glm::vec3(x, 0, 0), Transparency::Opaque, center);
+        }
+        assert_eq!(
+            allocator.resize(1),
+            vec![glm::vec3(1, 0, 0), glm::vec3(2, 0, 0)]
+        );
+        assert_eq!(
+            allocator.iter().collect::<Vec<_>>(),
+            vec![(0, glm::vec3(0, 0, 0), Transparency::Opaque)]
+        );
+
+        assert!(allocator.resize(4).is_empty());
+        assert_eq!(allocator.free_count(), 3);
+        assert_eq!(allocator.free(0), Some(glm::vec3(0, 0, 0)));
+        assert_eq!(allocator.free(0), None);
+    }
+
+    #[test]
+    fn chunks_take_a_buffer_per_transparency() {
+        let center = glm::vec3(0, 0, 0);
+        let mut allocator = ChunkBufferAllocator::new();
+        allocator.resize(3);
+        let far = glm::vec3(4, 0, 0);
+        allocator.allocate(far, Transparency::Opaque, center);
+        allocator.allocate(far, Transparency::Translucent, center);
+        allocator.allocate(glm::vec3(2, 0, 0), Transparency::Opaque, center);
+        assert_eq!(allocator.buffers_of(far), vec![0, 1]);
+        assert_eq!(allocator.buffer_of(far, Transparency::Translucent), Some(1));
+        assert_eq!(allocator.buffer_of(far, Transparency::Cutout), None);
+
+        // Only one buffer is taken from the evicted chunk. The caller frees the rest.
+        let (buffer, evicted) = allocator
+            .allocate(center, Transparency::Cutout, center)
+            .unwrap();
+        assert_eq!(evicted, Some(far));
+        assert_eq!(allocator.buffers_of(far).len(), 1);
+        assert_eq!(
+            allocator.buffer_of(center, Transparency::Cutout),
+            Some(buffer)
+        );
+
+        assert_eq!(buffer, 1);
+        assert_eq!(allocator.resize(0), vec![far, center, glm::vec3(2, 0, 0)]);
+    }
+
+    #[test]
+    fn never_hands_out_the_scratch_buffer() {
+        use graphics::{BufferTarget, VERTEX_BUFFER_METADATA};
+
+        let metadata = &VERTEX_BUFFER_METADATA;
+        let mut allocator = ChunkBufferAllocator::new();
+        allocator.resize(metadata.world_buffer_count(metadata.default_vbo_count()));
+        let center = glm::vec3(0, 0, 0);
+        let scratch = BufferTarget::ScratchBuffer.get_target_id(metadata);
+        for x in 0..allocator.buffer_count() as i32 {
+            let (buffer, _) = allocator
+                .allocate(glm::vec3(x, 0, 0), Transparency::Opaque, center)
+                .unwrap();
+            assert_ne!(
+                BufferTarget::WorldBuffer(buffer).get_target_id(metadata),
+                scratch
+            );
+        }
+        assert_eq!(allocator.free_count(), 0);
+    }
+}
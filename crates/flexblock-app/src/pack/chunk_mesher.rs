@@ -0,0 +1,812 @@
+use super::block_textures::{BlockTextures, TILE_STRIDE};
+use graphics::pack::cube_faces;
+use graphics::VertexPack;
+use world::chunk::{ChunkLocation, CHUNK_SIZE};
+use world::light::{self, MAX_LIGHT};
+use world::{raytrace, Chunk, Location, Terrain, Transparency, VoxelType};
+
+/// How much darker each level of light below the maximum makes a face.
+const LIGHT_FALLOFF: f32 = 0.85;
+
+/// How bright a corner of a face is when 0, 1, 2 or 3 of the voxels touching it in front of
+/// the face are opaque.
+const OCCLUSION_BRIGHTNESS: [f32; 4] = [1., 0.8, 0.65, 0.5];
+
+/// How bright a face is drawn at the given light level.
+fn brightness(light: f32) -> f32 {
+    LIGHT_FALLOFF.powf(MAX_LIGHT as f32 - light)
+}
+
+/// The six directions a voxel face can point in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaceDirection {
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Back,
+    Front,
+}
+
+impl FaceDirection {
+    pub const ALL: [FaceDirection; 6] = [
+        FaceDirection::Left,
+        FaceDirection::Right,
+        FaceDirection::Bottom,
+        FaceDirection::Top,
+        FaceDirection::Back,
+        FaceDirection::Front,
+    ];
+
+    /// The axis the face is perpendicular to. 0 is x, 1 is y and 2 is z.
+    pub fn axis(self) -> usize {
+        match self {
+            FaceDirection::Left | FaceDirection::Right => 0,
+            FaceDirection::Bottom | FaceDirection::Top => 1,
+            FaceDirection::Back | FaceDirection::Front => 2,
+        }
+    }
+
+    /// Whether the face points towards the positive end of its axis.
+    pub fn is_positive(self) -> bool {
+        matches!(
+            self,
+            FaceDirection::Right | FaceDirection::Top | FaceDirection::Front
+        )
+    }
+
+    /// The face pointing the other way along the same axis.
+    pub fn opposite(self) -> FaceDirection {
+        match self {
+            FaceDirection::Left => FaceDirection::Right,
+            FaceDirection::Right => FaceDirection::Left,
+            FaceDirection::Bottom => FaceDirection::Top,
+            FaceDirection::Top => FaceDirection::Bottom,
+            FaceDirection::Back => FaceDirection::Front,
+            FaceDirection::Front => FaceDirection::Back,
+        }
+    }
+
+    /// The unit vector the face points along.
+    pub fn normal(self) -> glm::IVec3 {
+        let mut normal = glm::vec3(0, 0, 0);
+        normal[self.axis()] = if self.is_positive() { 1 } else { -1 };
+        normal
+    }
+
+    /// How bright faces in this direction are drawn, so the sides of blocks can be told apart.
+    fn shade(self) -> f32 {
+        match self {
+            FaceDirection::Top => 1.,
+            FaceDirection::Left | FaceDirection::Right => 0.8,
+            FaceDirection::Back | FaceDirection::Front => 0.7,
+            FaceDirection::Bottom => 0.5,
+        }
+    }
+}
+
+/// Whether voxels of the given type are drawn at all.
+pub fn is_visible(voxel_type: VoxelType) -> bool {
+    !raytrace::ignore_voxel_type(voxel_type)
+}
+
+/// Whether voxels of the given type hide the faces of voxels next to them.
+pub fn is_opaque(voxel_type: VoxelType) -> bool {
+    light::is_opaque(voxel_type)
+}
+
+/// Whether the face of a voxel is hidden by the voxel in front of it.
+/// Besides opaque voxels, translucent voxels hide the faces between each other when they are of
+/// the same type, so a body of water is only drawn where it meets something else.
+fn hides_face(voxel_type: VoxelType, neighbour: VoxelType) -> bool {
+    is_opaque(neighbour)
+        || (neighbour == voxel_type && voxel_type.transparency() == Transparency::Translucent)
+}
+
+/// A rectangle made of coplanar voxel faces of the same type and direction.
+/// Coordinates are in voxels relative to the chunk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quad {
+    pub direction: FaceDirection,
+    pub voxel_type: VoxelType,
+    /// The corner of the quad with the lowest coordinates.
+    pub min: [u32; 3],
+    /// The corner of the quad with the highest coordinates.
+    /// Equal to `min` along the direction's axis.
+    pub max: [u32; 3],
+    /// The brightness of each corner of the quad, indexed by `u * 2 + v`, where `u` and `v` are
+    /// 1 at the high end of the first and second axis after the direction's axis and 0 at the low end.
+    pub light: [f32; 4],
+}
+
+impl Quad {
+    /// The number of voxel faces the quad covers.
+    pub fn area(&self) -> u32 {
+        (0..3)
+            .filter(|&axis| axis != self.direction.axis())
+            .map(|axis| self.max[axis] - self.min[axis])
+            .product()
+    }
+}
+
+/// A chunk together with the chunks bordering each of its faces, so voxels can be looked up
+/// one voxel past the chunk border.
+struct ChunkNeighbourhood<'a> {
+    terrain: &'a Terrain,
+    location: glm::IVec3,
+    chunk: &'a Chunk,
+    /// The neighbouring chunks in the same order as `FaceDirection::ALL`.
+    neighbours: [Option<&'a Chunk>; 6],
+}
+
+impl<'a> ChunkNeighbourhood<'a> {
+    fn new(terrain: &'a Terrain, chunk: &'a Chunk, location: glm::IVec3) -> Self {
+        let mut neighbours = [None; 6];
+        for (neighbour, direction) in neighbours.iter_mut().zip(FaceDirection::ALL) {
+            *neighbour = terrain.chunk(location + direction.normal());
+        }
+        ChunkNeighbourhood {
+            terrain,
+            location,
+            chunk,
+            neighbours,
+        }
+    }
+
+    /// The type of the voxel at the given position in the chunk.
+    fn voxel_type(&self, position: [u32; 3]) -> VoxelType {
+        self.chunk
+            .voxel_type_unchecked(ChunkLocation::new(position[0], position[1], position[2]))
+    }
+
+    /// Whether the face of the voxel at the given position in the given direction is hidden by
+    /// the voxel next to it. Faces against chunks that don't exist are not.
+    fn face_hidden(&self, mut position: [u32; 3], direction: FaceDirection) -> bool {
+        let voxel_type = self.voxel_type(position);
+        let axis = direction.axis();
+        let chunk = if direction.is_positive() {
+            if position[axis] + 1 < CHUNK_SIZE {
+                position[axis] += 1;
+                Some(self.chunk)
+            } else {
+                position[axis] = 0;
+                self.neighbours[direction as usize]
+            }
+        } else if position[axis] > 0 {
+            position[axis] -= 1;
+            Some(self.chunk)
+        } else {
+            position[axis] = CHUNK_SIZE - 1;
+            self.neighbours[direction as usize]
+        };
+        chunk.is_some_and(|chunk| {
+            hides_face(
+                voxel_type,
+                chunk.voxel_type_unchecked(ChunkLocation::new(
+                    position[0],
+                    position[1],
+                    position[2],
+                )),
+            )
+        })
+    }
+
+    /// Whether the voxel at the position relative to the chunk is opaque, and its light level.
+    /// The position may be in any chunk of the terrain.
+    fn opaque_and_light(&self, position: [i32; 3]) -> (bool, u8) {
+        let size = CHUNK_SIZE as i32;
+        let location = Location::new(
+            self.location
+                + glm::vec3(position[0], position[1], position[2]).map(|x| x.div_euclid(size)),
+            glm::vec3(position[0], position[1], position[2]).map(|x| x.rem_euclid(size) as f32),
+        );
+        (
+            is_opaque(self.terrain.voxel_type(location)),
+            self.terrain.light(location).level(),
+        )
+    }
+
+    /// The brightness of each corner of the face of the voxel at the given position, indexed
+    /// like `Quad::light`.
+    ///
+    /// Each corner gets the average light of the non-opaque voxels touching it in front of the
+    /// face, and is darkened by the opaque ones, so corners and crevices look shadowed.
+    fn face_light(&self, position: [u32; 3], direction: FaceDirection) -> [f32; 4] {
+        let axis = direction.axis();
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut front = position.map(|coord| coord as i32);
+        front[axis] += direction.normal()[axis];
+        // The voxel in front of the face and the 8 around it, indexed by offset + 1 along u and v.
+        let mut voxels = [[(false, 0); 3]; 3];
+        for (u, row) in voxels.iter_mut().enumerate() {
+            for (v, voxel) in row.iter_mut().enumerate() {
+                let mut position = front;
+                position[u_axis] += u as i32 - 1;
+                position[v_axis] += v as i32 - 1;
+                *voxel = self.opaque_and_light(position);
+            }
+        }
+
+        let (_, front_light) = voxels[1][1];
+        let mut corners = [0.; 4];
+        for (corner, (u, v)) in corners.iter_mut().zip([(0, 0), (0, 2), (2, 0), (2, 2)]) {
+            let side_u = voxels[u][1];
+            let side_v = voxels[1][v];
+            // If both sides are opaque, the diagonal voxel can't be seen from the corner.
+            let diagonal = if side_u.0 && side_v.0 {
+                (true, 0)
+            } else {
+                voxels[u][v]
+            };
+            let mut light = front_light as f32;
+            let mut occlusion = 0;
+            for (opaque, voxel_light) in [side_u, side_v, diagonal] {
+                if opaque {
+                    occlusion += 1;
+                } else {
+                    light += voxel_light as f32;
+                }
+            }
+            let light = light / (4 - occlusion) as f32;
+            *corner = brightness(light) * OCCLUSION_BRIGHTNESS[occlusion];
+        }
+        corners
+    }
+}
+
+/// Finds all visible faces of a chunk and merges coplanar faces of the same type into
+/// as few rectangles as it can.
+/// Faces hidden by the voxel in front of them are left out, including faces against voxels in
+/// the neighbouring chunks of the terrain.
+///
+/// Each slice of the chunk is covered greedily: starting from the first visible face, the
+/// rectangle is first grown as far as it can along one axis and then along the other.
+/// This is not always the fewest possible rectangles, but is close and fast.
+/// Only faces lit the same at all four corners are merged, since the light of a quad is
+/// interpolated between its corners.
+pub fn greedy_quads(terrain: &Terrain, chunk: &Chunk, location: glm::IVec3) -> Vec<Quad> {
+    let neighbourhood = ChunkNeighbourhood::new(terrain, chunk, location);
+    let size = CHUNK_SIZE as usize;
+    let mut quads = Vec::new();
+
+    // Single typed chunks are either completely empty or completely full,
+    // in which case only faces on the border can be visible.
+    if let Chunk::SingleType(voxel_type) = chunk {
+        if !is_visible(*voxel_type) {
+            return quads;
+        }
+    }
+
+    // The type and corner light of each visible face in the current slice,
+    // indexed by `u * size + v`.
+    let mut mask: Vec<Option<(VoxelType, [f32; 4])>> = vec![None; size * size];
+    for direction in FaceDirection::ALL {
+        let axis = direction.axis();
+        let u_axis = (axis + 1) % 3;
+        let v_axis = (axis + 2) % 3;
+        for slice in 0..CHUNK_SIZE {
+            for u in 0..CHUNK_SIZE {
+                for v in 0..CHUNK_SIZE {
+                    let mut position = [0; 3];
+                    position[axis] = slice;
+                    position[u_axis] = u;
+                    position[v_axis] = v;
+                    let voxel_type = neighbourhood.voxel_type(position);
+                    mask[u as usize * size + v as usize] = if is_visible(voxel_type)
+                        && !neighbourhood.face_hidden(position, direction)
+                    {
+                        Some((voxel_type, neighbourhood.face_light(position, direction)))
+                    } else {
+                        None
+                    };
+                }
+            }
+
+            for u in 0..size {
+                let mut v = 0;
+                while v < size {
+                    let face = match mask[u * size + v] {
+                        Some(face) => face,
+                        None => {
+                            v += 1;
+                            continue;
+                        }
+                    };
+                    let (voxel_type, light) = face;
+                    let (width, height) = if light.iter().all(|&corner| corner == light[0]) {
+                        let width = (v..size)
+                            .take_while(|&v| mask[u * size + v] == Some(face))
+                            .count();
+                        let height = (u..size)
+                            .take_while(|&u| {
+                                mask[u * size + v..u * size + v + width]
+                                    .iter()
+                                    .all(|&other| other == Some(face))
+                            })
+                            .count();
+                        (width, height)
+                    } else {
+                        (1, 1)
+                    };
+                    for row in u..u + height {
+                        mask[row * size + v..row * size + v + width].fill(None);
+                    }
+
+                    let mut min = [0; 3];
+                    min[axis] = slice + direction.is_positive() as u32;
+                    min[u_axis] = u as u32;
+                    min[v_axis] = v as u32;
+                    let mut max = min;
+                    max[u_axis] += height as u32;
+                    max[v_axis] += width as u32;
+                    quads.push(Quad {
+                        direction,
+                        voxel_type,
+                        min,
+                        max,
+                        light,
+                    });
+                    v += width;
+                }
+            }
+        }
+    }
+    quads
+}
+
+/// Turns quads into a vertex pack.
+///
+/// Vertex colors are the brightness of the quad's corners, shaded by the direction it faces.
+/// Texture coordinates are the position of the vertex on the quad in voxels, offset by
+/// `TILE_STRIDE` times the atlas tile of the face, so textures repeat once per voxel across
+/// merged faces. The vertical coordinate of side faces counts down from the top of the quad,
+/// so side textures are upright.
+pub fn quads_to_pack(quads: &[Quad], textures: &BlockTextures) -> VertexPack {
+    let mut vertices = Vec::with_capacity(quads.len() * 4);
+    let mut elements = Vec::with_capacity(quads.len() * 6);
+    for quad in quads {
+        let index = vertices.len() as u32;
+        let min = quad.min.map(|coord| coord as f32);
+        let max = quad.max.map(|coord| coord as f32);
+        let shade = quad.direction.shade();
+        let (r, g, b) = (shade, shade, shade);
+        let (mut vadd, mut eadd) = match quad.direction {
+            FaceDirection::Left => {
+                cube_faces::left(min[0], min[1], min[2], max[1], max[2], r, g, b, index)
+            }
+            FaceDirection::Right => {
+                cube_faces::right(min[0], min[1], min[2], max[1], max[2], r, g, b, index)
+            }
+            FaceDirection::Bottom => {
+                cube_faces::bottom(min[1], min[0], min[2], max[0], max[2], r, g, b, index)
+            }
+            FaceDirection::Top => {
+                cube_faces::top(min[1], min[0], min[2], max[0], max[2], r, g, b, index)
+            }
+            FaceDirection::Back => {
+                cube_faces::back(min[2], min[0], min[1], max[0], max[1], r, g, b, index)
+            }
+            FaceDirection::Front => {
+                cube_faces::front(min[2], min[0], min[1], max[0], max[1], r, g, b, index)
+            }
+        };
+        let tile = textures.tile(quad.voxel_type, quad.direction);
+        let axis = quad.direction.axis();
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        for vertex in vadd.iter_mut() {
+            let position = [vertex.x, vertex.y, vertex.z];
+            let corner = (position[u_axis] > min[u_axis]) as usize * 2
+                + (position[v_axis] > min[v_axis]) as usize;
+            vertex.r *= quad.light[corner];
+            vertex.g *= quad.light[corner];
+            vertex.b *= quad.light[corner];
+
+            let (u, v) = match quad.direction.axis() {
+                0 => (vertex.z - min[2], max[1] - vertex.y),
+                1 => (vertex.x - min[0], vertex.z - min[2]),
+                _ => (vertex.x - min[0], max[1] - vertex.y),
+            };
+            vertex.u = tile[0] as f32 * TILE_STRIDE + 1. + u;
+            vertex.v = tile[1] as f32 * TILE_STRIDE + 1. + v;
+        }
+        vertices.append(&mut vadd);
+        elements.append(&mut eadd);
+    }
+    VertexPack::new(vertices, Some(elements))
+}
+
+/// Sorts quads so the ones furthest from the view come first.
+/// The view is given in voxels relative to the chunk the quads are in.
+pub fn sort_back_to_front(quads: &mut [Quad], view: glm::Vec3) {
+    let distance_squared = |quad: &Quad| {
+        let center = glm::vec3(
+            quad.min[0] + quad.max[0],
+            quad.min[1] + quad.max[1],
+            quad.min[2] + quad.max[2],
+        )
+        .map(|coord| coord as f32 / 2.);
+        glm::distance2(&center, &view)
+    };
+    quads.sort_by(|a, b| distance_squared(b).total_cmp(&distance_squared(a)));
+}
+
+/// Creates a vertex pack for each transparency of voxel in the chunk at the given location in
+/// the terrain, leaving out the empty ones.
+///
+/// Translucent faces are blended with what is behind them, so they are sorted back to front
+/// from the view, given in voxels relative to the chunk. The order is only right for the view
+/// the pack was made for.
+pub fn create_chunk_packs(
+    terrain: &Terrain,
+    chunk: &Chunk,
+    location: glm::IVec3,
+    textures: &BlockTextures,
+    view: glm::Vec3,
+) -> Vec<(Transparency, VertexPack)> {
+    let quads = greedy_quads(terrain, chunk, location);
+    Transparency::ALL
+        .into_iter()
+        .filter_map(|transparency| {
+            let mut layer: Vec<Quad> = quads
+                .iter()
+                .filter(|quad| quad.voxel_type.transparency() == transparency)
+                .copied()
+                .collect();
+            if layer.is_empty() {
+                return None;
+            }
+            if transparency == Transparency::Translucent {
+                sort_back_to_front(&mut layer, view);
+            }
+            Some((transparency, quads_to_pack(&layer, textures)))
+        })
+        .collect()
+}
+
+/// The latest generation any voxel affecting the mesh of the chunk was modified in.
+/// Since faces are hidden and lit by the voxels around them, this includes all 26 neighbours.
+pub fn neighbourhood_generation(terrain: &Terrain, location: glm::IVec3) -> u64 {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| glm::vec3(x, y, z))))
+        .map(|offset| terrain.chunk_generation(location + offset))
+        .fold(0, u64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+    use world::Location;
+
+    /// Emits all six faces of every visible voxel, like the packer did before greedy meshing.
+    fn naive_chunk_pack(chunk: &Chunk) -> VertexPack {
+        let quads: Vec<Quad> = chunk
+            .iter()
+            .filter(|(voxel_type, _)| is_visible(*voxel_type))
+            .flat_map(|(voxel_type, position)| {
+                let position = [position.x as u32, position.y as u32, position.z as u32];
+                FaceDirection::ALL.map(|direction| {
+                    let mut min = position;
+                    let mut max = position.map(|coord| coord + 1);
+                    if direction.is_positive() {
+                        min[direction.axis()] += 1;
+                    } else {
+                        max[direction.axis()] -= 1;
+                    }
+                    Quad {
+                        direction,
+                        voxel_type,
+                        min,
+                        max,
+                        light: [1.; 4],
+                    }
+                })
+            })
+            .collect();
+        quads_to_pack(&quads, &BlockTextures::default())
+    }
+
+    fn num_triangles(pack: &VertexPack) -> usize {
+        pack.elements.len() / 3
+    }
+
+    fn location(x: u32, y: u32, z: u32) -> Location {
+        Location::from_coords(x as f32, y as f32, z as f32)
+    }
+
+    #[test]
+    fn single_voxel() {
+        let mut terrain = Terrain::new();
+        terrain.set_voxel_type(location(3, 4, 5), VoxelType(1));
+        let chunk = terrain.chunk(glm::vec3(0, 0, 0)).unwrap();
+        let quads = greedy_quads(&terrain, chunk, glm::vec3(0, 0, 0));
+        assert_eq!(quads.len(), 6);
+        assert_eq!(
+            num_triangles(&quads_to_pack(&quads, &BlockTextures::default())),
+            num_triangles(&naive_chunk_pack(chunk))
+        );
+    }
+
+    #[test]
+    fn full_chunk_against_neighbour() {
+        let mut terrain = Terrain::new();
+        for x in 0..CHUNK_SIZE * 2 {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    terrain.set_voxel_type(location(x, y, z), VoxelType(1));
+                }
+            }
+        }
+        terrain.clean();
+        let chunk = terrain.chunk(glm::vec3(0, 0, 0)).unwrap();
+        let quads = greedy_quads(&terrain, chunk, glm::vec3(0, 0, 0));
+        // The right side is hidden by the neighbouring chunk and the other sides are one quad each.
+        assert_eq!(quads.len(), 5);
+        assert!(quads
+            .iter()
+            .all(|quad| quad.direction != FaceDirection::Right));
+        assert_eq!(
+            num_triangles(&naive_chunk_pack(chunk)),
+            CHUNK_SIZE.pow(3) as usize * 12
+        );
+    }
+
+    #[test]
+    fn random_terrain_exposed_surface() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut terrain = Terrain::new();
+        for x in 0..CHUNK_SIZE + 2 {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    if rng.gen_bool(0.3) {
+                        terrain.set_voxel_type(location(x, y, z), VoxelType(rng.gen_range(1..3)));
+                    }
+                }
+            }
+        }
+        let chunk = terrain.chunk(glm::vec3(0, 0, 0)).unwrap();
+        let quads = greedy_quads(&terrain, chunk, glm::vec3(0, 0, 0));
+
+        // Every face the quads cover must be on a visible voxel of the quad's type
+        // facing a transparent voxel, and no face may be covered twice.
+        let mut covered = HashSet::new();
+        for quad in quads.iter() {
+            let axis = quad.direction.axis();
+            let mut cell = quad.min;
+            if quad.direction.is_positive() {
+                cell[axis] -= 1;
+            }
+            let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+            for u in quad.min[u_axis]..quad.max[u_axis] {
+                for v in quad.min[v_axis]..quad.max[v_axis] {
+                    cell[u_axis] = u;
+                    cell[v_axis] = v;
+                    let voxel = location(cell[0], cell[1], cell[2]);
+                    assert_eq!(terrain.voxel_type(voxel), quad.voxel_type);
+                    let normal = quad.direction.normal();
+                    let neighbour = terrain.voxel_type(Location::from_coords(
+                        (cell[0] as i32 + normal.x) as f32,
+                        (cell[1] as i32 + normal.y) as f32,
+                        (cell[2] as i32 + normal.z) as f32,
+                    ));
+                    assert!(!is_opaque(neighbour));
+                    assert!(covered.insert((cell, quad.direction)));
+                }
+            }
+        }
+
+        // The quads must cover every exposed face exactly once.
+        let exposed = chunk
+            .iter()
+            .filter(|(voxel_type, _)| is_visible(*voxel_type))
+            .map(|(_, position)| {
+                FaceDirection::ALL
+                    .iter()
+                    .filter(|direction| {
+                        let normal = direction.normal();
+                        let neighbour = terrain.voxel_type(Location::from_coords(
+                            position.x + normal.x as f32,
+                            position.y + normal.y as f32,
+                            position.z + normal.z as f32,
+                        ));
+                        !is_opaque(neighbour)
+                    })
+                    .count()
+            })
+            .sum::<usize>();
+        assert_eq!(covered.len(), exposed);
+        assert_eq!(quads.iter().map(Quad::area).sum::<u32>() as usize, exposed);
+
+        assert!(
+            num_triangles(&quads_to_pack(&quads, &BlockTextures::default()))
+                < num_triangles(&naive_chunk_pack(chunk))
+        );
+    }
+
+    #[test]
+    fn texture_coordinates_repeat_per_voxel() {
+        let quad = Quad {
+            direction: FaceDirection::Front,
+            voxel_type: VoxelType(1),
+            min: [2, 0, 5],
+            max: [5, 2, 5],
+            light: [1.; 4],
+        };
+        let pack = quads_to_pack(&[quad], &BlockTextures::default());
+        for vertex in pack.vertices.iter() {
+            let u = vertex.u - 1. - (vertex.x - 2.);
+            let v = vertex.v - 1. - (2. - vertex.y);
+            // Both are offset by the missing tile, which is the first tile.
+            assert_eq!((u, v), (0., 0.));
+        }
+        let (max_u, max_v) = pack.vertices.iter().fold((0f32, 0f32), |(u, v), vertex| {
+            (u.max(vertex.u), v.max(vertex.v))
+        });
+        assert_eq!((max_u, max_v), (4., 3.));
+        assert!(max_u < TILE_STRIDE && max_v < TILE_STRIDE);
+    }
+
+    #[test]
+    fn translucent_voxels_are_split_and_sorted() {
+        let mut terrain = Terrain::new();
+        // A row of water, a stone and a glass voxel, with leaves on top of the stone.
+        for x in 1..4 {
+            terrain.set_voxel_type(location(x, 1, 1), VoxelType(6));
+        }
+        terrain.set_voxel_type(location(4, 1, 1), VoxelType(3));
+        terrain.set_voxel_type(location(5, 1, 1), VoxelType(7));
+        terrain.set_voxel_type(location(4, 2, 1), VoxelType(5));
+        let chunk = terrain.chunk(glm::vec3(0, 0, 0)).unwrap();
+        let quads = greedy_quads(&terrain, chunk, glm::vec3(0, 0, 0));
+
+        // Faces between the water voxels are hidden, but the stone is seen through the water,
+        // the glass and the leaves.
+        let water: Vec<&Quad> = quads
+            .iter()
+            .filter(|quad| quad.voxel_type == VoxelType(6))
+            .collect();
+        assert!(water
+            .iter()
+            .all(|quad| quad.direction != FaceDirection::Right || quad.min[0] == 4));
+        let stone = quads
+            .iter()
+            .filter(|quad| quad.voxel_type == VoxelType(3))
+            .count();
+        assert_eq!(stone, 6);
+
+        let view = glm::vec3(0., 1.5, 1.5);
+        let packs = create_chunk_packs(
+            &terrain,
+            chunk,
+            glm::vec3(0, 0, 0),
+            &BlockTextures::default(),
+            view,
+        );
+        let layers: Vec<Transparency> = packs.iter().map(|(layer, _)| *layer).collect();
+        assert_eq!(layers, Transparency::ALL);
+        let (_, translucent) = &packs[2];
+        let distances: Vec<f32> = translucent
+            .vertices
+            .chunks(4)
+            .map(|face| {
+                let center = face.iter().fold(glm::vec3(0., 0., 0.), |sum, vertex| {
+                    sum + glm::vec3(vertex.x, vertex.y, vertex.z)
+                }) / 4.;
+                glm::distance2(&center, &view)
+            })
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn corners_next_to_walls_are_darker() {
+        let mut terrain = Terrain::new();
+        terrain.set_voxel_type(location(1, 0, 1), VoxelType(1));
+        terrain.set_voxel_type(location(2, 1, 1), VoxelType(1));
+        terrain.update_light();
+        let chunk = terrain.chunk(glm::vec3(0, 0, 0)).unwrap();
+        let quads = greedy_quads(&terrain, chunk, glm::vec3(0, 0, 0));
+        let top = quads
+            .iter()
+            .find(|quad| quad.direction == FaceDirection::Top && quad.min == [1, 1, 1])
+            .unwrap();
+        // The corners along the low x side are open, the ones along the high x side are against
+        // the wall. The top face's axes are z and then x.
+        assert_eq!(top.light[0], 1.);
+        assert_eq!(top.light[2], 1.);
+        assert!(top.light[1] < top.light[0]);
+        assert!(top.light[3] < top.light[2]);
+
+        // The bottom face is in the dark under the voxel, but still lit from the sides.
+        let bottom = quads
+            .iter()
+            .find(|quad| quad.direction == FaceDirection::Bottom && quad.min == [1, 0, 1])
+            .unwrap();
+        assert!(bottom
+            .light
+            .iter()
+            .all(|&corner| corner < 1. && corner > 0.));
+    }
+
+    #[test]
+    fn chunk_render_matches_golden() {
+        use graphics::{
+            software::compare_with_golden, BufferTarget, RenderMessage, ShaderIdentifier,
+            SoftwareRenderer, UniformData, BLOCK_ATLAS_TEXTURE,
+        };
+
+        let mut terrain = Terrain::new();
+        for x in 4..10 {
+            for z in 4..10 {
+                terrain.set_voxel_type(location(x, 0, z), VoxelType(1));
+            }
+        }
+        terrain.set_voxel_type(location(5, 1, 5), VoxelType(3));
+        terrain.set_voxel_type(location(5, 2, 5), VoxelType(5));
+        terrain.set_voxel_type(location(7, 1, 5), VoxelType(7));
+        for z in 7..9 {
+            terrain.set_voxel_type(location(7, 1, z), VoxelType(6));
+        }
+        terrain.update_light();
+        let chunk = terrain.chunk(glm::vec3(0, 0, 0)).unwrap();
+
+        let mut renderer = SoftwareRenderer::new((200, 150));
+        let capabilities = renderer.capabilities();
+        let textures = BlockTextures::load(
+            utils::ASSETS_PATH.join("graphics/textures/blocks/block_textures.toml"),
+            &capabilities.block_atlas,
+        );
+        let view = game::View::new(
+            Location::from_coords(2.5, 3., 2.5),
+            game::view::PrincipalAxes::new(2.36, 1.2),
+        );
+        let vp = super::super::render_state::get_vp_matrix(&view, capabilities.screen_dimensions);
+        let packs = create_chunk_packs(
+            &terrain,
+            chunk,
+            glm::vec3(0, 0, 0),
+            &textures,
+            view.location().position,
+        );
+
+        renderer.read_message(&RenderMessage::ClearBuffers {
+            color_buffer: true,
+            depth_buffer: true,
+        });
+        renderer.read_message(&RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Chunk,
+        });
+        let (columns, rows) = textures.grid_size();
+        for (i, (transparency, pack)) in packs.into_iter().enumerate() {
+            let buffer = BufferTarget::WorldBuffer(i);
+            let mut uniforms = UniformData::new();
+            uniforms.texture(String::from(BLOCK_ATLAS_TEXTURE), "block_atlas");
+            uniforms.float(TILE_STRIDE, "tile_stride");
+            uniforms.vec2(glm::vec2(columns as f32, rows as f32), "atlas_tiles");
+            let alpha_cutoff = if transparency == Transparency::Cutout {
+                0.5
+            } else {
+                0.
+            };
+            uniforms.float(alpha_cutoff, "alpha_cutoff");
+            uniforms.mat4(vp, String::from("MVP"));
+            let blending = transparency == Transparency::Translucent;
+            for message in [
+                RenderMessage::Pack { buffer, pack },
+                RenderMessage::Uniforms {
+                    uniforms: Box::new(uniforms),
+                },
+                RenderMessage::Blending { enabled: blending },
+                RenderMessage::Draw { buffer },
+            ] {
+                renderer.read_message(&message);
+            }
+        }
+
+        if let Err(error) = compare_with_golden(&renderer.screenshot(), "chunk_mesh") {
+            panic!("{}", error);
+        }
+    }
+}
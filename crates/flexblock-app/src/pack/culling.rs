@@ -0,0 +1,313 @@
+use super::chunk_mesher::{self, FaceDirection};
+use std::collections::{HashSet, VecDeque};
+use world::chunk::{ChunkLocation, CHUNK_SIZE, CHUNK_SIZE_F};
+use world::Chunk;
+
+/// The six planes bounding the volume a camera can see.
+pub struct Frustum {
+    /// Each plane as `(a, b, c, d)` with the normal `(a, b, c)` pointing into the frustum,
+    /// so a point `p` is inside the plane if `a * p.x + b * p.y + c * p.z + d >= 0`.
+    planes: [glm::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum from a view projection matrix, like the one from `get_vp_matrix`.
+    /// Points are in the frustum if the matrix puts them within the clip volume.
+    pub fn from_matrix(vp_matrix: &glm::Mat4) -> Frustum {
+        let row = |i: usize| vp_matrix.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        Frustum {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+        }
+    }
+
+    /// Whether any part of the axis aligned box is within the frustum.
+    /// May return true for some boxes just outside the corners of the frustum.
+    pub fn intersects_aabb(&self, min: &glm::Vec3, max: &glm::Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane's normal.
+            let corner = glm::vec3(
+                if plane.x >= 0. { max.x } else { min.x },
+                if plane.y >= 0. { max.y } else { min.y },
+                if plane.z >= 0. { max.z } else { min.z },
+            );
+            plane.x * corner.x + plane.y * corner.y + plane.z * corner.z + plane.w >= 0.
+        })
+    }
+
+    /// Whether any part of the chunk with the given index is within the frustum.
+    /// The index is relative to the chunk the camera is in, since view projection matrices from
+    /// `get_vp_matrix` are.
+    pub fn intersects_chunk(&self, location: glm::IVec3) -> bool {
+        let min = world::chunk_index_to_position(location);
+        let max = min + glm::vec3(CHUNK_SIZE_F, CHUNK_SIZE_F, CHUNK_SIZE_F);
+        self.intersects_aabb(&min, &max)
+    }
+}
+
+/// Records which pairs of a chunk's faces are connected by a path through non-opaque voxels.
+/// If two faces are not connected, nothing can be seen through the chunk from one to the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkVisibility {
+    /// Bit `a * 6 + b` is set if face `a` connects to face `b`, indexed like `FaceDirection::ALL`.
+    connections: u64,
+}
+
+impl ChunkVisibility {
+    /// Every face connects to every other face, as in an empty chunk.
+    pub const ALL: ChunkVisibility = ChunkVisibility {
+        connections: (1 << 36) - 1,
+    };
+    /// No faces are connected, as in a solid chunk.
+    pub const NONE: ChunkVisibility = ChunkVisibility { connections: 0 };
+
+    /// Finds the connected faces of the chunk by flood filling each region of non-opaque voxels
+    /// and connecting all the faces the region touches.
+    pub fn compute(chunk: &Chunk) -> ChunkVisibility {
+        if let Chunk::SingleType(voxel_type) = chunk {
+            return if chunk_mesher::is_opaque(*voxel_type) {
+                ChunkVisibility::NONE
+            } else {
+                ChunkVisibility::ALL
+            };
+        }
+
+        let size = CHUNK_SIZE as usize;
+        let index = |position: [u32; 3]| {
+            (position[0] as usize * size + position[1] as usize) * size + position[2] as usize
+        };
+        let mut visited = vec![false; size * size * size];
+        let mut visibility = ChunkVisibility::NONE;
+        let mut frontier = Vec::new();
+        for (voxel_type, position) in chunk.iter() {
+            let position = [position.x as u32, position.y as u32, position.z as u32];
+            if visited[index(position)] || chunk_mesher::is_opaque(voxel_type) {
+                continue;
+            }
+            // Flood fill the region, recording which faces of the chunk it touches.
+            let mut touched = 0u8;
+            visited[index(position)] = true;
+            frontier.push(position);
+            while let Some(position) = frontier.pop() {
+                for direction in FaceDirection::ALL {
+                    let axis = direction.axis();
+                    let mut neighbour = position;
+                    if direction.is_positive() {
+                        if position[axis] + 1 == CHUNK_SIZE {
+                            touched |= 1 << direction as u8;
+                            continue;
+                        }
+                        neighbour[axis] += 1;
+                    } else {
+                        if position[axis] == 0 {
+                            touched |= 1 << direction as u8;
+                            continue;
+                        }
+                        neighbour[axis] -= 1;
+                    }
+                    if !visited[index(neighbour)]
+                        && !chunk_mesher::is_opaque(chunk.voxel_type_unchecked(ChunkLocation::new(
+                            neighbour[0],
+                            neighbour[1],
+                            neighbour[2],
+                        )))
+                    {
+                        visited[index(neighbour)] = true;
+                        frontier.push(neighbour);
+                    }
+                }
+            }
+            for a in 0..6 {
+                for b in 0..6 {
+                    if touched & (1 << a) != 0 && touched & (1 << b) != 0 {
+                        visibility.connections |= 1 << (a * 6 + b);
+                    }
+                }
+            }
+        }
+        visibility
+    }
+
+    /// Whether the two faces are connected through the chunk.
+    pub fn connects(&self, a: FaceDirection, b: FaceDirection) -> bool {
+        self.connections & (1 << (a as u64 * 6 + b as u64)) != 0
+    }
+}
+
+/// Finds the chunks that might be visible from the chunk the camera is in.
+///
+/// Walks outwards from the camera's chunk, only entering chunks in the frustum and only
+/// leaving a chunk through a face that is connected to the face it was entered through.
+/// A chunk can be entered once through each of its faces, since each face may connect to different exits.
+/// The walk never turns back along an axis it has already moved along in the other direction,
+/// since a line of sight can't either.
+///
+/// # Arguments
+///
+/// * `center` - The chunk the camera is in. Always visible.
+/// * `frustum` - The frustum of the camera, relative to `center`.
+/// * `max_distance_squared` - Chunks further than this from `center` are not visited.
+/// * `visibility` - Gives the visibility of each chunk.
+pub fn visible_chunks(
+    center: glm::IVec3,
+    frustum: &Frustum,
+    max_distance_squared: i32,
+    visibility: impl Fn(glm::IVec3) -> ChunkVisibility,
+) -> HashSet<glm::IVec3> {
+    let mut visible = HashSet::new();
+    visible.insert(center);
+    let mut entered = HashSet::new();
+    // Each chunk with the face it was entered through and the directions moved to reach it.
+    let mut frontier: VecDeque<(glm::IVec3, Option<FaceDirection>, u8)> = VecDeque::new();
+    frontier.push_back((center, None, 0));
+    while let Some((location, entry, directions)) = frontier.pop_front() {
+        let chunk_visibility = visibility(location);
+        for direction in FaceDirection::ALL {
+            if directions & (1 << direction.opposite() as u8) != 0 {
+                continue;
+            }
+            if let Some(entry) = entry {
+                if !chunk_visibility.connects(entry, direction) {
+                    continue;
+                }
+            }
+            let neighbour = location + direction.normal();
+            let v = neighbour - center;
+            if v.x * v.x + v.y * v.y + v.z * v.z > max_distance_squared
+                || entered.contains(&(neighbour, direction.opposite()))
+                || !frustum.intersects_chunk(v)
+            {
+                continue;
+            }
+            visible.insert(neighbour);
+            entered.insert((neighbour, direction.opposite()));
+            frontier.push_back((
+                neighbour,
+                Some(direction.opposite()),
+                directions | (1 << direction as u8),
+            ));
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use world::VoxelType;
+
+    /// A camera at the given position looking along the positive x axis.
+    fn frustum(position: glm::Vec3) -> Frustum {
+        let view = glm::look_at(
+            &position,
+            &(position + glm::vec3(1., 0., 0.)),
+            &glm::vec3(0., 1., 0.),
+        );
+        let projection = glm::perspective_fov(std::f32::consts::FRAC_PI_2, 1., 1., 0.1, 100.);
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    #[test]
+    fn frustum_culls_chunks_behind_and_beside() {
+        let frustum = frustum(glm::vec3(8., 8., 8.));
+        assert!(frustum.intersects_chunk(glm::vec3(0, 0, 0)));
+        assert!(frustum.intersects_chunk(glm::vec3(2, 0, 0)));
+        assert!(frustum.intersects_chunk(glm::vec3(2, 1, -1)));
+        assert!(!frustum.intersects_chunk(glm::vec3(-2, 0, 0)));
+        assert!(!frustum.intersects_chunk(glm::vec3(1, 0, 3)));
+        assert!(!frustum.intersects_chunk(glm::vec3(10, 0, 0)));
+    }
+
+    #[test]
+    fn tunnel_visibility() {
+        // A solid chunk with a tunnel along the x axis.
+        let mut chunk = Chunk::SingleType(VoxelType(1));
+        for x in 0..CHUNK_SIZE {
+            chunk.set_voxel_type_unchecked(ChunkLocation::new(x, 3, 3), VoxelType(0));
+        }
+        let visibility = ChunkVisibility::compute(&chunk);
+        assert!(visibility.connects(FaceDirection::Left, FaceDirection::Right));
+        assert!(visibility.connects(FaceDirection::Right, FaceDirection::Left));
+        assert!(!visibility.connects(FaceDirection::Left, FaceDirection::Top));
+        assert!(!visibility.connects(FaceDirection::Back, FaceDirection::Front));
+        assert_eq!(
+            ChunkVisibility::compute(&Chunk::new()),
+            ChunkVisibility::ALL
+        );
+    }
+
+    #[test]
+    fn walls_hide_chunks() {
+        let frustum = frustum(glm::vec3(8., 8., 8.));
+        // Chunk 2 along x is a solid wall, everything else is air.
+        let visibility = |location: glm::IVec3| {
+            if location.x == 2 {
+                ChunkVisibility::NONE
+            } else {
+                ChunkVisibility::ALL
+            }
+        };
+        let visible = visible_chunks(glm::vec3(0, 0, 0), &frustum, 16, visibility);
+        assert!(visible.contains(&glm::vec3(1, 0, 0)));
+        // The wall itself can be seen, but nothing behind it.
+        assert!(visible.contains(&glm::vec3(2, 0, 0)));
+        assert!(!visible.contains(&glm::vec3(3, 0, 0)));
+        // Behind the camera is outside the frustum.
+        assert!(!visible.contains(&glm::vec3(-1, 0, 0)));
+
+        let visible = visible_chunks(glm::vec3(0, 0, 0), &frustum, 16, |_| ChunkVisibility::ALL);
+        assert!(visible.contains(&glm::vec3(3, 0, 0)));
+    }
+
+    /// A chunk where only the two faces are connected.
+    fn connecting(a: FaceDirection, b: FaceDirection) -> ChunkVisibility {
+        ChunkVisibility {
+            connections: (1 << (a as u64 * 6 + b as u64)) | (1 << (b as u64 * 6 + a as u64)),
+        }
+    }
+
+    #[test]
+    fn chunks_are_expanded_through_every_entry_face() {
+        let frustum = frustum(glm::vec3(8., 8., 8.));
+        // The chunk at (2, 1, 0) is reached both from the left, through (1, 1, 0), and from below,
+        // through (2, 0, 0), at the same distance. Only one of those faces connects to the exit,
+        // so both cases are tried, to test whichever entry the walk takes first.
+        let cases = [
+            (
+                FaceDirection::Bottom,
+                FaceDirection::Right,
+                glm::vec3(3, 1, 0),
+            ),
+            (FaceDirection::Left, FaceDirection::Top, glm::vec3(2, 2, 0)),
+        ];
+        for (entry, exit, behind) in cases {
+            let visibility = |location: glm::IVec3| {
+                if location == glm::vec3(2, 1, 0) {
+                    connecting(entry, exit)
+                } else if [(1, 0, 0), (1, 1, 0), (2, 0, 0)]
+                    .contains(&(location.x, location.y, location.z))
+                {
+                    ChunkVisibility::ALL
+                } else {
+                    ChunkVisibility::NONE
+                }
+            };
+            let visible = visible_chunks(glm::vec3(0, 0, 0), &frustum, 16, visibility);
+            assert!(
+                visible.contains(&behind),
+                "Nothing seen through the {:?} face of (2, 1, 0)",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn culling_is_relative_to_center() {
+        let frustum = frustum(glm::vec3(8., 8., 8.));
+        let near = visible_chunks(glm::vec3(0, 0, 0), &frustum, 16, |_| ChunkVisibility::ALL);
+        // Millions of voxels from the origin, the same chunks around the camera are visible.
+        let center = glm::vec3(1 << 20, -(1 << 18), 1 << 20);
+        let far = visible_chunks(center, &frustum, 16, |_| ChunkVisibility::ALL);
+        assert_eq!(far, near.iter().map(|location| location + center).collect());
+    }
+}
@@ -0,0 +1,263 @@
+use super::block_textures::BlockTextures;
+use super::chunk_mesher;
+use super::culling::ChunkVisibility;
+use graphics::VertexPack;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use world::chunk::CHUNK_SIZE;
+use world::{Location, Terrain, Transparency};
+
+/// A request to mesh a chunk.
+struct MeshJob {
+    /// The terrain to mesh the chunk from. Shared between all jobs requested at the same time.
+    terrain: Arc<Terrain>,
+    /// The generation of the chunk's neighbourhood in `terrain`.
+    generation: u64,
+    /// The atlas tiles to texture the faces with.
+    textures: Arc<BlockTextures>,
+}
+
+/// A finished chunk mesh.
+pub struct MeshResult {
+    pub location: glm::IVec3,
+    /// The generation of the chunk's neighbourhood the mesh was made from.
+    pub generation: u64,
+    /// The vertex pack of each transparency of voxel in the chunk. Empty ones are left out.
+    pub packs: Vec<(Transparency, VertexPack)>,
+    /// The chunk the view was in when the mesh was made. Translucent faces are sorted for it.
+    pub sorted_for: glm::IVec3,
+    /// Which faces of the chunk can be seen through from each other.
+    pub visibility: ChunkVisibility,
+}
+
+/// The chunks waiting to be meshed.
+/// There is at most one job per chunk, and the chunk closest to the view is meshed first.
+struct MeshQueue {
+    jobs: HashMap<glm::IVec3, MeshJob>,
+    /// Where the view is.
+    view: Location,
+    shutdown: bool,
+}
+
+impl MeshQueue {
+    fn new() -> MeshQueue {
+        MeshQueue {
+            jobs: HashMap::new(),
+            view: Location::origin(),
+            shutdown: false,
+        }
+    }
+
+    /// Adds a job, replacing any job already queued for the same chunk.
+    fn push(&mut self, location: glm::IVec3, job: MeshJob) {
+        self.jobs.insert(location, job);
+    }
+
+    /// Removes and returns the job for the chunk closest to the center.
+    fn pop_nearest(&mut self) -> Option<(glm::IVec3, MeshJob)> {
+        let center = self.view.chunk;
+        let location = *self.jobs.keys().min_by_key(|location| {
+            let v = *location - center;
+            v.x * v.x + v.y * v.y + v.z * v.z
+        })?;
+        self.jobs.remove(&location).map(|job| (location, job))
+    }
+}
+
+/// A pool of threads that mesh chunks in the background.
+///
+/// Requests are prioritized by distance to the view. Finished meshes are collected with
+/// `try_recv` and may arrive in any order.
+pub struct MeshWorkerPool {
+    queue: Arc<(Mutex<MeshQueue>, Condvar)>,
+    results: mpsc::Receiver<MeshResult>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl MeshWorkerPool {
+    /// Starts a pool with the given number of worker threads.
+    pub fn new(num_workers: usize) -> MeshWorkerPool {
+        let queue = Arc::new((Mutex::new(MeshQueue::new()), Condvar::new()));
+        let (result_sender, results) = mpsc::channel();
+        let workers = (0..num_workers)
+            .map(|i| {
+                let queue = Arc::clone(&queue);
+                let result_sender = result_sender.clone();
+                thread::Builder::new()
+                    .name(format!("mesh worker {}", i))
+                    .spawn(move || Self::work(&queue, &result_sender))
+                    .expect("Failed to spawn mesh worker thread.")
+            })
+            .collect();
+        MeshWorkerPool {
+            queue,
+            results,
+            workers,
+        }
+    }
+
+    /// Starts a pool with a worker for each core not used by the main threads of the program.
+    pub fn with_available_parallelism() -> MeshWorkerPool {
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        // The logic, packing and window threads each need a core.
+        MeshWorkerPool::new(cores.saturating_sub(3).max(1))
+    }
+
+    fn work(queue: &(Mutex<MeshQueue>, Condvar), result_sender: &mpsc::Sender<MeshResult>) {
+        let (queue, condvar) = queue;
+        loop {
+            let (location, job, view) = {
+                let mut queue = queue.lock().unwrap();
+                loop {
+                    if queue.shutdown {
+                        return;
+                    }
+                    if let Some((location, job)) = queue.pop_nearest() {
+                        break (location, job, queue.view);
+                    }
+                    queue = condvar.wait(queue).unwrap();
+                }
+            };
+            let (packs, visibility) = match job.terrain.chunk(location) {
+                Some(chunk) => {
+                    let relative_view = (view.chunk - location)
+                        .map(|x| (x * CHUNK_SIZE as i32) as f32)
+                        + view.position;
+                    (
+                        chunk_mesher::create_chunk_packs(
+                            &job.terrain,
+                            chunk,
+                            location,
+                            &job.textures,
+                            relative_view,
+                        ),
+                        ChunkVisibility::compute(chunk),
+                    )
+                }
+                None => (Vec::new(), ChunkVisibility::ALL),
+            };
+            let result = MeshResult {
+                location,
+                generation: job.generation,
+                packs,
+                sorted_for: view.chunk,
+                visibility,
+            };
+            if result_sender.send(result).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Sets where the view is. Queued chunks closer to it are meshed first, and translucent
+    /// faces are sorted back to front from it.
+    pub fn set_view(&self, view: Location) {
+        self.queue.0.lock().unwrap().view = view;
+    }
+
+    /// Requests that the chunk is meshed from the given terrain with the given textures.
+    /// Replaces any earlier request for the same chunk that hasn't been started yet.
+    pub fn request(
+        &self,
+        location: glm::IVec3,
+        generation: u64,
+        terrain: Arc<Terrain>,
+        textures: Arc<BlockTextures>,
+    ) {
+        let (queue, condvar) = &*self.queue;
+        queue.lock().unwrap().push(
+            location,
+            MeshJob {
+                terrain,
+                generation,
+                textures,
+            },
+        );
+        condvar.notify_one();
+    }
+
+    /// Drops queued requests for chunks the predicate returns false for.
+    pub fn retain_requests(&self, mut predicate: impl FnMut(glm::IVec3) -> bool) {
+        self.queue
+            .0
+            .lock()
+            .unwrap()
+            .jobs
+            .retain(|&location, _| predicate(location));
+    }
+
+    /// Returns the next finished mesh, or None if no mesh is finished. Never blocks.
+    pub fn try_recv(&self) -> Option<MeshResult> {
+        self.results.try_recv().ok()
+    }
+}
+
+impl Drop for MeshWorkerPool {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.queue;
+        queue.lock().unwrap().shutdown = true;
+        condvar.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use world::VoxelType;
+
+    #[test]
+    fn nearest_first() {
+        let terrain = Arc::new(Terrain::new());
+        let mut queue = MeshQueue::new();
+        for x in [5, -1, 3, 0, -4] {
+            queue.push(
+                glm::vec3(x, 0, 0),
+                MeshJob {
+                    terrain: Arc::clone(&terrain),
+                    generation: 0,
+                    textures: Arc::default(),
+                },
+            );
+        }
+        queue.view = Location::new(glm::vec3(2, 0, 0), glm::vec3(0., 0., 0.));
+        let order: Vec<i32> = std::iter::from_fn(|| queue.pop_nearest())
+            .map(|(location, _)| location.x)
+            .collect();
+        assert_eq!(order[0], 3);
+        assert_eq!(order[order.len() - 1], -4);
+    }
+
+    #[test]
+    fn meshes_in_background() {
+        let mut terrain = Terrain::new();
+        terrain.set_voxel_type(Location::from_coords(1., 1., 1.), VoxelType(1));
+        let terrain = Arc::new(terrain);
+        let pool = MeshWorkerPool::new(2);
+        let textures = Arc::new(BlockTextures::default());
+        pool.request(
+            glm::vec3(0, 0, 0),
+            1,
+            Arc::clone(&terrain),
+            Arc::clone(&textures),
+        );
+        pool.request(glm::vec3(1, 0, 0), 0, terrain, textures);
+
+        let mut results = Vec::new();
+        while results.len() < 2 {
+            results.extend(pool.try_recv());
+            thread::sleep(Duration::from_millis(1));
+        }
+        results.sort_by_key(|result| result.location.x);
+        assert_eq!(results[0].generation, 1);
+        assert_eq!(results[0].packs.len(), 1);
+        assert_eq!(results[0].packs[0].0, Transparency::Opaque);
+        assert_eq!(results[0].packs[0].1.elements.len(), 6 * 6);
+        assert!(results[1].packs.is_empty());
+        assert_eq!(results[1].visibility, ChunkVisibility::ALL);
+    }
+}
@@ -1,8 +1,15 @@
+mod block_textures;
+mod chunk_buffers;
+mod chunk_mesher;
+mod culling;
+mod mesh_workers;
+mod shadows;
+
 mod render_state;
 pub use render_state::RenderState;
 
-mod render_message_validator;
-pub use render_message_validator::RenderMessageValidator;
+mod render_recording;
+pub use render_recording::{RecordedEvent, RenderRecorder, RenderRecordingReader};
 
 mod packing;
 pub use packing::start_packing_thread;
\ No newline at end of file
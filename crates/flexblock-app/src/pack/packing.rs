@@ -1,12 +1,11 @@
-use graphics::FramebufferIdentifier;
-
 use crate::channels::*;
-use crate::pack::{RenderMessageValidator, RenderState};
+use crate::pack::{RenderRecorder, RenderState};
 use graphics::pack::*;
 use graphics::BufferTarget;
+use graphics::GraphicsCapabilities;
 use graphics::ShaderIdentifier;
-use graphics::UniformData;
-use graphics::{RenderMessage, RenderMessages, VertexPack};
+use graphics::{RenderMessage, RenderMessageValidator, RenderMessages, ValidationMode, VertexPack};
+use log::{error, warn};
 use std::collections::HashSet;
 use std::thread::{self, JoinHandle};
 use utils::Vertex3D;
@@ -99,26 +98,50 @@ fn get_reticle_pack() -> VertexPack {
     )
 }
 
-#[cfg(not(debug_assertions))]
-fn validate(
-    validator: &mut RenderMessageValidator,
-    state: &RenderState,
-    messages: &RenderMessages,
-) {
+/// When this environment variable is set, it chooses how render messages are validated:
+/// `panic`, `log`, or `off`. Without it, debug builds panic on invalid render messages, and
+/// release builds don't validate them.
+pub const VALIDATION_VARIABLE: &str = "FLEXBLOCK_VALIDATION";
+
+/// Creates the validator chosen by `FLEXBLOCK_VALIDATION`, or None if render messages shouldn't be validated.
+fn validator_from_env() -> Option<RenderMessageValidator> {
+    let mode = match std::env::var(VALIDATION_VARIABLE) {
+        Ok(mode) if mode == "off" => return None,
+        Ok(mode) => match mode.parse() {
+            // Nothing takes the collected reports in the game, so they would only pile up.
+            Ok(ValidationMode::Collect) | Err(_) => {
+                error!(
+                    "{} must be panic, log or off, not {}. Logging validation errors instead.",
+                    VALIDATION_VARIABLE, mode
+                );
+                ValidationMode::Log
+            }
+            Ok(mode) => mode,
+        },
+        Err(_) if cfg!(debug_assertions) => ValidationMode::Panic,
+        Err(_) => return None,
+    };
+    Some(RenderMessageValidator::new(mode))
 }
 
-#[cfg(debug_assertions)]
-fn validate(
-    validator: &mut RenderMessageValidator,
-    state: &RenderState,
-    messages: &RenderMessages,
+/// Output textures are only created when the game starts, so passes added while it runs can't be dispatched yet.
+fn disable_passes_without_textures(
+    post_processing: &mut PostProcessingWatcher,
+    cx: &GraphicsCapabilities,
 ) {
-    match validator.validate(state, messages) {
-        Ok(()) => {}
-        Err(e) => {
-            println!("Validation error: {:?}", e);
-            panic!();
-        }
+    let missing: Vec<String> = post_processing
+        .config()
+        .passes
+        .iter()
+        .filter(|pass| pass.enabled && !cx.texture_metadata.contains_key(&pass.output))
+        .map(|pass| pass.name.clone())
+        .collect();
+    for name in missing {
+        warn!(
+            "Post processing pass {} writes to a texture that doesn't exist yet, so it is disabled until restarting.",
+            name
+        );
+        post_processing.config_mut().set_enabled(&name, false);
     }
 }
 
@@ -128,9 +151,15 @@ pub fn start_packing_thread(
     window_rx: WindowToPackingReceiver,
 ) -> JoinHandle<()> {
     let mut state = RenderState::new();
-    // Only used by debug validation
-    // Keeps all state needed for running validation.
-    let mut validator = RenderMessageValidator::new();
+    // Keeps all state needed for running validation, if render messages are validated.
+    let mut validator = validator_from_env();
+    // Records everything sent to the window if FLEXBLOCK_RECORD_RENDER is set.
+    let mut recorder = RenderRecorder::from_env();
+
+    // Reloaded when the config file changes, so passes can be toggled and reordered while the game runs.
+    let mut post_processing =
+        PostProcessingWatcher::new(utils::ASSETS_PATH.join(POST_PROCESSING_CONFIG))
+            .unwrap_or_else(|error| panic!("Could not load post processing! {}", error));
 
     let mut model_manager = graphics::model::ModelManager::load_models();
     let mut models_to_pack: Option<HashSet<String>> = Some(
@@ -143,6 +172,10 @@ pub fn start_packing_thread(
     thread::spawn(move || {
         for _ in logic_rx.channel_receiver.iter() {
             while let Ok(cap) = window_rx.channel_receiver.try_recv() {
+                if let Some(Err(error)) = recorder.as_mut().map(|r| r.record_capabilities(&cap)) {
+                    error!("Stopped recording render messages. Error: {:?}", error);
+                    recorder = None;
+                }
                 state.update_capabilities(cap);
             }
             let data = logic_rx.graphics_state_model.lock().unwrap();
@@ -154,112 +187,22 @@ pub fn start_packing_thread(
                     messages.merge_current(model_manager.pack_models(pack_models));
                 }
 
-                messages.add_message(RenderMessage::ChooseFramebuffer {
-                    framebuffer: Some(FramebufferIdentifier::FirstPass),
-                });
-
                 messages.add_message(RenderMessage::SwitchTo3D {});
                 messages.merge_current(state.create_render_messages(&data, &model_manager));
 
                 {
                     let cx = state.render_capabilities().as_ref().unwrap();
-                    let mut cp = ComputePipeline::new();
-
-                    // Sobel
-                    let mut uniforms = UniformData::new();
-                    uniforms.texture("fpf_color".to_owned(), "from_tex");
-
-                    cp.add_dispatch(ComputeDispatch::new(
-                        ShaderIdentifier::Sobel,
-                        uniforms,
-                        "sobel_output",
-                        (cx.screen_dimensions.0, cx.screen_dimensions.1, 1),
-                    ));
-
-                    // Artsyfartsy
-                    let mut uniforms = UniformData::new();
-                    uniforms.texture("sobel_output".to_owned(), "sobel_tex");
-                    uniforms.texture("fpf_depth".to_owned(), "depth_tex");
-                    uniforms.texture("fpf_color".to_owned(), "color_tex");
-
-                    cp.add_dispatch(ComputeDispatch::new(
-                        ShaderIdentifier::Artsyfartsy,
-                        uniforms,
-                        "artsy_output",
-                        (cx.screen_dimensions.0, cx.screen_dimensions.1, 1),
-                    ));
-
-                    messages.merge_current(cp.get_messages());
+                    match post_processing.reload_if_changed() {
+                        Ok(true) => disable_passes_without_textures(&mut post_processing, cx),
+                        Ok(false) => {}
+                        Err(error) => error!("Failed to reload post processing! {}", error),
+                    }
+                    let config = post_processing.config();
+                    messages.merge_current(
+                        ComputePipeline::from_config(config, cx.screen_dimensions).get_messages(),
+                    );
+                    messages.merge_current(config.composite_messages());
                 }
-
-                // Framebuffer test code (Renders the texture sobel_output to screen)
-                messages.add_message(RenderMessage::ChooseShader {
-                    shader: ShaderIdentifier::Simple,
-                });
-                let mut ud = UniformData::new();
-                ud.texture("artsy_output".to_owned(), "tex");
-                messages.add_message(RenderMessage::Uniforms {
-                    uniforms: Box::new(ud),
-                });
-                messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
-                messages.add_message(RenderMessage::ClearBuffers {
-                    color_buffer: true,
-                    depth_buffer: true,
-                });
-                messages.add_message(RenderMessage::Pack {
-                    buffer: BufferTarget::WorldBuffer(80),
-                    pack: VertexPack::new(
-                        vec![
-                            Vertex3D {
-                                x: -1.,
-                                y: -1.,
-                                z: -1.,
-                                r: 1.,
-                                g: 1.,
-                                b: 1.,
-                                u: 0.,
-                                v: 0.,
-                            },
-                            Vertex3D {
-                                x: 1.,
-                                y: -1.,
-                                z: -1.,
-                                r: 1.,
-                                g: 1.,
-                                b: 1.,
-                                u: 1.,
-                                v: 0.,
-                            },
-                            Vertex3D {
-                                x: 1.,
-                                y: 1.,
-                                z: -1.,
-                                r: 1.,
-                                g: 1.,
-                                b: 1.,
-                                u: 1.,
-                                v: 1.,
-                            },
-                            Vertex3D {
-                                x: -1.,
-                                y: 1.,
-                                z: -1.,
-                                r: 1.,
-                                g: 1.,
-                                b: 1.,
-                                u: 0.,
-                                v: 1.,
-                            },
-                        ],
-                        Some(vec![0, 1, 2, 0, 2, 3]),
-                    ),
-                });
-                messages.add_message(RenderMessage::Draw {
-                    buffer: BufferTarget::WorldBuffer(80),
-                });
-                messages.add_message(RenderMessage::ClearArray {
-                    buffer: BufferTarget::WorldBuffer(80),
-                });
                 messages.add_message(RenderMessage::ChooseShader {
                     shader: ShaderIdentifier::Color,
                 });
@@ -268,14 +211,14 @@ pub fn start_packing_thread(
                     depth_buffer: true,
                 });
                 messages.add_message(RenderMessage::Pack {
-                    buffer: BufferTarget::WorldBuffer(80),
+                    buffer: BufferTarget::ScratchBuffer,
                     pack: get_reticle_pack(),
                 });
                 messages.add_message(RenderMessage::Draw {
-                    buffer: BufferTarget::WorldBuffer(80),
+                    buffer: BufferTarget::ScratchBuffer,
                 });
                 messages.add_message(RenderMessage::ClearArray {
-                    buffer: BufferTarget::WorldBuffer(80),
+                    buffer: BufferTarget::ScratchBuffer,
                 });
             }
 
@@ -287,8 +230,15 @@ pub fn start_packing_thread(
             } else {
             }
 
-            // Validate render messages. Doesn't run in release builds.
-            validate(&mut validator, &state, &messages);
+            // Validate render messages. Doesn't run in release builds unless asked to.
+            if let Some(validator) = validator.as_mut() {
+                validator.validate(state.render_capabilities().as_ref(), &messages);
+            }
+
+            if let Some(Err(error)) = recorder.as_mut().map(|r| r.record_frame(&messages)) {
+                error!("Stopped recording render messages. Error: {:?}", error);
+                recorder = None;
+            }
 
             *message_mutex = Some(messages);
         }
@@ -0,0 +1,288 @@
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use graphics::{GraphicsCapabilities, RenderMessages};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// When this environment variable is set, the packing thread records the render messages it sends
+/// to the window to the file it names, so they can be replayed with `--replay`.
+pub const RECORD_RENDER_VARIABLE: &str = "FLEXBLOCK_RECORD_RENDER";
+
+/// One entry in a render recording.
+#[derive(Serialize, Deserialize)]
+pub enum RecordedEvent {
+    /// The window supplied new capabilities. Frames after this are validated against them.
+    Capabilities(GraphicsCapabilities),
+    /// The render messages of one frame, as they were handed to the window.
+    Frame(RenderMessages),
+}
+
+/// Borrowing version of [`RecordedEvent`], so nothing has to be cloned to record it.
+/// The variants have to match those of [`RecordedEvent`], since it is read back as one.
+#[derive(Serialize)]
+enum RecordedEventRef<'a> {
+    Capabilities(&'a GraphicsCapabilities),
+    Frame(&'a RenderMessages),
+}
+
+/// Writes the render messages of every frame to a compressed bincode stream.
+pub struct RenderRecorder<W: Write = BufWriter<File>> {
+    encoder: DeflateEncoder<W>,
+}
+
+impl RenderRecorder {
+    /// Starts recording to the file named by `FLEXBLOCK_RECORD_RENDER`.
+    /// Returns None if the variable isn't set or the file can't be created.
+    pub fn from_env() -> Option<RenderRecorder> {
+        let path = std::env::var_os(RECORD_RENDER_VARIABLE)?;
+        let path = Path::new(&path);
+        if let Some(parent) = path.parent() {
+            if let Err(error) = std::fs::create_dir_all(parent) {
+                error!(
+                    "Could not create directory for render recording. Error: {:?}",
+                    error
+                );
+                return None;
+            }
+        }
+        match File::create(path) {
+            Ok(file) => {
+                info!("Recording render messages to {}", path.display());
+                Some(RenderRecorder::new(BufWriter::new(file)))
+            }
+            Err(error) => {
+                error!("Could not create render recording. Error: {:?}", error);
+                None
+            }
+        }
+    }
+}
+
+impl<W: Write> RenderRecorder<W> {
+    pub fn new(writer: W) -> RenderRecorder<W> {
+        RenderRecorder {
+            encoder: DeflateEncoder::new(writer, Compression::fast()),
+        }
+    }
+
+    pub fn record_capabilities(
+        &mut self,
+        capabilities: &GraphicsCapabilities,
+    ) -> Result<(), bincode::Error> {
+        self.record(RecordedEventRef::Capabilities(capabilities))
+    }
+
+    /// Records the render messages of a frame.
+    /// Every frame is flushed, so the recording survives the program crashing.
+    pub fn record_frame(&mut self, messages: &RenderMessages) -> Result<(), bincode::Error> {
+        self.record(RecordedEventRef::Frame(messages))
+    }
+
+    fn record(&mut self, event: RecordedEventRef) -> Result<(), bincode::Error> {
+        bincode::serialize_into(&mut self.encoder, &event)?;
+        self.encoder.flush()?;
+        Ok(())
+    }
+
+    /// The writer the recording is written to. Every recorded event has already reached it.
+    pub fn get_ref(&self) -> &W {
+        self.encoder.get_ref()
+    }
+
+    /// Finishes the compressed stream and returns the writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.encoder.finish()
+    }
+}
+
+/// Reads the events of a render recording in the order they were recorded.
+/// A recording cut short by a crash ends at the last complete event.
+pub struct RenderRecordingReader<R: Read> {
+    decoder: DeflateDecoder<EndTracker<R>>,
+    done: bool,
+}
+
+/// Remembers whether the reader it wraps ran out of input.
+/// The decoder reports a deflate stream that stops halfway through the same way as a corrupt one,
+/// so this tells the two apart.
+struct EndTracker<R: Read> {
+    reader: R,
+    reached_end: bool,
+}
+
+impl<R: Read> Read for EndTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            self.reached_end = true;
+        }
+        Ok(read)
+    }
+}
+
+impl RenderRecordingReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<RenderRecordingReader<BufReader<File>>> {
+        Ok(RenderRecordingReader::new(BufReader::new(File::open(
+            path,
+        )?)))
+    }
+}
+
+impl<R: Read> RenderRecordingReader<R> {
+    pub fn new(reader: R) -> RenderRecordingReader<R> {
+        RenderRecordingReader {
+            decoder: DeflateDecoder::new(EndTracker {
+                reader,
+                reached_end: false,
+            }),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for RenderRecordingReader<R> {
+    type Item = Result<RecordedEvent, bincode::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match bincode::deserialize_from(&mut self.decoder) {
+            Ok(event) => Some(Ok(event)),
+            Err(error) => {
+                self.done = true;
+                match *error {
+                    // Every event is flushed as it is recorded, so a cut off recording only
+                    // breaks the deflate stream after the last complete event.
+                    bincode::ErrorKind::Io(ref io_error)
+                        if self.decoder.get_ref().reached_end
+                            && matches!(
+                                io_error.kind(),
+                                io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidInput
+                            ) =>
+                    {
+                        None
+                    }
+                    _ => Some(Err(error)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::TextureAtlas;
+    use graphics::{BufferTarget, RenderMessage, ShaderIdentifier, UniformData, VertexPack};
+    use std::collections::HashMap;
+    use utils::Vertex3D;
+
+    fn capabilities() -> GraphicsCapabilities {
+        GraphicsCapabilities {
+            vbo_count: 200,
+            texture_metadata: HashMap::new(),
+            shader_metadata: Vec::new(),
+            framebuffer_metadata: Vec::new(),
+            block_atlas: TextureAtlas::default(),
+            screen_dimensions: (1024, 768),
+        }
+    }
+
+    fn frame() -> RenderMessages {
+        let mut messages = RenderMessages::new();
+        messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: VertexPack::new(
+                vec![
+                    Vertex3D {
+                        x: 1.,
+                        y: 2.,
+                        z: 3.,
+                        r: 0.5,
+                        ..Vertex3D::default()
+                    };
+                    3
+                ],
+                Some(vec![0, 1, 2]),
+            ),
+        });
+        messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Chunk,
+        });
+        let mut uniforms = UniformData::new();
+        uniforms.mat4(glm::Mat4::identity(), "vp");
+        uniforms.texture("block_atlas".to_owned(), "atlas");
+        messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(uniforms),
+        });
+        messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+        messages
+    }
+
+    #[test]
+    fn recordings_read_back_unchanged() {
+        let mut recorder = RenderRecorder::new(Vec::new());
+        recorder.record_capabilities(&capabilities()).unwrap();
+        recorder.record_frame(&frame()).unwrap();
+        recorder.record_frame(&RenderMessages::new()).unwrap();
+        let recording = recorder.finish().unwrap();
+
+        let events: Vec<RecordedEvent> = RenderRecordingReader::new(recording.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        match &events[0] {
+            RecordedEvent::Capabilities(read) => assert_eq!(
+                bincode::serialize(read).unwrap(),
+                bincode::serialize(&capabilities()).unwrap()
+            ),
+            RecordedEvent::Frame(_) => panic!("Expected capabilities first"),
+        }
+        match &events[1] {
+            RecordedEvent::Frame(read) => assert_eq!(
+                bincode::serialize(read).unwrap(),
+                bincode::serialize(&frame()).unwrap()
+            ),
+            RecordedEvent::Capabilities(_) => panic!("Expected a frame"),
+        }
+        assert!(matches!(&events[2], RecordedEvent::Frame(read) if read.is_empty()));
+    }
+
+    #[test]
+    fn truncated_recordings_end_at_last_complete_event() {
+        let mut recorder = RenderRecorder::new(Vec::new());
+        recorder.record_frame(&frame()).unwrap();
+        let first_frame_length = recorder.encoder.get_ref().len();
+        recorder.record_frame(&frame()).unwrap();
+        let second_frame_length = recorder.encoder.get_ref().len();
+        let recording = recorder.finish().unwrap();
+
+        // The flush marker ending each event isn't needed to decode the event before it.
+        let flush_marker_length = 5;
+        for length in first_frame_length..second_frame_length - flush_marker_length {
+            let events: Vec<RecordedEvent> = RenderRecordingReader::new(&recording[..length])
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(events.len(), 1, "Recording cut after {} bytes", length);
+        }
+    }
+
+    #[test]
+    fn corrupt_recordings_are_errors() {
+        let mut recorder = RenderRecorder::new(Vec::new());
+        recorder.record_frame(&frame()).unwrap();
+        recorder.record_frame(&frame()).unwrap();
+        let mut recording = recorder.finish().unwrap();
+        // Marks the first deflate block with the reserved block type.
+        recording[0] |= 0b110;
+
+        let mut events = RenderRecordingReader::new(recording.as_slice());
+        assert!(matches!(events.next(), Some(Err(_))));
+        assert!(events.next().is_none());
+    }
+}
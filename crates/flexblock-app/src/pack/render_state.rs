@@ -1,100 +1,64 @@
+use super::block_textures::{BlockTextures, TILE_STRIDE};
+use super::chunk_buffers::ChunkBufferAllocator;
+use super::chunk_mesher;
+use super::culling::{self, ChunkVisibility, Frustum};
+use super::mesh_workers::MeshWorkerPool;
+use super::shadows::{self, ShadowCascade};
 use game::{GraphicsStateModel, View};
 use graphics::model::{ModelManager, PlacedModel};
 use graphics::BufferTarget;
-use graphics::ShaderIdentifier;
 use graphics::VertexPack;
+use graphics::{FramebufferIdentifier, ShaderIdentifier};
 use graphics::{GraphicsCapabilities, RenderMessage, RenderMessages, UniformData};
+use graphics::{BLOCK_ATLAS_TEXTURE, VERTEX_BUFFER_METADATA};
 use konst::{option::unwrap_or, primitive::parse_u32, result::unwrap_ctx};
 use log::error;
-use std::collections::VecDeque;
-use std::sync::MutexGuard;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, MutexGuard};
 use utils::mesh_iterator::MeshIterator;
-use world::{self, Chunk, Terrain};
-
-/// This creates the vertex pack for a specific chunk. It just goes through all the voxels and adds their faces.
-fn create_chunk_pack(chunk: &Chunk) -> VertexPack {
-    let mut vertices = Vec::new();
-    let mut elements = Vec::new();
-    let mut index = 0;
-    for (voxel, position) in chunk.iter() {
-        let x0 = position.x;
-        let x1 = x0 + 1.;
-        let y0 = position.y;
-        let y1 = y0 + 1.;
-        let z0 = position.z;
-        let z1 = z0 + 1.;
-
-        if voxel.0 == 1 {
-            // Back face
-            let (mut vadd, mut eadd) =
-                graphics::pack::cube_faces::back(z0, x0, y0, x1, y1, 1., 0., 0., index);
-            index += vadd.len() as u32;
-            vertices.append(&mut vadd);
-            elements.append(&mut eadd);
-
-            //Front face
-            let (mut vadd, mut eadd) =
-                graphics::pack::cube_faces::front(z1, x0, y0, x1, y1, 0., 1., 0., index);
-            index += vadd.len() as u32;
-            vertices.append(&mut vadd);
-            elements.append(&mut eadd);
-
-            //Left face
-            let (mut vadd, mut eadd) =
-                graphics::pack::cube_faces::left(x0, y0, z0, y1, z1, 0., 0., 1., index);
-            index += vadd.len() as u32;
-            vertices.append(&mut vadd);
-            elements.append(&mut eadd);
-
-            //Right face
-            let (mut vadd, mut eadd) =
-                graphics::pack::cube_faces::right(x1, y0, z0, y1, z1, 1., 1., 0., index);
-            index += vadd.len() as u32;
-            vertices.append(&mut vadd);
-            elements.append(&mut eadd);
-
-            //Bottom face
-            let (mut vadd, mut eadd) =
-                graphics::pack::cube_faces::bottom(y0, x0, z0, x1, z1, 1., 0., 1., index);
-            index += vadd.len() as u32;
-            vertices.append(&mut vadd);
-            elements.append(&mut eadd);
-
-            //Top face
-            let (mut vadd, mut eadd) =
-                graphics::pack::cube_faces::top(y1, x0, z0, x1, z1, 0., 1., 1., index);
-            index += vadd.len() as u32;
-            vertices.append(&mut vadd);
-            elements.append(&mut eadd);
-        }
-    }
-    VertexPack::new(vertices, Some(elements))
-}
+use world::chunk::CHUNK_SIZE;
+use world::{self, Location, Terrain, Transparency};
+
+/// The distance from the camera to the near plane of its projection.
+pub const NEAR_PLANE: f32 = 0.1;
+/// The distance from the camera to the far plane of its projection.
+pub const FAR_PLANE: f32 = 100.;
 
 /// Returns the view * projection matrix of the supplied camera.
 /// Doesn't get us all the way to mvp (multiply this by the model matrix, and you're there boyo).
+///
+/// The matrix works in coordinates relative to the origin of the chunk the camera is in, so it
+/// is as precise far from the world origin as close to it. Model matrices have to be made
+/// relative to the same chunk, see `chunk_offset` and `camera_relative_position`.
 pub fn get_vp_matrix(view: &View, screen_dimensions: (u32, u32)) -> glm::Mat4 {
-    let (width, height) = screen_dimensions;
+    get_projection_matrix(screen_dimensions, NEAR_PLANE, FAR_PLANE) * get_view_matrix(view)
+}
 
+/// Returns the view matrix of the camera, relative to the chunk it is in like `get_vp_matrix`.
+pub fn get_view_matrix(view: &View) -> glm::Mat4 {
     let direction = view.view_direction();
-    let position = view.location().as_coords();
+    let position = view.location().position;
     let center = position + direction;
     let up = view.up();
 
-    let v: glm::Mat4 = glm::look_at(
+    glm::look_at(
         &glm::vec3(position[0], position[1], position[2]),
         &glm::vec3(center[0], center[1], center[2]),
         &glm::vec3(up[0], up[1], up[2]),
-    );
-    let p: glm::Mat4 = glm::perspective_fov(
+    )
+}
+
+/// Returns the projection matrix of the camera between the given distances from it.
+/// Shadow cascades use it to find the part of the view each of them covers.
+pub fn get_projection_matrix(screen_dimensions: (u32, u32), near: f32, far: f32) -> glm::Mat4 {
+    let (width, height) = screen_dimensions;
+    glm::perspective_fov(
         90. / 180. * std::f32::consts::PI,
         width as f32,
         height as f32,
-        0.1,
-        100.0,
-    ); //TODO: CORRECT FOV, WIDTH, AND HEIGHT
-
-    p * v
+        near,
+        far,
+    ) //TODO: CORRECT FOV, WIDTH, AND HEIGHT
 }
 
 /// Returns a model matrix.
@@ -103,6 +67,18 @@ pub fn get_model_matrix(offset: &glm::Vec3) -> glm::Mat4 {
     glm::translation(offset)
 }
 
+/// The position of the origin of the chunk relative to the origin of the chunk the camera is in.
+/// The difference is taken in chunks before converting to voxels, so it is exact no matter how
+/// far both chunks are from the world origin.
+pub fn chunk_offset(chunk: glm::IVec3, camera_chunk: glm::IVec3) -> glm::Vec3 {
+    world::chunk_index_to_position(chunk - camera_chunk)
+}
+
+/// The position of the location relative to the origin of the chunk the camera is in.
+pub fn camera_relative_position(location: Location, camera_chunk: glm::IVec3) -> glm::Vec3 {
+    chunk_offset(location.chunk, camera_chunk) + location.position
+}
+
 /// Breadth first search
 struct BreadthFirstSearch {
     visited_locations: Vec<glm::IVec3>,
@@ -153,144 +129,247 @@ impl BreadthFirstSearch {
     }
 }
 
+/// The largest number of chunk meshes packed per update.
+/// Read from an environment variable so it can be tuned to the machine. Default is 8.
+const PACKS_PER_UPDATE: usize = unwrap_ctx!(parse_u32(unwrap_or!(
+    option_env!("FLEXBLOCK_PACKS_PER_UPDATE"),
+    "8"
+))) as usize;
+
+/// Fragments of cutout voxels less opaque than this are not drawn.
+const ALPHA_CUTOFF: f32 = 0.5;
+
 /// Contains state information that is needed by the packing thread.
 pub struct RenderState {
-    /// Contains a list of packed chunk locations. The index is which buffer they're packed into. None means no chunk is packed into that buffer.
-    pub(super) packed_chunks: Vec<Option<glm::IVec3>>,
-    pub(super) capabilities: Option<GraphicsCapabilities>,
+    /// Keeps track of which chunk is packed into each world buffer.
+    chunk_buffers: ChunkBufferAllocator,
+    /// Chunks that have been meshed but couldn't be packed because every buffer was taken,
+    /// or that lost their buffer to a closer chunk.
+    unpacked_chunks: HashSet<glm::IVec3>,
+    /// Chunks within this many chunks of the view are packed. Everything further away is cleared.
+    render_radius: f32,
+    /// The generation of the terrain around each chunk when it was last meshed.
+    /// Contains every chunk within render distance that has been meshed, including empty ones.
+    mesh_generations: HashMap<glm::IVec3, u64>,
+    /// The generation of the terrain around each chunk that is currently being meshed.
+    pending_generations: HashMap<glm::IVec3, u64>,
+    /// Which faces of each meshed chunk can be seen through from each other.
+    chunk_visibility: HashMap<glm::IVec3, ChunkVisibility>,
+    /// The chunk the view was in when each packed chunk with translucent voxels was meshed.
+    /// Their translucent faces are sorted back to front from there, so they're meshed again
+    /// when the view moves to another chunk.
+    sorted_for: HashMap<glm::IVec3, glm::IVec3>,
+    /// The atlas tiles chunk faces are textured with. Follows the block atlas of the window.
+    block_textures: Arc<BlockTextures>,
+    mesh_workers: MeshWorkerPool,
+    capabilities: Option<GraphicsCapabilities>,
 }
 
 /// Keeps track of all state necessary to correctly supply graphics calls to the window each frame.
 impl RenderState {
     pub fn new() -> RenderState {
-        //TODO: Buffer space should be sized according to the number of buffers in the target VertexArray. This should coordinate.
-        let packed_chunks = vec![None; 0];
-
         RenderState {
-            packed_chunks,
+            chunk_buffers: ChunkBufferAllocator::new(),
+            unpacked_chunks: HashSet::new(),
+            render_radius: game::DEFAULT_RENDER_RADIUS,
+            mesh_generations: HashMap::new(),
+            pending_generations: HashMap::new(),
+            chunk_visibility: HashMap::new(),
+            sorted_for: HashMap::new(),
+            block_textures: Arc::default(),
+            mesh_workers: MeshWorkerPool::with_available_parallelism(),
             capabilities: None,
         }
     }
 
-    /// Registers that a chunk has been packed into the given buffer
-    fn register_packed_chunk(&mut self, location: glm::IVec3, buffer: usize) {
-        /*debug_assert!(
-            buffer < self.packed_chunks.len(),
-            "Trying to pack a chunk into a buffer outside buffer range!"
-        );
-        debug_assert!(
-            self.packed_chunks[buffer].is_none(),
-            "Trying to pack a chunk into an already taken buffer slot!"
-        );*/
-
-        self.packed_chunks[buffer] = Some(location);
-    }
-
-    /// Deregisters that a chunk has been packed into the given buffer.
-    fn unregister_packed_chunk(&mut self, buffer: usize) {
-        /*debug_assert!(
-            buffer < self.packed_chunks.len(),
-            "Trying to pack a chunk into a buffer outside buffer range!"
-        );
-        debug_assert!(
-            self.packed_chunks[buffer].is_some(),
-            "Trying to remove a chunk from a buffer that does not currently have a bound chunk!"
-        );*/
-
-        self.packed_chunks[buffer] = None;
-    }
-
-    /// Finds a free VBO available for chunk packing
-    fn find_free_location(&self) -> Option<usize> {
-        for (counter, c) in self.packed_chunks.iter().enumerate() {
-            if c.is_none() {
-                return Some(counter);
+    /// Packs a finished chunk mesh, replacing the chunk's previous mesh if it is packed.
+    /// Each transparency gets its own buffer, and a chunk is either packed completely or not at all.
+    /// If every buffer is taken by chunks closer to the center, the chunk is left unpacked.
+    fn pack_mesh(
+        &mut self,
+        location: glm::IVec3,
+        packs: Vec<(Transparency, VertexPack)>,
+        center: glm::IVec3,
+        messages: &mut RenderMessages,
+    ) -> Result<(), String> {
+        self.unpack_chunk_buffers(location, messages)?;
+        self.unpacked_chunks.remove(&location);
+        for (transparency, pack) in packs {
+            match self.chunk_buffers.allocate(location, transparency, center) {
+                Some((buffer, evicted)) => {
+                    if let Some(evicted) = evicted {
+                        messages.add_message(RenderMessage::ClearArray {
+                            buffer: BufferTarget::WorldBuffer(buffer),
+                        });
+                        self.unpack_chunk_buffers(evicted, messages)?;
+                        self.unpacked_chunks.insert(evicted);
+                    }
+                    messages.add_message(RenderMessage::Pack {
+                        buffer: BufferTarget::WorldBuffer(buffer),
+                        pack,
+                    });
+                }
+                None => {
+                    self.unpack_chunk_buffers(location, messages)?;
+                    self.unpacked_chunks.insert(location);
+                    break;
+                }
             }
         }
-        None
+        Ok(())
     }
 
-    /// Packs a given chunk
-    fn pack_chunk(
+    /// Unpacks every buffer the chunk is packed into.
+    fn unpack_chunk_buffers(
         &mut self,
-        chunk: &Chunk,
         location: glm::IVec3,
         messages: &mut RenderMessages,
     ) -> Result<(), String> {
-        if self.packed_chunks.contains(&Some(location)) {
-            return Err(String::from("The given chunk has already been packed"));
-        }
-        if let Some(buffer) = self.find_free_location() {
-            let pack = create_chunk_pack(chunk);
-            if !pack.vertices.is_empty() {
-                messages.add_message(RenderMessage::Pack {
-                    buffer: BufferTarget::WorldBuffer(buffer),
-                    pack: create_chunk_pack(chunk),
-                });
-                self.register_packed_chunk(location, buffer);
-            }
-
-            Ok(())
-        } else {
-            Err(String::from("No buffer available for passed chunk!"))
+        for buffer in self.chunk_buffers.buffers_of(location) {
+            self.unpack_chunk(buffer, messages)?;
         }
+        Ok(())
     }
 
     /// Unpacks a given chunk
     fn unpack_chunk(&mut self, buffer: usize, messages: &mut RenderMessages) -> Result<(), String> {
-        if self.packed_chunks[buffer].is_none() {
+        if self.chunk_buffers.free(buffer).is_none() {
             return Err(String::from("Unpacking an unpacked buffer!"));
         }
         messages.add_message(RenderMessage::ClearArray {
             buffer: BufferTarget::WorldBuffer(buffer),
         });
-
-        self.unregister_packed_chunk(buffer);
         Ok(())
     }
 
     /// Tells whether a chunk is currently packed
     pub fn is_packed(&self, location: glm::IVec3) -> bool {
-        self.packed_chunks.contains(&Some(location))
+        !self.chunk_buffers.buffers_of(location).is_empty()
+    }
+
+    /// Sets the radius in chunks around the view within which chunks are packed.
+    /// Chunks outside the new radius are cleared the next time render messages are created.
+    pub fn set_render_radius(&mut self, render_radius: f32) {
+        self.render_radius = render_radius.max(0.);
     }
 
-    /// Packs a chunk if it hasn't been packed before, or unpacks and packs it if it has been packed before.
-    pub fn repack_chunk(
+    /// The largest squared distance in chunks from the view at which chunks are packed.
+    fn max_distance_squared(&self) -> i32 {
+        (self.render_radius * self.render_radius).floor() as i32
+    }
+
+    /// Requests a new mesh for the chunk if it hasn't been meshed yet, if it or one of its
+    /// neighbours has been modified since it was last meshed, or if its translucent faces were
+    /// sorted for the view in another chunk than `center`.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The terrain shared by all requests made this update.
+    ///   Is created from `terrain` the first time a mesh is requested.
+    fn update_chunk(
         &mut self,
         location: glm::IVec3,
-        messages: &mut RenderMessages,
+        center: glm::IVec3,
         terrain: &Terrain,
+        snapshot: &mut Option<Arc<Terrain>>,
     ) {
-        for (counter, chunk) in self.packed_chunks.iter().enumerate() {
-            if let Some(chunk) = chunk {
-                if *chunk == location {
-                    self.unregister_packed_chunk(counter);
-                    messages.add_message(RenderMessage::ClearArray {
-                        buffer: BufferTarget::WorldBuffer(counter),
-                    });
-                    break;
+        let generation = chunk_mesher::neighbourhood_generation(terrain, location);
+        let sorted = self
+            .sorted_for
+            .get(&location)
+            .is_none_or(|&sorted_for| sorted_for == center);
+        if (self.mesh_generations.get(&location) == Some(&generation) && sorted)
+            || self.pending_generations.get(&location) == Some(&generation)
+        {
+            return;
+        }
+        let snapshot = snapshot.get_or_insert_with(|| Arc::new(terrain.clone()));
+        self.mesh_workers.request(
+            location,
+            generation,
+            Arc::clone(snapshot),
+            Arc::clone(&self.block_textures),
+        );
+        self.pending_generations.insert(location, generation);
+    }
+
+    /// Packs meshes finished by the mesh workers, at most `PACKS_PER_UPDATE` of them.
+    /// Meshes made from outdated terrain are thrown away.
+    fn collect_meshes(&mut self, center: glm::IVec3, messages: &mut RenderMessages) {
+        let mut packed = 0;
+        while packed < PACKS_PER_UPDATE {
+            let result = match self.mesh_workers.try_recv() {
+                Some(result) => result,
+                None => break,
+            };
+            if self.pending_generations.get(&result.location) != Some(&result.generation) {
+                continue;
+            }
+            self.pending_generations.remove(&result.location);
+            self.chunk_visibility
+                .insert(result.location, result.visibility);
+            if result
+                .packs
+                .iter()
+                .any(|(transparency, _)| *transparency == Transparency::Translucent)
+            {
+                self.sorted_for.insert(result.location, result.sorted_for);
+            } else {
+                self.sorted_for.remove(&result.location);
+            }
+            match self.pack_mesh(result.location, result.packs, center, messages) {
+                Ok(()) => {
+                    self.mesh_generations
+                        .insert(result.location, result.generation);
                 }
+                Err(s) => error!("{}", s),
             }
+            packed += 1;
         }
+    }
 
-        if let Some(chunk) = terrain.chunk(location) {
-            if let Err(s) = self.pack_chunk(chunk, location, messages) {
-                error!("{}", s);
-            }
+    /// Lets the nearest chunks that were left unpacked be meshed again if buffers have been
+    /// freed since, for example because the view moved away from other chunks.
+    fn retry_unpacked_chunks(&mut self, center: glm::IVec3) {
+        let free = self
+            .chunk_buffers
+            .free_count()
+            .saturating_sub(self.pending_generations.len());
+        if free == 0 || self.unpacked_chunks.is_empty() {
+            return;
+        }
+        let mut unpacked: Vec<glm::IVec3> = self.unpacked_chunks.iter().copied().collect();
+        unpacked.sort_by_key(|chunk| {
+            let v = chunk - center;
+            v.x * v.x + v.y * v.y + v.z * v.z
+        });
+        for location in unpacked.into_iter().take(free) {
+            self.unpacked_chunks.remove(&location);
+            self.mesh_generations.remove(&location);
         }
     }
 
-    /// Clears chunks that are further than four chunks from the given location.
+    /// Clears chunks that are outside the render radius around the given location.
     pub fn clear_distant_chunks(&mut self, location: glm::IVec3, messages: &mut RenderMessages) {
-        let mut remove = Vec::new();
-        for (counter, chunk) in self.packed_chunks.iter().enumerate() {
-            if let Some(chunk) = chunk {
-                let v = chunk - location;
-                if v.x * v.x + v.y * v.y + v.z * v.z > 4 {
-                    remove.push(counter);
-                }
-            }
-        }
+        let max_distance_squared = self.max_distance_squared();
+        let is_distant = |chunk: &glm::IVec3| {
+            let v = chunk - location;
+            v.x * v.x + v.y * v.y + v.z * v.z > max_distance_squared
+        };
+        let remove: Vec<usize> = self
+            .chunk_buffers
+            .iter()
+            .filter(|(_, chunk, _)| is_distant(chunk))
+            .map(|(buffer, _, _)| buffer)
+            .collect();
+        self.unpacked_chunks.retain(|chunk| !is_distant(chunk));
+        self.mesh_generations.retain(|chunk, _| !is_distant(chunk));
+        self.pending_generations
+            .retain(|chunk, _| !is_distant(chunk));
+        self.chunk_visibility.retain(|chunk, _| !is_distant(chunk));
+        self.sorted_for.retain(|chunk, _| !is_distant(chunk));
+        self.mesh_workers
+            .retain_requests(|chunk| !is_distant(&chunk));
         for i in remove {
             match self.unpack_chunk(i, messages) {
                 Ok(()) => {}
@@ -301,47 +380,163 @@ impl RenderState {
         }
     }
 
-    /// This renders all currently packed chunks
-    /// TODO: This needs to work in local coordinates out from the player.
-    pub fn render_packed_chunks(
+    /// The uniforms for texturing chunks from the block atlas, which the chunk and shadow shaders share.
+    fn block_atlas_uniforms(&self, alpha_cutoff: f32) -> UniformData {
+        let (columns, rows) = self.block_textures.grid_size();
+        let mut ud = UniformData::new();
+        ud.texture(String::from(BLOCK_ATLAS_TEXTURE), "block_atlas");
+        ud.float(TILE_STRIDE, "tile_stride");
+        ud.vec2(glm::vec2(columns as f32, rows as f32), "atlas_tiles");
+        ud.float(alpha_cutoff, "alpha_cutoff");
+        ud
+    }
+
+    /// Draws a packed chunk with the chunk shader.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The position of the chunk relative to the chunk the camera is in.
+    fn draw_chunk(
         &self,
         render_messages: &mut RenderMessages,
         vp_matrix: &glm::Mat4,
+        buffer: usize,
+        offset: glm::Vec3,
+        alpha_cutoff: f32,
     ) {
-        for (counter, location) in self.packed_chunks.iter().enumerate() {
-            if let Some(location) = location {
-                let mvp = glm::translate(vp_matrix, &world::chunk_index_to_position(*location));
-                let mut ud = UniformData::new();
-                ud.texture(String::from("/atlas.png"), String::from("test_texture"));
-                ud.mat4(mvp, String::from("MVP"));
+        let mvp = glm::translate(vp_matrix, &offset);
+        let mut ud = self.block_atlas_uniforms(alpha_cutoff);
+        ud.mat4(mvp, String::from("MVP"));
+        ud.vec3(offset, "chunk_offset");
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(buffer),
+        });
+    }
+
+    /// Renders the packed chunks into the shadow map of each cascade with the shadow shader.
+    /// Only chunks within a cascade are drawn into it. Translucent voxels don't cast shadows,
+    /// and cutout voxels only cast them where they are opaque.
+    fn render_shadow_maps(
+        &self,
+        render_messages: &mut RenderMessages,
+        cascades: &[ShadowCascade],
+        center: glm::IVec3,
+    ) {
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Shadow,
+        });
+        for cascade in cascades {
+            render_messages.add_message(RenderMessage::ChooseFramebuffer {
+                framebuffer: Some(cascade.framebuffer),
+            });
+            render_messages.add_message(RenderMessage::ClearBuffers {
+                color_buffer: false,
+                depth_buffer: true,
+            });
+            let frustum = Frustum::from_matrix(&cascade.view_projection);
+            for (buffer, location, transparency) in self.chunk_buffers.iter() {
+                if transparency == Transparency::Translucent
+                    || !frustum.intersects_chunk(location - center)
+                {
+                    continue;
+                }
+                // Cutout voxels are tested like when they are drawn, so light gets through their gaps.
+                let alpha_cutoff = if transparency == Transparency::Cutout {
+                    ALPHA_CUTOFF
+                } else {
+                    0.
+                };
+                let mut ud = self.block_atlas_uniforms(alpha_cutoff);
+                ud.mat4(
+                    glm::translate(&cascade.view_projection, &chunk_offset(location, center)),
+                    String::from("MVP"),
+                );
                 render_messages.add_message(RenderMessage::Uniforms {
                     uniforms: Box::new(ud),
                 });
-
                 render_messages.add_message(RenderMessage::Draw {
-                    buffer: BufferTarget::WorldBuffer(counter),
+                    buffer: BufferTarget::WorldBuffer(buffer),
                 });
             }
         }
     }
 
-    /// When the window supplies new capabilities, update local capabilities to those.
-    pub fn update_capabilities(&mut self, capabilities: GraphicsCapabilities) {
-        let vbo_count = match self.capabilities.take() {
-            Some(cap2) => cap2.vbo_count,
-            None => 0,
+    /// This renders the packed chunks that may be visible from the view.
+    /// Chunks outside the view frustum or hidden behind opaque chunks are skipped.
+    ///
+    /// Opaque voxels are drawn first, then cutout voxels, and last translucent voxels with
+    /// blending, from the furthest chunk to the closest.
+    /// The view projection matrix has to be relative to the chunk the view is in, like the one
+    /// from `get_vp_matrix`.
+    pub fn render_packed_chunks(
+        &self,
+        render_messages: &mut RenderMessages,
+        vp_matrix: &glm::Mat4,
+        view: Location,
+    ) {
+        let center = view.chunk;
+        let frustum = Frustum::from_matrix(vp_matrix);
+        // Chunks that haven't been meshed yet might be seen through, so they're treated as empty.
+        let max_distance_squared = self.max_distance_squared();
+        let visible = culling::visible_chunks(center, &frustum, max_distance_squared, |location| {
+            self.chunk_visibility
+                .get(&location)
+                .copied()
+                .unwrap_or(ChunkVisibility::ALL)
+        });
+        // Each visible buffer of the transparency with the offset of its chunk from the view.
+        let visible_buffers = |transparency: Transparency| -> Vec<(usize, glm::Vec3)> {
+            self.chunk_buffers
+                .iter()
+                .filter(|(_, location, other)| *other == transparency && visible.contains(location))
+                .map(|(buffer, location, _)| (buffer, chunk_offset(location, center)))
+                .collect()
         };
 
-        match capabilities.vbo_count {
-            c if c > vbo_count => {
-                self.packed_chunks.append(&mut vec![None; c - vbo_count]);
-            }
-            c if c < vbo_count => {
-                panic!(
-                    "There is currently no support for a shrink in the number of available VBOs"
-                );
-            }
-            _ => {}
+        for (buffer, offset) in visible_buffers(Transparency::Opaque) {
+            self.draw_chunk(render_messages, vp_matrix, buffer, offset, 0.);
+        }
+        for (buffer, offset) in visible_buffers(Transparency::Cutout) {
+            self.draw_chunk(render_messages, vp_matrix, buffer, offset, ALPHA_CUTOFF);
+        }
+
+        let mut translucent = visible_buffers(Transparency::Translucent);
+        if translucent.is_empty() {
+            return;
+        }
+        let half_chunk = glm::vec3(1., 1., 1.) * (CHUNK_SIZE as f32 / 2.);
+        let distance_squared =
+            |offset: glm::Vec3| glm::distance2(&(offset + half_chunk), &view.position);
+        translucent.sort_by(|(_, a), (_, b)| distance_squared(*b).total_cmp(&distance_squared(*a)));
+        render_messages.add_message(RenderMessage::Blending { enabled: true });
+        for (buffer, offset) in translucent {
+            self.draw_chunk(render_messages, vp_matrix, buffer, offset, 0.);
+        }
+        render_messages.add_message(RenderMessage::Blending { enabled: false });
+    }
+
+    /// When the window supplies new capabilities, update local capabilities to those.
+    /// Chunks packed into world buffers that no longer exist are packed again when there's room,
+    /// and every chunk is meshed again if the block atlas changed.
+    pub fn update_capabilities(&mut self, capabilities: GraphicsCapabilities) {
+        let world_buffer_count = VERTEX_BUFFER_METADATA.world_buffer_count(capabilities.vbo_count);
+        self.unpacked_chunks
+            .extend(self.chunk_buffers.resize(world_buffer_count));
+        let atlas_changed = self
+            .capabilities
+            .as_ref()
+            .is_none_or(|old| old.block_atlas != capabilities.block_atlas);
+        if atlas_changed {
+            self.block_textures = Arc::new(BlockTextures::load(
+                utils::ASSETS_PATH.join("graphics/textures/blocks/block_textures.toml"),
+                &capabilities.block_atlas,
+            ));
+            self.mesh_generations.clear();
+            self.pending_generations.clear();
         }
         self.capabilities = Some(capabilities);
     }
@@ -361,13 +556,14 @@ impl RenderState {
         model_manager: &ModelManager,
     ) -> RenderMessages {
         // What should happen:
-        // 1. Clear color and depth buffer
-        // 2. Supply commmon uniforms
-        // 3. For every new chunk we're interested in (Or dirty chunks)
-        //   3a. Fill the chunk into a vertex array or update the existing vertex array
-        // 4. For every chunk already filled into a vertex array
-        //   4a. Supply specific uniforms
-        //   4b. Draw
+        // 1. For every new chunk we're interested in (Or dirty chunks)
+        //   1a. Fill the chunk into a vertex array or update the existing vertex array
+        // 2. Render the shadow map of every cascade
+        // 3. Clear color and depth buffer of the first pass framebuffer
+        // 4. Supply commmon uniforms
+        // 5. For every chunk already filled into a vertex array
+        //   5a. Supply specific uniforms
+        //   5b. Draw
 
         let mut messages = RenderMessages::new();
 
@@ -380,57 +576,274 @@ impl RenderState {
             None => unreachable!(),
         };
 
-        messages.add_message(RenderMessage::ChooseShader {
-            shader: ShaderIdentifier::Default,
-        });
-        messages.add_message(RenderMessage::ClearBuffers {
-            color_buffer: true,
-            depth_buffer: true,
-        });
-
-        // Read chunk render distance from environment variable. Default is 1 chunk and the value should be given in thousandths of chunks.
-        const REPACK_CHUNK_RADIUS: f32 = unwrap_ctx!(parse_u32(unwrap_or!(
-            option_env!("FLEXBLOCK_RENDER_RADIUS"),
-            "1100"
-        ))) as f32
-            / 1000.;
+        self.set_render_radius(data.render_radius);
         let repack_chunk_vec = glm::TVec3::new(
-            REPACK_CHUNK_RADIUS.floor() as i32,
-            REPACK_CHUNK_RADIUS.floor() as i32,
-            REPACK_CHUNK_RADIUS.floor() as i32,
+            self.render_radius.floor() as i32,
+            self.render_radius.floor() as i32,
+            self.render_radius.floor() as i32,
         );
 
         // Create a mesh iterator walking through all possible chunks to draw.
         let chunk_mesh = MeshIterator::create(repack_chunk_vec.map(|x| x * 2 + 1))
             .map(|chunk_vec| chunk_vec - repack_chunk_vec);
 
-        // Walk through the mesh iterator and repack the chunks that are close enough.
+        // Walk through the mesh iterator and request meshes for the chunks that are close enough and have changed.
+        let center = data.view.location().chunk;
+        self.mesh_workers.set_view(data.view.location());
+        self.retry_unpacked_chunks(center);
+        let max_distance_squared = self.max_distance_squared();
+        let mut snapshot = None;
         for chunk_vec in chunk_mesh {
-            if chunk_vec.map(|x| x as f32).norm() <= REPACK_CHUNK_RADIUS {
-                self.repack_chunk(
-                    data.view.location().chunk + chunk_vec,
-                    &mut messages,
-                    &data.terrain,
-                );
+            if chunk_vec.dot(&chunk_vec) <= max_distance_squared {
+                self.update_chunk(center + chunk_vec, center, &data.terrain, &mut snapshot);
             }
         }
-        self.clear_distant_chunks(data.view.location().chunk, &mut messages);
+        self.collect_meshes(center, &mut messages);
+        self.clear_distant_chunks(center, &mut messages);
+
+        let cascades = shadows::shadow_cascades(&data.view, (width, height));
+        self.render_shadow_maps(&mut messages, &cascades, center);
+
+        messages.add_message(RenderMessage::ChooseFramebuffer {
+            framebuffer: Some(FramebufferIdentifier::FirstPass),
+        });
+        messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        messages.add_message(RenderMessage::ClearBuffers {
+            color_buffer: true,
+            depth_buffer: true,
+        });
 
         let vp = get_vp_matrix(&data.view, (width, height));
-        self.render_packed_chunks(&mut messages, &vp);
+        messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Chunk,
+        });
+        messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(shadows::shadow_uniforms(&cascades)),
+        });
+        self.render_packed_chunks(&mut messages, &vp, data.view.location());
+        messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
 
-        let k = glm::vec3(2.0, 3.0, 1.0);
+        let k = Location::from_coords(2.0, 3.0, 1.0);
         let models = vec![("/test.obj", k)]; // TODO: USE INFO FROM THE GRAPHICS STATE HERE
 
         let models = models.into_iter().map(|(model_name, location)| {
-            PlacedModel::new(model_name.into(), location, glm::vec3(1.0, 1.0, 1.0))
+            PlacedModel::new(
+                model_name.into(),
+                camera_relative_position(location, center),
+                glm::vec3(1.0, 1.0, 1.0),
+            )
         });
 
-        messages.merge_current(model_manager.draw_models(
-            models.collect(),
-            &get_vp_matrix(&data.view, (width, height)),
-        ));
+        messages.merge_current(model_manager.draw_models(models.collect(), &vp));
 
         messages
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::view::PrincipalAxes;
+    use graphics::{RenderMessageValidator, SoftwareRenderer, ValidationMode, SHADOW_CASCADES};
+    use world::chunk::CHUNK_SIZE_F;
+    use world::VoxelType;
+
+    const SCREEN: (u32, u32) = (800, 600);
+    /// Smaller, since software rendering is slow.
+    const SOFTWARE_SCREEN: (u32, u32) = (200, 150);
+
+    /// A chunk millions of voxels from the origin, where f32 world coordinates can't tell
+    /// voxels apart.
+    fn far_chunk() -> glm::IVec3 {
+        glm::vec3(1 << 22, -(1 << 20), 1 << 22)
+    }
+
+    fn view(chunk: glm::IVec3, position: glm::Vec3) -> View {
+        View::new(Location::new(chunk, position), PrincipalAxes::new(0.3, 1.2))
+    }
+
+    /// Where the matrix puts the point, in normalized device coordinates.
+    fn project(matrix: &glm::Mat4, point: glm::Vec3) -> glm::Vec3 {
+        let clip = matrix * glm::vec4(point.x, point.y, point.z, 1.);
+        clip.xyz() / clip.w
+    }
+
+    #[test]
+    fn vp_matrix_only_depends_on_position_in_chunk() {
+        let position = glm::vec3(3.25, 10.5, 7.75);
+        assert_eq!(
+            get_vp_matrix(&view(glm::vec3(0, 0, 0), position), SCREEN),
+            get_vp_matrix(&view(far_chunk(), position), SCREEN)
+        );
+    }
+
+    #[test]
+    fn offsets_are_exact_far_from_origin() {
+        assert_eq!(
+            chunk_offset(far_chunk() + glm::vec3(1, 0, -2), far_chunk()),
+            glm::vec3(CHUNK_SIZE_F, 0., -2. * CHUNK_SIZE_F)
+        );
+        let location = Location::new(
+            far_chunk() + glm::vec3(0, 1, 0),
+            glm::vec3(0.5, 0.25, 0.125),
+        );
+        assert_eq!(
+            camera_relative_position(location, far_chunk()),
+            glm::vec3(0.5, CHUNK_SIZE_F + 0.25, 0.125)
+        );
+        // The absolute position has lost the position within the voxel.
+        assert_ne!(location.as_coords().x.fract(), 0.5);
+    }
+
+    #[test]
+    fn points_ahead_of_far_camera_are_centered() {
+        let view = view(far_chunk(), glm::vec3(8.5, 8.25, 8.75));
+        let vp = get_vp_matrix(&view, SCREEN);
+        let mut ahead = view.location();
+        ahead += view.view_direction() * 20.;
+        assert_ne!(ahead.chunk, far_chunk());
+
+        let position = project(&vp, camera_relative_position(ahead, far_chunk()));
+        assert!(position.x.abs() < 1e-5 && position.y.abs() < 1e-5);
+        // Drawing the point from inside its chunk with the chunk's model matrix puts it in the same place.
+        let chunk_vp = glm::translate(&vp, &chunk_offset(ahead.chunk, far_chunk()));
+        assert!((project(&chunk_vp, ahead.position) - position).norm() < 1e-5);
+    }
+
+    /// Renders a floor with a voxel of the type floating above it to the screen of a software renderer.
+    fn render_floating_voxel(view: &View, voxel: VoxelType, shadows: bool) -> SoftwareRenderer {
+        let mut renderer = SoftwareRenderer::new(SOFTWARE_SCREEN);
+        let capabilities = renderer.capabilities();
+        let mut state = RenderState::new();
+        state.update_capabilities(renderer.capabilities());
+
+        let mut terrain = Terrain::new();
+        for x in 0..16 {
+            for z in 0..16 {
+                terrain.set_voxel_type(Location::from_coords(x as f32, 0., z as f32), VoxelType(1));
+            }
+        }
+        terrain.set_voxel_type(Location::from_coords(8., 6., 8.), voxel);
+        terrain.update_light();
+        let chunk = glm::vec3(0, 0, 0);
+        let packs = chunk_mesher::create_chunk_packs(
+            &terrain,
+            terrain.chunk(chunk).unwrap(),
+            chunk,
+            &state.block_textures,
+            view.location().position,
+        );
+
+        let mut messages = RenderMessages::new();
+        state.pack_mesh(chunk, packs, chunk, &mut messages).unwrap();
+        let cascades = shadows::shadow_cascades(view, SOFTWARE_SCREEN);
+        if shadows {
+            state.render_shadow_maps(&mut messages, &cascades, chunk);
+        }
+        messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        messages.add_message(RenderMessage::ClearBuffers {
+            color_buffer: true,
+            depth_buffer: true,
+        });
+        messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Chunk,
+        });
+        if shadows {
+            messages.add_message(RenderMessage::Uniforms {
+                uniforms: Box::new(shadows::shadow_uniforms(&cascades)),
+            });
+            RenderMessageValidator::new(ValidationMode::Panic)
+                .validate(Some(&capabilities), &messages);
+        }
+        let vp = get_vp_matrix(view, SOFTWARE_SCREEN);
+        state.render_packed_chunks(&mut messages, &vp, view.location());
+        for message in messages.iter() {
+            renderer.read_message(message);
+        }
+        renderer
+    }
+
+    fn floating_voxel_view() -> View {
+        View::new(
+            Location::from_coords(4., 8., 4.),
+            PrincipalAxes::new(2.36, 1.2),
+        )
+    }
+
+    #[test]
+    fn voxels_shadow_what_is_below_them() {
+        let view = floating_voxel_view();
+        let shadowed = render_floating_voxel(&view, VoxelType(1), true);
+        let unshadowed = render_floating_voxel(&view, VoxelType(1), false);
+
+        // The color of the pixel the point is drawn to, in both renders.
+        let vp = get_vp_matrix(&view, SOFTWARE_SCREEN);
+        let pixel = |point: glm::Vec3| {
+            let position = project(&vp, point);
+            assert!(
+                position.x.abs() < 1. && position.y.abs() < 1.,
+                "{:?}",
+                position
+            );
+            let (shadowed, unshadowed) = (shadowed.screenshot(), unshadowed.screenshot());
+            let x = ((position.x + 1.) / 2. * shadowed.width as f32) as usize;
+            let y = ((1. - position.y) / 2. * shadowed.height as f32) as usize;
+            let i = (y * shadowed.width as usize + x) * 3;
+            let brightness = |data: &[u8]| data[i..i + 3].iter().map(|&c| c as u32).sum::<u32>();
+            (brightness(&shadowed.data), brightness(&unshadowed.data))
+        };
+
+        // Where the sun shining through the center of the floating voxel hits the top of the floor.
+        let center = glm::vec3(8.5, 6.5, 8.5);
+        let direction = shadows::sun_direction();
+        let shadow = center + direction * ((1. - center.y) / direction.y);
+        let (dark, light) = pixel(shadow);
+        assert!(
+            dark < light * 9 / 10,
+            "{} isn't darker than {}",
+            dark,
+            light
+        );
+
+        // The floor next to the shadow is lit the same as without shadows.
+        let (lit, light) = pixel(shadow - glm::vec3(3., 0., 0.));
+        assert_eq!(lit, light);
+    }
+
+    #[test]
+    fn light_shines_through_gaps_in_cutout_voxels() {
+        let view = floating_voxel_view();
+        let shadow_maps = |voxel| {
+            let renderer = render_floating_voxel(&view, voxel, true);
+            SHADOW_CASCADES
+                .iter()
+                .flat_map(|cascade| {
+                    let texture = cascade.depth_texture().unwrap();
+                    renderer.texture_image(texture).unwrap().data
+                })
+                .collect::<Vec<u8>>()
+        };
+        // The shadow map texels the floating voxel is drawn to, compared to floating air.
+        let floor = shadow_maps(VoxelType(0));
+        let covered = |voxel| {
+            let shadow_maps = shadow_maps(voxel);
+            floor
+                .iter()
+                .zip(&shadow_maps)
+                .filter(|(floor, depth)| floor != depth)
+                .count()
+        };
+
+        let (solid, cutout) = (covered(VoxelType(1)), covered(VoxelType(5)));
+        assert!(
+            0 < cutout && cutout < solid,
+            "Cutout voxel covers {} texels, solid voxel {}",
+            cutout,
+            solid
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use super::render_state::{get_projection_matrix, get_view_matrix, NEAR_PLANE};
+use game::View;
+use graphics::{FramebufferIdentifier, UniformData, FOG_DISTANCE, SHADOW_CASCADES};
+
+/// The direction sunlight travels in. Doesn't need to be normalized.
+const SUN_DIRECTION: [f32; 3] = [0.35, -1., 0.25];
+
+/// Shadows are drawn this far from the view. Everything further away is hidden by fog.
+pub const SHADOW_DISTANCE: f32 = FOG_DISTANCE;
+
+/// Where the splits between cascades are, from evenly spaced (0) to logarithmically spaced (1).
+/// Logarithmic splits give close shadows more detail, but leave the last cascade covering most of the view.
+const SPLIT_LAMBDA: f32 = 0.75;
+
+/// How far towards the sun from the part of the view a cascade covers voxels still cast shadows into it.
+const CASTER_DISTANCE: f32 = 32.;
+
+/// How much closer to the sun than the shadow map a surface has to be to be lit, in shadow map texels.
+/// Surfaces at an angle to the sun change depth across a texel, so they would shadow themselves without it.
+const BIAS_TEXELS: f32 = 3.;
+
+/// How dark shadows are. Some light still reaches shadows from the sky.
+const SHADOW_STRENGTH: f32 = 0.45;
+
+/// One of the shadow maps, covering part of the view.
+pub struct ShadowCascade {
+    /// The framebuffer the shadow map is rendered to.
+    pub framebuffer: FramebufferIdentifier,
+    /// The view projection matrix of the sun, relative to the chunk the camera is in like `get_vp_matrix`.
+    pub view_projection: glm::Mat4,
+    /// The depth from the view at which the cascade ends, and the next one starts.
+    pub end: f32,
+    /// How much closer to the sun than the shadow map a surface has to be to be lit, in shadow map depth.
+    pub bias: f32,
+}
+
+/// The normalized direction sunlight travels in.
+pub fn sun_direction() -> glm::Vec3 {
+    glm::normalize(&glm::Vec3::from(SUN_DIRECTION))
+}
+
+/// The depths from the view at which each of `count` cascades covering `near` to `far` ends.
+pub fn cascade_ends(near: f32, far: f32, count: usize) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let fraction = i as f32 / count as f32;
+            let logarithmic = near * (far / near).powf(fraction);
+            let uniform = near + (far - near) * fraction;
+            SPLIT_LAMBDA * logarithmic + (1. - SPLIT_LAMBDA) * uniform
+        })
+        .collect()
+}
+
+/// The corners of the part of the view between the two depths.
+fn frustum_corners(
+    view: &View,
+    screen_dimensions: (u32, u32),
+    near: f32,
+    far: f32,
+) -> Vec<glm::Vec3> {
+    let inverse = glm::inverse(
+        &(get_projection_matrix(screen_dimensions, near, far) * get_view_matrix(view)),
+    );
+    let mut corners = Vec::with_capacity(8);
+    for x in [-1., 1.] {
+        for y in [-1., 1.] {
+            for z in [-1., 1.] {
+                let corner = inverse * glm::vec4(x, y, z, 1.);
+                corners.push(corner.xyz() / corner.w);
+            }
+        }
+    }
+    corners
+}
+
+///
+/// Creates one cascade for each shadow cascade framebuffer, covering the view up to `SHADOW_DISTANCE` between them.
+///
+/// Each cascade is a square around the sphere containing its part of the view, so it doesn't change size when the
+/// view turns, and it is moved by whole shadow map texels, so shadow edges don't flicker when the view moves.
+///
+pub fn shadow_cascades(view: &View, screen_dimensions: (u32, u32)) -> Vec<ShadowCascade> {
+    let direction = sun_direction();
+    let up = if direction.x.abs() < 1e-3 && direction.z.abs() < 1e-3 {
+        glm::vec3(1., 0., 0.)
+    } else {
+        glm::vec3(0., 1., 0.)
+    };
+    let ends = cascade_ends(NEAR_PLANE, SHADOW_DISTANCE, SHADOW_CASCADES.len());
+    let starts = std::iter::once(NEAR_PLANE).chain(ends.iter().copied());
+
+    SHADOW_CASCADES
+        .iter()
+        .zip(starts.zip(ends.iter().copied()))
+        .map(|(&framebuffer, (start, end))| {
+            let corners = frustum_corners(view, screen_dimensions, start, end);
+            let center = corners.iter().sum::<glm::Vec3>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| glm::distance(corner, &center))
+                .fold(0., f32::max);
+            // Rounding the radius keeps tiny changes in it from moving every texel.
+            let radius = (radius * 16.).ceil() / 16.;
+
+            let depth_range = 2. * radius + CASTER_DISTANCE;
+            let eye = center - direction * (radius + CASTER_DISTANCE);
+            let light_view = glm::look_at(&eye, &center, &up);
+            let mut projection = glm::ortho(-radius, radius, -radius, radius, 0., depth_range);
+
+            let size = framebuffer.dimensions(screen_dimensions).0 as f32;
+            let origin = (projection * light_view) * glm::vec4(0., 0., 0., 1.);
+            let texels = origin.xy() * (size / 2.);
+            let snap = (texels.map(f32::round) - texels) * (2. / size);
+            projection[(0, 3)] += snap.x;
+            projection[(1, 3)] += snap.y;
+
+            let texel_size = 2. * radius / size;
+            ShadowCascade {
+                framebuffer,
+                view_projection: projection * light_view,
+                end,
+                bias: BIAS_TEXELS * texel_size / depth_range,
+            }
+        })
+        .collect()
+}
+
+/// One value for each cascade, as the chunk shader takes them.
+/// The shader has a vector component per cascade, so this only compiles while there are three of them.
+fn per_cascade(cascades: &[ShadowCascade], value: impl Fn(&ShadowCascade) -> f32) -> glm::Vec3 {
+    let mut values = [0.; SHADOW_CASCADES.len()];
+    for (slot, cascade) in values.iter_mut().zip(cascades) {
+        *slot = value(cascade);
+    }
+    glm::Vec3::from(values)
+}
+
+/// The uniforms the chunk shader needs to shade chunks with the cascades.
+/// They stay the same for every chunk, so they are given once after choosing the chunk shader.
+pub fn shadow_uniforms(cascades: &[ShadowCascade]) -> UniformData {
+    let mut uniforms = UniformData::new();
+    for (i, cascade) in cascades.iter().enumerate() {
+        if let Some(shadow_map) = cascade.framebuffer.depth_texture() {
+            uniforms.texture(String::from(shadow_map), format!("shadow_map{}", i));
+        }
+        uniforms.mat4(cascade.view_projection, format!("shadow_VP{}", i));
+    }
+    uniforms.vec3(per_cascade(cascades, |cascade| cascade.end), "cascade_ends");
+    uniforms.vec3(per_cascade(cascades, |cascade| cascade.bias), "shadow_bias");
+    uniforms.float(SHADOW_STRENGTH, "shadow_strength");
+    uniforms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::view::PrincipalAxes;
+    use world::Location;
+
+    const SCREEN: (u32, u32) = (800, 600);
+
+    fn view() -> View {
+        View::new(
+            Location::from_coords(3.25, 10.5, 7.75),
+            PrincipalAxes::new(0.3, 1.2),
+        )
+    }
+
+    /// Where the matrix puts the point, in normalized device coordinates.
+    fn project(matrix: &glm::Mat4, point: glm::Vec3) -> glm::Vec3 {
+        let clip = matrix * glm::vec4(point.x, point.y, point.z, 1.);
+        clip.xyz() / clip.w
+    }
+
+    fn in_clip_volume(point: glm::Vec3) -> bool {
+        point.iter().all(|coordinate| coordinate.abs() <= 1. + 1e-4)
+    }
+
+    #[test]
+    fn cascades_split_the_shadow_distance() {
+        let ends = cascade_ends(NEAR_PLANE, SHADOW_DISTANCE, 3);
+        assert_eq!(ends.len(), 3);
+        assert!(ends.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((ends[2] - SHADOW_DISTANCE).abs() < 1e-3);
+        // Closer cascades cover less of the view, so their shadows are sharper.
+        assert!(ends[0] < SHADOW_DISTANCE / 3.);
+    }
+
+    #[test]
+    fn cascades_contain_their_part_of_the_view() {
+        let view = view();
+        let cascades = shadow_cascades(&view, SCREEN);
+        assert_eq!(cascades.len(), SHADOW_CASCADES.len());
+        let mut start = NEAR_PLANE;
+        for cascade in &cascades {
+            for corner in frustum_corners(&view, SCREEN, start, cascade.end) {
+                let position = project(&cascade.view_projection, corner);
+                assert!(in_clip_volume(position), "{:?}", position);
+                // Voxels between the sun and the cascade cast shadows into it too.
+                let caster = corner - sun_direction() * (CASTER_DISTANCE * 0.9);
+                assert!(in_clip_volume(project(&cascade.view_projection, caster)));
+            }
+            start = cascade.end;
+        }
+    }
+
+    #[test]
+    fn cascades_move_by_whole_texels() {
+        let mut view = view();
+        let before = shadow_cascades(&view, SCREEN);
+        view.translate(glm::vec3(0.013, 0., 0.021));
+        let after = shadow_cascades(&view, SCREEN);
+        for (before, after) in before.iter().zip(&after) {
+            let size = before.framebuffer.dimensions(SCREEN).0 as f32;
+            let point = glm::vec3(1., 2., 3.);
+            let moved = (project(&after.view_projection, point)
+                - project(&before.view_projection, point))
+            .xy()
+                * (size / 2.);
+            for texels in moved.iter() {
+                assert!((texels - texels.round()).abs() < 1e-2, "{:?}", moved);
+            }
+        }
+    }
+
+    #[test]
+    fn uniforms_cover_every_cascade() {
+        let uniforms = shadow_uniforms(&shadow_cascades(&view(), SCREEN));
+        let locations = uniforms.get_uniform_locations();
+        for i in 0..SHADOW_CASCADES.len() {
+            for name in [format!("shadow_map{}", i), format!("shadow_VP{}", i)] {
+                assert!(locations.contains(&&name), "{}", name);
+            }
+        }
+    }
+}
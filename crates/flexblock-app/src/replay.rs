@@ -0,0 +1,226 @@
+use crate::channels::*;
+use crate::pack::{RecordedEvent, RenderRecordingReader};
+use crate::window;
+use graphics::VERTEX_BUFFER_METADATA;
+use graphics::{
+    GraphicsCapabilities, RenderMessageValidator, RenderMessages, SoftwareRenderer, ValidationMode,
+};
+use log::{error, info};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Validates every frame of a render recording against the capabilities recorded before it,
+/// handing each frame to `on_frame` afterwards.
+/// Returns the number of frames and the number of frames that failed validation.
+fn validate_recording<I>(events: I, mut on_frame: impl FnMut(RenderMessages)) -> (usize, usize)
+where
+    I: Iterator<Item = Result<RecordedEvent, bincode::Error>>,
+{
+    let mut capabilities: Option<GraphicsCapabilities> = None;
+    let mut validator = RenderMessageValidator::new(ValidationMode::Log);
+    let mut frames = 0;
+    let mut failed_frames = 0;
+    for event in events {
+        match event {
+            Ok(RecordedEvent::Capabilities(new_capabilities)) => {
+                capabilities = Some(new_capabilities)
+            }
+            Ok(RecordedEvent::Frame(messages)) => {
+                let report = validator.validate(capabilities.as_ref(), &messages);
+                if report.has_errors() {
+                    error!("Frame {} failed validation.", frames);
+                    failed_frames += 1;
+                }
+                frames += 1;
+                on_frame(messages);
+            }
+            Err(error) => {
+                error!("Could not read render recording. Error: {:?}", error);
+            }
+        }
+    }
+    (frames, failed_frames)
+}
+
+///
+/// Replays a render recording made with `FLEXBLOCK_RECORD_RENDER`, validating every frame.
+/// If show_in_window is set, the frames are also rendered in a window at the tick rate they were recorded at.
+///
+/// NOTE: If the frames are shown, the window takes over the thread, like in a normal run.
+///
+pub fn replay<P: AsRef<Path>>(path: P, show_in_window: bool) {
+    let events = match RenderRecordingReader::open(&path) {
+        Ok(events) => events,
+        Err(error) => {
+            error!("Could not open render recording. Error: {:?}", error);
+            return;
+        }
+    };
+    info!("Replaying render recording {}", path.as_ref().display());
+
+    if !show_in_window {
+        let (frames, failed_frames) = validate_recording(events, |_| {});
+        info!(
+            "Replayed {} frames, {} failed validation.",
+            frames, failed_frames
+        );
+        return;
+    }
+
+    let render_pack: Arc<Mutex<Option<RenderMessages>>> = Arc::new(Mutex::new(None));
+    let packing_to_window_sender = PackingToWindowSender {
+        render_pack: render_pack.clone(),
+    };
+    let packing_to_window_receiver = PackingToWindowReceiver { render_pack };
+    // Nothing listens to the window during a replay, but it expects someone to.
+    let (game_event_sender, _game_event_receiver) = mpsc::channel();
+    let (graphics_capabilities_sender, _graphics_capabilities_receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let tick = Duration::from_secs_f32(1. / game::TPS as f32);
+        let (frames, failed_frames) = validate_recording(events, |mut messages| {
+            // Every frame reaches the window, so persistent messages from earlier frames are already there.
+            messages.discard_old();
+            loop {
+                let mut message_mutex = packing_to_window_sender.render_pack.lock().unwrap();
+                if message_mutex.is_none() {
+                    *message_mutex = Some(messages);
+                    break;
+                }
+                drop(message_mutex);
+                thread::sleep(Duration::from_millis(1));
+            }
+            thread::sleep(tick);
+        });
+        info!(
+            "Replayed {} frames, {} failed validation.",
+            frames, failed_frames
+        );
+    });
+
+    window::start_window(
+        packing_to_window_receiver,
+        WindowToLogicSender {
+            channel_sender: game_event_sender,
+        },
+        WindowToPackingSender {
+            channel_sender: graphics_capabilities_sender,
+        },
+    );
+}
+
+///
+/// Renders every frame of a render recording with the software renderer, and saves them as
+/// `frame_<number>.png` in the given directory. Doesn't need a GPU, so it works in CI.
+///
+pub fn replay_to_screenshots<P: AsRef<Path>, D: AsRef<Path>>(path: P, directory: D) {
+    let events = match RenderRecordingReader::open(&path) {
+        Ok(events) => events,
+        Err(error) => {
+            error!("Could not open render recording. Error: {:?}", error);
+            return;
+        }
+    };
+    if let Err(error) = std::fs::create_dir_all(&directory) {
+        error!("Could not create screenshot directory. Error: {:?}", error);
+        return;
+    }
+    info!(
+        "Rendering render recording {} to {}",
+        path.as_ref().display(),
+        directory.as_ref().display()
+    );
+
+    let mut renderer: Option<SoftwareRenderer> = None;
+    let mut frames = 0;
+    for event in events {
+        match event {
+            Ok(RecordedEvent::Capabilities(capabilities)) => {
+                let renderer = renderer
+                    .get_or_insert_with(|| SoftwareRenderer::new(capabilities.screen_dimensions));
+                renderer.update_screen_dimensions(capabilities.screen_dimensions);
+                renderer.set_world_buffer_count(
+                    VERTEX_BUFFER_METADATA.world_buffer_count(capabilities.vbo_count),
+                );
+            }
+            Ok(RecordedEvent::Frame(mut messages)) => {
+                let renderer = match renderer.as_mut() {
+                    Some(renderer) => renderer,
+                    None => {
+                        error!("Frame {} was recorded before any capabilities.", frames);
+                        continue;
+                    }
+                };
+                // Every frame is rendered, so persistent messages from earlier frames are already there.
+                messages.discard_old();
+                for message in messages.iter() {
+                    renderer.read_message(message);
+                }
+                let file = directory.as_ref().join(format!("frame_{:05}.png", frames));
+                if let Err(error) = utils::write_png(&file, &renderer.screenshot()) {
+                    error!("Could not save {}. Error: {:?}", file.display(), error);
+                }
+                frames += 1;
+            }
+            Err(error) => {
+                error!("Could not read render recording. Error: {:?}", error);
+            }
+        }
+    }
+    info!("Rendered {} frames.", frames);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pack::RenderRecorder;
+    use graphics::{RenderMessage, TextureAtlas};
+    use std::collections::HashMap;
+
+    fn capabilities() -> GraphicsCapabilities {
+        GraphicsCapabilities {
+            vbo_count: 200,
+            texture_metadata: HashMap::new(),
+            shader_metadata: Vec::new(),
+            framebuffer_metadata: Vec::new(),
+            block_atlas: TextureAtlas::default(),
+            screen_dimensions: (1024, 768),
+        }
+    }
+
+    fn frame() -> RenderMessages {
+        let mut messages = RenderMessages::new();
+        messages.add_message(RenderMessage::SwitchTo3D {});
+        messages
+    }
+
+    /// Records a frame before any capabilities, which fails validation, then two frames after them.
+    /// Returns the recording and its length after the first of the valid frames.
+    fn recording() -> (Vec<u8>, usize) {
+        let mut recorder = RenderRecorder::new(Vec::new());
+        recorder.record_frame(&frame()).unwrap();
+        recorder.record_capabilities(&capabilities()).unwrap();
+        recorder.record_frame(&frame()).unwrap();
+        let cut = recorder.get_ref().len();
+        recorder.record_frame(&frame()).unwrap();
+        (recorder.finish().unwrap(), cut)
+    }
+
+    #[test]
+    fn replays_every_frame() {
+        let (recording, _) = recording();
+        let mut replayed = 0;
+        let events = RenderRecordingReader::new(recording.as_slice());
+        assert_eq!(validate_recording(events, |_| replayed += 1), (3, 1));
+        assert_eq!(replayed, 3);
+    }
+
+    #[test]
+    fn replays_truncated_recordings_up_to_the_cut() {
+        let (recording, cut) = recording();
+        let events = RenderRecordingReader::new(&recording[..cut + 1]);
+        assert_eq!(validate_recording(events, |_| {}), (2, 1));
+    }
+}
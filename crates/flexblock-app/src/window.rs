@@ -1,10 +1,10 @@
-use graphics::{Gui, GraphicsCapabilities, RenderCaller, ExternalEvent};
+use graphics::{Gui, GraphicsCapabilities, RenderCaller, ExternalEvent, ShaderIdentifier};
 use crate::{
     channels::{PackingToWindowReceiver, WindowToPackingSender},
 };
 use glutin::event::{Event, WindowEvent};
 use glutin::event_loop::ControlFlow;
-use log::info;
+use log::{error, info};
 
 use crate::channels::*;
 
@@ -32,6 +32,8 @@ pub struct Window {
     render_messages: PackingToWindowReceiver,
     capabilities_sender: WindowToPackingSender,
     gui: Gui,
+    /// Shaders that failed to recompile, with their errors, shown until they compile again.
+    shader_errors: Vec<(ShaderIdentifier, String)>,
 }
 
 pub type EventHandler = Box<dyn FnMut(glutin::event::Event<()>) + Send + 'static>;
@@ -67,6 +69,7 @@ impl Window {
             render_messages: rx,
             capabilities_sender: packing_tx,
             gui: Gui::new((400.0, 400.0), (-1.0, -1.0)),
+            shader_errors: Vec::new(),
         };
         res.send_capabilities();
 
@@ -88,6 +91,11 @@ impl Window {
                 .render_caller
                 .get_framebuffer_manager()
                 .get_framebuffer_metadata(),
+            block_atlas: self
+                .render_caller
+                .get_texture_manager()
+                .block_atlas()
+                .clone(),
             screen_dimensions: (
                 self.context.window().inner_size().width,
                 self.context.window().inner_size().height,
@@ -113,6 +121,16 @@ impl Window {
             200.0,
             16.0,
         );
+        for (i, (identifier, error)) in self.shader_errors.iter().enumerate() {
+            let first_line = error.lines().next().unwrap_or_default();
+            self.gui.add_text(
+                &format!("{} failed to compile: {}", identifier.name(), first_line),
+                (10.0, 300.0 + 20.0 * i as f32),
+                380.0,
+                20.0,
+                10.0,
+            );
+        }
 
         if let Ok(mut render_messages) = render_messages {
             if let Some(messages) = render_messages.take() {
@@ -138,6 +156,26 @@ impl Window {
         self.send_capabilities();
     }
 
+    /// Recompiles shaders whose source files changed, and tells the packer about their new uniforms.
+    unsafe fn reload_shaders(&mut self) {
+        let reload = self.render_caller.reload_changed_shaders();
+        let changed_metadata = reload.changed_metadata();
+        for identifier in &reload.reloaded {
+            info!("Reloaded shader {}", identifier.name());
+        }
+        self.shader_errors.retain(|(identifier, _)| {
+            !reload.reloaded.contains(identifier)
+        });
+        for (identifier, error) in reload.errors {
+            error!("Failed to reload shader {}, keeping the old one! Error: {}", identifier.name(), error);
+            self.shader_errors.retain(|(failed, _)| *failed as usize != identifier as usize);
+            self.shader_errors.push((identifier, error));
+        }
+        if changed_metadata {
+            self.send_capabilities();
+        }
+    }
+
     ///
     /// Starts this graphics object
     ///
@@ -168,6 +206,7 @@ impl Window {
                     Event::NewEvents(cs) => match cs {
                         glutin::event::StartCause::Poll => {
                             // Perform a render
+                            self.reload_shaders();
                             self.render();
                         }
                         _ => event_handler(Event::NewEvents(cs)),
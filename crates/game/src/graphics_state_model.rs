@@ -1,8 +1,14 @@
 use crate::View;
 use world::Terrain;
+/// The render radius in chunks used unless the player changes it.
+pub const DEFAULT_RENDER_RADIUS: f32 = 1.1;
+
 pub struct GraphicsStateModel {
     pub terrain: Terrain,
     pub view: View,
+    /// Chunks within this many chunks of the view are drawn.
+    /// A setting of the player rather than part of the state, so it is set by the logic around the state.
+    pub render_radius: f32,
 }
 
 impl GraphicsStateModel {
@@ -10,6 +16,7 @@ impl GraphicsStateModel {
         GraphicsStateModel {
             terrain: Terrain::new(),
             view: View::default(),
+            render_radius: DEFAULT_RENDER_RADIUS,
         }
     }
 }
@@ -16,9 +16,11 @@ mod player;
 pub use player::Player;
 
 mod graphics_state_model;
-pub use graphics_state_model::GraphicsStateModel;
+pub use graphics_state_model::{GraphicsStateModel, DEFAULT_RENDER_RADIUS};
 
 extern crate nalgebra_glm as glm;
 
 pub const TPS: u32 = 24;
 pub const SECONDS_PER_TICK: f32 = 1. / (TPS as f32);
+/// The length of a full day-night cycle in ticks.
+pub const TICKS_PER_DAY: u64 = 20 * 60 * TPS as u64;
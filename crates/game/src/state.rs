@@ -1,8 +1,13 @@
 use crate::{GraphicsStateModel, Player, StateInputEvent};
-use audio::{AudioMessage, AudioMessageHandle, Listener};
+use audio::{AmbienceContext, AudioMessage, AudioMessageHandle, Listener};
 use glm::Vec3;
 use serde::{Deserialize, Serialize};
-use world::{self, Location, Terrain, VoxelType};
+use world::{self, raytrace, Location, Terrain, VoxelType};
+
+/// How many voxels above the player to look for a roof when computing depth.
+const AMBIENCE_DEPTH_SCAN: u32 = 32;
+/// How many voxels in each direction to look for walls when computing enclosure.
+const AMBIENCE_ENCLOSURE_SCAN: u32 = 8;
 
 /// Holds the entire world state.
 /// Everything that is part of the game is held within.
@@ -32,6 +37,7 @@ impl State {
         state
             .terrain
             .set_voxel_type(Location::from_coords(4., 0., -4.), world::VoxelType(1));
+        state.terrain.update_light();
         state
     }
 
@@ -40,6 +46,8 @@ impl State {
     }
 
     /// Runs one game tick reacting to the given input events.
+    /// Light is updated once every voxel modified by the events has been set,
+    /// so the terrain is always lit when the tick ends.
     ///
     /// # Arguments
     ///
@@ -47,6 +55,7 @@ impl State {
     pub fn tick(&mut self, events: &[StateInputEvent], audio_message_handle: &AudioMessageHandle) {
         self.cur_tick += 1;
         self.handle_events(events, audio_message_handle);
+        self.terrain.update_light();
 
         self.player.tick(&self.terrain);
 
@@ -54,6 +63,41 @@ impl State {
             self.player.view().location(),
             self.player.view().right(),
         )));
+        audio_message_handle.send_message(AudioMessage::Ambience(self.ambience_context()));
+    }
+
+    /// Describes the player's surroundings for the ambience director.
+    fn ambience_context(&self) -> AmbienceContext {
+        let location = self.player.view().location();
+        let is_solid =
+            |offset: Vec3| !raytrace::ignore_voxel_type(self.terrain.voxel_type(location + offset));
+
+        let depth = (1..=AMBIENCE_DEPTH_SCAN)
+            .filter(|&i| is_solid(Vec3::new(0., i as f32, 0.)))
+            .count() as f32;
+
+        let directions = [
+            Vec3::new(1., 0., 0.),
+            Vec3::new(-1., 0., 0.),
+            Vec3::new(0., 1., 0.),
+            Vec3::new(0., -1., 0.),
+            Vec3::new(0., 0., 1.),
+            Vec3::new(0., 0., -1.),
+        ];
+        let walls = directions
+            .iter()
+            .filter(|&&direction| {
+                (1..=AMBIENCE_ENCLOSURE_SCAN).any(|i| is_solid(direction * i as f32))
+            })
+            .count();
+
+        AmbienceContext {
+            altitude: location.as_coords().y,
+            depth,
+            enclosure: walls as f32 / directions.len() as f32,
+            time_of_day: (self.cur_tick % crate::TICKS_PER_DAY) as f32
+                / crate::TICKS_PER_DAY as f32,
+        }
     }
 
     fn handle_events(
@@ -0,0 +1,386 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Data, DeriveInput, Error, Ident, LitFloat, LitInt, LitStr, Result, Token};
+
+/// Everything the attributes of one framebuffer identifier variant say about it.
+struct FramebufferVariant {
+    ident: Ident,
+    name: LitStr,
+    color_texture: Option<LitStr>,
+    depth_texture: Option<LitStr>,
+    /// Whether the framebuffer has a depth buffer. Having a depth texture implies it.
+    depth: bool,
+    /// How large the framebuffer is compared to the screen. None is the same size as the screen.
+    scale: Option<LitFloat>,
+    /// The width and height of a framebuffer that doesn't follow the screen, like a shadow map.
+    size: Option<(u32, u32)>,
+}
+
+/// Sets an attribute that can only be given once per variant.
+fn set_once<T>(slot: &mut Option<T>, value: T, attr: &Attribute) -> Result<()> {
+    if slot.is_some() {
+        return Err(Error::new_spanned(
+            attr,
+            "A framebuffer identifier has been given this attribute twice!",
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+impl FramebufferVariant {
+    fn parse(variant: syn::Variant) -> Result<FramebufferVariant> {
+        let mut name = None;
+        let mut color_texture = None;
+        let mut depth_texture = None;
+        let mut depth = None;
+        let mut scale = None;
+        let mut size = None;
+        for attr in &variant.attrs {
+            if attr.path.is_ident("name") {
+                set_once(&mut name, attr.parse_args::<LitStr>()?, attr)?;
+            } else if attr.path.is_ident("color_texture") {
+                set_once(&mut color_texture, attr.parse_args::<LitStr>()?, attr)?;
+            } else if attr.path.is_ident("depth_texture") {
+                set_once(&mut depth_texture, attr.parse_args::<LitStr>()?, attr)?;
+            } else if attr.path.is_ident("depth") {
+                if !attr.tokens.is_empty() {
+                    return Err(Error::new_spanned(
+                        attr,
+                        "The depth attribute takes no arguments",
+                    ));
+                }
+                set_once(&mut depth, (), attr)?;
+            } else if attr.path.is_ident("scale") {
+                let value = attr.parse_args::<LitFloat>()?;
+                if value.base10_parse::<f32>()? <= 0. {
+                    return Err(Error::new_spanned(
+                        value,
+                        "A framebuffer must be scaled by a positive number",
+                    ));
+                }
+                set_once(&mut scale, value, attr)?;
+            } else if attr.path.is_ident("size") {
+                set_once(&mut size, parse_size(attr)?, attr)?;
+            }
+        }
+
+        let name = name.ok_or_else(|| {
+            Error::new_spanned(
+                &variant.ident,
+                "Not all framebuffer identifiers have been given names!",
+            )
+        })?;
+        if color_texture.is_none() && depth_texture.is_none() && depth.is_none() {
+            return Err(Error::new_spanned(
+                &variant.ident,
+                "A framebuffer needs a color texture, a depth texture or a depth buffer!",
+            ));
+        }
+        if scale.is_some() && size.is_some() {
+            return Err(Error::new_spanned(
+                &variant.ident,
+                "A framebuffer can't have both a scale and a size!",
+            ));
+        }
+        Ok(FramebufferVariant {
+            ident: variant.ident,
+            name,
+            depth: depth.is_some() || depth_texture.is_some(),
+            color_texture,
+            depth_texture,
+            scale,
+            size,
+        })
+    }
+}
+
+/// Parses the width and height of a `size` attribute, which both have to be positive.
+fn parse_size(attr: &Attribute) -> Result<(u32, u32)> {
+    let values = attr.parse_args_with(Punctuated::<LitInt, Token![,]>::parse_terminated)?;
+    let values = values
+        .iter()
+        .map(|value| match value.base10_parse::<u32>()? {
+            0 => Err(Error::new_spanned(
+                value,
+                "A framebuffer must be at least one pixel wide and high",
+            )),
+            value => Ok(value),
+        })
+        .collect::<Result<Vec<u32>>>()?;
+    match values[..] {
+        [width, height] => Ok((width, height)),
+        _ => Err(Error::new_spanned(
+            attr,
+            "The size attribute takes a width and a height",
+        )),
+    }
+}
+
+fn optional(texture: &Option<LitStr>) -> TokenStream {
+    match texture {
+        Some(texture) => quote! { Some(#texture) },
+        None => quote! { None },
+    }
+}
+
+///
+/// Implements `name`, `color_texture`, `depth_texture`, `has_depth` and `dimensions` for a framebuffer identifier enum.
+/// Scaled framebuffers round their dimensions up, like textures with a screen scale do.
+/// Framebuffers with a size keep it whatever the screen dimensions are.
+///
+pub fn expand_framebuffer_id(input: DeriveInput) -> Result<TokenStream> {
+    let enum_name = input.ident;
+    let variants = match input.data {
+        Data::Enum(data_enum) => data_enum
+            .variants
+            .into_iter()
+            .map(FramebufferVariant::parse)
+            .collect::<Result<Vec<_>>>()?,
+        _ => {
+            return Err(Error::new_spanned(
+                enum_name,
+                "FramebufferId can only be derived for enums",
+            ))
+        }
+    };
+
+    let idents: Vec<_> = variants.iter().map(|variant| &variant.ident).collect();
+    let names = variants.iter().map(|variant| &variant.name);
+    let color_textures = variants
+        .iter()
+        .map(|variant| optional(&variant.color_texture));
+    let depth_textures = variants
+        .iter()
+        .map(|variant| optional(&variant.depth_texture));
+    let depths = variants.iter().map(|variant| variant.depth);
+    let dimensions = variants
+        .iter()
+        .map(|variant| match (&variant.scale, variant.size) {
+            (Some(scale), _) => quote! {
+                {
+                    let scale = |x: u32| ((x as f32 * #scale).ceil() as u32).max(1);
+                    (scale(screen_dimensions.0), scale(screen_dimensions.1))
+                }
+            },
+            (None, Some((width, height))) => quote! { (#width, #height) },
+            (None, None) => quote! { screen_dimensions },
+        });
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #enum_name {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#enum_name::#idents => #names,)*
+                }
+            }
+            pub fn color_texture(&self) -> Option<&'static str> {
+                match self {
+                    #(#enum_name::#idents => #color_textures,)*
+                }
+            }
+            pub fn depth_texture(&self) -> Option<&'static str> {
+                match self {
+                    #(#enum_name::#idents => #depth_textures,)*
+                }
+            }
+            pub fn has_depth(&self) -> bool {
+                match self {
+                    #(#enum_name::#idents => #depths,)*
+                }
+            }
+            pub fn dimensions(&self, screen_dimensions: (u32, u32)) -> (u32, u32) {
+                match self {
+                    #(#enum_name::#idents => #dimensions,)*
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand(input: DeriveInput) -> String {
+        expand_framebuffer_id(input).unwrap().to_string()
+    }
+
+    fn error(input: DeriveInput) -> String {
+        expand_framebuffer_id(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn expands_every_method() {
+        let expanded = expand(parse_quote! {
+            enum Framebuffers {
+                #[name("First pass")]
+                #[color_texture("color")]
+                #[depth_texture("depth")]
+                FirstPass,
+                #[name("Bloom")]
+                #[color_texture("bloom")]
+                #[scale(0.5)]
+                Bloom,
+                #[name("Shadows")]
+                #[depth]
+                Shadows,
+            }
+        });
+        let expected = quote! {
+            #[automatically_derived]
+            impl Framebuffers {
+                pub fn name(&self) -> &'static str {
+                    match self {
+                        Framebuffers::FirstPass => "First pass",
+                        Framebuffers::Bloom => "Bloom",
+                        Framebuffers::Shadows => "Shadows",
+                    }
+                }
+                pub fn color_texture(&self) -> Option<&'static str> {
+                    match self {
+                        Framebuffers::FirstPass => Some("color"),
+                        Framebuffers::Bloom => Some("bloom"),
+                        Framebuffers::Shadows => None,
+                    }
+                }
+                pub fn depth_texture(&self) -> Option<&'static str> {
+                    match self {
+                        Framebuffers::FirstPass => Some("depth"),
+                        Framebuffers::Bloom => None,
+                        Framebuffers::Shadows => None,
+                    }
+                }
+                pub fn has_depth(&self) -> bool {
+                    match self {
+                        Framebuffers::FirstPass => true,
+                        Framebuffers::Bloom => false,
+                        Framebuffers::Shadows => true,
+                    }
+                }
+                pub fn dimensions(&self, screen_dimensions: (u32, u32)) -> (u32, u32) {
+                    match self {
+                        Framebuffers::FirstPass => screen_dimensions,
+                        Framebuffers::Bloom => {
+                            let scale = |x: u32| ((x as f32 * 0.5).ceil() as u32).max(1);
+                            (scale(screen_dimensions.0), scale(screen_dimensions.1))
+                        },
+                        Framebuffers::Shadows => screen_dimensions,
+                    }
+                }
+            }
+        };
+        assert_eq!(expanded, expected.to_string());
+    }
+
+    #[test]
+    fn missing_name_is_an_error() {
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[color_texture("color")]
+                FirstPass,
+            }
+        });
+        assert!(message.contains("names"), "{}", message);
+    }
+
+    #[test]
+    fn framebuffer_without_buffers_is_an_error() {
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[name("Nothing")]
+                Nothing,
+            }
+        });
+        assert!(message.contains("needs a color texture"), "{}", message);
+    }
+
+    #[test]
+    fn repeated_attribute_is_an_error() {
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[name("First")]
+                #[color_texture("a")]
+                #[color_texture("b")]
+                FirstPass,
+            }
+        });
+        assert!(message.contains("twice"), "{}", message);
+    }
+
+    #[test]
+    fn non_positive_scale_is_an_error() {
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[name("First")]
+                #[depth]
+                #[scale(0.0)]
+                FirstPass,
+            }
+        });
+        assert!(message.contains("positive"), "{}", message);
+    }
+
+    #[test]
+    fn sized_framebuffers_ignore_the_screen() {
+        let expanded = expand(parse_quote! {
+            enum Framebuffers {
+                #[name("Shadow map")]
+                #[depth_texture("shadow")]
+                #[size(1024, 512)]
+                Shadow,
+            }
+        });
+        let expected = quote! {
+            pub fn dimensions(&self, screen_dimensions: (u32, u32)) -> (u32, u32) {
+                match self {
+                    Framebuffers::Shadow => (1024u32, 512u32),
+                }
+            }
+        };
+        assert!(expanded.contains(&expected.to_string()), "{}", expanded);
+    }
+
+    #[test]
+    fn invalid_sizes_are_errors() {
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[name("Shadow map")]
+                #[depth]
+                #[size(1024)]
+                Shadow,
+            }
+        });
+        assert!(message.contains("width and a height"), "{}", message);
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[name("Shadow map")]
+                #[depth]
+                #[size(0, 1024)]
+                Shadow,
+            }
+        });
+        assert!(message.contains("at least one pixel"), "{}", message);
+        let message = error(parse_quote! {
+            enum Framebuffers {
+                #[name("Shadow map")]
+                #[depth]
+                #[scale(0.5)]
+                #[size(1024, 1024)]
+                Shadow,
+            }
+        });
+        assert!(message.contains("both a scale and a size"), "{}", message);
+    }
+
+    #[test]
+    fn structs_are_rejected() {
+        let message = error(parse_quote! {
+            struct Framebuffer;
+        });
+        assert!(message.contains("only be derived for enums"), "{}", message);
+    }
+}
@@ -1,2 +1,4 @@
+mod framebuffer;
 mod shader;
+pub use framebuffer::expand_framebuffer_id;
 pub use shader::expand_shader_id;
@@ -1,7 +1,9 @@
 use proc_macro::TokenStream;
 use quote::quote;
+use syn::punctuated::Punctuated;
 use syn::Data;
 use syn::DeriveInput;
+use syn::{LitStr, Token};
 
 pub fn expand_shader_id(input: DeriveInput) -> TokenStream {
     // TODO: Make it so two shaders cannot have same name / extensionless path
@@ -10,6 +12,7 @@ pub fn expand_shader_id(input: DeriveInput) -> TokenStream {
     let mut names = Vec::new();
     let mut extensionless_paths = Vec::new();
     let mut are_compute = Vec::new();
+    let mut defines = Vec::new();
     let mut variants = Vec::new();
     if let Data::Enum(data_enum) = input.data {
         for variant in data_enum.variants.into_iter() {
@@ -18,6 +21,7 @@ pub fn expand_shader_id(input: DeriveInput) -> TokenStream {
             let mut has_name = false;
             let mut has_exp = false;
             let mut has_comp = false;
+            let mut variant_defines = None;
             for attr in attrs {
                 match &attr.path.get_ident().unwrap().to_string()[..] {
                     "name" => {
@@ -41,9 +45,20 @@ pub fn expand_shader_id(input: DeriveInput) -> TokenStream {
                         are_compute.push(attr.tokens);
                         has_comp = true;
                     }
+                    "defines" => {
+                        if variant_defines.is_some() {
+                            panic!("A shader identifier has been given two lists of defines!");
+                        }
+                        let list = attr
+                            .parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)
+                            .expect("Shader defines must be a list of strings!");
+                        variant_defines = Some(list.into_iter().collect::<Vec<_>>());
+                    }
                     _ => {}
                 }
             }
+            // Defines are optional, unlike the other attributes.
+            defines.push(variant_defines.unwrap_or_default());
         }
     }
 
@@ -82,6 +97,15 @@ pub fn expand_shader_id(input: DeriveInput) -> TokenStream {
         }
     });
 
+    let range = 0..variants.len();
+    let defs = range.map(|x| {
+        let id = &variants[x];
+        let res = &defines[x];
+        quote! {
+            #name::#id => &[#(#res),*],
+        }
+    });
+
     let k = quote! {
         #[automatically_derived]
         impl #name {
@@ -103,6 +127,12 @@ pub fn expand_shader_id(input: DeriveInput) -> TokenStream {
                     #(#cmps)*
                 }
             }
+            /// The defines injected into the shader's sources, each a name or a name followed by a value.
+            pub fn defines(&self) -> &'static [&'static str] {
+                match self {
+                    #(#defs)*
+                }
+            }
         }
     };
 
@@ -5,9 +5,21 @@ use syn::{parse_macro_input, DeriveInput};
 
 mod expansion;
 
-#[proc_macro_derive(ShaderId, attributes(extensionless_path, name, is_compute))]
+#[proc_macro_derive(ShaderId, attributes(extensionless_path, name, is_compute, defines))]
 pub fn derive_shader_id(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     expansion::expand_shader_id(input)
 }
+
+#[proc_macro_derive(
+    FramebufferId,
+    attributes(name, color_texture, depth_texture, depth, scale, size)
+)]
+pub fn derive_framebuffer_id(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expansion::expand_framebuffer_id(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
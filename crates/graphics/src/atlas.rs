@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+use utils::png_reader::{PngData, PngLoadError};
+use utils::ColorFormat;
+
+#[derive(Error, Debug)]
+pub enum AtlasError {
+    #[error("Atlas tile {name} is {width}x{height}, but tiles must be {tile_size}x{tile_size}")]
+    WrongTileSize {
+        name: String,
+        width: u32,
+        height: u32,
+        tile_size: u32,
+    },
+    #[error("Atlas tile {0} was added twice")]
+    DuplicateTile(String),
+    #[error("Failed to load atlas tile {0}: {1:?}")]
+    Load(String, PngLoadError),
+    #[error("Failed to read the atlas tile folder: {0:?}")]
+    Folder(utils::VisitDirError),
+}
+
+/// Says where each tile of a texture atlas is.
+/// Tiles are squares of the same size laid out in a grid, and are found by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextureAtlas {
+    tile_size: u32,
+    columns: u32,
+    rows: u32,
+    /// The column and row of each tile. Row 0 is at the start of the texture data.
+    tiles: HashMap<String, [u32; 2]>,
+}
+
+impl TextureAtlas {
+    /// The name of the tile that is always in an atlas built by `AtlasBuilder`,
+    /// meant to be used in place of tiles that don't exist.
+    pub const MISSING_TILE: &'static str = "missing";
+
+    /// The column and row of the tile with the given name.
+    pub fn tile(&self, name: &str) -> Option<[u32; 2]> {
+        self.tiles.get(name).copied()
+    }
+
+    /// The width and height of each tile in pixels.
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// The number of tiles across and down the atlas.
+    pub fn grid_size(&self) -> (u32, u32) {
+        (self.columns, self.rows)
+    }
+
+    /// The width and height of the atlas in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.columns * self.tile_size, self.rows * self.tile_size)
+    }
+
+    pub fn tile_names(&self) -> impl Iterator<Item = &String> {
+        self.tiles.keys()
+    }
+}
+
+/// Packs square images of the same size into one texture atlas.
+pub struct AtlasBuilder {
+    tile_size: u32,
+    tiles: Vec<(String, PngData)>,
+}
+
+impl AtlasBuilder {
+    /// The tile size of atlases with no tiles added.
+    const DEFAULT_TILE_SIZE: u32 = 16;
+
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder {
+            tile_size: 0,
+            tiles: Vec::new(),
+        }
+    }
+
+    /// Creates a builder with every png in the folder added as a tile,
+    /// named by its file name without the extension.
+    pub fn from_folder(folder: &Path) -> Result<AtlasBuilder, AtlasError> {
+        let mut builder = AtlasBuilder::new();
+        let entries = utils::dir_entries(folder, "").map_err(AtlasError::Folder)?;
+        for (entry, _) in entries {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "png") {
+                let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+                let image =
+                    utils::read_png(&path).map_err(|error| AtlasError::Load(name.clone(), error))?;
+                builder.add(&name, image)?;
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Adds a tile. Every tile must be square and the same size as the first tile.
+    pub fn add(&mut self, name: &str, image: PngData) -> Result<(), AtlasError> {
+        if self.tiles.is_empty() {
+            self.tile_size = image.width;
+        }
+        if image.width != self.tile_size || image.height != self.tile_size {
+            return Err(AtlasError::WrongTileSize {
+                name: name.to_string(),
+                width: image.width,
+                height: image.height,
+                tile_size: self.tile_size,
+            });
+        }
+        if name == TextureAtlas::MISSING_TILE || self.tiles.iter().any(|(tile, _)| tile == name) {
+            return Err(AtlasError::DuplicateTile(name.to_string()));
+        }
+        self.tiles.push((name.to_string(), image));
+        Ok(())
+    }
+
+    /// A magenta and black checkerboard.
+    fn missing_tile(tile_size: u32) -> PngData {
+        let half = (tile_size / 2).max(1);
+        let data = (0..tile_size * tile_size)
+            .flat_map(|i| {
+                let (x, y) = (i % tile_size, i / tile_size);
+                if (x / half + y / half).is_multiple_of(2) {
+                    [255, 0, 255, 255]
+                } else {
+                    [0, 0, 0, 255]
+                }
+            })
+            .collect();
+        PngData {
+            width: tile_size,
+            height: tile_size,
+            data,
+            format: ColorFormat::RGBA,
+        }
+    }
+
+    /// Lays the tiles out in a grid that is as close to square as possible.
+    /// Returns the RGBA image of the atlas and where each tile is in it.
+    pub fn build(mut self) -> (PngData, TextureAtlas) {
+        if self.tiles.is_empty() {
+            self.tile_size = Self::DEFAULT_TILE_SIZE;
+        }
+        let tile_size = self.tile_size;
+        self.tiles.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.tiles.insert(
+            0,
+            (
+                TextureAtlas::MISSING_TILE.to_string(),
+                Self::missing_tile(tile_size),
+            ),
+        );
+
+        let count = self.tiles.len() as u32;
+        let columns = (count as f32).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+        let width = columns * tile_size;
+        let mut data = vec![0; (width * rows * tile_size * 4) as usize];
+        let mut tiles = HashMap::new();
+        for (index, (name, image)) in self.tiles.into_iter().enumerate() {
+            let (column, row) = (index as u32 % columns, index as u32 / columns);
+            let channels = match image.format {
+                ColorFormat::RGBA => 4,
+                _ => 3,
+            };
+            for y in 0..tile_size {
+                for x in 0..tile_size {
+                    let source = ((y * tile_size + x) * channels) as usize;
+                    let target =
+                        (((row * tile_size + y) * width + column * tile_size + x) * 4) as usize;
+                    data[target..target + 3].copy_from_slice(&image.data[source..source + 3]);
+                    data[target + 3] = if channels == 4 {
+                        image.data[source + 3]
+                    } else {
+                        255
+                    };
+                }
+            }
+            tiles.insert(name, [column, row]);
+        }
+
+        let image = PngData {
+            width,
+            height: rows * tile_size,
+            data,
+            format: ColorFormat::RGBA,
+        };
+        let atlas = TextureAtlas {
+            tile_size,
+            columns,
+            rows,
+            tiles,
+        };
+        (image, atlas)
+    }
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_tile(size: u32, color: [u8; 3]) -> PngData {
+        PngData {
+            width: size,
+            height: size,
+            data: (0..size * size).flat_map(|_| color).collect(),
+            format: ColorFormat::RGB,
+        }
+    }
+
+    #[test]
+    fn packs_tiles_into_grid() {
+        let mut builder = AtlasBuilder::new();
+        builder.add("stone", solid_tile(2, [1, 2, 3])).unwrap();
+        builder.add("dirt", solid_tile(2, [4, 5, 6])).unwrap();
+        builder.add("grass", solid_tile(2, [7, 8, 9])).unwrap();
+        let (image, atlas) = builder.build();
+
+        assert_eq!(atlas.grid_size(), (2, 2));
+        assert_eq!((image.width, image.height), atlas.dimensions());
+        assert_eq!(atlas.tile(TextureAtlas::MISSING_TILE), Some([0, 0]));
+        assert_eq!(atlas.tile("dirt"), Some([1, 0]));
+        assert_eq!(atlas.tile("grass"), Some([0, 1]));
+        assert_eq!(atlas.tile("stone"), Some([1, 1]));
+        assert_eq!(atlas.tile("water"), None);
+
+        // The bottom right pixel of the stone tile.
+        let pixel = ((3 * image.width + 3) * 4) as usize;
+        assert_eq!(&image.data[pixel..pixel + 4], &[1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn rejects_mismatched_tiles() {
+        let mut builder = AtlasBuilder::new();
+        builder.add("stone", solid_tile(4, [0, 0, 0])).unwrap();
+        assert!(matches!(
+            builder.add("dirt", solid_tile(2, [0, 0, 0])),
+            Err(AtlasError::WrongTileSize { .. })
+        ));
+        assert!(matches!(
+            builder.add("stone", solid_tile(4, [0, 0, 0])),
+            Err(AtlasError::DuplicateTile(_))
+        ));
+    }
+}
@@ -1,8 +1,11 @@
 use crate::wrapper::{FramebufferMetadata, ShaderMetadata, TextureMetadata};
+use crate::TextureAtlas;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 ///TODO
 ///Contains the capabilities that the Graphics wrapper makes available to the packer.
+#[derive(Serialize, Deserialize)]
 pub struct GraphicsCapabilities {
     /// The number of avaliable VBOS in the 3d vertex array
     pub vbo_count: usize,
@@ -13,6 +16,8 @@ pub struct GraphicsCapabilities {
     pub shader_metadata: Vec<ShaderMetadata>,
     /// A hashmap of framebuffer names and their metadata
     pub framebuffer_metadata: Vec<FramebufferMetadata>,
+    /// Where each block texture is in the block atlas texture.
+    pub block_atlas: TextureAtlas,
     /// A tuple of current (width,height) of the screen.
     pub screen_dimensions: (u32, u32),
 }
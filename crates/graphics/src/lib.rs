@@ -2,12 +2,17 @@ extern crate nalgebra_glm as glm;
 
 pub mod pack;
 pub mod model;
+pub mod atlas;
+pub mod software;
+pub mod validation;
 mod wrapper;
 
 //TODO: Move these things under here out of wrapper.
-pub use wrapper::{ShaderIdentifier, ShaderMetadata, BufferTarget, ProgramType, TextureMetadata, InternalFormat, FramebufferIdentifier, FramebufferMetadata, RenderCaller};
+pub use wrapper::{ShaderIdentifier, ShaderMetadata, BufferTarget, ProgramType, TextureMetadata, InternalFormat, FramebufferIdentifier, FramebufferMetadata, RenderCaller, ShaderReload, FOG_DISTANCE, SHADER_DIRECTORY, SHADOW_CASCADES};
+pub use wrapper::{LineOrigin, PreprocessError, PreprocessedSource, Preprocessor};
 pub use wrapper::gui::Gui;
 pub use wrapper::VERTEX_BUFFER_METADATA;
+pub use wrapper::BLOCK_ATLAS_TEXTURE;
 
 mod external_event;
 pub use external_event::ExternalEvent;
@@ -16,4 +21,7 @@ mod render_messages;
 pub use render_messages::{RenderMessage, RenderMessages, UniformData, VertexPack};
 
 mod capabilities;
-pub use capabilities::GraphicsCapabilities;
\ No newline at end of file
+pub use capabilities::GraphicsCapabilities;
+pub use atlas::TextureAtlas;
+pub use software::SoftwareRenderer;
+pub use validation::{RenderMessageValidator, ValidationMode};
@@ -230,6 +230,7 @@ impl Default for Model {
 ///
 pub struct PlacedModel {
     model_name: String,
+    /// Where the model is, in the coordinates of the view projection matrix it is drawn with.
     position: glm::Vec3,
     scale: glm::Vec3,
 }
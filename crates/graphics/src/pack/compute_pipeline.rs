@@ -1,3 +1,4 @@
+use super::PostProcessingConfig;
 use crate::UniformData;
 use crate::{wrapper::ShaderIdentifier, RenderMessage, RenderMessages};
 
@@ -19,6 +20,28 @@ impl ComputePipeline {
         }
     }
 
+    /// Creates a dispatch for every pass of the post processing config that runs, in order.
+    pub fn from_config(
+        config: &PostProcessingConfig,
+        screen_dimensions: (u32, u32),
+    ) -> ComputePipeline {
+        let mut pipeline = ComputePipeline::new();
+        for pass in config.active_passes() {
+            let mut uniforms = UniformData::new();
+            for (uniform, texture) in &pass.inputs {
+                uniforms.texture(texture.clone(), uniform.as_str());
+            }
+            let (width, height) = pass.output_metadata().dimensions(screen_dimensions);
+            pipeline.add_dispatch(ComputeDispatch::new(
+                pass.shader,
+                uniforms,
+                pass.output.as_str(),
+                (width, height, 1),
+            ));
+        }
+        pipeline
+    }
+
     pub fn add_dispatch(&mut self, dispatch: ComputeDispatch) {
         self.dispatches.push(dispatch);
     }
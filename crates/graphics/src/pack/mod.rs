@@ -2,3 +2,9 @@ pub mod cube_faces;
 
 mod compute_pipeline;
 pub use compute_pipeline::{ComputeDispatch, ComputePipeline};
+
+mod post_processing;
+pub use post_processing::{
+    PostProcessingConfig, PostProcessingError, PostProcessingPass, PostProcessingWatcher,
+    POST_PROCESSING_CONFIG,
+};
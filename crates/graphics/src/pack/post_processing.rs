@@ -0,0 +1,381 @@
+use crate::{
+    BufferTarget, InternalFormat, RenderMessage, RenderMessages, ShaderIdentifier, TextureMetadata,
+    UniformData, VertexPack,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+use utils::{ColorFormat, Vertex3D};
+
+/// Where the post processing pipeline of the game is described, relative to the assets folder.
+pub const POST_PROCESSING_CONFIG: &str = "graphics/post_processing.json";
+
+#[derive(Error, Debug)]
+pub enum PostProcessingError {
+    #[error("Failed to read post processing config {0:?}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Json error in post processing config {0:?}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("Post processing passes {0} and {1} both write to texture {2}")]
+    DuplicateOutput(String, String, String),
+    #[error("Post processing pass {0} writes to the source texture {1}")]
+    WritesSource(String, String),
+}
+
+///
+/// One compute shader dispatch in the post processing pipeline.
+/// The shader runs once for every texel of the output texture.
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostProcessingPass {
+    /// Used to refer to the pass when enabling or disabling it.
+    pub name: String,
+    #[serde(default = "PostProcessingPass::default_enabled")]
+    pub enabled: bool,
+    pub shader: ShaderIdentifier,
+    /// Which texture is given to each texture uniform of the shader.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+    /// The texture the shader writes to. It is created by the texture loader.
+    pub output: String,
+    #[serde(default = "PostProcessingPass::default_format")]
+    pub format: ColorFormat,
+    #[serde(default = "PostProcessingPass::default_internal_format")]
+    pub internal_format: InternalFormat,
+    /// How large the output texture is compared to the screen.
+    #[serde(default = "PostProcessingPass::default_scale")]
+    pub scale: f32,
+}
+
+impl PostProcessingPass {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_format() -> ColorFormat {
+        ColorFormat::RGBA
+    }
+
+    fn default_internal_format() -> InternalFormat {
+        InternalFormat::RGBA8
+    }
+
+    fn default_scale() -> f32 {
+        1.
+    }
+
+    /// The metadata of the texture this pass writes to.
+    pub fn output_metadata(&self) -> TextureMetadata {
+        TextureMetadata {
+            format: self.format,
+            internal_format: self.internal_format,
+            width: 0,
+            height: 0,
+            name: self.output.clone(),
+            screen_dependant_dimensions: true,
+            screen_scale: self.scale,
+        }
+    }
+}
+
+///
+/// The post processing pipeline: the compute passes run on the rendered scene, in order,
+/// and the texture that is drawn to the screen after them.
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostProcessingConfig {
+    /// The texture the scene is rendered to, which is drawn to the screen if no passes run.
+    pub source: String,
+    pub passes: Vec<PostProcessingPass>,
+}
+
+impl PostProcessingConfig {
+    pub fn from_json(path: &Path, json: &str) -> Result<PostProcessingConfig, PostProcessingError> {
+        let config: PostProcessingConfig = serde_json::from_str(json)
+            .map_err(|error| PostProcessingError::Parse(path.to_owned(), error))?;
+        let mut outputs: Vec<&PostProcessingPass> = Vec::new();
+        for pass in &config.passes {
+            if pass.output == config.source {
+                return Err(PostProcessingError::WritesSource(
+                    pass.name.clone(),
+                    pass.output.clone(),
+                ));
+            }
+            if let Some(other) = outputs.iter().find(|other| other.output == pass.output) {
+                return Err(PostProcessingError::DuplicateOutput(
+                    other.name.clone(),
+                    pass.name.clone(),
+                    pass.output.clone(),
+                ));
+            }
+            outputs.push(pass);
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<PostProcessingConfig, PostProcessingError> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|error| PostProcessingError::Read(path.to_owned(), error))?;
+        PostProcessingConfig::from_json(path, &json)
+    }
+
+    ///
+    /// The passes that run, in order.
+    /// A pass runs if it is enabled and doesn't read the output of a pass that doesn't run,
+    /// since that texture would never be written.
+    ///
+    pub fn active_passes(&self) -> Vec<&PostProcessingPass> {
+        let outputs: HashSet<&str> = self
+            .passes
+            .iter()
+            .map(|pass| pass.output.as_str())
+            .collect();
+        let mut written = HashSet::new();
+        let mut active = Vec::new();
+        for pass in &self.passes {
+            let inputs_written = pass
+                .inputs
+                .values()
+                .all(|input| !outputs.contains(input.as_str()) || written.contains(input.as_str()));
+            if pass.enabled && inputs_written {
+                written.insert(pass.output.as_str());
+                active.push(pass);
+            }
+        }
+        active
+    }
+
+    /// The texture drawn to the screen: the output of the last pass that runs.
+    pub fn final_texture(&self) -> &str {
+        self.active_passes()
+            .last()
+            .map_or(&self.source, |pass| &pass.output)
+    }
+
+    /// Enables or disables the pass with the given name. Returns false if there is no such pass.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.passes.iter_mut().find(|pass| pass.name == name) {
+            Some(pass) => {
+                pass.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The textures every pass writes to, whether it runs or not, so passes can be enabled without new textures.
+    pub fn output_textures(&self) -> impl Iterator<Item = TextureMetadata> + '_ {
+        self.passes.iter().map(PostProcessingPass::output_metadata)
+    }
+
+    ///
+    /// Draws the final texture over the whole screen, using the scratch buffer for the fullscreen quad.
+    /// The depth buffer is cleared as well, so anything drawn after this ends up on top.
+    ///
+    pub fn composite_messages(&self) -> RenderMessages {
+        let buffer = BufferTarget::ScratchBuffer;
+        let mut messages = RenderMessages::new();
+        messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Simple,
+        });
+        let mut uniforms = UniformData::new();
+        uniforms.texture(self.final_texture().to_owned(), "tex");
+        messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(uniforms),
+        });
+        messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        messages.add_message(RenderMessage::ClearBuffers {
+            color_buffer: true,
+            depth_buffer: true,
+        });
+        messages.add_message(RenderMessage::Pack {
+            buffer,
+            pack: fullscreen_quad(),
+        });
+        messages.add_message(RenderMessage::Draw { buffer });
+        messages.add_message(RenderMessage::ClearArray { buffer });
+        messages
+    }
+}
+
+/// A quad covering the whole screen, with texture coordinates going from 0 to 1 across it.
+fn fullscreen_quad() -> VertexPack {
+    let corner = |x: f32, y: f32| Vertex3D {
+        x,
+        y,
+        z: -1.,
+        r: 1.,
+        g: 1.,
+        b: 1.,
+        u: (x + 1.) / 2.,
+        v: (y + 1.) / 2.,
+    };
+    VertexPack::new(
+        vec![
+            corner(-1., -1.),
+            corner(1., -1.),
+            corner(1., 1.),
+            corner(-1., 1.),
+        ],
+        Some(vec![0, 1, 2, 0, 2, 3]),
+    )
+}
+
+///
+/// Keeps the post processing config up to date with its file, so passes can be
+/// enabled, disabled or reordered while the game runs.
+///
+pub struct PostProcessingWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    config: PostProcessingConfig,
+}
+
+impl PostProcessingWatcher {
+    pub fn new(path: PathBuf) -> Result<PostProcessingWatcher, PostProcessingError> {
+        let modified = modified_time(&path);
+        let config = PostProcessingConfig::load(&path)?;
+        Ok(PostProcessingWatcher {
+            path,
+            modified,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &PostProcessingConfig {
+        &self.config
+    }
+
+    /// Changes made here last until the file changes and the config is reloaded.
+    pub fn config_mut(&mut self) -> &mut PostProcessingConfig {
+        &mut self.config
+    }
+
+    ///
+    /// Reloads the config if its file changed since it was last loaded. Returns whether it was reloaded.
+    /// If the new config is broken, the old config is kept until the file changes again.
+    ///
+    pub fn reload_if_changed(&mut self) -> Result<bool, PostProcessingError> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+        self.config = PostProcessingConfig::load(&self.path)?;
+        Ok(true)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> Result<PostProcessingConfig, PostProcessingError> {
+        PostProcessingConfig::from_json(Path::new("test.json"), json)
+    }
+
+    fn chain() -> PostProcessingConfig {
+        config(
+            r#"{
+                "source": "color",
+                "passes": [
+                    {"name": "edges", "shader": "Sobel", "inputs": {"from_tex": "color"}, "output": "edges"},
+                    {"name": "half", "shader": "Transfer", "inputs": {"from_tex": "color"}, "output": "half", "scale": 0.5},
+                    {"name": "style", "shader": "Artsyfartsy", "inputs": {"sobel_tex": "edges", "color_tex": "color"}, "output": "styled"}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn active_names(config: &PostProcessingConfig) -> Vec<&str> {
+        config
+            .active_passes()
+            .iter()
+            .map(|pass| pass.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn game_config_loads() {
+        let config =
+            PostProcessingConfig::load(&utils::ASSETS_PATH.join(POST_PROCESSING_CONFIG)).unwrap();
+        assert!(!config.active_passes().is_empty());
+    }
+
+    #[test]
+    fn defaults_are_filled_in() {
+        let config = chain();
+        let pass = &config.passes[0];
+        assert!(pass.enabled);
+        assert_eq!(pass.scale, 1.);
+        assert!(matches!(pass.format, ColorFormat::RGBA));
+        assert_eq!(config.passes[1].scale, 0.5);
+    }
+
+    #[test]
+    fn disabling_a_pass_disables_the_passes_reading_it() {
+        let mut config = chain();
+        assert_eq!(active_names(&config), ["edges", "half", "style"]);
+        assert_eq!(config.final_texture(), "styled");
+
+        assert!(config.set_enabled("edges", false));
+        assert_eq!(active_names(&config), ["half"]);
+        assert_eq!(config.final_texture(), "half");
+
+        assert!(config.set_enabled("half", false));
+        assert!(active_names(&config).is_empty());
+        assert_eq!(config.final_texture(), "color");
+        assert!(!config.set_enabled("missing", false));
+    }
+
+    #[test]
+    fn reading_a_later_output_disables_the_pass() {
+        let mut config = chain();
+        config.passes.swap(0, 2);
+        assert_eq!(active_names(&config), ["half", "edges"]);
+    }
+
+    #[test]
+    fn broken_configs_are_rejected() {
+        let duplicate = config(
+            r#"{"source": "color", "passes": [
+                {"name": "a", "shader": "Sobel", "output": "out"},
+                {"name": "b", "shader": "Transfer", "output": "out"}
+            ]}"#,
+        );
+        assert!(matches!(
+            duplicate,
+            Err(PostProcessingError::DuplicateOutput(..))
+        ));
+        let writes_source = config(
+            r#"{"source": "color", "passes": [{"name": "a", "shader": "Sobel", "output": "color"}]}"#,
+        );
+        assert!(matches!(
+            writes_source,
+            Err(PostProcessingError::WritesSource(..))
+        ));
+        assert!(matches!(
+            config(
+                r#"{"source": "color", "passes": [{"name": "a", "shader": "Nope", "output": "b"}]}"#
+            ),
+            Err(PostProcessingError::Parse(..))
+        ));
+    }
+
+    #[test]
+    fn output_textures_follow_the_screen() {
+        let textures: Vec<_> = chain().output_textures().collect();
+        assert_eq!(textures.len(), 3);
+        assert_eq!(textures[0].dimensions((100, 75)), (100, 75));
+        assert_eq!(textures[1].dimensions((100, 75)), (50, 38));
+    }
+}
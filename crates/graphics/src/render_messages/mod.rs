@@ -6,9 +6,10 @@ pub use vertex_pack::VertexPack;
 
 use crate::wrapper::BufferTarget;
 use crate::wrapper::{FramebufferIdentifier, ShaderIdentifier};
+use serde::{Deserialize, Serialize};
 use std::slice::Iter;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RenderMessages {
     messages: Vec<RenderMessage>,
     /// TODO: THIS IS SUCH A BAD NAME
@@ -62,6 +63,14 @@ impl RenderMessages {
         self.messages = new_messages;
     }
 
+    /// Removes the persistent render messages that were merged in from old render packs.
+    /// Used when the old render packs are known to have reached the window already,
+    /// like when replaying every recorded render pack in order.
+    pub fn discard_old(&mut self) {
+        self.messages.drain(..self.old_new_split_index);
+        self.old_new_split_index = 0;
+    }
+
     /// Merges a current render pack onto the end of this one.
     pub fn merge_current(&mut self, new_pack: RenderMessages) {
         let mut new_pack = new_pack;
@@ -77,7 +86,7 @@ impl Default for RenderMessages {
 
 /// One render message to the graphics thread.
 /// TODO: Documentation & contract checking
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum RenderMessage {
     /// buffer = which buffer in the vertex array to target
     Pack {
@@ -116,6 +125,12 @@ pub enum RenderMessage {
     /// Switches the rendering to a 2D context.
     SwitchTo2D {},
     SwitchTo3D {},
+    /// Turns alpha blending on or off. While it is on, drawn fragments are blended with what is
+    /// already drawn by their alpha, and don't write to the depth buffer.
+    /// Blending has to be turned off again before the end of the render messages.
+    Blending {
+        enabled: bool,
+    },
 }
 
 impl RenderMessage {
@@ -140,6 +155,7 @@ impl RenderMessage {
             } => false,
             RenderMessage::SwitchTo2D {} => false,
             RenderMessage::SwitchTo3D {} => false,
+            RenderMessage::Blending { enabled: _ } => false,
         }
     }
 }
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 // OLD TT-MUNCHER STYLE UNIFORM MACROS
 /* macro_rules! uniform_data {
     ( () -> {struct UniformData $( ($name:ident : $type:ty) )*}) => {
@@ -65,7 +67,7 @@ macro_rules! create_uniform_data {
 
 macro_rules! create_uniform_data {
     ( $($name:ident ($type:ty), )* ) => {
-        #[derive(Debug)]
+        #[derive(Debug, Serialize, Deserialize)]
         pub struct UniformData {
             $(pub $name : Vec<($type, String)>,)*
         }
@@ -1,7 +1,8 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use utils::Vertex3D;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct VertexPack {
     pub vertices: Vec<Vertex3D>,
     pub elements: Vec<u32>,
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use thiserror::Error;
+use utils::png_reader::{PngData, PngLoadError, PngSaveError};
+
+/// When this environment variable is set, golden image comparisons save the image as the new golden image
+/// instead of comparing them.
+pub const UPDATE_GOLDEN_VARIABLE: &str = "FLEXBLOCK_UPDATE_GOLDEN";
+
+/// How much a channel can differ from the golden image without the pixel counting as different,
+/// so small rounding differences between platforms are allowed.
+const CHANNEL_TOLERANCE: u8 = 2;
+/// The fraction of pixels that can be different before the images don't match.
+const DIFFERENT_PIXEL_TOLERANCE: f32 = 0.001;
+
+#[derive(Error, Debug)]
+pub enum GoldenImageError {
+    #[error("Golden image {0:?} is missing. Run with {UPDATE_GOLDEN_VARIABLE} set to create it.")]
+    Missing(PathBuf),
+    #[error("Failed to load golden image {0:?}: {1:?}")]
+    Load(PathBuf, PngLoadError),
+    #[error("Failed to save image {0:?}: {1:?}")]
+    Save(PathBuf, PngSaveError),
+    #[error("Image is {actual:?}, but golden image {golden:?} is {expected:?}. The image was saved to {actual_path:?}")]
+    WrongDimensions {
+        golden: PathBuf,
+        expected: (u32, u32),
+        actual: (u32, u32),
+        actual_path: PathBuf,
+    },
+    #[error("{different_pixels} pixels differ from golden image {golden:?}. The image was saved to {actual_path:?}")]
+    Different {
+        golden: PathBuf,
+        different_pixels: usize,
+        actual_path: PathBuf,
+    },
+}
+
+/// The golden image with the given name.
+fn golden_path(name: &str) -> PathBuf {
+    utils::ASSETS_PATH
+        .join("graphics/golden")
+        .join(name)
+        .with_extension("png")
+}
+
+fn save(path: PathBuf, image: &PngData) -> Result<PathBuf, GoldenImageError> {
+    match utils::write_png(&path, image) {
+        Ok(()) => Ok(path),
+        Err(error) => Err(GoldenImageError::Save(path, error)),
+    }
+}
+
+/// The channels of each pixel. Images without alpha are compared as if they were opaque.
+fn pixels(image: &PngData) -> Vec<[u8; 4]> {
+    match image.format {
+        utils::ColorFormat::RGBA => image
+            .data
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect(),
+        _ => image
+            .data
+            .chunks_exact(3)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+    }
+}
+
+///
+/// Compares a rendered image to the golden image with the given name in `assets/graphics/golden`.
+/// If they don't match, the rendered image is saved to the temporary directory so it can be looked at.
+///
+/// When `FLEXBLOCK_UPDATE_GOLDEN` is set, the rendered image becomes the golden image instead.
+///
+pub fn compare_with_golden(image: &PngData, name: &str) -> Result<(), GoldenImageError> {
+    let golden = golden_path(name);
+    if std::env::var_os(UPDATE_GOLDEN_VARIABLE).is_some() {
+        if let Some(parent) = golden.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        return save(golden, image).map(|_| ());
+    }
+    if !golden.is_file() {
+        return Err(GoldenImageError::Missing(golden));
+    }
+    let expected = match utils::read_png(&golden) {
+        Ok(expected) => expected,
+        Err(error) => return Err(GoldenImageError::Load(golden, error)),
+    };
+    let actual_path = std::env::temp_dir().join(format!("{}.actual.png", name));
+
+    if (expected.width, expected.height) != (image.width, image.height) {
+        return Err(GoldenImageError::WrongDimensions {
+            golden,
+            expected: (expected.width, expected.height),
+            actual: (image.width, image.height),
+            actual_path: save(actual_path, image)?,
+        });
+    }
+    let different_pixels = pixels(&expected)
+        .iter()
+        .zip(pixels(image).iter())
+        .filter(|(expected, actual)| {
+            expected
+                .iter()
+                .zip(actual.iter())
+                .any(|(expected, actual)| expected.abs_diff(*actual) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    if different_pixels as f32 > DIFFERENT_PIXEL_TOLERANCE * (image.width * image.height) as f32 {
+        return Err(GoldenImageError::Different {
+            golden,
+            different_pixels,
+            actual_path: save(actual_path, image)?,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,484 @@
+//! A CPU renderer that reads the same render messages as `RenderCaller`, without needing GL.
+//! Used for screenshots where there is no GPU, and for golden image tests.
+
+mod golden;
+pub use golden::{compare_with_golden, GoldenImageError, UPDATE_GOLDEN_VARIABLE};
+
+mod raster;
+mod shaders;
+mod texture;
+
+use crate::wrapper::load_texture_sources;
+use crate::{
+    BufferTarget, FramebufferIdentifier, FramebufferMetadata, GraphicsCapabilities, InternalFormat,
+    RenderMessage, ShaderIdentifier, ShaderMetadata, TextureAtlas, TextureMetadata, UniformData,
+    VertexPack, VERTEX_BUFFER_METADATA,
+};
+use log::error;
+use raster::{RasterSettings, RenderTarget};
+use shaders::{StoredUniform, Uniforms};
+use std::collections::HashMap;
+use strum::{EnumCount, IntoEnumIterator};
+use texture::SoftwareTexture;
+use utils::png_reader::PngData;
+use utils::ColorFormat;
+
+/// The color buffers are cleared to. The same as the one `RenderCaller` sets.
+const CLEAR_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+///
+/// Renders render messages into images in memory.
+///
+/// Mirrors the state `RenderCaller` keeps in GL: packed buffers, the chosen shader and framebuffer,
+/// uniforms of every shader, and depth testing, culling, and blending.
+/// Shaders are CPU versions of the GLSL shaders, and textures are loaded from the same assets.
+///
+pub struct SoftwareRenderer {
+    /// The packs in each vertex buffer, indexed like the GL vertex array.
+    buffers: Vec<Option<VertexPack>>,
+    textures: HashMap<String, SoftwareTexture>,
+    block_atlas: TextureAtlas,
+    shader_metadata: Vec<ShaderMetadata>,
+    /// The color and depth buffer of the screen.
+    screen: RenderTarget,
+    screen_dimensions: (u32, u32),
+    /// Depth buffers of framebuffers that have depth, but no depth texture.
+    depth_renderbuffers: HashMap<usize, SoftwareTexture>,
+    framebuffer: Option<FramebufferIdentifier>,
+    shader: Option<ShaderIdentifier>,
+    /// The uniforms last given to each shader, indexed by shader identifier.
+    /// Like in GL, a shader keeps its uniforms when another shader is chosen.
+    uniforms: Vec<HashMap<String, StoredUniform>>,
+    depth_test: bool,
+    cull_faces: bool,
+    blending: bool,
+}
+
+impl SoftwareRenderer {
+    pub fn new(screen_dimensions: (u32, u32)) -> SoftwareRenderer {
+        let (sources, block_atlas) = load_texture_sources(screen_dimensions);
+        let textures = sources
+            .into_iter()
+            .map(|source| {
+                let texture = SoftwareTexture::new(source.metadata, source.data.as_deref());
+                (texture.metadata.name.clone(), texture)
+            })
+            .collect();
+        let shader_metadata = ShaderIdentifier::iter()
+            .map(|identifier| match ShaderMetadata::from_source(identifier) {
+                Ok(metadata) => metadata,
+                Err(s) => panic!("Loading shader {:?} failed! Error: {}", identifier, s),
+            })
+            .collect();
+
+        let mut renderer = SoftwareRenderer {
+            buffers: vec![None; VERTEX_BUFFER_METADATA.default_vbo_count()],
+            textures,
+            block_atlas,
+            shader_metadata,
+            screen: RenderTarget::default(),
+            screen_dimensions,
+            depth_renderbuffers: HashMap::new(),
+            framebuffer: None,
+            shader: None,
+            uniforms: vec![HashMap::new(); ShaderIdentifier::COUNT],
+            depth_test: true,
+            cull_faces: true,
+            blending: false,
+        };
+        renderer.create_render_targets();
+        renderer
+    }
+
+    /// Creates the screen and the depth buffers of framebuffers at the current screen dimensions.
+    fn create_render_targets(&mut self) {
+        let metadata = |name: &str, format, internal_format, (width, height)| TextureMetadata {
+            format,
+            internal_format,
+            width,
+            height,
+            name: name.to_owned(),
+            screen_dependant_dimensions: true,
+            screen_scale: 1.,
+        };
+        let clear = |mut texture: SoftwareTexture, value: [f32; 4]| {
+            texture.clear(value.into());
+            texture
+        };
+        self.screen = RenderTarget {
+            color: Some(clear(
+                SoftwareTexture::new(
+                    metadata(
+                        "screen",
+                        ColorFormat::RGB,
+                        InternalFormat::RGB8,
+                        self.screen_dimensions,
+                    ),
+                    None,
+                ),
+                CLEAR_COLOR,
+            )),
+            depth: Some(clear(
+                SoftwareTexture::new(
+                    metadata(
+                        "screen depth",
+                        ColorFormat::D,
+                        InternalFormat::D16,
+                        self.screen_dimensions,
+                    ),
+                    None,
+                ),
+                [1.; 4],
+            )),
+        };
+        self.depth_renderbuffers = FramebufferIdentifier::iter()
+            .filter(|identifier| identifier.has_depth() && identifier.depth_texture().is_none())
+            .map(|identifier| {
+                let dimensions = identifier.dimensions(self.screen_dimensions);
+                let depth = SoftwareTexture::new(
+                    metadata(
+                        identifier.name(),
+                        ColorFormat::D,
+                        InternalFormat::D16,
+                        dimensions,
+                    ),
+                    None,
+                );
+                (identifier as usize, clear(depth, [1.; 4]))
+            })
+            .collect();
+    }
+
+    /// Resizes the screen and every texture that follows it. Their contents are lost.
+    pub fn update_screen_dimensions(&mut self, screen_dimensions: (u32, u32)) {
+        self.screen_dimensions = screen_dimensions;
+        for texture in self.textures.values_mut() {
+            if texture.metadata.screen_dependant_dimensions {
+                let mut metadata = texture.metadata.clone();
+                (metadata.width, metadata.height) = metadata.dimensions(screen_dimensions);
+                *texture = SoftwareTexture::new(metadata, None);
+            }
+        }
+        self.create_render_targets();
+    }
+
+    ///
+    /// Changes the number of world buffers chunks can be packed into.
+    /// The contents of removed world buffers are lost.
+    pub fn set_world_buffer_count(&mut self, world_buffer_count: usize) {
+        self.buffers.resize(
+            VERTEX_BUFFER_METADATA.world_buffer_start() + world_buffer_count,
+            None,
+        );
+    }
+
+    pub fn get_vbo_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// What the renderer makes available to the packer, like the capabilities the window sends.
+    pub fn capabilities(&self) -> GraphicsCapabilities {
+        GraphicsCapabilities {
+            vbo_count: self.buffers.len(),
+            texture_metadata: self
+                .textures
+                .iter()
+                .map(|(name, texture)| (name.clone(), texture.metadata.clone()))
+                .collect(),
+            shader_metadata: self.shader_metadata.clone(),
+            framebuffer_metadata: FramebufferIdentifier::iter()
+                .map(|identifier| {
+                    let (width, height) = identifier.dimensions(self.screen_dimensions);
+                    FramebufferMetadata {
+                        identifier,
+                        width,
+                        height,
+                    }
+                })
+                .collect(),
+            block_atlas: self.block_atlas.clone(),
+            screen_dimensions: self.screen_dimensions,
+        }
+    }
+
+    /// What is on the screen, with the top row first.
+    pub fn screenshot(&self) -> PngData {
+        self.screen.color.as_ref().unwrap().to_png()
+    }
+
+    /// The contents of a texture, with the top row first like a screenshot.
+    /// Returns None if there's no texture with the name.
+    pub fn texture_image(&self, name: &str) -> Option<PngData> {
+        self.textures.get(name).map(SoftwareTexture::to_png)
+    }
+
+    pub fn read_message(&mut self, message: &RenderMessage) {
+        match message {
+            RenderMessage::Pack { buffer, pack } => self.pack(buffer, pack),
+            RenderMessage::ClearArray { buffer } => self.clear(buffer),
+            RenderMessage::ChooseShader { shader } => self.shader = Some(*shader),
+            RenderMessage::Uniforms { uniforms } => self.uniforms(uniforms),
+            RenderMessage::Draw { buffer } => self.render(buffer),
+            RenderMessage::ClearBuffers {
+                color_buffer,
+                depth_buffer,
+            } => self.clear_buffers(*color_buffer, *depth_buffer),
+            RenderMessage::ChooseFramebuffer { framebuffer } => {
+                self.framebuffer = *framebuffer;
+                self.depth_test = framebuffer.is_none_or(|framebuffer| framebuffer.has_depth());
+            }
+            RenderMessage::Compute {
+                output_texture,
+                dimensions,
+            } => self.dispatch_compute(output_texture, *dimensions),
+            RenderMessage::SwitchTo2D {} => {
+                self.clear_buffers(false, true);
+                self.depth_test = false;
+                self.cull_faces = false;
+            }
+            RenderMessage::SwitchTo3D {} => {
+                self.depth_test = true;
+                self.cull_faces = true;
+            }
+            RenderMessage::Blending { enabled } => self.blending = *enabled,
+        }
+    }
+
+    /// The index of the buffer in the vertex buffers, or None if there's no such buffer.
+    fn buffer_index(&self, buffer: &BufferTarget) -> Option<usize> {
+        let target_id = buffer.get_target_id(&VERTEX_BUFFER_METADATA);
+        if target_id < self.buffers.len() {
+            Some(target_id)
+        } else {
+            error!(
+                "Trying to use {}, but there's only {} buffers",
+                buffer,
+                self.buffers.len()
+            );
+            None
+        }
+    }
+
+    fn pack(&mut self, buffer: &BufferTarget, pack: &VertexPack) {
+        if let Some(target_id) = self.buffer_index(buffer) {
+            self.buffers[target_id] = Some(pack.clone());
+        }
+    }
+
+    fn clear(&mut self, buffer: &BufferTarget) {
+        if let Some(target_id) = self.buffer_index(buffer) {
+            self.buffers[target_id] = None;
+        }
+    }
+
+    fn uniforms(&mut self, uniforms: &UniformData) {
+        let shader = match self.shader {
+            Some(shader) => shader,
+            None => {
+                error!("Uniforms were sent, but there's no bound shader!");
+                return;
+            }
+        };
+        for uniform in uniforms.get_uniforms() {
+            if let Some(value) = StoredUniform::from_value(&uniform.value) {
+                self.uniforms[shader as usize].insert(uniform.location.clone(), value);
+            }
+        }
+    }
+
+    /// Takes the textures of the chosen framebuffer out of the renderer, so they can be drawn to
+    /// while the other textures are sampled. They have to be put back with `restore_render_target`.
+    fn take_render_target(&mut self) -> RenderTarget {
+        match self.framebuffer {
+            None => std::mem::take(&mut self.screen),
+            Some(framebuffer) => RenderTarget {
+                color: framebuffer
+                    .color_texture()
+                    .and_then(|name| self.textures.remove(name)),
+                depth: match framebuffer.depth_texture() {
+                    Some(name) => self.textures.remove(name),
+                    None => self.depth_renderbuffers.remove(&(framebuffer as usize)),
+                },
+            },
+        }
+    }
+
+    fn restore_render_target(&mut self, target: RenderTarget) {
+        match self.framebuffer {
+            None => self.screen = target,
+            Some(framebuffer) => {
+                if let Some(color) = target.color {
+                    self.textures.insert(color.metadata.name.clone(), color);
+                }
+                if let Some(depth) = target.depth {
+                    if framebuffer.depth_texture().is_some() {
+                        self.textures.insert(depth.metadata.name.clone(), depth);
+                    } else {
+                        self.depth_renderbuffers.insert(framebuffer as usize, depth);
+                    }
+                }
+            }
+        }
+    }
+
+    fn clear_buffers(&mut self, color_buffer: bool, depth_buffer: bool) {
+        let mut target = self.take_render_target();
+        if let (true, Some(color)) = (color_buffer, target.color.as_mut()) {
+            color.clear(CLEAR_COLOR.into());
+        }
+        // Like in GL, the depth buffer isn't written while blending, not even when clearing it.
+        if let (true, false, Some(depth)) = (depth_buffer, self.blending, target.depth.as_mut()) {
+            depth.clear(glm::vec4(1., 1., 1., 1.));
+        }
+        self.restore_render_target(target);
+    }
+
+    fn render(&mut self, buffer: &BufferTarget) {
+        let target_id = match self.buffer_index(buffer) {
+            Some(target_id) => target_id,
+            None => return,
+        };
+        let shader = match self.shader {
+            Some(shader) if !shader.is_compute() => shader,
+            _ => {
+                error!("Trying to render {} without a graphics shader!", buffer);
+                return;
+            }
+        };
+        let mut target = self.take_render_target();
+        let settings = RasterSettings {
+            depth_test: self.depth_test,
+            depth_write: !self.blending,
+            cull_back_faces: self.cull_faces,
+            blending: self.blending,
+        };
+        let uniforms = Uniforms {
+            values: &self.uniforms[shader as usize],
+            textures: &self.textures,
+        };
+        if let Some(pack) = &self.buffers[target_id] {
+            let vertices: Vec<_> = pack
+                .vertices
+                .iter()
+                .map(|vertex| shaders::vertex(shader, vertex, &uniforms))
+                .collect();
+            let elements: Vec<u32> = if pack.elements.is_empty() {
+                (0..vertices.len() as u32).collect()
+            } else {
+                pack.elements.clone()
+            };
+            let mut fragment_shader = |varyings: &_| shaders::fragment(shader, varyings, &uniforms);
+            for triangle in elements.chunks_exact(3) {
+                match triangle
+                    .iter()
+                    .map(|&i| vertices.get(i as usize).copied())
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(triangle) => raster::draw_triangle(
+                        &mut target,
+                        &settings,
+                        [triangle[0], triangle[1], triangle[2]],
+                        &mut fragment_shader,
+                    ),
+                    None => error!("An element in {} is out of bounds!", buffer),
+                }
+            }
+        } else {
+            error!("Trying to render {}, which is empty!", buffer);
+        }
+        self.restore_render_target(target);
+    }
+
+    fn dispatch_compute(&mut self, output_texture: &str, dimensions: (u32, u32, u32)) {
+        let shader = match self.shader {
+            Some(shader) if shader.is_compute() => shader,
+            _ => {
+                error!("Trying to dispatch a compute shader without choosing one!");
+                return;
+            }
+        };
+        let mut output = match self.textures.remove(output_texture) {
+            Some(output) => output,
+            None => {
+                error!("Compute output texture {} doesn't exist!", output_texture);
+                return;
+            }
+        };
+        let uniforms = Uniforms {
+            values: &self.uniforms[shader as usize],
+            textures: &self.textures,
+        };
+        let (width, height) = output.dimensions();
+        for y in 0..dimensions.1.min(height) {
+            for x in 0..dimensions.0.min(width) {
+                let value =
+                    shaders::compute(shader, (x, y), (dimensions.0, dimensions.1), &uniforms);
+                output.set_texel(x, y, value);
+            }
+        }
+        self.textures.insert(output_texture.to_owned(), output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Gui, RenderMessages};
+
+    #[test]
+    fn gui_text_matches_golden() {
+        let mut renderer = SoftwareRenderer::new((256, 256));
+        let mut gui = Gui::new((400.0, 400.0), (-1.0, -1.0));
+        gui.add_text(
+            "the quick brown fox jumped over the lazy dog",
+            (100.0, 100.0),
+            200.0,
+            200.0,
+            16.0,
+        );
+        let mut messages = RenderMessages::new();
+        messages.add_message(RenderMessage::ClearBuffers {
+            color_buffer: true,
+            depth_buffer: true,
+        });
+        messages.merge_current(gui.collect_render_messages());
+        for message in messages.iter() {
+            renderer.read_message(message);
+        }
+
+        if let Err(error) = compare_with_golden(&renderer.screenshot(), "gui_text") {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn compute_shaders_write_their_output_texture() {
+        let mut renderer = SoftwareRenderer::new((16, 16));
+        let mut uniforms = UniformData::new();
+        uniforms.texture("fpf_color".to_owned(), "from_tex");
+        for message in [
+            RenderMessage::ChooseFramebuffer {
+                framebuffer: Some(FramebufferIdentifier::FirstPass),
+            },
+            RenderMessage::ClearBuffers {
+                color_buffer: true,
+                depth_buffer: true,
+            },
+            RenderMessage::ChooseShader {
+                shader: ShaderIdentifier::Transfer,
+            },
+            RenderMessage::Uniforms {
+                uniforms: Box::new(uniforms),
+            },
+            RenderMessage::Compute {
+                output_texture: "artsy_output".to_owned(),
+                dimensions: (16, 16, 1),
+            },
+        ] {
+            renderer.read_message(&message);
+        }
+
+        let copy = renderer.texture_image("artsy_output").unwrap();
+        assert!(copy.data.chunks(4).all(|texel| texel[..3] == [153, 153, 153]));
+    }
+}
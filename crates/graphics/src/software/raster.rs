@@ -0,0 +1,335 @@
+use super::texture::SoftwareTexture;
+
+/// The values a vertex shader hands on to be interpolated across a triangle for the fragment shader.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Varyings {
+    pub color: glm::Vec3,
+    pub uv: glm::Vec2,
+    /// The position relative to the chunk the camera is in. Only given by the chunk shader, for its shadows.
+    pub shadow_position: glm::Vec3,
+    /// The depth from the view. Only given by the chunk shader, to choose a shadow cascade.
+    pub view_depth: f32,
+}
+
+impl Varyings {
+    fn lerp(&self, other: &Varyings, t: f32) -> Varyings {
+        Varyings {
+            color: glm::lerp(&self.color, &other.color, t),
+            uv: glm::lerp(&self.uv, &other.uv, t),
+            shadow_position: glm::lerp(&self.shadow_position, &other.shadow_position, t),
+            view_depth: self.view_depth + (other.view_depth - self.view_depth) * t,
+        }
+    }
+}
+
+/// A vertex after the vertex shader, with its position in clip space.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct ShadedVertex {
+    pub position: glm::Vec4,
+    pub varyings: Varyings,
+}
+
+impl ShadedVertex {
+    fn lerp(&self, other: &ShadedVertex, t: f32) -> ShadedVertex {
+        ShadedVertex {
+            position: glm::lerp(&self.position, &other.position, t),
+            varyings: self.varyings.lerp(&other.varyings, t),
+        }
+    }
+}
+
+/// The fixed function state that decides what happens to the fragments of a triangle.
+pub(super) struct RasterSettings {
+    /// Fragments are only drawn if they are closer than what is in the depth buffer.
+    pub depth_test: bool,
+    /// Whether drawn fragments write their depth. Only matters when depth testing.
+    pub depth_write: bool,
+    /// Triangles that are clockwise on screen are not drawn.
+    pub cull_back_faces: bool,
+    /// Fragments are blended with what is already drawn by their alpha.
+    pub blending: bool,
+}
+
+/// What a triangle is drawn to. Either texture can be missing, in which case it isn't written.
+#[derive(Default)]
+pub(super) struct RenderTarget {
+    pub color: Option<SoftwareTexture>,
+    pub depth: Option<SoftwareTexture>,
+}
+
+impl RenderTarget {
+    fn dimensions(&self) -> (u32, u32) {
+        self.color
+            .as_ref()
+            .or(self.depth.as_ref())
+            .map_or((0, 0), |texture| texture.dimensions())
+    }
+}
+
+/// Twice the signed area of the triangle a, b, p. Positive when p is to the left of a -> b.
+fn edge_function(a: &glm::Vec2, b: &glm::Vec2, p: &glm::Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Whether pixel centers exactly on the edge a -> b are drawn.
+/// The edge is drawn by exactly one of two triangles sharing it, since they go along it in opposite directions.
+fn owns_edge(a: &glm::Vec2, b: &glm::Vec2) -> bool {
+    let direction = b - a;
+    direction.y > 0. || (direction.y == 0. && direction.x < 0.)
+}
+
+/// Cuts away the part of the triangle in front of the near plane, leaving a polygon of up to four vertices.
+fn clip_to_near_plane(triangle: [ShadedVertex; 3]) -> Vec<ShadedVertex> {
+    let distance = |vertex: &ShadedVertex| vertex.position.z + vertex.position.w;
+    let mut polygon = Vec::with_capacity(4);
+    for i in 0..3 {
+        let current = &triangle[i];
+        let next = &triangle[(i + 1) % 3];
+        let (current_distance, next_distance) = (distance(current), distance(next));
+        if current_distance >= 0. {
+            polygon.push(*current);
+        }
+        if (current_distance >= 0.) != (next_distance >= 0.) {
+            let t = current_distance / (current_distance - next_distance);
+            polygon.push(current.lerp(next, t));
+        }
+    }
+    polygon
+}
+
+/// Draws a triangle given in clip space, calling the fragment shader for every covered pixel that passes the
+/// depth test. The fragment shader returns the color of the fragment, or None to discard it.
+/// Pixels are covered if their center is inside the triangle, and varyings are interpolated with perspective.
+pub(super) fn draw_triangle<F>(
+    target: &mut RenderTarget,
+    settings: &RasterSettings,
+    triangle: [ShadedVertex; 3],
+    fragment_shader: &mut F,
+) where
+    F: FnMut(&Varyings) -> Option<glm::Vec4>,
+{
+    let polygon = clip_to_near_plane(triangle);
+    for i in 1..polygon.len().saturating_sub(1) {
+        draw_clipped_triangle(
+            target,
+            settings,
+            [polygon[0], polygon[i], polygon[i + 1]],
+            fragment_shader,
+        );
+    }
+}
+
+fn draw_clipped_triangle<F>(
+    target: &mut RenderTarget,
+    settings: &RasterSettings,
+    triangle: [ShadedVertex; 3],
+    fragment_shader: &mut F,
+) where
+    F: FnMut(&Varyings) -> Option<glm::Vec4>,
+{
+    if triangle
+        .iter()
+        .any(|vertex| vertex.position.w <= f32::EPSILON)
+    {
+        return;
+    }
+    let (width, height) = target.dimensions();
+    // Window coordinates have y going up, and depth from 0 to 1.
+    let window = triangle.map(|vertex| {
+        let ndc = vertex.position.xyz() / vertex.position.w;
+        glm::vec3(
+            (ndc.x + 1.) * 0.5 * width as f32,
+            (ndc.y + 1.) * 0.5 * height as f32,
+            (ndc.z + 1.) * 0.5,
+        )
+    });
+    let mut corners = [0, 1, 2];
+    let area = edge_function(&window[0].xy(), &window[1].xy(), &window[2].xy());
+    if area == 0. || (area < 0. && settings.cull_back_faces) {
+        return;
+    }
+    if area < 0. {
+        // Makes the triangle counterclockwise, so the edge functions are positive inside it.
+        corners.swap(1, 2);
+    }
+    let [a, b, c] = corners.map(|i| window[i].xy());
+    let area = area.abs();
+
+    let min = glm::min2(&glm::min2(&a, &b), &c);
+    let max = glm::max2(&glm::max2(&a, &b), &c);
+    let x_range = (min.x.floor().max(0.) as u32)..(max.x.ceil().min(width as f32) as u32);
+    let y_range = (min.y.floor().max(0.) as u32)..(max.y.ceil().min(height as f32) as u32);
+    let edges = [(b, c), (c, a), (a, b)];
+    let owned = edges.map(|(from, to)| owns_edge(&from, &to));
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let pixel = glm::vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let weights = [0, 1, 2].map(|i| edge_function(&edges[i].0, &edges[i].1, &pixel));
+            if (0..3).any(|i| weights[i] < 0. || (weights[i] == 0. && !owned[i])) {
+                continue;
+            }
+            let weights = weights.map(|weight| weight / area);
+            let vertices = corners.map(|i| (&window[i], &triangle[i]));
+
+            let depth: f32 = (0..3).map(|i| weights[i] * vertices[i].0.z).sum();
+            if !(0. ..=1.).contains(&depth) {
+                continue;
+            }
+            let depth_tested = settings.depth_test && target.depth.is_some();
+            if depth_tested && depth >= target.depth.as_ref().unwrap().texel(x, y).x {
+                continue;
+            }
+
+            // Interpolates in clip space, so textures aren't distorted by perspective.
+            let perspective = (0..3).map(|i| weights[i] / vertices[i].1.position.w);
+            let perspective_sum: f32 = perspective.clone().sum();
+            let varyings = perspective
+                .enumerate()
+                .fold(Varyings::default(), |sum, (i, weight)| {
+                    let weight = weight / perspective_sum;
+                    let varyings = &vertices[i].1.varyings;
+                    Varyings {
+                        color: sum.color + varyings.color * weight,
+                        uv: sum.uv + varyings.uv * weight,
+                        shadow_position: sum.shadow_position + varyings.shadow_position * weight,
+                        view_depth: sum.view_depth + varyings.view_depth * weight,
+                    }
+                });
+
+            let color = match fragment_shader(&varyings) {
+                Some(color) => color,
+                None => continue,
+            };
+            if depth_tested && settings.depth_write {
+                let depth_texture = target.depth.as_mut().unwrap();
+                depth_texture.set_texel(x, y, glm::vec4(depth, depth, depth, 1.));
+            }
+            if let Some(color_texture) = target.color.as_mut() {
+                let color = if settings.blending {
+                    let destination = color_texture.texel(x, y);
+                    color * color.w + destination * (1. - color.w)
+                } else {
+                    color
+                };
+                color_texture.set_texel(x, y, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InternalFormat, TextureMetadata};
+    use utils::ColorFormat;
+
+    fn target(size: u32) -> RenderTarget {
+        let metadata = |name: &str, format, internal_format| TextureMetadata {
+            format,
+            internal_format,
+            width: size,
+            height: size,
+            name: name.to_owned(),
+            screen_dependant_dimensions: false,
+            screen_scale: 1.,
+        };
+        let mut depth =
+            SoftwareTexture::new(metadata("depth", ColorFormat::D, InternalFormat::D16), None);
+        depth.clear(glm::vec4(1., 1., 1., 1.));
+        RenderTarget {
+            color: Some(SoftwareTexture::new(
+                metadata("color", ColorFormat::RGBA, InternalFormat::RGBA8),
+                None,
+            )),
+            depth: Some(depth),
+        }
+    }
+
+    fn vertex(x: f32, y: f32, z: f32) -> ShadedVertex {
+        ShadedVertex {
+            position: glm::vec4(x, y, z, 1.),
+            varyings: Varyings {
+                color: glm::vec3(1., 1., 1.),
+                uv: glm::vec2(0., 0.),
+                ..Varyings::default()
+            },
+        }
+    }
+
+    /// Draws a square covering the whole target out of two triangles.
+    fn draw_square(target: &mut RenderTarget, settings: &RasterSettings, z: f32, color: glm::Vec4) {
+        let corners = [
+            vertex(-1., -1., z),
+            vertex(1., -1., z),
+            vertex(1., 1., z),
+            vertex(-1., 1., z),
+        ];
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            draw_triangle(target, settings, triangle.map(|i| corners[i]), &mut |_| {
+                Some(color)
+            });
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        let mut target = target(8);
+        let settings = RasterSettings {
+            depth_test: false,
+            depth_write: false,
+            cull_back_faces: true,
+            blending: true,
+        };
+        draw_square(&mut target, &settings, 0., glm::vec4(1., 1., 1., 0.5));
+
+        let color = target.color.unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                // Blending once gives half white, blending twice would give three quarters.
+                assert_eq!(color.texel(x, y).x, 128. / 255., "Pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn closer_fragments_stay_in_front() {
+        let mut target = target(4);
+        let settings = RasterSettings {
+            depth_test: true,
+            depth_write: true,
+            cull_back_faces: true,
+            blending: false,
+        };
+        draw_square(&mut target, &settings, 0., glm::vec4(1., 0., 0., 1.));
+        draw_square(&mut target, &settings, 0.5, glm::vec4(0., 1., 0., 1.));
+
+        assert_eq!(target.color.unwrap().texel(2, 2), glm::vec4(1., 0., 0., 1.));
+    }
+
+    #[test]
+    fn triangles_behind_the_near_plane_are_clipped() {
+        let mut target = target(4);
+        let settings = RasterSettings {
+            depth_test: true,
+            depth_write: true,
+            cull_back_faces: false,
+            blending: false,
+        };
+        // Half of this triangle is in front of the near plane.
+        let triangle = [
+            vertex(-1., -1., -3.),
+            vertex(1., -1., 1.),
+            vertex(0., 1., 1.),
+        ];
+        let mut fragments = 0;
+        draw_triangle(&mut target, &settings, triangle, &mut |_| {
+            fragments += 1;
+            Some(glm::vec4(1., 1., 1., 1.))
+        });
+
+        assert!(fragments > 0);
+        let color = target.color.unwrap();
+        assert_eq!(color.texel(0, 0), glm::vec4(0., 0., 0., 1.));
+    }
+}
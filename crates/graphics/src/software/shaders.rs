@@ -0,0 +1,306 @@
+//! CPU versions of the shaders in `assets/graphics/shaders`, which have to be kept in sync with them.
+
+use super::raster::{ShadedVertex, Varyings};
+use super::texture::SoftwareTexture;
+use crate::render_messages::UniformValue;
+use crate::{ShaderIdentifier, FOG_DISTANCE};
+use std::collections::HashMap;
+use utils::Vertex3D;
+
+/// A uniform given to a software shader.
+/// Only the uniform types read by the software shaders are kept.
+#[derive(Clone, Debug)]
+pub(super) enum StoredUniform {
+    Float(f32),
+    Vec2(glm::Vec2),
+    Vec3(glm::Vec3),
+    Mat4(glm::Mat4),
+    Texture(String),
+}
+
+impl StoredUniform {
+    pub fn from_value(value: &UniformValue) -> Option<StoredUniform> {
+        match value {
+            UniformValue::float(value) => Some(StoredUniform::Float(**value)),
+            UniformValue::vec2(value) => Some(StoredUniform::Vec2(**value)),
+            UniformValue::vec3(value) => Some(StoredUniform::Vec3(**value)),
+            UniformValue::mat4(value) => Some(StoredUniform::Mat4(**value)),
+            UniformValue::texture(value) => Some(StoredUniform::Texture((*value).clone())),
+            _ => None,
+        }
+    }
+}
+
+/// The uniforms of the chosen shader, and the textures they can refer to.
+/// Like in GL, uniforms that were never given are zero, and missing textures are black.
+pub(super) struct Uniforms<'a> {
+    pub values: &'a HashMap<String, StoredUniform>,
+    pub textures: &'a HashMap<String, SoftwareTexture>,
+}
+
+impl<'a> Uniforms<'a> {
+    fn float(&self, name: &str) -> f32 {
+        match self.values.get(name) {
+            Some(StoredUniform::Float(value)) => *value,
+            _ => 0.,
+        }
+    }
+
+    fn vec2(&self, name: &str) -> glm::Vec2 {
+        match self.values.get(name) {
+            Some(StoredUniform::Vec2(value)) => *value,
+            _ => glm::Vec2::zeros(),
+        }
+    }
+
+    fn vec3(&self, name: &str) -> glm::Vec3 {
+        match self.values.get(name) {
+            Some(StoredUniform::Vec3(value)) => *value,
+            _ => glm::Vec3::zeros(),
+        }
+    }
+
+    fn mat4(&self, name: &str) -> glm::Mat4 {
+        match self.values.get(name) {
+            Some(StoredUniform::Mat4(value)) => *value,
+            _ => glm::Mat4::zeros(),
+        }
+    }
+
+    fn texture(&self, name: &str) -> Option<&SoftwareTexture> {
+        match self.values.get(name) {
+            Some(StoredUniform::Texture(texture)) => self.textures.get(texture),
+            _ => None,
+        }
+    }
+
+    fn sample(&self, name: &str, uv: glm::Vec2) -> glm::Vec4 {
+        self.texture(name)
+            .map_or(glm::vec4(0., 0., 0., 1.), |texture| texture.sample(uv))
+    }
+
+    /// The dimensions of a texture, like `textureSize` in GLSL. Missing textures are one texel large.
+    fn texture_size(&self, name: &str) -> glm::Vec2 {
+        let (width, height) = self
+            .texture(name)
+            .map_or((1, 1), SoftwareTexture::dimensions);
+        glm::vec2(width.max(1) as f32, height.max(1) as f32)
+    }
+}
+
+/// How much of the color at the distance from the camera is fog, from 0 to 1.
+fn fog_amount(camera_distance: f32) -> f32 {
+    (camera_distance / FOG_DISTANCE).abs().clamp(0., 1.)
+}
+
+/// The fraction of the 3x3 texels of the cascade's shadow map around the position that the position is lit in.
+fn filtered_sunlight(uniforms: &Uniforms, cascade: usize, bias: f32, position: glm::Vec3) -> f32 {
+    let shadow_map = format!("shadow_map{}", cascade);
+    let light_position = uniforms.mat4(&format!("shadow_VP{}", cascade))
+        * glm::vec4(position.x, position.y, position.z, 1.);
+    let coordinates = light_position.xyz() / light_position.w * 0.5 + glm::vec3(0.5, 0.5, 0.5);
+    let texel = glm::vec2(1., 1.).component_div(&uniforms.texture_size(&shadow_map));
+    let mut lit = 0.;
+    for x in -1..=1 {
+        for y in -1..=1 {
+            let offset = glm::vec2(x as f32, y as f32).component_mul(&texel);
+            let depth = uniforms.sample(&shadow_map, coordinates.xy() + offset).x;
+            if coordinates.z - bias <= depth {
+                lit += 1.;
+            }
+        }
+    }
+    lit / 9.
+}
+
+/// How much of the color is left in the shade of the cascaded shadow maps, like `shade` in `shadows.glsl`.
+fn shade(uniforms: &Uniforms, varyings: &Varyings) -> f32 {
+    let cascade_ends = uniforms.vec3("cascade_ends");
+    let bias = uniforms.vec3("shadow_bias");
+    let sunlight = (0..3)
+        .find(|&cascade| varyings.view_depth < cascade_ends[cascade])
+        .map_or(1., |cascade| {
+            filtered_sunlight(uniforms, cascade, bias[cascade], varyings.shadow_position)
+        });
+    1. - uniforms.float("shadow_strength")
+        * (1. - sunlight)
+        * (1. - fog_amount(varyings.view_depth))
+}
+
+/// The vertex stage of a graphics shader.
+pub(super) fn vertex(
+    shader: ShaderIdentifier,
+    vertex: &Vertex3D,
+    uniforms: &Uniforms,
+) -> ShadedVertex {
+    let position = glm::vec3(vertex.x, vertex.y, vertex.z);
+    let color = glm::vec3(vertex.r, vertex.g, vertex.b);
+    let uv = glm::vec2(vertex.u, vertex.v);
+    match shader {
+        ShaderIdentifier::Default | ShaderIdentifier::Chunk => {
+            let clip_position =
+                uniforms.mat4("MVP") * glm::vec4(position.x, position.y, position.z, 1.);
+            let fog = fog_amount(clip_position.w);
+            let fog_color = glm::vec3(0.6, 0.6, 0.6);
+            // The default shader has no shadows, so it ignores the shadow varyings.
+            ShadedVertex {
+                position: clip_position,
+                varyings: Varyings {
+                    color: color * (1. - fog) + fog_color * fog,
+                    uv,
+                    shadow_position: position + uniforms.vec3("chunk_offset"),
+                    view_depth: clip_position.w,
+                },
+            }
+        }
+        ShaderIdentifier::Simple | ShaderIdentifier::Color => ShadedVertex {
+            position: glm::vec4(position.x, position.y, position.z, 1.),
+            varyings: Varyings {
+                color,
+                uv,
+                ..Varyings::default()
+            },
+        },
+        ShaderIdentifier::Shadow => ShadedVertex {
+            position: uniforms.mat4("MVP") * glm::vec4(position.x, position.y, position.z, 1.),
+            varyings: Varyings {
+                uv,
+                ..Varyings::default()
+            },
+        },
+        ShaderIdentifier::Gui => {
+            let xy = (position.xy().component_mul(&uniforms.vec2("scale")) - glm::vec2(1., 1.))
+                .component_mul(&glm::vec2(1., -1.));
+            ShadedVertex {
+                position: glm::vec4(xy.x, xy.y, position.z, 1.),
+                varyings: Varyings {
+                    color,
+                    uv,
+                    ..Varyings::default()
+                },
+            }
+        }
+        ShaderIdentifier::Sobel | ShaderIdentifier::Artsyfartsy | ShaderIdentifier::Transfer => {
+            unreachable!("Compute shader {} has no vertex stage", shader.name())
+        }
+    }
+}
+
+/// The texel of the block atlas at the UV of a chunk vertex, like `block_texel` in `block_atlas.glsl`.
+fn block_texel(uniforms: &Uniforms, uv: glm::Vec2) -> glm::Vec4 {
+    let tile_stride = uniforms.float("tile_stride");
+    let tile = (uv / tile_stride).map(f32::floor);
+    let position = (uv - tile * tile_stride).map(|x| x - x.floor());
+    uniforms.sample(
+        "block_atlas",
+        (tile + position).component_div(&uniforms.vec2("atlas_tiles")),
+    )
+}
+
+/// The fragment stage of a graphics shader. Returns None if the fragment is discarded.
+pub(super) fn fragment(
+    shader: ShaderIdentifier,
+    varyings: &Varyings,
+    uniforms: &Uniforms,
+) -> Option<glm::Vec4> {
+    let opaque = |color: glm::Vec3| glm::vec4(color.x, color.y, color.z, 1.);
+    match shader {
+        ShaderIdentifier::Default => {
+            Some(opaque(varyings.color.component_mul(
+                &uniforms.sample("test_texture", varyings.uv).xyz(),
+            )))
+        }
+        ShaderIdentifier::Simple => Some(opaque(
+            varyings
+                .color
+                .component_mul(&uniforms.sample("tex", varyings.uv).xyz()),
+        )),
+        ShaderIdentifier::Color => Some(opaque(varyings.color)),
+        ShaderIdentifier::Gui => {
+            let texel = uniforms.sample("tex", varyings.uv);
+            if texel.w < 0.3 {
+                return None;
+            }
+            let color = texel.xyz().component_mul(&varyings.color);
+            Some(glm::vec4(color.x, color.y, color.z, texel.w))
+        }
+        ShaderIdentifier::Chunk => {
+            let texel = block_texel(uniforms, varyings.uv);
+            if texel.w < uniforms.float("alpha_cutoff") {
+                return None;
+            }
+            let color = varyings.color.component_mul(&texel.xyz()) * shade(uniforms, varyings);
+            Some(glm::vec4(color.x, color.y, color.z, texel.w))
+        }
+        // Only depth is written to a shadow map, but cutout voxels let light through their gaps.
+        ShaderIdentifier::Shadow => {
+            if block_texel(uniforms, varyings.uv).w < uniforms.float("alpha_cutoff") {
+                return None;
+            }
+            Some(glm::vec4(1., 1., 1., 1.))
+        }
+        ShaderIdentifier::Sobel | ShaderIdentifier::Artsyfartsy | ShaderIdentifier::Transfer => {
+            unreachable!("Compute shader {} has no fragment stage", shader.name())
+        }
+    }
+}
+
+/// Runs a compute shader for one pixel of its output texture.
+/// The work groups are the dimensions the shader is dispatched with.
+pub(super) fn compute(
+    shader: ShaderIdentifier,
+    pixel: (u32, u32),
+    work_groups: (u32, u32),
+    uniforms: &Uniforms,
+) -> glm::Vec4 {
+    let pixel = glm::vec2(pixel.0 as f32, pixel.1 as f32);
+    let scale = glm::vec2(1. / work_groups.0 as f32, 1. / work_groups.1 as f32);
+    let sample = |name: &str, offset: (f32, f32)| {
+        uniforms.sample(
+            name,
+            (pixel + glm::vec2(offset.0, offset.1)).component_mul(&scale),
+        )
+    };
+    match shader {
+        ShaderIdentifier::Sobel => {
+            let side = |offsets: [(f32, f32); 3]| {
+                sample("from_tex", offsets[0])
+                    + sample("from_tex", offsets[1]) * 2.
+                    + sample("from_tex", offsets[2])
+            };
+            let horizontal = (side([(1., -1.), (1., 0.), (1., 1.)])
+                - side([(-1., -1.), (-1., 0.), (-1., 1.)]))
+            .abs()
+            .xyz();
+            let vertical = (side([(-1., 1.), (0., 1.), (1., 1.)])
+                - side([(-1., -1.), (0., -1.), (1., -1.)]))
+            .abs()
+            .xyz();
+            let result = (horizontal.sum() + vertical.sum()) / 3.;
+            glm::vec4(result, result, result, 1.)
+        }
+        ShaderIdentifier::Artsyfartsy => {
+            let sobel = sample("sobel_tex", (0., 0.)).x;
+            let depth = 2. * sample("depth_tex", (0., 0.)).x - 1.;
+            let depth = 2. * 100. * 0.1 / (100. + 0.1 - depth * (100. - 0.1));
+            let depth = (20. - depth).clamp(0., 10.) / 10.;
+
+            let base_color = glm::vec3(124. / 255., 88. / 255., 61. / 255.) * 0.3;
+            let top_color = sample("color_tex", (0., 0.)).xyz();
+            let luminance = top_color.dot(&glm::vec3(0.2126, 0.7152, 0.0722));
+            let top_color = glm::vec3(246. / 255., 209. / 255., 166. / 255.) * luminance;
+
+            let color = glm::lerp(&base_color, &(top_color * (1. - sobel)), depth);
+            glm::vec4(color.x, color.y, color.z, 1.)
+        }
+        ShaderIdentifier::Transfer => sample("from_tex", (0., 0.)),
+        ShaderIdentifier::Default
+        | ShaderIdentifier::Simple
+        | ShaderIdentifier::Color
+        | ShaderIdentifier::Gui
+        | ShaderIdentifier::Chunk
+        | ShaderIdentifier::Shadow => {
+            unreachable!("Graphics shader {} can't be dispatched", shader.name())
+        }
+    }
+}
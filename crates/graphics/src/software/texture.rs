@@ -0,0 +1,125 @@
+use crate::{InternalFormat, TextureMetadata};
+use utils::png_reader::PngData;
+use utils::ColorFormat;
+
+/// Texture coordinates are snapped to this fraction of a texel before sampling, like GPUs do,
+/// so coordinates computed as `pixel / width` land on the texel they were meant for.
+const SUBTEXEL_PRECISION: f32 = 256.;
+
+/// A texture in CPU memory.
+/// Texels are rounded to the precision of the internal format when they are written, like in a GL texture.
+pub(super) struct SoftwareTexture {
+    pub metadata: TextureMetadata,
+    /// Row 0 is the first row of the texture data, which is the bottom row when it is rendered to.
+    texels: Vec<glm::Vec4>,
+}
+
+impl SoftwareTexture {
+    /// Creates a texture with the dimensions in the metadata. Without data, every texel is black.
+    pub fn new(metadata: TextureMetadata, data: Option<&[u8]>) -> SoftwareTexture {
+        let texel_count = (metadata.width * metadata.height) as usize;
+        let mut texture = SoftwareTexture {
+            metadata,
+            texels: vec![glm::vec4(0., 0., 0., 1.); texel_count],
+        };
+        if let Some(data) = data {
+            let stride = match texture.metadata.format {
+                ColorFormat::RGB => 3,
+                ColorFormat::RGBA => 4,
+                ColorFormat::D => 2,
+            };
+            for (i, bytes) in data.chunks_exact(stride).take(texel_count).enumerate() {
+                let channel = |i: usize| bytes[i] as f32 / 255.;
+                let value = match texture.metadata.format {
+                    ColorFormat::RGB => glm::vec4(channel(0), channel(1), channel(2), 1.),
+                    ColorFormat::RGBA => glm::vec4(channel(0), channel(1), channel(2), channel(3)),
+                    ColorFormat::D => {
+                        let depth = u16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / 65535.;
+                        glm::vec4(depth, depth, depth, 1.)
+                    }
+                };
+                texture.texels[i] = texture.round(value);
+            }
+        }
+        texture
+    }
+
+    /// Rounds a value to what the texture can store.
+    fn round(&self, value: glm::Vec4) -> glm::Vec4 {
+        let round_to = |value: f32, steps: f32| (value.clamp(0., 1.) * steps).round() / steps;
+        match self.metadata.internal_format {
+            InternalFormat::RGB8 => glm::vec4(
+                round_to(value.x, 255.),
+                round_to(value.y, 255.),
+                round_to(value.z, 255.),
+                1.,
+            ),
+            InternalFormat::RGBA8 => value.map(|channel| round_to(channel, 255.)),
+            InternalFormat::D16 => {
+                let depth = round_to(value.x, 65535.);
+                glm::vec4(depth, depth, depth, 1.)
+            }
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.metadata.width, self.metadata.height)
+    }
+
+    pub fn texel(&self, x: u32, y: u32) -> glm::Vec4 {
+        self.texels[(y * self.metadata.width + x) as usize]
+    }
+
+    pub fn set_texel(&mut self, x: u32, y: u32, value: glm::Vec4) {
+        let value = self.round(value);
+        self.texels[(y * self.metadata.width + x) as usize] = value;
+    }
+
+    /// Sets every texel to the value.
+    pub fn clear(&mut self, value: glm::Vec4) {
+        let value = self.round(value);
+        self.texels.fill(value);
+    }
+
+    /// Samples the texel nearest to the texture coordinates, clamping them to the edge of the texture.
+    pub fn sample(&self, uv: glm::Vec2) -> glm::Vec4 {
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 {
+            return glm::vec4(0., 0., 0., 1.);
+        }
+        let texel_index = |coordinate: f32, size: u32| {
+            let position =
+                (coordinate * size as f32 * SUBTEXEL_PRECISION).round() / SUBTEXEL_PRECISION;
+            (position.floor().max(0.) as u32).min(size - 1)
+        };
+        self.texel(texel_index(uv.x, width), texel_index(uv.y, height))
+    }
+
+    /// Converts the texture to an 8 bit image with the top row first, like images are usually stored.
+    /// Depth textures become grayscale.
+    pub fn to_png(&self) -> PngData {
+        let (width, height) = self.dimensions();
+        let format = match self.metadata.internal_format {
+            InternalFormat::RGBA8 => ColorFormat::RGBA,
+            InternalFormat::RGB8 | InternalFormat::D16 => ColorFormat::RGB,
+        };
+        let mut data = Vec::with_capacity(self.texels.len() * 4);
+        for y in (0..height).rev() {
+            for x in 0..width {
+                let texel = self
+                    .texel(x, y)
+                    .map(|channel| (channel * 255.).round() as u8);
+                match format {
+                    ColorFormat::RGBA => data.extend([texel.x, texel.y, texel.z, texel.w]),
+                    _ => data.extend([texel.x, texel.y, texel.z]),
+                }
+            }
+        }
+        PngData {
+            width,
+            height,
+            data,
+            format,
+        }
+    }
+}
@@ -0,0 +1,1054 @@
+//! Checks that render messages follow the contract `RenderCaller` expects, before they reach the window.
+
+mod report;
+pub use report::{Severity, ValidationErrorType, ValidationIssue, ValidationReport};
+
+use crate::{
+    BufferTarget, FramebufferIdentifier, GraphicsCapabilities, RenderMessage, RenderMessages,
+    ShaderIdentifier, ShaderMetadata, UniformData, VERTEX_BUFFER_METADATA,
+};
+use log::{debug, error, warn};
+use std::str::FromStr;
+
+const VERBOSE: bool = false;
+
+/// What the validator does with what it finds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Panics with the report when there are errors. Warnings are logged.
+    Panic,
+    /// Logs every issue and carries on.
+    Log,
+    /// Keeps every report with issues, to be taken with `take_reports`.
+    Collect,
+}
+
+impl FromStr for ValidationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "panic" => Ok(ValidationMode::Panic),
+            "log" => Ok(ValidationMode::Log),
+            "collect" => Ok(ValidationMode::Collect),
+            _ => Err(format!(
+                "Unknown validation mode {}, expected panic, log or collect",
+                s
+            )),
+        }
+    }
+}
+
+/// What the validator knows about the graphics state while going through one set of render messages.
+struct FrameState<'c> {
+    capabilities: &'c GraphicsCapabilities,
+    /// What shader is currently chosen
+    chosen_shader: Option<&'c ShaderMetadata>,
+    /// Whether a render target(framebuffer) has currently been chosen
+    has_render_target: bool,
+    /// What uniforms are currently bound for the current shader
+    bound_uniforms: Vec<String>,
+    /// Whether alpha blending is currently enabled. It is disabled at the start of every frame.
+    blending: bool,
+    /// Warnings found in the current message.
+    warnings: Vec<ValidationErrorType>,
+}
+
+impl<'c> FrameState<'c> {
+    /// The uniforms the chosen shader needs that haven't been given yet.
+    fn unfilled_uniforms(&self, shader: &ShaderMetadata) -> Result<(), ValidationErrorType> {
+        let uniforms: Vec<String> = shader
+            .required_uniforms
+            .iter()
+            .map(|x| String::from(&x.0))
+            .filter(|x| !self.bound_uniforms.contains(x))
+            .collect();
+        if uniforms.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrorType::UnfilledUniform { uniforms })
+        }
+    }
+}
+
+/// Validates that a set of render messages are legal.
+///
+/// The validator is stateful, since it keeps track of which vbos are packed across sets of render messages.
+/// So it has to see every set of render messages from the start of the program.
+pub struct RenderMessageValidator {
+    /// Which vbos are currently packed as far as the validator knows (note that this doesn't update until validate is called)
+    packed_vbos: Vec<bool>,
+    mode: ValidationMode,
+    /// Reports with issues, when collecting them.
+    reports: Vec<ValidationReport>,
+}
+
+impl RenderMessageValidator {
+    pub fn new(mode: ValidationMode) -> RenderMessageValidator {
+        RenderMessageValidator {
+            packed_vbos: Vec::new(),
+            mode,
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> ValidationMode {
+        self.mode
+    }
+
+    /// Takes the reports collected so far. Always empty unless the mode is `ValidationMode::Collect`.
+    pub fn take_reports(&mut self) -> Vec<ValidationReport> {
+        std::mem::take(&mut self.reports)
+    }
+
+    ///
+    /// Validates that this contains only allowed render messages in an allowed order, and handles
+    /// what was found according to the mode. The report is returned in every mode.
+    ///
+    /// Validation of a set of render messages stops at the first error, but not at warnings.
+    /// The vbos packed and cleared by the messages after the error are still tracked, unless the mode panics.
+    /// Note that this cannot be turned on in the middle of the program; it is stateful (since vbos are packed and unpacked.)
+    ///
+    pub fn validate(
+        &mut self,
+        capabilities: Option<&GraphicsCapabilities>,
+        messages: &RenderMessages,
+    ) -> ValidationReport {
+        let report = self.check(capabilities, messages);
+        match self.mode {
+            ValidationMode::Panic => {
+                for warning in report.warnings() {
+                    warn!("{}", warning);
+                }
+                if report.has_errors() {
+                    panic!("Render messages failed validation!\n{}", report);
+                }
+            }
+            ValidationMode::Log => {
+                for issue in &report.issues {
+                    match issue.severity() {
+                        Severity::Warning => warn!("{}", issue),
+                        Severity::Error => error!("{}", issue),
+                    }
+                }
+            }
+            ValidationMode::Collect => {
+                if !report.is_clean() {
+                    self.reports.push(report.clone());
+                }
+            }
+        }
+        report
+    }
+
+    fn check(
+        &mut self,
+        capabilities: Option<&GraphicsCapabilities>,
+        messages: &RenderMessages,
+    ) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        if VERBOSE {
+            debug!(
+                "Validating render message pack with {} messages!",
+                messages.size()
+            );
+        }
+
+        let capabilities = match capabilities {
+            Some(capabilities) => capabilities,
+            None => {
+                if !messages.is_empty() {
+                    report.issues.push(ValidationIssue::new(
+                        ValidationErrorType::NoGraphicsCapabilities,
+                        messages,
+                        0,
+                    ));
+                }
+                return report;
+            }
+        };
+
+        // The number of vbos can change. Removed vbos lose their contents.
+        self.packed_vbos.resize(capabilities.vbo_count, false);
+        let mut frame = FrameState {
+            capabilities,
+            chosen_shader: None,
+            has_render_target: false,
+            bound_uniforms: Vec::new(),
+            blending: false,
+            warnings: Vec::new(),
+        };
+
+        for (message_index, message) in messages.iter().enumerate() {
+            // Messages before the split are persistent messages left over from earlier frames,
+            // which the validator has already seen.
+            let is_new = message_index >= messages.old_new_split_index();
+            let result = self.check_message(&mut frame, message, is_new);
+            for warning in frame.warnings.drain(..) {
+                report
+                    .issues
+                    .push(ValidationIssue::new(warning, messages, message_index));
+            }
+            if let Err(error_type) = result {
+                report
+                    .issues
+                    .push(ValidationIssue::new(error_type, messages, message_index));
+                if self.mode == ValidationMode::Panic {
+                    return report;
+                }
+                // The window still runs the rest of the messages, so later sets of messages
+                // shouldn't be blamed for vbos this one left packed or empty.
+                for (index, message) in messages.iter().enumerate().skip(message_index) {
+                    self.track_vbos(message, index >= messages.old_new_split_index());
+                }
+                return report;
+            }
+        }
+
+        if frame.blending {
+            report.issues.push(ValidationIssue::new(
+                ValidationErrorType::BlendingLeftEnabled,
+                messages,
+                messages.size() - 1,
+            ));
+        }
+        report
+    }
+
+    /// The index of the buffer in the vbos, or an error if it's out of bounds.
+    fn vbo_index(&self, buffer: &BufferTarget) -> Result<usize, ValidationErrorType> {
+        let index = buffer.get_target_id(&VERTEX_BUFFER_METADATA);
+        if index < self.packed_vbos.len() {
+            Ok(index)
+        } else {
+            Err(ValidationErrorType::VBOOutOfBounds { vbo: *buffer })
+        }
+    }
+
+    /// Updates which vbos are packed like the message does, without checking it.
+    fn track_vbos(&mut self, message: &RenderMessage, is_new: bool) {
+        let (buffer, packed) = match message {
+            RenderMessage::Pack { buffer, .. } => (buffer, true),
+            RenderMessage::ClearArray { buffer } => (buffer, false),
+            _ => return,
+        };
+        if let (true, Ok(index)) = (is_new, self.vbo_index(buffer)) {
+            self.packed_vbos[index] = packed;
+        }
+    }
+
+    /// Validates a single render message, returning the first error in it.
+    /// Warnings are added to the frame state.
+    fn check_message(
+        &mut self,
+        frame: &mut FrameState,
+        message: &RenderMessage,
+        is_new: bool,
+    ) -> Result<(), ValidationErrorType> {
+        match message {
+            RenderMessage::ChooseShader { shader } => {
+                frame.chosen_shader = Some(Self::shader_metadata(frame.capabilities, *shader)?);
+                frame.bound_uniforms = Vec::new();
+            }
+            RenderMessage::ClearArray { buffer } => {
+                let index = self.vbo_index(buffer)?;
+                if is_new {
+                    if !self.packed_vbos[index] {
+                        return Err(ValidationErrorType::ClearEmptyVBO { vbo: *buffer });
+                    }
+                    self.packed_vbos[index] = false;
+                }
+            }
+            RenderMessage::ClearBuffers {
+                color_buffer,
+                depth_buffer,
+            } => {
+                if !color_buffer && !depth_buffer {
+                    return Err(ValidationErrorType::NoClearedBuffers);
+                }
+            }
+            RenderMessage::Draw { buffer } => {
+                if !frame.has_render_target {
+                    return Err(ValidationErrorType::NoRenderTarget);
+                }
+                let shader = frame
+                    .chosen_shader
+                    .ok_or(ValidationErrorType::NoShaderChosen)?;
+                if shader.identifier.is_compute() {
+                    return Err(ValidationErrorType::NonGraphicsShader {
+                        shader: String::from(shader.identifier.name()),
+                    });
+                }
+                frame.unfilled_uniforms(shader)?;
+
+                // This one doesn't need to check whether it is above the old/new split, since draw is not a persistent render message.
+                // So it will always be in the new part.
+                if !self.packed_vbos[self.vbo_index(buffer)?] {
+                    return Err(ValidationErrorType::DrawEmptyVBO { vbo: *buffer });
+                }
+            }
+            RenderMessage::Pack { buffer, pack } => {
+                let index = self.vbo_index(buffer)?;
+                if pack.elements.len() % 3 != 0
+                    || (pack.elements.is_empty() && pack.vertices.len() % 3 != 0)
+                {
+                    return Err(ValidationErrorType::WrongTriangleCount);
+                }
+                if pack.vertices.is_empty() {
+                    return Err(ValidationErrorType::EmptyVertexPack);
+                }
+                if is_new {
+                    if self.packed_vbos[index] {
+                        return Err(ValidationErrorType::PackFullVBO { vbo: *buffer });
+                    }
+                    self.packed_vbos[index] = true;
+                }
+            }
+            RenderMessage::Uniforms { uniforms } => Self::check_uniforms(frame, uniforms)?,
+            RenderMessage::ChooseFramebuffer { framebuffer } => {
+                if let Some(framebuffer) = framebuffer {
+                    Self::check_framebuffer(frame.capabilities, *framebuffer)?;
+                }
+                frame.has_render_target = true;
+            }
+            RenderMessage::Compute {
+                output_texture,
+                dimensions,
+            } => {
+                //TODO: ENFORCE TEXTURE FORMAT FIT
+                let texture = frame
+                    .capabilities
+                    .texture_metadata
+                    .get(output_texture)
+                    .ok_or_else(|| ValidationErrorType::InvalidTexture {
+                        texture: String::from(output_texture),
+                    })?;
+                let shader = frame
+                    .chosen_shader
+                    .ok_or(ValidationErrorType::NoShaderChosen)?;
+                if !shader.identifier.is_compute() {
+                    return Err(ValidationErrorType::NonComputeShader {
+                        shader: String::from(shader.identifier.name()),
+                    });
+                }
+                frame.unfilled_uniforms(shader)?;
+
+                // The compute shaders have a local size of 1, so they are dispatched once per texel.
+                let (x, y, z) = *dimensions;
+                let texture_dimensions = (texture.width, texture.height);
+                if x == 0 || y == 0 || z == 0 {
+                    return Err(ValidationErrorType::EmptyComputeDispatch {
+                        dimensions: *dimensions,
+                    });
+                }
+                if x < texture.width || y < texture.height {
+                    return Err(ValidationErrorType::ComputeDispatchTooSmall {
+                        texture: String::from(output_texture),
+                        dimensions: *dimensions,
+                        texture_dimensions,
+                    });
+                }
+                if x > texture.width || y > texture.height || z > 1 {
+                    frame
+                        .warnings
+                        .push(ValidationErrorType::ComputeDispatchTooLarge {
+                            texture: String::from(output_texture),
+                            dimensions: *dimensions,
+                            texture_dimensions,
+                        });
+                }
+            }
+            RenderMessage::SwitchTo2D {} => {
+                // TODO: IS THERE ANY CONTEXT WHERE SWITCHING TO 2D IS ILLEGAL
+            }
+            RenderMessage::SwitchTo3D {} => {
+                // TODO: IS THERE ANY CONTEXT WHERE SWITCHING TO 3D IS ILLEGAL
+            }
+            RenderMessage::Blending { enabled } => {
+                if *enabled == frame.blending {
+                    return Err(ValidationErrorType::RedundantBlending { enabled: *enabled });
+                }
+                frame.blending = *enabled;
+            }
+        }
+        Ok(())
+    }
+
+    fn shader_metadata(
+        capabilities: &GraphicsCapabilities,
+        shader: ShaderIdentifier,
+    ) -> Result<&ShaderMetadata, ValidationErrorType> {
+        capabilities
+            .shader_metadata
+            .get(shader as usize)
+            .ok_or_else(|| ValidationErrorType::InvalidShader {
+                shader: String::from(shader.name()),
+            })
+    }
+
+    /// Checks that the framebuffer exists, and that its textures have the same dimensions as it.
+    fn check_framebuffer(
+        capabilities: &GraphicsCapabilities,
+        framebuffer: FramebufferIdentifier,
+    ) -> Result<(), ValidationErrorType> {
+        let metadata = capabilities
+            .framebuffer_metadata
+            .iter()
+            .find(|metadata| metadata.identifier as usize == framebuffer as usize)
+            .ok_or_else(|| ValidationErrorType::InvalidFramebuffer {
+                framebuffer: String::from(framebuffer.name()),
+            })?;
+        let framebuffer_dimensions = (metadata.width, metadata.height);
+        for texture in [framebuffer.color_texture(), framebuffer.depth_texture()]
+            .into_iter()
+            .flatten()
+        {
+            let texture_metadata = capabilities.texture_metadata.get(texture).ok_or_else(|| {
+                ValidationErrorType::InvalidTexture {
+                    texture: String::from(texture),
+                }
+            })?;
+            let texture_dimensions = (texture_metadata.width, texture_metadata.height);
+            if texture_dimensions != framebuffer_dimensions {
+                return Err(ValidationErrorType::FramebufferTextureMismatch {
+                    framebuffer: String::from(framebuffer.name()),
+                    texture: String::from(texture),
+                    framebuffer_dimensions,
+                    texture_dimensions,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate a single RenderMessage::Uniforms
+    fn check_uniforms(
+        frame: &mut FrameState,
+        uniforms: &UniformData,
+    ) -> Result<(), ValidationErrorType> {
+        //TODO: Enforce uniform type matching to shader known type
+
+        let shader = frame
+            .chosen_shader
+            .ok_or(ValidationErrorType::NoShaderChosen)?;
+        // Test if every passed texture exists in the graphics capabilities.
+        for entry in &uniforms.texture {
+            if !frame.capabilities.texture_metadata.contains_key(&entry.0) {
+                return Err(ValidationErrorType::InvalidTexture {
+                    texture: String::from(&entry.0),
+                });
+            }
+        }
+
+        // Uniforms the shader doesn't want are ignored by GL, so they are only warned about.
+        for uniform in uniforms.get_uniform_locations() {
+            if !shader.required_uniforms.iter().any(|x| x.0 == *uniform) {
+                frame.warnings.push(ValidationErrorType::UnwantedUniform {
+                    uniform: String::from(uniform),
+                    shader: String::from(shader.identifier.name()),
+                });
+            } else if !frame.bound_uniforms.contains(uniform) {
+                frame.bound_uniforms.push(String::from(uniform));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FramebufferMetadata, ProgramType, TextureMetadata};
+    use crate::{InternalFormat, TextureAtlas, VertexPack};
+    use std::collections::HashMap;
+    use utils::ColorFormat;
+
+    fn create_shader_metadata(extra_uniform: bool) -> Vec<ShaderMetadata> {
+        let mut res = Vec::new();
+        let mut required_uniforms = vec![(String::from("test_texture"), String::from(""))];
+        if extra_uniform {
+            required_uniforms.push((String::from("vector"), String::from("")));
+        }
+        let s1 = ShaderMetadata {
+            identifier: ShaderIdentifier::Default,
+            required_uniforms,
+            shader_type: ProgramType::Graphics,
+        };
+        res.push(s1);
+        let sobel = ShaderMetadata {
+            identifier: ShaderIdentifier::Sobel,
+            required_uniforms: vec![(String::from("from_tex"), String::from(""))],
+            shader_type: ProgramType::Compute,
+        };
+        res.push(sobel);
+
+        res
+    }
+
+    fn texture(name: &str, width: u32, height: u32) -> (String, TextureMetadata) {
+        (
+            String::from(name),
+            TextureMetadata {
+                format: ColorFormat::RGB,
+                internal_format: InternalFormat::RGB8,
+                width,
+                height,
+                name: String::from(name),
+                screen_dependant_dimensions: false,
+                screen_scale: 1.,
+            },
+        )
+    }
+
+    /// Creates a basic capabilities object with:
+    ///  - one shader (s1) that needs one uniform (test_texture)
+    ///  - one compute shader (sobel) that needs one uniform (from_tex)
+    ///  - one texture (atlas), and the 2x2 textures of the first pass framebuffer
+    ///  - 200 vbos
+    ///
+    /// If extra_uniform is supplied
+    ///  - another uniform, of type vec4, with name "vector"
+    fn create_capabilities(extra_uniform: bool) -> GraphicsCapabilities {
+        let shader_metadata = create_shader_metadata(extra_uniform);
+        let texture_metadata: HashMap<String, TextureMetadata> = [
+            texture("atlas", 2, 2),
+            texture("fpf_color", 2, 2),
+            texture("fpf_depth", 2, 2),
+        ]
+        .into_iter()
+        .collect();
+        let framebuffer_metadata = vec![FramebufferMetadata {
+            identifier: FramebufferIdentifier::FirstPass,
+            width: 2,
+            height: 2,
+        }];
+        GraphicsCapabilities {
+            vbo_count: 200,
+            texture_metadata,
+            shader_metadata,
+            framebuffer_metadata,
+            block_atlas: TextureAtlas::default(),
+            screen_dimensions: (2, 2),
+        }
+    }
+
+    fn collecting_validator() -> RenderMessageValidator {
+        RenderMessageValidator::new(ValidationMode::Collect)
+    }
+
+    ///Creates a VertexPack for a basic quad
+    fn create_quad_pack() -> VertexPack {
+        let mut vertices = Vec::new();
+        let mut elements = Vec::new();
+        let x0 = 0.;
+        let x1 = x0 + 1.;
+        let y0 = 0.;
+        let y1 = y0 + 1.;
+        let z0 = 0.;
+
+        // Back face
+        let (mut vadd, mut eadd) = crate::pack::cube_faces::back(z0, x0, y0, x1, y1, 1., 0., 0., 0);
+        vertices.append(&mut vadd);
+        elements.append(&mut eadd);
+        VertexPack::new(vertices, Some(elements))
+    }
+
+    #[test]
+    fn basic_validation() {
+        // Does extremely basic validation
+        let rs = create_capabilities(false);
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+
+        let report = validator.validate(Some(&rs), &render_messages);
+        assert!(report.is_clean(), "Failed to validate.\n{}", report);
+    }
+
+    #[test]
+    fn texture_name_validation() {
+        // Ensures that we can only use textures that exist in the graphics capabilities object
+        let rs = create_capabilities(false);
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+
+        let report = validator.validate(Some(&rs), &render_messages);
+        assert!(
+            !report.has_errors(),
+            "Uniforms the shader doesn't want should only be warnings!"
+        );
+        assert_eq!(
+            report.warnings().next().map(|issue| &issue.error_type),
+            Some(&ValidationErrorType::UnwantedUniform {
+                uniform: String::from("test"),
+                shader: String::from(ShaderIdentifier::Default.name()),
+            }),
+            "Validate doesn't warn about non-existent uniform name!"
+        );
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlass"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+
+        let res = validator.validate(Some(&rs), &render_messages);
+        assert!(
+            res.has_errors(),
+            "Validate wrongfully accepts non-existent texture name!"
+        );
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+
+        assert!(
+            !validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate wrongfully doesn't accept correct texture and uniform name!"
+        );
+    }
+
+    #[test]
+    fn uniform_validation() {
+        // Ensures that the uniform validation method works correctly
+        let rs = create_capabilities(true);
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        render_messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+
+        assert!(
+            validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate wrongfully accepts non-filled uniforms!"
+        );
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+
+        assert!(
+            validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate wrongfully accepts non-filled uniforms!"
+        );
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        ud.vec3(glm::vec3(0., 0., 0.), String::from("vector"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+        assert!(
+            !validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate doesn't accept filled out uniforms!"
+        );
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        ud.vec3(glm::vec3(0., 0., 0.), String::from("vector"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+
+        assert!(
+            validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate wrongfully accepts non-filled uniforms after shader swap!"
+        );
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        ud.vec3(glm::vec3(0., 0., 0.), String::from("vector"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        ud.vec3(glm::vec3(0., 0., 0.), String::from("vector"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+
+        assert!(
+            !validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate doesn't accept filled out uniforms after shader swap!"
+        );
+    }
+
+    #[test]
+    fn framebuffer_validation() {
+        let rs = create_capabilities(false);
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+
+        assert!(
+            validator.validate(Some(&rs), &render_messages).has_errors(),
+            "Validate wrongfully accepts no render target!"
+        );
+
+        let mut render_messages = RenderMessages::new();
+        let mut validator = collecting_validator();
+        render_messages.add_message(RenderMessage::ChooseShader {
+            shader: ShaderIdentifier::Default,
+        });
+        render_messages.add_message(RenderMessage::ChooseFramebuffer { framebuffer: None });
+        let mut ud = UniformData::new();
+        ud.texture(String::from("atlas"), String::from("test_texture"));
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(ud),
+        });
+        render_messages.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        render_messages.add_message(RenderMessage::Draw {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+
+        let res = validator.validate(Some(&rs), &render_messages);
+        assert!(
+            !res.has_errors(),
+            "Validate wrongfully doesn't accept a render target!\n{}",
+            res
+        );
+    }
+
+    #[test]
+    fn blending_validation() {
+        let rs = create_capabilities(false);
+
+        let blending = |states: &[bool]| {
+            let mut render_messages = RenderMessages::new();
+            for &enabled in states {
+                render_messages.add_message(RenderMessage::Blending { enabled });
+            }
+            !collecting_validator()
+                .validate(Some(&rs), &render_messages)
+                .has_errors()
+        };
+
+        assert!(
+            blending(&[true, false]),
+            "Validate wrongfully doesn't accept enabling and disabling blending!"
+        );
+        assert!(
+            !blending(&[true]),
+            "Validate wrongfully accepts blending left enabled!"
+        );
+        assert!(
+            !blending(&[true, true, false]),
+            "Validate wrongfully accepts enabling blending twice!"
+        );
+        assert!(
+            !blending(&[false]),
+            "Validate wrongfully accepts disabling blending that isn't enabled!"
+        );
+    }
+
+    #[test]
+    fn framebuffer_dimension_validation() {
+        let mut rs = create_capabilities(false);
+        let choose_framebuffer = |rs: &GraphicsCapabilities| {
+            let mut render_messages = RenderMessages::new();
+            render_messages.add_message(RenderMessage::ChooseFramebuffer {
+                framebuffer: Some(FramebufferIdentifier::FirstPass),
+            });
+            collecting_validator()
+                .validate(Some(rs), &render_messages)
+                .errors()
+                .next()
+                .map(|issue| issue.error_type.clone())
+        };
+
+        assert_eq!(choose_framebuffer(&rs), None);
+
+        rs.texture_metadata
+            .insert(String::from("fpf_depth"), texture("fpf_depth", 4, 2).1);
+        assert!(
+            matches!(
+                choose_framebuffer(&rs),
+                Some(ValidationErrorType::FramebufferTextureMismatch {
+                    texture_dimensions: (4, 2),
+                    ..
+                })
+            ),
+            "Validate wrongfully accepts a depth texture that doesn't fit the framebuffer!"
+        );
+
+        rs.framebuffer_metadata.clear();
+        assert!(
+            matches!(
+                choose_framebuffer(&rs),
+                Some(ValidationErrorType::InvalidFramebuffer { .. })
+            ),
+            "Validate wrongfully accepts a framebuffer that doesn't exist!"
+        );
+    }
+
+    #[test]
+    fn compute_dispatch_validation() {
+        let rs = create_capabilities(false);
+        let dispatch = |dimensions: (u32, u32, u32)| {
+            let mut render_messages = RenderMessages::new();
+            render_messages.add_message(RenderMessage::ChooseShader {
+                shader: ShaderIdentifier::Sobel,
+            });
+            let mut ud = UniformData::new();
+            ud.texture(String::from("atlas"), "from_tex");
+            render_messages.add_message(RenderMessage::Uniforms {
+                uniforms: Box::new(ud),
+            });
+            render_messages.add_message(RenderMessage::Compute {
+                output_texture: String::from("fpf_color"),
+                dimensions,
+            });
+            collecting_validator().validate(Some(&rs), &render_messages)
+        };
+
+        assert!(dispatch((2, 2, 1)).is_clean());
+        assert!(
+            dispatch((0, 2, 1)).has_errors(),
+            "Validate wrongfully accepts an empty dispatch!"
+        );
+        assert!(
+            dispatch((2, 1, 1)).has_errors(),
+            "Validate wrongfully accepts a dispatch that leaves part of the texture unwritten!"
+        );
+        let too_large = dispatch((3, 2, 1));
+        assert!(
+            !too_large.has_errors() && too_large.warnings().count() == 1,
+            "Validate should warn about dispatches larger than the texture!"
+        );
+    }
+
+    #[test]
+    fn reports_point_at_offending_message() {
+        let rs = create_capabilities(false);
+        let mut render_messages = RenderMessages::new();
+        for _ in 0..5 {
+            render_messages.add_message(RenderMessage::SwitchTo3D {});
+        }
+        render_messages.add_message(RenderMessage::ClearBuffers {
+            color_buffer: false,
+            depth_buffer: false,
+        });
+        for _ in 0..5 {
+            render_messages.add_message(RenderMessage::SwitchTo2D {});
+        }
+
+        let mut validator = collecting_validator();
+        let report = validator.validate(Some(&rs), &render_messages);
+        let issue = report.errors().next().unwrap();
+        assert_eq!(issue.error_type, ValidationErrorType::NoClearedBuffers);
+        assert_eq!(issue.message_index, 5);
+        let context: Vec<usize> = issue.context.iter().map(|(i, _)| *i).collect();
+        assert_eq!(context, vec![3, 4, 5, 6, 7]);
+        assert!(issue.to_string().contains(">     5 | ClearBuffers"));
+
+        assert_eq!(validator.take_reports().len(), 1);
+        assert!(validator.take_reports().is_empty());
+    }
+
+    #[test]
+    fn vbos_are_tracked_after_errors() {
+        let rs = create_capabilities(false);
+        let mut validator = collecting_validator();
+
+        let mut first = RenderMessages::new();
+        first.add_message(RenderMessage::ClearBuffers {
+            color_buffer: false,
+            depth_buffer: false,
+        });
+        first.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(0),
+            pack: create_quad_pack(),
+        });
+        first.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(1),
+            pack: create_quad_pack(),
+        });
+        first.add_message(RenderMessage::ClearArray {
+            buffer: BufferTarget::WorldBuffer(1),
+        });
+        let report = validator.validate(Some(&rs), &first);
+        assert_eq!(report.errors().count(), 1);
+
+        // Only the vbo packed in the first frame is full.
+        let mut second = RenderMessages::new();
+        second.add_message(RenderMessage::ClearArray {
+            buffer: BufferTarget::WorldBuffer(0),
+        });
+        second.add_message(RenderMessage::Pack {
+            buffer: BufferTarget::WorldBuffer(1),
+            pack: create_quad_pack(),
+        });
+        let report = validator.validate(Some(&rs), &second);
+        assert!(report.is_clean(), "Errors in the second frame.\n{}", report);
+    }
+
+    #[test]
+    fn context_of_long_messages_is_cut_short() {
+        let rs = create_capabilities(false);
+        let mut render_messages = RenderMessages::new();
+        let mut uniforms = UniformData::new();
+        for _ in 0..10000 {
+            uniforms.texture(String::from("atlas"), String::from("test_texture"));
+        }
+        render_messages.add_message(RenderMessage::Uniforms {
+            uniforms: Box::new(uniforms),
+        });
+
+        let report = collecting_validator().validate(Some(&rs), &render_messages);
+        let issue = report.errors().next().unwrap();
+        let (_, line) = &issue.context[0];
+        assert!(line.starts_with("Uniforms"));
+        assert!(line.ends_with("..."));
+        assert_eq!(line.chars().count(), report::CONTEXT_LINE_LENGTH + 3);
+    }
+
+    #[test]
+    fn messages_need_capabilities() {
+        let mut render_messages = RenderMessages::new();
+        assert!(collecting_validator()
+            .validate(None, &render_messages)
+            .is_clean());
+        render_messages.add_message(RenderMessage::SwitchTo3D {});
+        assert!(collecting_validator()
+            .validate(None, &render_messages)
+            .has_errors());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{BufferTarget, RenderMessages};
+use std::fmt::{self, Write};
+use thiserror::Error;
+
+/// How many messages before and after the offending message are shown in a report.
+const CONTEXT_MESSAGES: usize = 2;
+/// Messages in the context are cut off after this many characters, since uniforms can be huge.
+pub(super) const CONTEXT_LINE_LENGTH: usize = 120;
+
+/// How bad a validation issue is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The messages will render, but probably not like intended.
+    Warning,
+    /// The messages break the contract of the render messages.
+    Error,
+}
+
+///Types of validator errors.
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum ValidationErrorType {
+    #[error("Trying to choose shader {shader}, that does not exist!")]
+    InvalidShader { shader: String },
+    #[error("Trying to access VBO {vbo}, which is out of bounds.")]
+    VBOOutOfBounds { vbo: BufferTarget },
+    #[error("Trying to clear VBO {vbo}, which is already empty!")]
+    ClearEmptyVBO { vbo: BufferTarget },
+    #[error("Trying to draw VBO {vbo}, which is empty!")]
+    DrawEmptyVBO { vbo: BufferTarget },
+    #[error("Trying to pack VBO {vbo}, which is already full!")]
+    PackFullVBO { vbo: BufferTarget },
+    #[error("A clear buffers render message is sent, but no buffers are cleared!")]
+    NoClearedBuffers,
+    #[error("Trying to draw without first picking a render target!")]
+    NoRenderTarget,
+    #[error("Trying to draw without picking a shader first!")]
+    NoShaderChosen,
+    #[error("Trying to bind framebuffer {framebuffer}, which does not exist")]
+    InvalidFramebuffer { framebuffer: String },
+    #[error("Framebuffer {framebuffer} is {framebuffer_dimensions:?}, but its texture {texture} is {texture_dimensions:?}!")]
+    FramebufferTextureMismatch {
+        framebuffer: String,
+        texture: String,
+        framebuffer_dimensions: (u32, u32),
+        texture_dimensions: (u32, u32),
+    },
+    #[error("Received a vertex pack that does not contain a whole number of triangles!")]
+    WrongTriangleCount,
+    #[error("Trying to fill VBO with empty vertex pack! A VBO is cleared by sending a RenderMessage::ClearArray message!")]
+    EmptyVertexPack,
+    #[error("Trying to pass texture {texture} as shader uniform, but texture does not exist in the graphics capabilities object!")]
+    InvalidTexture { texture: String },
+    #[error("Trying to pass uniform \"{uniform}\" to shader \"{shader}\" that does not want it!")]
+    UnwantedUniform { uniform: String, shader: String },
+    #[error(
+        "Trying to draw without supplying all needed uniforms! Missing uniforms: {uniforms:?}"
+    )]
+    UnfilledUniform { uniforms: Vec<String> },
+    #[error("Trying to send RenderMessages with no graphics capabilities!")]
+    NoGraphicsCapabilities,
+    #[error("Trying to run a compute dispatch with graphics shader {shader}!")]
+    NonComputeShader { shader: String },
+    #[error("Trying to render with compute shader {shader}!")]
+    NonGraphicsShader { shader: String },
+    #[error("Trying to dispatch a compute shader with no work groups: {dimensions:?}!")]
+    EmptyComputeDispatch { dimensions: (u32, u32, u32) },
+    #[error("Compute dispatch of {dimensions:?} doesn't cover all of output texture {texture}, which is {texture_dimensions:?}!")]
+    ComputeDispatchTooSmall {
+        texture: String,
+        dimensions: (u32, u32, u32),
+        texture_dimensions: (u32, u32),
+    },
+    #[error("Compute dispatch of {dimensions:?} goes outside output texture {texture}, which is {texture_dimensions:?}.")]
+    ComputeDispatchTooLarge {
+        texture: String,
+        dimensions: (u32, u32, u32),
+        texture_dimensions: (u32, u32),
+    },
+    #[error("Trying to set blending to {enabled}, which it already is!")]
+    RedundantBlending { enabled: bool },
+    #[error("Blending is still enabled after the last render message! It has to be disabled again so the depth buffer can be written and cleared.")]
+    BlendingLeftEnabled,
+}
+
+impl ValidationErrorType {
+    /// Issues that don't break anything when rendering are warnings, the rest are errors.
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationErrorType::UnwantedUniform { .. }
+            | ValidationErrorType::ComputeDispatchTooLarge { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// One problem found by the validator, with the messages around where it was found.
+#[derive(Clone, Debug)]
+pub struct ValidationIssue {
+    pub error_type: ValidationErrorType,
+    /// The index of the offending message in the render messages.
+    pub message_index: usize,
+    /// The number of render messages that were validated.
+    pub message_count: usize,
+    /// The offending message and the messages around it, with their indices.
+    pub context: Vec<(usize, String)>,
+}
+
+impl ValidationIssue {
+    /// Creates an issue for the message at the index, capturing the messages around it.
+    pub fn new(
+        error_type: ValidationErrorType,
+        messages: &RenderMessages,
+        message_index: usize,
+    ) -> ValidationIssue {
+        let context = messages
+            .iter()
+            .enumerate()
+            .skip(message_index.saturating_sub(CONTEXT_MESSAGES))
+            .take(CONTEXT_MESSAGES * 2 + 1)
+            .map(|(i, message)| {
+                let mut line = ContextLine::default();
+                // Formatting stops once the line is full, so packs aren't formatted whole.
+                let _ = write!(line, "{:?}", message);
+                (i, line.finish())
+            })
+            .collect();
+        ValidationIssue {
+            error_type,
+            message_index,
+            message_count: messages.size(),
+            context,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.error_type.severity()
+    }
+}
+
+/// Collects the start of a formatted message, refusing any characters after the first `CONTEXT_LINE_LENGTH`.
+#[derive(Default)]
+struct ContextLine {
+    line: String,
+    length: usize,
+    truncated: bool,
+}
+
+impl ContextLine {
+    fn finish(mut self) -> String {
+        if self.truncated {
+            self.line.push_str("...");
+        }
+        self.line
+    }
+}
+
+impl fmt::Write for ContextLine {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.length == CONTEXT_LINE_LENGTH {
+                self.truncated = true;
+                return Err(fmt::Error);
+            }
+            self.line.push(c);
+            self.length += 1;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity() {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        writeln!(f, "{}: {}", severity, self.error_type)?;
+        write!(
+            f,
+            "  at message {} of {}:",
+            self.message_index, self.message_count
+        )?;
+        for (i, line) in &self.context {
+            let marker = if *i == self.message_index { ">" } else { " " };
+            write!(f, "\n{} {:>5} | {}", marker, i, line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything the validator found in one set of render messages.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// Whether the validator found nothing at all, not even warnings.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n\n")?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
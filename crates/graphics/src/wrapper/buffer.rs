@@ -1,15 +1,19 @@
 use std::{fmt::Display, marker::PhantomData};
 
+use serde::{Deserialize, Serialize};
 use utils::vertex::Vertex;
 
 use super::vertex_buffer_metadata::VertexBufferMetadata;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BufferTarget {
     //TODO: RENAME TO VERTEXBUFFERTARGET
     GuiBuffer,
     WorldBuffer(usize),
     ModelBuffer(usize),
+    /// A buffer for geometry that is packed, drawn and cleared within one frame, like the reticle.
+    /// It is never handed out to chunks or models.
+    ScratchBuffer,
 }
 
 impl BufferTarget {
@@ -21,6 +25,7 @@ impl BufferTarget {
             BufferTarget::GuiBuffer => 0,
             BufferTarget::WorldBuffer(i) => i + vertex_buffer_metadata.world_buffer_start(),
             BufferTarget::ModelBuffer(i) => i + vertex_buffer_metadata.model_buffer_start(),
+            BufferTarget::ScratchBuffer => vertex_buffer_metadata.scratch_buffer(),
         }
     }
 }
@@ -35,6 +40,7 @@ impl Display for BufferTarget {
             BufferTarget::ModelBuffer(i) => {
                 f.write_fmt(format_args!("BufferTarget::ModelBuffer({})", i))
             }
+            BufferTarget::ScratchBuffer => f.write_fmt(format_args!("BufferTarget::ScratchBuffer")),
         }
     }
 }
@@ -1,47 +1,44 @@
 use super::TextureManager;
 use log::debug;
+use macros::FramebufferId;
 use serde::{Deserialize, Serialize};
 use strum::{EnumCount, EnumIter};
 
 const VERBOSE: bool = false;
 
-#[derive(Clone, Copy, Debug, EnumCount, EnumIter, Serialize, Deserialize)]
+///
+/// The framebuffers that can be rendered to. Each variant needs a `name`, and a `color_texture`,
+/// a `depth_texture` or a `depth` buffer that isn't a texture.
+/// `scale` makes the framebuffer a fraction of the screen size; its textures need the same screen scale.
+/// `size` gives the framebuffer fixed dimensions instead; its textures need the same dimensions and mustn't follow the screen.
+///
+#[derive(Clone, Copy, Debug, EnumCount, EnumIter, FramebufferId, Serialize, Deserialize)]
 pub enum FramebufferIdentifier {
+    #[name("First pass framebuffer")]
+    #[color_texture("fpf_color")]
+    #[depth_texture("fpf_depth")]
     FirstPass,
+    #[name("Shadow cascade 0")]
+    #[depth_texture("shadow_map0")]
+    #[size(1024, 1024)]
+    ShadowCascade0,
+    #[name("Shadow cascade 1")]
+    #[depth_texture("shadow_map1")]
+    #[size(1024, 1024)]
+    ShadowCascade1,
+    #[name("Shadow cascade 2")]
+    #[depth_texture("shadow_map2")]
+    #[size(1024, 1024)]
+    ShadowCascade2,
 }
 
-///TODO: make a FramebufferIdentifier derive macro
-impl FramebufferIdentifier {
-    pub fn name(&self) -> &'static str {
-        match self {
-            FramebufferIdentifier::FirstPass => "First pass framebuffer",
-        }
-    }
-
-    pub fn color_texture(&self) -> Option<&'static str> {
-        match self {
-            FramebufferIdentifier::FirstPass => Some("fpf_color"),
-        }
-    }
-
-    pub fn depth_texture(&self) -> Option<&'static str> {
-        match self {
-            FramebufferIdentifier::FirstPass => Some("fpf_depth"),
-        }
-    }
-
-    pub fn has_depth(&self) -> bool {
-        match self {
-            FramebufferIdentifier::FirstPass => true,
-        }
-    }
-
-    pub fn dimensions(&self, screen_dimensions: (u32, u32)) -> (u32, u32) {
-        match self {
-            FramebufferIdentifier::FirstPass => screen_dimensions,
-        }
-    }
-}
+/// The framebuffers the shadow cascades are rendered to, from the one closest to the view to the furthest.
+/// The chunk shader samples one shadow map per cascade, so it has to be changed along with this.
+pub const SHADOW_CASCADES: [FramebufferIdentifier; 3] = [
+    FramebufferIdentifier::ShadowCascade0,
+    FramebufferIdentifier::ShadowCascade1,
+    FramebufferIdentifier::ShadowCascade2,
+];
 
 pub struct Framebuffer {
     id: u32,
@@ -123,6 +120,7 @@ impl Framebuffer {
             //TODO: DO COLOR TEXTURES WORK??
         } else {
             gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
             //TODO: DOES NO DRAW BUFFER WORK??
         }
 
@@ -138,6 +136,7 @@ impl Framebuffer {
 
     pub unsafe fn bind(&self) {
         gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        gl::Viewport(0, 0, self.metadata.width as i32, self.metadata.height as i32);
         if !self.metadata.identifier.has_depth() {
             gl::Disable(gl::DEPTH_TEST);
         } else {
@@ -192,13 +191,19 @@ impl FramebufferManager {
     }
 
     ///Passing nothing as the framebuffer will bind the screen - the standard draw buffer.
-    pub unsafe fn bind_framebuffer(&self, framebuffer: &Option<FramebufferIdentifier>) {
+    /// The viewport is set to the dimensions of what is bound, since framebuffers can be smaller than the screen.
+    pub unsafe fn bind_framebuffer(
+        &self,
+        framebuffer: &Option<FramebufferIdentifier>,
+        screen_dimensions: (u32, u32),
+    ) {
         match framebuffer {
             Some(fb) => {
                 self.framebuffers[*fb as usize].bind();
             }
             None => {
                 gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, screen_dimensions.0 as i32, screen_dimensions.1 as i32);
                 gl::Enable(gl::DEPTH_TEST);
             }
         }
@@ -229,3 +234,61 @@ impl FramebufferManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InternalFormat, TextureMetadata};
+    use utils::ColorFormat;
+
+    #[derive(Clone, Copy, FramebufferId)]
+    enum TestFramebuffer {
+        #[name("Half resolution")]
+        #[color_texture("half_color")]
+        #[depth]
+        #[scale(0.5)]
+        Half,
+    }
+
+    #[test]
+    fn first_pass_framebuffer() {
+        let framebuffer = FramebufferIdentifier::FirstPass;
+        assert_eq!(framebuffer.color_texture(), Some("fpf_color"));
+        assert_eq!(framebuffer.depth_texture(), Some("fpf_depth"));
+        assert!(framebuffer.has_depth());
+        assert_eq!(framebuffer.dimensions((800, 600)), (800, 600));
+    }
+
+    #[test]
+    fn shadow_cascades_are_depth_only_and_fixed_size() {
+        for cascade in SHADOW_CASCADES {
+            assert_eq!(cascade.color_texture(), None);
+            assert!(cascade.depth_texture().is_some());
+            assert_eq!(
+                cascade.dimensions((800, 600)),
+                cascade.dimensions((1920, 1080))
+            );
+        }
+    }
+
+    #[test]
+    fn scaled_framebuffers_match_scaled_textures() {
+        let framebuffer = TestFramebuffer::Half;
+        assert_eq!(framebuffer.name(), "Half resolution");
+        assert_eq!(framebuffer.depth_texture(), None);
+        assert!(framebuffer.has_depth());
+
+        let texture = TextureMetadata {
+            format: ColorFormat::RGBA,
+            internal_format: InternalFormat::RGBA8,
+            width: 0,
+            height: 0,
+            name: "half_color".to_owned(),
+            screen_dependant_dimensions: true,
+            screen_scale: 0.5,
+        };
+        for screen in [(800, 600), (801, 599), (1, 1)] {
+            assert_eq!(framebuffer.dimensions(screen), texture.dimensions(screen));
+        }
+    }
+}
@@ -2,12 +2,19 @@ use super::{
     framebuffer::FramebufferIdentifier, Framebuffer, FramebufferManager, Shader, ShaderIdentifier,
     ShaderManager, Texture, TextureManager, TextureMetadata,
 };
+use crate::atlas::{AtlasBuilder, TextureAtlas};
+use crate::pack::{PostProcessingConfig, POST_PROCESSING_CONFIG};
 use log::{debug, error};
 use strum::IntoEnumIterator;
 use utils::read_png;
 
+/// The folder inside the texture folder with the block textures packed into the block atlas.
+const BLOCK_TEXTURE_FOLDER: &str = "blocks";
+/// The name of the texture the block textures are packed into.
+pub const BLOCK_ATLAS_TEXTURE: &str = "block_atlas";
+
 pub unsafe fn load_shaders() -> ShaderManager {
-    let folder = utils::ASSETS_PATH.join("graphics/shaders");
+    let folder = utils::ASSETS_PATH.join(super::SHADER_DIRECTORY);
     debug!("{}", folder.to_str().unwrap());
     debug!("{}", folder.canonicalize().unwrap().to_str().unwrap());
 
@@ -35,6 +42,10 @@ pub unsafe fn load_shaders() -> ShaderManager {
         } else if entry.1.ends_with(".comp") {
             let name = &entry.1[0..(entry.1.len() - 5)];
             compute_shaders.push(String::from(name));
+        } else if entry.0.path().extension()
+            == Some(std::ffi::OsStr::new(super::preprocessor::INCLUDE_EXTENSION))
+        {
+            // Included by shaders, which is checked when they are preprocessed.
         } else {
             error!("File {:?} does not contain a shader!", &entry.0);
         }
@@ -74,8 +85,39 @@ pub unsafe fn load_shaders() -> ShaderManager {
     ShaderManager::new(shaders)
 }
 
+/// A texture from the texture folder, before a renderer creates it.
+pub(crate) struct TextureSource {
+    /// Textures with screen dependant dimensions have their dimensions on the current screen here.
+    pub metadata: TextureMetadata,
+    /// What the texture is filled with. None for textures that are rendered to.
+    pub data: Option<Vec<u8>>,
+}
+
 pub unsafe fn load_textures(screen_dimensions: (u32, u32)) -> TextureManager {
     let mut texture_manager = TextureManager::new();
+    let (sources, block_atlas) = load_texture_sources(screen_dimensions);
+    for source in sources {
+        let mut t = Texture::new(source.metadata, screen_dimensions);
+        if let Some(data) = source.data {
+            t.fill(data);
+        }
+        texture_manager.add_texture(t).unwrap();
+    }
+    texture_manager.set_block_atlas(block_atlas);
+
+    //let mut t1 = Texture::new(800, 800, TextureFormat::RGB, "atlas");
+    //t1.fill(utils::read_png("textures/atlas.png"));
+    //texture_manager.add_texture(t1);
+
+    texture_manager
+}
+
+/// Reads every texture in the texture folder, and packs the block textures into the block atlas.
+/// Doesn't call any GL, so it is shared by every renderer.
+pub(crate) fn load_texture_sources(
+    screen_dimensions: (u32, u32),
+) -> (Vec<TextureSource>, TextureAtlas) {
+    let mut sources = Vec::new();
     let entries = utils::dir_entries(&utils::ASSETS_PATH.join("graphics/textures"), "");
     let entries = match entries {
         Ok(e) => e,
@@ -85,8 +127,12 @@ pub unsafe fn load_textures(screen_dimensions: (u32, u32)) -> TextureManager {
     };
 
     //TODO: Maybe panicking when failing to load a texture is a bit melodramatic.
+    let block_folder_prefix = format!("/{}/", BLOCK_TEXTURE_FOLDER);
     for entry in entries {
-        if entry.1.ends_with(".png") {
+        if entry.1.starts_with(&block_folder_prefix) {
+            // Block textures are packed into the block atlas below.
+            continue;
+        } else if entry.1.ends_with(".png") {
             let data = match read_png(&entry.0.path()) {
                 Ok(d) => d,
                 Err(error) => {
@@ -106,16 +152,19 @@ pub unsafe fn load_textures(screen_dimensions: (u32, u32)) -> TextureManager {
                 _ => unreachable!(), // read_png handles the case where the color format isn't one of these two with an error.
             };
 
-            let mut t = Texture::new(
-                Some((data.width, data.height)),
-                data.format,
-                int_format,
-                &entry.1,
-                screen_dimensions,
-            );
-            t.fill(data.data);
-            debug!("Loaded texture {}!", &t.metadata.name);
-            texture_manager.add_texture(t).unwrap();
+            debug!("Loaded texture {}!", &entry.1);
+            sources.push(TextureSource {
+                metadata: TextureMetadata {
+                    format: data.format,
+                    internal_format: int_format,
+                    width: data.width,
+                    height: data.height,
+                    name: entry.1,
+                    screen_dependant_dimensions: false,
+                    screen_scale: 1.,
+                },
+                data: Some(data.data),
+            });
         } else if entry.1.ends_with(".json") {
             let metadatas: Vec<TextureMetadata> =
                 match serde_json::from_str(match &std::fs::read_to_string(&entry.0.path()) {
@@ -126,26 +175,75 @@ pub unsafe fn load_textures(screen_dimensions: (u32, u32)) -> TextureManager {
                     Err(e) => panic!("Json error in file {:?}! Error {:?}", &entry.0, &e),
                 };
 
-            for metadata in metadatas {
-                let t = Texture::new(
-                    match metadata.screen_dependant_dimensions {
-                        false => Some((metadata.width, metadata.height)),
-                        true => None,
-                    },
-                    metadata.format,
-                    metadata.internal_format,
-                    &metadata.name,
-                    screen_dimensions,
-                );
-                texture_manager.add_texture(t).unwrap();
+            for mut metadata in metadatas {
+                (metadata.width, metadata.height) = metadata.dimensions(screen_dimensions);
+                sources.push(TextureSource {
+                    metadata,
+                    data: None,
+                });
             }
         }
     }
-    //let mut t1 = Texture::new(800, 800, TextureFormat::RGB, "atlas");
-    //t1.fill(utils::read_png("textures/atlas.png"));
-    //texture_manager.add_texture(t1);
+    sources.extend(post_processing_textures(screen_dimensions));
+    let (block_atlas_source, block_atlas) = load_block_atlas();
+    sources.push(block_atlas_source);
 
-    texture_manager
+    (sources, block_atlas)
+}
+
+/// The textures the post processing passes write to.
+fn post_processing_textures(screen_dimensions: (u32, u32)) -> Vec<TextureSource> {
+    let path = utils::ASSETS_PATH.join(POST_PROCESSING_CONFIG);
+    let config = match PostProcessingConfig::load(&path) {
+        Ok(config) => config,
+        Err(error) => {
+            error!("Failed to load post processing textures! {}", error);
+            return Vec::new();
+        }
+    };
+    config
+        .output_textures()
+        .map(|mut metadata| {
+            (metadata.width, metadata.height) = metadata.dimensions(screen_dimensions);
+            TextureSource {
+                metadata,
+                data: None,
+            }
+        })
+        .collect()
+}
+
+/// Packs the block textures into one atlas texture, and finds where each block texture ended up,
+/// so the packer can look up texture coordinates for each block.
+fn load_block_atlas() -> (TextureSource, TextureAtlas) {
+    let folder = utils::ASSETS_PATH
+        .join("graphics/textures")
+        .join(BLOCK_TEXTURE_FOLDER);
+    let builder = match AtlasBuilder::from_folder(&folder) {
+        Ok(builder) => builder,
+        Err(error) => {
+            error!("Failed to load block textures! {}", error);
+            AtlasBuilder::new()
+        }
+    };
+    let (image, atlas) = builder.build();
+    debug!(
+        "Packed {} block textures into the block atlas!",
+        atlas.tile_names().count()
+    );
+    let source = TextureSource {
+        metadata: TextureMetadata {
+            format: image.format,
+            internal_format: super::InternalFormat::RGBA8,
+            width: image.width,
+            height: image.height,
+            name: BLOCK_ATLAS_TEXTURE.to_owned(),
+            screen_dependant_dimensions: false,
+            screen_scale: 1.,
+        },
+        data: Some(image.data),
+    };
+    (source, atlas)
 }
 
 pub unsafe fn load_framebuffers(
@@ -4,12 +4,19 @@ use buffer::{ArrayBuffer, ElementBuffer};
 
 mod framebuffer;
 use framebuffer::{Framebuffer, FramebufferManager};
-pub use framebuffer::{FramebufferIdentifier, FramebufferMetadata};
+pub use framebuffer::{FramebufferIdentifier, FramebufferMetadata, SHADOW_CASCADES};
+
+mod preprocessor;
+pub use preprocessor::{LineOrigin, PreprocessError, PreprocessedSource, Preprocessor};
 
 mod shader;
-pub use shader::{ProgramType, ShaderIdentifier, ShaderMetadata};
+pub use shader::{ProgramType, ShaderIdentifier, ShaderMetadata, FOG_DISTANCE, SHADER_DIRECTORY};
 use shader::{Shader, ShaderManager};
 
+mod shader_watcher;
+pub use shader_watcher::ShaderReload;
+use shader_watcher::ShaderWatcher;
+
 mod vertex_array;
 use vertex_array::VertexArray;
 
@@ -22,6 +29,8 @@ use texture::{Texture, TextureManager};
 
 pub mod gui;
 mod loader;
+pub use loader::BLOCK_ATLAS_TEXTURE;
+pub(crate) use loader::load_texture_sources;
 
 mod vertex_buffer_metadata;
 pub use vertex_buffer_metadata::VERTEX_BUFFER_METADATA;
\ No newline at end of file
@@ -0,0 +1,439 @@
+//!
+//! A GLSL preprocessing step run before shaders are compiled.
+//!
+//! It resolves `#include "file"` relative to the shaders directory, injects `#define`s right after the `#version`
+//! line, and drops the branches of `#ifdef`/`#ifndef` that are inactive, so includes and uniforms in them are ignored.
+//! Every line of the result remembers which file and line it came from, so compile errors and uniforms can refer to
+//! the original sources. `#if` is left to the GLSL compiler.
+//!
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use thiserror::Error;
+
+/// The extension of files that are only included by shaders, and aren't shaders themselves.
+pub const INCLUDE_EXTENSION: &str = "glsl";
+
+/// A location in a GL compile log, in either of the notations drivers use.
+static LOG_LOCATION: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"\b0(?::(\d+)|\((\d+)\))").unwrap());
+
+#[derive(Error, Debug)]
+pub enum PreprocessError {
+    #[error("Couldn't read shader source {0:?}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("{0}: #include needs a file name in quotes")]
+    MalformedInclude(LineOrigin),
+    #[error("{0}: included file {1:?} doesn't exist in the shaders directory")]
+    IncludeNotFound(LineOrigin, String),
+    #[error("{0}: #{1} without a matching #if, #ifdef or #ifndef")]
+    UnmatchedConditional(LineOrigin, String),
+    #[error("{0}: #elif can't follow #ifdef or #ifndef, since only #if is left to the compiler")]
+    ElifAfterIfdef(LineOrigin),
+    #[error("{0}: #{1} is never closed with #endif")]
+    UnterminatedConditional(LineOrigin, String),
+}
+
+/// Where a line of preprocessed source came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineOrigin {
+    /// The file, relative to the shaders directory if it is inside it.
+    pub file: String,
+    /// The line in the file, counting from 1.
+    pub line: usize,
+}
+
+impl fmt::Display for LineOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Shader source ready for compiling, with the origin of every line.
+#[derive(Debug)]
+pub struct PreprocessedSource {
+    pub source: String,
+    /// The origin of each line of the source. Injected defines have no origin.
+    origins: Vec<Option<LineOrigin>>,
+}
+
+impl PreprocessedSource {
+    /// Where a line of the preprocessed source came from, counting from 1 like compilers do.
+    pub fn origin(&self, line: usize) -> Option<&LineOrigin> {
+        line.checked_sub(1)
+            .and_then(|index| self.origins.get(index))
+            .and_then(Option::as_ref)
+    }
+
+    /// Every line of the preprocessed source with where it came from.
+    pub fn lines(&self) -> impl Iterator<Item = (&str, Option<&LineOrigin>)> {
+        self.source
+            .lines()
+            .zip(self.origins.iter().map(Option::as_ref))
+    }
+
+    ///
+    /// Rewrites the line numbers in a GL compile log to the files and lines they came from.
+    /// Drivers write locations as `0:12` or `0(12)`, where 0 is the only source string.
+    ///
+    pub fn map_error_log(&self, log: &str) -> String {
+        LOG_LOCATION
+            .replace_all(log, |captures: &regex::Captures| {
+                let line = captures
+                    .get(1)
+                    .or_else(|| captures.get(2))
+                    .and_then(|line| line.as_str().parse().ok());
+                match line.and_then(|line| self.origin(line)) {
+                    Some(origin) => origin.to_string(),
+                    None => captures[0].to_owned(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// A conditional block being preprocessed.
+struct Conditional {
+    directive: String,
+    origin: LineOrigin,
+    /// Whether the lines in the current branch are kept.
+    active: bool,
+    /// Whether the lines around the conditional are kept.
+    parent_active: bool,
+    /// Whether this is an `#if`, which is passed on to the compiler instead of evaluated.
+    passed_through: bool,
+}
+
+/// Preprocesses shader sources with includes from one directory and a set of injected defines.
+pub struct Preprocessor {
+    include_directory: PathBuf,
+    defines: Vec<String>,
+}
+
+impl Preprocessor {
+    pub fn new<P: Into<PathBuf>>(include_directory: P) -> Preprocessor {
+        Preprocessor {
+            include_directory: include_directory.into(),
+            defines: Vec::new(),
+        }
+    }
+
+    /// Injects `#define <define>` into the shader. The define can be a name, or a name followed by a value.
+    pub fn define<T: Into<String>>(mut self, define: T) -> Preprocessor {
+        self.defines.push(define.into());
+        self
+    }
+
+    pub fn preprocess(&self, file: &Path) -> Result<PreprocessedSource, PreprocessError> {
+        let mut state = State {
+            defined: self
+                .defines
+                .iter()
+                .filter_map(|define| define.split_whitespace().next())
+                .map(str::to_owned)
+                .collect(),
+            included: HashSet::new(),
+            lines: Vec::new(),
+        };
+        self.process_file(file, &mut state)?;
+
+        // The version has to come before anything else, so the defines are injected right after it.
+        let version = state
+            .lines
+            .iter()
+            .position(|(line, _)| line.trim_start().starts_with("#version"))
+            .map_or(0, |index| index + 1);
+        let defines = self
+            .defines
+            .iter()
+            .map(|define| (format!("#define {}", define), None));
+        state.lines.splice(version..version, defines);
+
+        let mut source = String::new();
+        let mut origins = Vec::new();
+        for (line, origin) in state.lines {
+            source.push_str(&line);
+            source.push('\n');
+            origins.push(origin);
+        }
+        Ok(PreprocessedSource { source, origins })
+    }
+
+    /// The name a file is shown with in errors and uniform locations.
+    fn display_name(&self, file: &Path) -> String {
+        file.strip_prefix(&self.include_directory)
+            .unwrap_or(file)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    fn process_file(&self, file: &Path, state: &mut State) -> Result<(), PreprocessError> {
+        // Every file is included once, which also stops files from including each other forever.
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.to_owned());
+        if !state.included.insert(canonical) {
+            return Ok(());
+        }
+        let source = std::fs::read_to_string(file)
+            .map_err(|error| PreprocessError::Read(file.to_owned(), error))?;
+        let name = self.display_name(file);
+
+        let mut conditionals: Vec<Conditional> = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let origin = LineOrigin {
+                file: name.clone(),
+                line: index + 1,
+            };
+            let active = conditionals.last().is_none_or(|c| c.active);
+            let trimmed = line.trim_start();
+            let (directive, argument) = match trimmed.strip_prefix('#') {
+                Some(rest) => {
+                    let rest = rest.trim_start();
+                    let end = rest
+                        .find(|c: char| !c.is_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len());
+                    (&rest[..end], rest[end..].trim())
+                }
+                None => ("", ""),
+            };
+            let first_word = argument.split_whitespace().next().unwrap_or_default();
+
+            match directive {
+                "ifdef" | "ifndef" => {
+                    let defined = state.defined.contains(first_word);
+                    conditionals.push(Conditional {
+                        directive: directive.to_owned(),
+                        origin,
+                        active: active && defined == (directive == "ifdef"),
+                        parent_active: active,
+                        passed_through: false,
+                    });
+                }
+                "if" => {
+                    if active {
+                        state.lines.push((line.to_owned(), Some(origin.clone())));
+                    }
+                    conditionals.push(Conditional {
+                        directive: directive.to_owned(),
+                        origin,
+                        active,
+                        parent_active: active,
+                        passed_through: true,
+                    });
+                }
+                "elif" | "else" | "endif" => {
+                    let conditional = match conditionals.last_mut() {
+                        Some(conditional) => conditional,
+                        None => {
+                            return Err(PreprocessError::UnmatchedConditional(
+                                origin,
+                                directive.to_owned(),
+                            ))
+                        }
+                    };
+                    if conditional.passed_through {
+                        if conditional.parent_active {
+                            state.lines.push((line.to_owned(), Some(origin)));
+                        }
+                    } else if directive == "elif" {
+                        return Err(PreprocessError::ElifAfterIfdef(origin));
+                    } else if directive == "else" {
+                        if conditional.directive == "else" {
+                            return Err(PreprocessError::UnmatchedConditional(
+                                origin,
+                                directive.to_owned(),
+                            ));
+                        }
+                        conditional.directive = "else".to_owned();
+                        conditional.active = conditional.parent_active && !conditional.active;
+                    }
+                    if directive == "endif" {
+                        conditionals.pop();
+                    }
+                }
+                _ if !active => {}
+                "include" => {
+                    let include = argument
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.split_once('"'))
+                        .map(|(include, _)| include)
+                        .ok_or_else(|| PreprocessError::MalformedInclude(origin.clone()))?;
+                    let path = self.include_directory.join(include);
+                    if !path.is_file() {
+                        return Err(PreprocessError::IncludeNotFound(origin, include.to_owned()));
+                    }
+                    self.process_file(&path, state)?;
+                }
+                "define" => {
+                    state.defined.insert(first_word.to_owned());
+                    state.lines.push((line.to_owned(), Some(origin)));
+                }
+                "undef" => {
+                    state.defined.remove(first_word);
+                    state.lines.push((line.to_owned(), Some(origin)));
+                }
+                _ => state.lines.push((line.to_owned(), Some(origin))),
+            }
+        }
+        match conditionals.pop() {
+            Some(conditional) => Err(PreprocessError::UnterminatedConditional(
+                conditional.origin,
+                conditional.directive,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+struct State {
+    defined: HashSet<String>,
+    included: HashSet<PathBuf>,
+    lines: Vec<(String, Option<LineOrigin>)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A shaders directory in the temporary directory with the given files.
+    fn shader_directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("preprocessor_{}_{}", name, std::process::id()));
+        for (file, source) in files {
+            let path = directory.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        directory
+    }
+
+    fn origin(file: &str, line: usize) -> Option<LineOrigin> {
+        Some(LineOrigin {
+            file: file.to_owned(),
+            line,
+        })
+    }
+
+    #[test]
+    fn includes_and_defines_keep_their_origins() {
+        let directory = shader_directory(
+            "includes",
+            &[
+                (
+                    "main.vert",
+                    "#version 330 core\n#include \"include/fog.glsl\"\nvoid main() {}",
+                ),
+                (
+                    "include/fog.glsl",
+                    "// Fog\n#include \"include/fog.glsl\"\nuniform float fog;",
+                ),
+            ],
+        );
+        let source = Preprocessor::new(&directory)
+            .define("VERTEX_SHADER")
+            .define("FOG_DISTANCE 50.0")
+            .preprocess(&directory.join("main.vert"))
+            .unwrap();
+        let lines: Vec<_> = source
+            .lines()
+            .map(|(line, origin)| (line, origin.cloned()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("#version 330 core", origin("main.vert", 1)),
+                ("#define VERTEX_SHADER", None),
+                ("#define FOG_DISTANCE 50.0", None),
+                ("// Fog", origin("include/fog.glsl", 1)),
+                ("uniform float fog;", origin("include/fog.glsl", 3)),
+                ("void main() {}", origin("main.vert", 3)),
+            ]
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn inactive_branches_are_dropped() {
+        let directory = shader_directory(
+            "conditionals",
+            &[(
+                "main.frag",
+                "#ifdef FOG\nuniform float fog;\n#else\n#include \"missing.glsl\"\n#endif\n\
+                 #ifndef FOG\n#if 1\nuniform float no_fog;\n#endif\n#endif\n\
+                 #if 1\n#ifdef FOG\nkept\n#endif\n#else\nalso kept\n#endif",
+            )],
+        );
+        let source = Preprocessor::new(&directory)
+            .define("FOG")
+            .preprocess(&directory.join("main.frag"))
+            .unwrap();
+        let lines: Vec<_> = source.lines().map(|(line, _)| line).collect();
+        assert_eq!(
+            lines,
+            [
+                "#define FOG",
+                "uniform float fog;",
+                "#if 1",
+                "kept",
+                "#else",
+                "also kept",
+                "#endif"
+            ]
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn broken_sources_are_reported_with_their_location() {
+        let directory = shader_directory(
+            "errors",
+            &[
+                ("missing.vert", "\n#include \"nope.glsl\""),
+                ("malformed.vert", "#include nope.glsl"),
+                ("unterminated.vert", "#ifdef A\n"),
+                ("double_else.vert", "#ifdef A\n#else\n#else\n#endif"),
+                ("stray_endif.vert", "#if A\n#endif\n#endif"),
+            ],
+        );
+        let error = |file: &str| {
+            Preprocessor::new(&directory)
+                .preprocess(&directory.join(file))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("missing.vert"),
+            "missing.vert:2: included file \"nope.glsl\" doesn't exist in the shaders directory"
+        );
+        assert!(error("malformed.vert").starts_with("malformed.vert:1"));
+        assert!(error("unterminated.vert").starts_with("unterminated.vert:1"));
+        assert!(error("double_else.vert").starts_with("double_else.vert:3"));
+        assert!(error("stray_endif.vert").starts_with("stray_endif.vert:3"));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn error_logs_point_at_the_original_lines() {
+        let directory = shader_directory(
+            "logs",
+            &[
+                (
+                    "main.frag",
+                    "#version 330 core\n#include \"common.glsl\"\nbroken",
+                ),
+                ("common.glsl", "vec3 fog;"),
+            ],
+        );
+        let source = Preprocessor::new(&directory)
+            .define("FRAGMENT_SHADER")
+            .preprocess(&directory.join("main.frag"))
+            .unwrap();
+        // Mesa style, then Nvidia style. Line 2 is the injected define, which has no origin to point to.
+        assert_eq!(
+            source.map_error_log("0:4(1): error: syntax error\n0(3) : error C0000: vec3\n0:2: x"),
+            "main.frag:3(1): error: syntax error\ncommon.glsl:1 : error C0000: vec3\n0:2: x"
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -3,7 +3,8 @@
 
 use super::{
     vertex_buffer_metadata::VERTEX_BUFFER_METADATA, BufferTarget, FramebufferIdentifier,
-    FramebufferManager, ShaderIdentifier, ShaderManager, TextureManager, VertexArray,
+    FramebufferManager, ShaderIdentifier, ShaderManager, ShaderReload, ShaderWatcher,
+    TextureManager, VertexArray,
 };
 use crate::{RenderMessage, UniformData, VertexPack};
 use glutin::{window::Window, ContextWrapper, PossiblyCurrent};
@@ -23,6 +24,7 @@ const VERBOSE: bool = false;
 pub struct RenderCaller {
     vertex_array: VertexArray<Vertex3D>,
     pub shader_manager: ShaderManager,
+    shader_watcher: ShaderWatcher,
     texture_manager: TextureManager,
     framebuffer_manager: FramebufferManager,
     screen_dimensions: (u32, u32),
@@ -32,9 +34,10 @@ impl RenderCaller {
     ///
     /// Marked as unsafe because it calls GL code
     pub unsafe fn new(screen_dimensions: (u32, u32)) -> RenderCaller {
-        let vertex_array = VertexArray::new().unwrap();
+        let vertex_array = VertexArray::new(VERTEX_BUFFER_METADATA.default_vbo_count()).unwrap();
 
         let shader_manager = super::loader::load_shaders();
+        let shader_watcher = ShaderWatcher::new();
         let texture_manager = super::loader::load_textures(screen_dimensions);
         let framebuffer_manager =
             super::loader::load_framebuffers(&texture_manager, screen_dimensions);
@@ -42,6 +45,7 @@ impl RenderCaller {
         RenderCaller {
             vertex_array,
             shader_manager,
+            shader_watcher,
             texture_manager,
             framebuffer_manager,
             screen_dimensions,
@@ -78,12 +82,14 @@ impl RenderCaller {
     /// TODO: Enforce requirements on RenderPack<T> to make this safe.
     unsafe fn pack(&mut self, buffer: &BufferTarget, pack: &VertexPack) {
         let target_id = buffer.get_target_id(&VERTEX_BUFFER_METADATA);
+        // The number of buffers can shrink while the packer still uses the old count.
         if target_id >= self.vertex_array.get_vbo_count() {
-            panic!(
-                "Trying to clear {}, but there's only {} normal buffers ",
+            error!(
+                "Trying to pack {}, but there's only {} buffers",
                 buffer,
-                self.vertex_array.get_vbo_count() - 1
+                self.vertex_array.get_vbo_count()
             );
+            return;
         }
         if VERBOSE {
             debug!("Packing buffer {}", buffer);
@@ -95,11 +101,12 @@ impl RenderCaller {
     unsafe fn clear(&mut self, buffer: &BufferTarget) {
         let target_id = buffer.get_target_id(&VERTEX_BUFFER_METADATA);
         if target_id >= self.vertex_array.get_vbo_count() {
-            panic!(
-                "Trying to clear {}, but there's only {} normal buffers ",
+            error!(
+                "Trying to clear {}, but there's only {} buffers",
                 buffer,
                 self.vertex_array.get_vbo_count()
             );
+            return;
         }
         if VERBOSE {
             debug!("Clearing buffer {}", buffer);
@@ -142,6 +149,20 @@ impl RenderCaller {
         gl::Enable(gl::CULL_FACE);
     }
 
+    unsafe fn blending(&mut self, enabled: bool) {
+        if enabled {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        } else {
+            gl::Disable(gl::BLEND);
+            gl::DepthMask(gl::TRUE);
+        }
+        if VERBOSE {
+            debug!("Setting blending to {}", enabled);
+        }
+    }
+
     pub unsafe fn read_message(&mut self, message: &RenderMessage) {
         match message {
             RenderMessage::Pack { buffer, pack } => self.pack(buffer, pack),
@@ -166,11 +187,13 @@ impl RenderCaller {
             RenderMessage::SwitchTo3D {} => {
                 self.switch_to_3d();
             }
+            RenderMessage::Blending { enabled } => self.blending(*enabled),
         }
     }
 
     pub unsafe fn choose_framebuffer(&mut self, framebuffer: &Option<FramebufferIdentifier>) {
-        self.framebuffer_manager.bind_framebuffer(framebuffer);
+        self.framebuffer_manager
+            .bind_framebuffer(framebuffer, self.screen_dimensions);
         if VERBOSE {
             debug!("Choosing framebuffer {:?}", framebuffer);
         }
@@ -178,6 +201,14 @@ impl RenderCaller {
 
     pub unsafe fn render(&mut self, buffer: &BufferTarget) {
         let target_id = buffer.get_target_id(&VERTEX_BUFFER_METADATA);
+        if target_id >= self.vertex_array.get_vbo_count() {
+            error!(
+                "Trying to render {}, but there's only {} buffers",
+                buffer,
+                self.vertex_array.get_vbo_count()
+            );
+            return;
+        }
         debug_assert!(
             self.vertex_array.get_size(target_id) > 0,
             "A render call was made on an empty vertex array!"
@@ -224,6 +255,20 @@ impl RenderCaller {
         }
     }
 
+    ///
+    /// Recompiles the shaders whose source files changed since they were last checked.
+    /// Shaders that fail to compile keep their old program.
+    pub unsafe fn reload_changed_shaders(&mut self) -> ShaderReload {
+        let mut reload = ShaderReload::default();
+        for identifier in self.shader_watcher.changed_shaders() {
+            match self.shader_manager.reload_shader(identifier) {
+                Ok(()) => reload.reloaded.push(identifier),
+                Err(error) => reload.errors.push((identifier, error)),
+            }
+        }
+        reload
+    }
+
     pub fn get_vbo_count(&self) -> usize {
         self.vertex_array.get_vbo_count()
     }
@@ -1,15 +1,28 @@
+use super::preprocessor::{PreprocessError, PreprocessedSource, Preprocessor};
 use super::TextureManager;
 use crate::{render_messages::UniformValue, UniformData};
 use log::info;
 use macros::ShaderId;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     ffi::{CStr, CString},
-    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
 };
 use strum::{EnumCount, EnumIter};
 
-#[derive(Clone)]
+/// The folder with the shader sources, which included files are relative to.
+pub const SHADER_DIRECTORY: &str = "graphics/shaders";
+
+/// The distance at which everything is fog.
+/// Shaders that include `fog.glsl` get it as their `FOG_DISTANCE` define, which has to match.
+pub const FOG_DISTANCE: f32 = 50.;
+
+/// A word in a uniform declaration.
+static UNIFORM_WORD: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"\w+").unwrap());
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ProgramType {
     Graphics,
     Compute,
@@ -22,11 +35,14 @@ pub struct Shader {
     metadata: ShaderMetadata,
 }
 
-#[derive(Clone, Copy, Debug, EnumCount, EnumIter, ShaderId)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, EnumCount, EnumIter, ShaderId, Serialize, Deserialize,
+)]
 pub enum ShaderIdentifier {
     #[name("Default shader")]
     #[extensionless_path("graphics/shaders/s1")]
     #[is_compute(false)]
+    #[defines("FOG_DISTANCE 50.0")]
     Default,
     #[name("Sobel shader")]
     #[extensionless_path("graphics/shaders/sobel")]
@@ -52,9 +68,53 @@ pub enum ShaderIdentifier {
     #[extensionless_path("graphics/shaders/gui")]
     #[is_compute(false)]
     Gui,
+    #[name("Chunk")]
+    #[extensionless_path("graphics/shaders/chunk")]
+    #[is_compute(false)]
+    #[defines("FOG_DISTANCE 50.0", "SHADOWS")]
+    Chunk,
+    #[name("Shadow")]
+    #[extensionless_path("graphics/shaders/shadow")]
+    #[is_compute(false)]
+    Shadow,
+}
+
+impl ShaderIdentifier {
+    ///
+    /// Preprocesses one of the shader's source files with its defines, and a define naming the stage it is compiled
+    /// for: `VERTEX_SHADER`, `FRAGMENT_SHADER` or `COMPUTE_SHADER`, so included files can differ between stages.
+    ///
+    pub fn preprocess(&self, file: &Path) -> Result<PreprocessedSource, PreprocessError> {
+        let stage = match file.extension().and_then(|extension| extension.to_str()) {
+            Some("vert") => "VERTEX_SHADER",
+            Some("frag") => "FRAGMENT_SHADER",
+            _ => "COMPUTE_SHADER",
+        };
+        self.defines()
+            .iter()
+            .fold(
+                Preprocessor::new(utils::ASSETS_PATH.join(SHADER_DIRECTORY)).define(stage),
+                |preprocessor, define| preprocessor.define(*define),
+            )
+            .preprocess(file)
+    }
+
+    /// The files the shader is compiled from.
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        let extensionless_path = utils::ASSETS_PATH.join(self.extensionless_path());
+        let extensions: &[&str] = if self.is_compute() {
+            &["comp"]
+        } else {
+            &["vert", "frag"]
+        };
+        extensions
+            .iter()
+            .map(|extension| extensionless_path.with_extension(extension))
+            .collect()
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ShaderMetadata {
     pub identifier: ShaderIdentifier,
     /// The uniforms that this shader needs filled out
@@ -63,6 +123,31 @@ pub struct ShaderMetadata {
     pub shader_type: ProgramType,
 }
 
+impl ShaderMetadata {
+    /// Finds the uniforms a shader needs from its source files, without compiling it.
+    /// Used where there is no GL context, like in the software renderer.
+    pub fn from_source(identifier: ShaderIdentifier) -> Result<ShaderMetadata, String> {
+        let shader_type = if identifier.is_compute() {
+            ProgramType::Compute
+        } else {
+            ProgramType::Graphics
+        };
+        let mut required_uniforms = Vec::new();
+        for file in identifier.source_files() {
+            let source = match identifier.preprocess(&file) {
+                Ok(source) => source,
+                Err(error) => return Err(error.to_string()),
+            };
+            required_uniforms.append(&mut Shader::find_uniforms(&source));
+        }
+        Ok(ShaderMetadata {
+            identifier,
+            required_uniforms,
+            shader_type,
+        })
+    }
+}
+
 impl Shader {
     unsafe fn compile_shader(source: &CStr, shader_type: u32) -> Result<u32, String> {
         if shader_type != gl::VERTEX_SHADER
@@ -94,6 +179,7 @@ impl Shader {
                 std::ptr::null_mut(),
                 error.as_ptr() as *mut gl::types::GLchar,
             );
+            gl::DeleteShader(id);
 
             return Err(error.to_string_lossy().into_owned());
         }
@@ -102,26 +188,25 @@ impl Shader {
     }
 
     unsafe fn load_shader(
+        identifier: ShaderIdentifier,
         file: &str,
         shader_type: u32,
     ) -> Result<(u32, Vec<(String, String)>), String> {
-        let shader_source = match fs::read_to_string(file) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(format!("Couldn't open file {}. {:?}", file, e));
-            }
+        let source = match identifier.preprocess(Path::new(file)) {
+            Ok(source) => source,
+            Err(error) => return Err(error.to_string()),
         };
-        let shader_source = shader_source + "\0";
+        let shader_source = format!("{}\0", source.source);
 
         let id = match Self::compile_shader(
             CStr::from_bytes_with_nul(shader_source.as_bytes()).unwrap(),
             shader_type,
         ) {
             Ok(vsid) => vsid,
-            Err(s) => return Err(s),
+            Err(log) => return Err(source.map_error_log(&log)),
         };
 
-        Ok((id, Self::find_uniforms(&shader_source, file)))
+        Ok((id, Self::find_uniforms(&source)))
     }
 
     pub unsafe fn new(identifier: ShaderIdentifier) -> Result<Shader, String> {
@@ -152,14 +237,11 @@ impl Shader {
             .unwrap()
             .to_owned();
         info!("Loading shader {}", name);
-        let (vsid, mut vsuniforms) = match Self::load_shader(&vertex_file, gl::VERTEX_SHADER) {
-            Ok(id) => id,
-            Err(s) => return Err(s),
-        };
-        let (fsid, mut fsuniforms) = match Self::load_shader(&fragment_file, gl::FRAGMENT_SHADER) {
-            Ok(id) => id,
-            Err(s) => return Err(s),
-        };
+        let (vsid, mut vsuniforms) =
+            Self::load_shader(identifier, &vertex_file, gl::VERTEX_SHADER)?;
+        let (fsid, mut fsuniforms) =
+            Self::load_shader(identifier, &fragment_file, gl::FRAGMENT_SHADER)
+                .inspect_err(|_| gl::DeleteShader(vsid))?;
 
         vsuniforms.append(&mut fsuniforms);
         let required_uniforms = vsuniforms;
@@ -185,6 +267,9 @@ impl Shader {
                 std::ptr::null_mut(),
                 error.as_ptr() as *mut gl::types::GLchar,
             );
+            gl::DeleteProgram(program_id);
+            gl::DeleteShader(vsid);
+            gl::DeleteShader(fsid);
 
             return Err(error.to_string_lossy().into_owned());
         }
@@ -225,10 +310,8 @@ impl Shader {
             .to_str()
             .unwrap()
             .to_owned();
-        let (id, required_uniforms) = match Self::load_shader(&compute_file, gl::COMPUTE_SHADER) {
-            Ok(id) => id,
-            Err(s) => return Err(s),
-        };
+        let (id, required_uniforms) =
+            Self::load_shader(identifier, &compute_file, gl::COMPUTE_SHADER)?;
 
         let program_id = gl::CreateProgram();
 
@@ -251,6 +334,8 @@ impl Shader {
                 std::ptr::null_mut(),
                 error.as_ptr() as *mut gl::types::GLchar,
             );
+            gl::DeleteProgram(program_id);
+            gl::DeleteShader(id);
 
             return Err(error.to_string_lossy().into_owned());
         }
@@ -297,20 +382,18 @@ impl Shader {
     }
 
     ///TODO: This should work for any valid notation.
-    fn find_uniforms(source: &str, filename: &str) -> Vec<(String, String)> {
+    /// Uniforms are found after preprocessing, so they are recorded at the file and line they came from.
+    fn find_uniforms(source: &PreprocessedSource) -> Vec<(String, String)> {
         let mut uniforms: Vec<(String, String)> = Vec::new();
-        let mut counter = 0;
-        for line in source.lines() {
-            counter += 1;
+        for (line, origin) in source.lines() {
             if line.starts_with("uniform") {
                 let next = &line[8..];
-                let re = regex::Regex::new(r"\w+").unwrap();
-                let mut ms = re.captures_iter(next);
+                let mut ms = UNIFORM_WORD.captures_iter(next);
                 ms.next();
                 if let Some(type_name) = ms.next() {
                     uniforms.push((
                         String::from(&type_name[0]),
-                        format!("{}:{}", filename, counter),
+                        origin.map_or_else(String::new, |origin| origin.to_string()),
                     ));
                 }
             }
@@ -378,6 +461,22 @@ impl ShaderManager {
         }
     }
 
+    ///
+    /// Recompiles a shader from its source files, finding its uniforms again.
+    /// If it fails to compile, the old program is kept and the error is returned.
+    ///
+    pub unsafe fn reload_shader(&mut self, identifier: ShaderIdentifier) -> Result<(), String> {
+        let shader = Shader::new(identifier)?;
+        self.shaders[identifier as usize] = shader;
+        // The old program was bound, so the new one has to be bound in its place.
+        if let Some(bound) = self.bound_shader {
+            if bound == identifier {
+                self.shaders[identifier as usize].bind();
+            }
+        }
+        Ok(())
+    }
+
     //pub unsafe fn add_shader(&mut self, shader: Shader) {
     //    self.shaders.push(shader);
     //}
@@ -496,9 +595,79 @@ impl ShaderManager {
 
 #[cfg(test)]
 mod tests {
-    use super::{ProgramType, Shader, ShaderIdentifier, ShaderManager, ShaderMetadata};
+    use super::{
+        ProgramType, Shader, ShaderIdentifier, ShaderManager, ShaderMetadata, FOG_DISTANCE,
+    };
     use std::collections::HashMap;
     use strum::IntoEnumIterator;
+
+    #[test]
+    fn fog_distance_defines_match() {
+        let fog_distances: Vec<f32> = ShaderIdentifier::iter()
+            .flat_map(|identifier| identifier.defines().iter())
+            .filter_map(|define| define.strip_prefix("FOG_DISTANCE "))
+            .map(|distance| distance.parse().unwrap())
+            .collect();
+        assert!(!fog_distances.is_empty());
+        assert!(fog_distances
+            .iter()
+            .all(|&distance| distance == FOG_DISTANCE));
+    }
+
+    #[test]
+    fn shaders_are_preprocessed_with_their_defines() {
+        for identifier in ShaderIdentifier::iter() {
+            for file in identifier.source_files() {
+                if let Err(error) = identifier.preprocess(&file) {
+                    panic!("{}", error);
+                }
+            }
+        }
+        let vertex = &ShaderIdentifier::Chunk.source_files()[0];
+        let source = ShaderIdentifier::Chunk.preprocess(vertex).unwrap();
+        let lines: Vec<&str> = source.lines().map(|(line, _)| line).collect();
+        assert_eq!(
+            lines[..3],
+            [
+                "#version 330 core",
+                "#define VERTEX_SHADER",
+                "#define FOG_DISTANCE 50.0"
+            ]
+        );
+        assert!(lines.iter().any(|line| line.starts_with("vec3 apply_fog")));
+    }
+
+    #[test]
+    fn uniforms_are_found_where_they_are_written() {
+        let metadata = ShaderMetadata::from_source(ShaderIdentifier::Chunk).unwrap();
+        let mvp = metadata
+            .required_uniforms
+            .iter()
+            .find(|(name, _)| name == "MVP")
+            .unwrap();
+        assert_eq!(mvp.1, "chunk.vert:15");
+        // Uniforms in included files are found in them, and only when the shader defines SHADOWS.
+        let shadow_map = metadata
+            .required_uniforms
+            .iter()
+            .find(|(name, _)| name == "shadow_map0")
+            .unwrap();
+        assert_eq!(shadow_map.1, "include/shadows.glsl:6");
+        let metadata = ShaderMetadata::from_source(ShaderIdentifier::Default).unwrap();
+        assert!(metadata
+            .required_uniforms
+            .iter()
+            .all(|(name, _)| !name.starts_with("shadow")));
+
+        // The output image of compute shaders is bound by the dispatch, not given as a uniform.
+        let metadata = ShaderMetadata::from_source(ShaderIdentifier::Sobel).unwrap();
+        let uniforms: Vec<&str> = metadata
+            .required_uniforms
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(uniforms, ["from_tex"]);
+    }
     #[test]
     #[should_panic]
     fn no_shader_test() {
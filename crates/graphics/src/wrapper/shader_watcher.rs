@@ -0,0 +1,178 @@
+use super::preprocessor::INCLUDE_EXTENSION;
+use super::{ShaderIdentifier, SHADER_DIRECTORY};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use strum::IntoEnumIterator;
+
+/// How often the shader files are checked for changes. Checking every frame would be wasteful.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+///
+/// Watches the source files of every shader, so shaders can be recompiled when they are edited.
+/// Files are polled for their modification time, since a shader change only has to show up within a moment.
+///
+pub struct ShaderWatcher {
+    /// Every source file, the shader it belongs to, and when it was last modified.
+    files: Vec<(ShaderIdentifier, PathBuf, Option<SystemTime>)>,
+    last_check: Instant,
+}
+
+/// The result of reloading the changed shaders.
+#[derive(Default)]
+pub struct ShaderReload {
+    /// The shaders that were recompiled.
+    pub reloaded: Vec<ShaderIdentifier>,
+    /// The shaders that failed to compile and kept their old program, with their errors.
+    pub errors: Vec<(ShaderIdentifier, String)>,
+}
+
+impl ShaderReload {
+    /// Whether the shader metadata changed, so the packer needs new graphics capabilities.
+    pub fn changed_metadata(&self) -> bool {
+        !self.reloaded.is_empty()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl ShaderWatcher {
+    /// Starts watching the source files of every shader identifier.
+    /// Shaders are reloaded when any included file changes, since finding which shaders include it takes preprocessing.
+    pub fn new() -> ShaderWatcher {
+        let include_files: Vec<PathBuf> =
+            utils::dir_entries(&utils::ASSETS_PATH.join(SHADER_DIRECTORY), "")
+                .map(|entries| {
+                    entries
+                        .into_iter()
+                        .map(|(entry, _)| entry.path())
+                        .filter(|path| path.extension() == Some(OsStr::new(INCLUDE_EXTENSION)))
+                        .collect()
+                })
+                .unwrap_or_default();
+        ShaderWatcher::with_files(
+            ShaderIdentifier::iter()
+                .flat_map(|identifier| {
+                    identifier
+                        .source_files()
+                        .into_iter()
+                        .chain(include_files.iter().cloned())
+                        .map(move |file| (identifier, file))
+                })
+                .collect(),
+        )
+    }
+
+    fn with_files(files: Vec<(ShaderIdentifier, PathBuf)>) -> ShaderWatcher {
+        ShaderWatcher {
+            files: files
+                .into_iter()
+                .map(|(identifier, file)| {
+                    let modified = modified_time(&file);
+                    (identifier, file, modified)
+                })
+                .collect(),
+            last_check: Instant::now(),
+        }
+    }
+
+    /// The shaders with source files that changed since the last check.
+    /// Returns nothing if the files were checked too recently.
+    pub fn changed_shaders(&mut self) -> Vec<ShaderIdentifier> {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = Instant::now();
+        self.check()
+    }
+
+    fn check(&mut self) -> Vec<ShaderIdentifier> {
+        let mut changed: Vec<ShaderIdentifier> = Vec::new();
+        for (identifier, file, modified) in &mut self.files {
+            let new_modified = modified_time(file);
+            if new_modified != *modified {
+                *modified = new_modified;
+                if !changed.contains(identifier) {
+                    changed.push(*identifier);
+                }
+            }
+        }
+        changed
+    }
+}
+
+impl Default for ShaderWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn touch(file: &Path, modified: SystemTime) {
+        File::options()
+            .write(true)
+            .open(file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn changed_files_are_reported_once_per_shader() {
+        let folder = std::env::temp_dir().join(format!("shader_watcher_{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let vertex = folder.join("simple.vert");
+        let fragment = folder.join("simple.frag");
+        let compute = folder.join("sobel.comp");
+        for file in [&vertex, &fragment, &compute] {
+            std::fs::write(file, "void main() {}").unwrap();
+            touch(file, SystemTime::UNIX_EPOCH);
+        }
+        let mut watcher = ShaderWatcher::with_files(vec![
+            (ShaderIdentifier::Simple, vertex.clone()),
+            (ShaderIdentifier::Simple, fragment.clone()),
+            (ShaderIdentifier::Sobel, compute.clone()),
+        ]);
+        assert!(watcher.check().is_empty());
+
+        let later = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        touch(&vertex, later);
+        touch(&fragment, later);
+        let changed = watcher.check();
+        assert_eq!(changed.len(), 1);
+        assert!(matches!(changed[0], ShaderIdentifier::Simple));
+        assert!(watcher.check().is_empty());
+
+        // A deleted file is a change too, so the shader fails to reload and keeps its old program.
+        std::fs::remove_file(&compute).unwrap();
+        let changed = watcher.check();
+        assert!(matches!(changed[..], [ShaderIdentifier::Sobel]));
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn every_shader_is_watched() {
+        let watcher = ShaderWatcher::new();
+        for identifier in ShaderIdentifier::iter() {
+            let files = watcher
+                .files
+                .iter()
+                .filter(|(watched, file, modified)| {
+                    *watched == identifier
+                        && modified.is_some()
+                        && file.extension() != Some(OsStr::new(INCLUDE_EXTENSION))
+                })
+                .count();
+            assert_eq!(files, if identifier.is_compute() { 1 } else { 2 });
+        }
+    }
+}
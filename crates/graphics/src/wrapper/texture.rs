@@ -1,3 +1,4 @@
+use crate::TextureAtlas;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ptr::null;
@@ -42,22 +43,18 @@ impl Drop for Texture {
 
 impl Texture {
     ///
-    ///Creates a new, empty texture, with the specified width, height, and format
-    /// If width and height are None, then
-    pub unsafe fn new(
-        dimensions: Option<(u32, u32)>,
-        format: ColorFormat,
-        internal_format: InternalFormat,
-        name: &str,
-        screen_dimensions: (u32, u32),
-    ) -> Texture {
+    /// Creates a new, empty texture with the format and dimensions in the metadata.
+    /// If the dimensions are screen dependant, they are instead the screen dimensions times the screen scale.
+    pub unsafe fn new(metadata: TextureMetadata, screen_dimensions: (u32, u32)) -> Texture {
+        let TextureMetadata {
+            format,
+            internal_format,
+            ..
+        } = metadata;
         let glf = format.gl_format();
         let mut id = 0;
 
-        let (width, height) = match dimensions {
-            Some(d) => d,
-            None => screen_dimensions,
-        };
+        let (width, height) = metadata.dimensions(screen_dimensions);
         gl::GenTextures(1, &mut id);
         gl::BindTexture(gl::TEXTURE_2D, id);
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32); //TODO: WHAT THE HELL IS GOING ON WITH THIS CONVERSION TO I32???
@@ -82,12 +79,9 @@ impl Texture {
             id,
             filled: false,
             metadata: TextureMetadata {
-                format,
-                internal_format,
                 width,
                 height,
-                name: String::from(name),
-                screen_dependant_dimensions: dimensions.is_none(),
+                ..metadata
             },
         }
     }
@@ -141,13 +135,38 @@ pub struct TextureMetadata {
     pub height: u32,
     pub name: String,
     /// Whether this texture changes size depending on the screen dimensions.
-    /// TODO: IMPLEMENT ALTERNATE SCREEN DEPENDENCIES THAN JUST TEXTURE_DIM = SCREEN_DIM
     pub screen_dependant_dimensions: bool,
+    /// How large a texture with screen dependant dimensions is compared to the screen.
+    #[serde(default = "TextureMetadata::full_screen")]
+    pub screen_scale: f32,
+}
+
+impl TextureMetadata {
+    fn full_screen() -> f32 {
+        1.
+    }
+
+    /// The dimensions the texture has on a screen with the given dimensions.
+    pub fn dimensions(&self, screen_dimensions: (u32, u32)) -> (u32, u32) {
+        if self.screen_dependant_dimensions {
+            scale_dimensions(screen_dimensions, self.screen_scale)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// Scales the dimensions, rounding up so nothing is ever scaled to nothing.
+fn scale_dimensions(dimensions: (u32, u32), scale: f32) -> (u32, u32) {
+    let scale = |x: u32| ((x as f32 * scale).ceil() as u32).max(1);
+    (scale(dimensions.0), scale(dimensions.1))
 }
 
 pub struct TextureManager {
     textures: Vec<Texture>,
     texture_names: HashMap<String, usize>,
+    /// Where each block texture is in the block atlas texture.
+    block_atlas: TextureAtlas,
 }
 
 impl TextureManager {
@@ -155,6 +174,7 @@ impl TextureManager {
         TextureManager {
             textures: vec![],
             texture_names: HashMap::new(),
+            block_atlas: TextureAtlas::default(),
         }
     }
 
@@ -169,6 +189,14 @@ impl TextureManager {
         res
     }
 
+    pub fn block_atlas(&self) -> &TextureAtlas {
+        &self.block_atlas
+    }
+
+    pub fn set_block_atlas(&mut self, block_atlas: TextureAtlas) {
+        self.block_atlas = block_atlas;
+    }
+
     pub fn add_texture(&mut self, texture: Texture) -> Result<(), String> {
         if self.texture_names.contains_key(&texture.metadata.name) {
             return Err(format!(
@@ -213,13 +241,7 @@ impl TextureManager {
         for i in 0..self.textures.len() {
             if self.textures[i].metadata.screen_dependant_dimensions {
                 let old_metadata = self.textures[i].metadata.clone();
-                self.textures[i] = Texture::new(
-                    None,
-                    old_metadata.format,
-                    old_metadata.internal_format,
-                    &old_metadata.name,
-                    screen_dimensions,
-                );
+                self.textures[i] = Texture::new(old_metadata, screen_dimensions);
             }
         }
     }
@@ -247,6 +269,7 @@ mod tests {
             height: 0,
             name: "bob".to_owned(),
             screen_dependant_dimensions: true,
+            screen_scale: 1.,
         };
         let j = serde_json::to_string(&metadata).unwrap();
 
@@ -1,5 +1,3 @@
-use crate::VERTEX_BUFFER_METADATA;
-
 //TODO: THIS IS ALL WRONG; ONE VERTEX ARRAY HOLDS A GROUP OF BUFFERS
 use super::ArrayBuffer;
 use super::ElementBuffer;
@@ -16,7 +14,7 @@ pub struct VertexArray<T: Vertex> {
 //TODO: WHAT TO DO WHEN MULTIPLE VERTREX ARRAYS TRY TO BIND SAME LOCATION AT THE SAME TIME??
 impl<T: Vertex> VertexArray<T> {
     //TODO: validate inputs
-    pub unsafe fn new() -> Result<VertexArray<T>, String> {
+    pub unsafe fn new(vbo_count: usize) -> Result<VertexArray<T>, String> {
         let attributes = Vertex3D::attribute_pointers();
 
         let mut vbos: Vec<ArrayBuffer<T>> = Vec::new();
@@ -25,7 +23,7 @@ impl<T: Vertex> VertexArray<T> {
         let mut id = 0;
         gl::GenVertexArrays(1, &mut id);
         gl::BindVertexArray(id);
-        for _ in 0..(VERTEX_BUFFER_METADATA.max_buffer_id()) {
+        for _ in 0..vbo_count {
             let ebo = ElementBuffer::new().unwrap();
             let vbo = ArrayBuffer::<T>::new().unwrap();
 
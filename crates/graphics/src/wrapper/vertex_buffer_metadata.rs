@@ -2,40 +2,48 @@ use crate::BufferTarget;
 
 ///
 /// Contains info about where buffer targets go in the range of available opengl buffers.
-/// 
+/// The gui buffer comes first, then the scratch buffer and a fixed range of model buffers. Every
+/// buffer after those is a world buffer, so the number of world buffers follows the number of
+/// buffers in the vertex array.
+///
 #[allow(dead_code)]
 pub struct VertexBufferMetadata {
     gui_buffer : usize,
-    world_buffer_start : usize,
+    scratch_buffer : usize,
     model_buffer_start : usize,
-    max_buffer_id : usize,
+    world_buffer_start : usize,
+    default_vbo_count : usize,
 }
 
 pub const VERTEX_BUFFER_METADATA : VertexBufferMetadata = VertexBufferMetadata {
     gui_buffer : 0,
-    world_buffer_start : 1,
-    model_buffer_start : 100,
-    max_buffer_id : 200,
+    scratch_buffer : 1,
+    model_buffer_start : 2,
+    world_buffer_start : 101,
+    default_vbo_count : 201,
 };
 
 impl VertexBufferMetadata {
-    pub fn new(model_buffer_start : usize, max_buffer_id : usize ) -> Self {
-        if model_buffer_start <= 1 {
-            panic!("Passed invalid data into VertexBufferMetadata! (graphics::wrapper::vertex_buffer_metadata l. 12");
+    pub fn new(world_buffer_start : usize, default_vbo_count : usize ) -> Self {
+        if world_buffer_start <= 2 {
+            panic!("Passed invalid data into VertexBufferMetadata! (graphics::wrapper::vertex_buffer_metadata l. 25");
         }
-        if max_buffer_id <= model_buffer_start {
-            panic!("Passed invalid data into VertexBufferMetadata! (graphics::wrapper::vertex_buffer_metadata l. 16");
+        if default_vbo_count < world_buffer_start {
+            panic!("Passed invalid data into VertexBufferMetadata! (graphics::wrapper::vertex_buffer_metadata l. 28");
         }
         Self {
-            gui_buffer : 0, world_buffer_start : 1, model_buffer_start, max_buffer_id
+            gui_buffer : 0, scratch_buffer : 1, model_buffer_start : 2, world_buffer_start, default_vbo_count
         }
     }
 
+    /// Whether the target has a fixed place among the buffers.
+    /// World buffers are always valid here, but might be beyond the number of buffers available.
     pub fn valid_target(&self, buffer_target : &BufferTarget) -> bool {
         match buffer_target {
             BufferTarget::GuiBuffer => true,
-            BufferTarget::WorldBuffer(i) => *i < self.model_buffer_start - self.world_buffer_start,
-            BufferTarget::ModelBuffer(i) => *i < self.max_buffer_id - self.model_buffer_start
+            BufferTarget::ScratchBuffer => true,
+            BufferTarget::WorldBuffer(_) => true,
+            BufferTarget::ModelBuffer(i) => *i < self.world_buffer_start - self.model_buffer_start
         }
     }
 
@@ -43,11 +51,21 @@ impl VertexBufferMetadata {
         self.world_buffer_start
     }
 
+    pub fn scratch_buffer(&self) -> usize {
+        self.scratch_buffer
+    }
+
     pub fn model_buffer_start(&self) -> usize {
         self.model_buffer_start
     }
 
-    pub fn max_buffer_id(&self)-> usize {
-        self.max_buffer_id
+    /// The number of buffers a vertex array is created with.
+    pub fn default_vbo_count(&self) -> usize {
+        self.default_vbo_count
+    }
+
+    /// The number of world buffers available when the vertex array has the given number of buffers.
+    pub fn world_buffer_count(&self, vbo_count : usize) -> usize {
+        vbo_count.saturating_sub(self.world_buffer_start)
     }
-}
\ No newline at end of file
+}
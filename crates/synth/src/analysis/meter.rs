@@ -0,0 +1,136 @@
+use crate::dynamics::gain_to_db;
+use crate::utils::RotatingArray;
+
+/// Measures the RMS and peak level of a signal.
+///
+/// The RMS is taken over a sliding window, while the peak is held and then decays,
+/// like the peak indicator on a mixing desk.
+#[derive(Clone)]
+pub struct LevelMeter {
+    window: RotatingArray<f32>,
+    /// Sum of the squares of the samples in the window.
+    square_sum: f64,
+    peak: f32,
+    peak_decay: f32,
+    clip_count: u64,
+    sample_count: u64,
+}
+
+impl LevelMeter {
+    /// Creates a new LevelMeter.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_time` - Seconds of signal the RMS is measured over.
+    /// * `peak_decay_time` - Seconds for the held peak to fall by 20 dB.
+    /// * `sample_rate` - The used sample rate.
+    pub fn new(window_time: f32, peak_decay_time: f32, sample_rate: u32) -> LevelMeter {
+        let window_size = ((window_time * sample_rate as f32) as usize).max(1);
+        let peak_decay = if peak_decay_time <= 0. {
+            0.
+        } else {
+            0.1f32.powf(1. / (peak_decay_time * sample_rate as f32))
+        };
+        LevelMeter {
+            window: RotatingArray::new(window_size, 0.),
+            square_sum: 0.,
+            peak: 0.,
+            peak_decay,
+            clip_count: 0,
+            sample_count: 0,
+        }
+    }
+
+    /// Feeds the meter the next sample.
+    pub fn push(&mut self, sample: f32) {
+        let removed = self.window.push_pop(sample);
+        self.square_sum += (sample as f64).powi(2) - (removed as f64).powi(2);
+        // Rounding errors could otherwise make the sum slightly negative.
+        self.square_sum = self.square_sum.max(0.);
+        self.peak = (self.peak * self.peak_decay).max(sample.abs());
+        if sample.abs() >= 1. {
+            self.clip_count += 1;
+        }
+        self.sample_count += 1;
+    }
+
+    /// Feeds the meter all the given samples in order.
+    pub fn process(&mut self, samples: &[f32]) {
+        samples.iter().for_each(|&sample| self.push(sample));
+    }
+
+    /// The RMS level of the samples in the window.
+    pub fn rms(&self) -> f32 {
+        (self.square_sum / self.window.len() as f64).sqrt() as f32
+    }
+
+    /// The RMS level in dB.
+    pub fn rms_db(&self) -> f32 {
+        gain_to_db(self.rms())
+    }
+
+    /// The held peak level.
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    /// The held peak level in dB.
+    pub fn peak_db(&self) -> f32 {
+        gain_to_db(self.peak)
+    }
+
+    /// The number of samples so far with a magnitude of 1 or more.
+    pub fn clip_count(&self) -> u64 {
+        self.clip_count
+    }
+
+    /// Whether any sample so far has had a magnitude of 1 or more.
+    pub fn has_clipped(&self) -> bool {
+        self.clip_count > 0
+    }
+
+    /// Whether both the RMS and the held peak are below the threshold in dB.
+    pub fn is_silent(&self, threshold: f32) -> bool {
+        self.rms_db() < threshold && self.peak_db() < threshold
+    }
+
+    /// The number of samples the meter has been fed.
+    pub fn sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Forgets all previous samples.
+    pub fn reset(&mut self) {
+        self.window = RotatingArray::new(self.window.len(), 0.);
+        self.square_sum = 0.;
+        self.peak = 0.;
+        self.clip_count = 0;
+        self.sample_count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_levels() {
+        let mut meter = LevelMeter::new(0.1, 1., 48000);
+        for i in 0..48000 {
+            meter.push((i as f32 * 440. / 48000. * std::f32::consts::TAU).sin() * 0.5);
+        }
+        assert!((meter.rms() - 0.5 / 2f32.sqrt()).abs() < 1e-3);
+        assert!((meter.peak() - 0.5).abs() < 1e-3);
+        assert!(!meter.has_clipped());
+        assert!(!meter.is_silent(-60.));
+    }
+
+    #[test]
+    fn clipping_and_silence() {
+        let mut meter = LevelMeter::new(0.01, 0.01, 48000);
+        meter.process(&[0.5, 1.2, -1., 0.2]);
+        assert_eq!(meter.clip_count(), 2);
+        meter.process(&[0.; 4800]);
+        assert!(meter.is_silent(-60.));
+    }
+}
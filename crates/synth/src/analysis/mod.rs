@@ -0,0 +1,9 @@
+//! Tools for inspecting audio, e.g. to display levels or to detect clipping and silence in tests.
+//! None of these modify the signal. Use a `Tap` module to get samples out of a module graph
+//! running on the audio thread.
+mod meter;
+pub use meter::*;
+mod spectrum;
+pub use spectrum::*;
+mod tap;
+pub use tap::*;
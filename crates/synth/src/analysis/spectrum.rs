@@ -0,0 +1,184 @@
+use crate::dynamics::gain_to_db;
+use crate::utils::RotatingArray;
+
+/// Computes the discrete Fourier transform of the input in place with an iterative radix-2 FFT.
+/// The real and imaginary parts are given as separate slices.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths or the length is not a power of two.
+pub fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+    let n = real.len();
+    assert_eq!(n, imaginary.len());
+    assert!(n.is_power_of_two(), "FFT size must be a power of two.");
+
+    // Reorder the input so the butterflies can be done in place.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -std::f64::consts::TAU / length as f64;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (cos, sin) = (cos as f32, sin as f32);
+                let even = start + k;
+                let odd = even + length / 2;
+                let odd_real = real[odd] * cos - imaginary[odd] * sin;
+                let odd_imaginary = real[odd] * sin + imaginary[odd] * cos;
+                real[odd] = real[even] - odd_real;
+                imaginary[odd] = imaginary[even] - odd_imaginary;
+                real[even] += odd_real;
+                imaginary[even] += odd_imaginary;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+/// Computes the magnitude spectrum of the most recent samples of a signal.
+///
+/// A Hann window is applied before the transform to reduce leakage between bins.
+/// Magnitudes are scaled so a full scale sine wave centered on a bin has magnitude 1.
+#[derive(Clone)]
+pub struct SpectrumAnalyzer {
+    /// The most recent samples, most recent first.
+    samples: RotatingArray<f32>,
+    window: Vec<f32>,
+    /// Scales magnitudes to compensate for the window and transform size.
+    normalization: f32,
+    sample_rate: u32,
+}
+
+impl SpectrumAnalyzer {
+    /// Creates a new SpectrumAnalyzer.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The number of samples transformed. Must be a power of two.
+    ///   Larger sizes give finer frequency resolution but react slower.
+    /// * `sample_rate` - The used sample rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not a power of two.
+    pub fn new(size: usize, sample_rate: u32) -> SpectrumAnalyzer {
+        assert!(size.is_power_of_two(), "FFT size must be a power of two.");
+        let window: Vec<f32> = (0..size)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / size as f32).cos())
+            .collect();
+        let normalization = 2. / window.iter().sum::<f32>();
+        SpectrumAnalyzer {
+            samples: RotatingArray::new(size, 0.),
+            window,
+            normalization,
+            sample_rate,
+        }
+    }
+
+    /// The number of samples transformed.
+    pub fn size(&self) -> usize {
+        self.window.len()
+    }
+
+    /// The number of bins in the spectrum, from 0 Hz up to and including the Nyquist frequency.
+    pub fn num_bins(&self) -> usize {
+        self.size() / 2 + 1
+    }
+
+    /// The center frequency of the bin in Hz.
+    pub fn bin_frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.sample_rate as f32 / self.size() as f32
+    }
+
+    /// Feeds the analyzer the next sample.
+    pub fn push(&mut self, sample: f32) {
+        self.samples.push(sample);
+    }
+
+    /// Feeds the analyzer all the given samples in order.
+    pub fn process(&mut self, samples: &[f32]) {
+        samples.iter().for_each(|&sample| self.push(sample));
+    }
+
+    /// The magnitude of each bin.
+    pub fn magnitudes(&self) -> Vec<f32> {
+        let size = self.size();
+        // The rotating array iterates from the most recent sample, so reverse it to get time order.
+        let mut real: Vec<f32> = self.samples.iter().copied().collect();
+        real.reverse();
+        real.iter_mut()
+            .zip(self.window.iter())
+            .for_each(|(sample, window)| *sample *= window);
+        let mut imaginary = vec![0.; size];
+        fft(&mut real, &mut imaginary);
+        real.iter()
+            .zip(imaginary.iter())
+            .take(self.num_bins())
+            .map(|(real, imaginary)| real.hypot(*imaginary) * self.normalization)
+            .collect()
+    }
+
+    /// The magnitude of each bin in dB.
+    pub fn magnitudes_db(&self) -> Vec<f32> {
+        self.magnitudes().into_iter().map(gain_to_db).collect()
+    }
+
+    /// The frequency in Hz of the bin with the largest magnitude, ignoring the 0 Hz bin.
+    pub fn peak_frequency(&self) -> f32 {
+        let (bin, _) = self.magnitudes().into_iter().enumerate().skip(1).fold(
+            (0, 0.),
+            |(max_bin, max), (bin, magnitude)| {
+                if magnitude > max {
+                    (bin, magnitude)
+                } else {
+                    (max_bin, max)
+                }
+            },
+        );
+        self.bin_frequency(bin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_impulse_is_flat() {
+        let mut real = vec![0.; 8];
+        real[0] = 1.;
+        let mut imaginary = vec![0.; 8];
+        fft(&mut real, &mut imaginary);
+        assert!(real.iter().all(|&value| (value - 1.).abs() < 1e-6));
+        assert!(imaginary.iter().all(|&value| value.abs() < 1e-6));
+    }
+
+    #[test]
+    fn sine_peak() {
+        let sample_rate = 48000;
+        let mut analyzer = SpectrumAnalyzer::new(1024, sample_rate);
+        // Exactly on bin 32.
+        let frequency = analyzer.bin_frequency(32);
+        for i in 0..2048 {
+            analyzer
+                .push((i as f32 * frequency / sample_rate as f32 * std::f32::consts::TAU).sin());
+        }
+        assert_eq!(analyzer.peak_frequency(), frequency);
+        let magnitudes = analyzer.magnitudes();
+        assert!((magnitudes[32] - 1.).abs() < 1e-2);
+        assert!(magnitudes[100] < 1e-3);
+    }
+}
@@ -0,0 +1,277 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// One sample in a `TapRing`.
+struct Slot {
+    /// The bits of the sample, so it can be stored atomically.
+    sample: AtomicU32,
+    /// The write count at which the slot can be written next, plus one once it has been written.
+    /// This keeps senders from writing to a slot another sender claimed, or that hasn't been read yet.
+    sequence: AtomicUsize,
+}
+
+/// A fixed size ring of samples shared between any number of writers and one reader.
+/// Samples are stored as their bit patterns so every slot can be atomic, which keeps the ring
+/// free of locks and of unsafe code.
+struct TapRing {
+    slots: Box<[Slot]>,
+    /// The total number of samples claimed by writers.
+    write_count: AtomicUsize,
+    /// The total number of samples read. Only changed by the reader.
+    read_count: AtomicUsize,
+    /// The number of samples dropped because the ring was full.
+    dropped_count: AtomicU64,
+    /// The number of senders that haven't been dropped.
+    senders: AtomicUsize,
+    /// Whether the receiver has been dropped.
+    receiver_dropped: AtomicBool,
+}
+
+/// Creates a connected tap sender and receiver with room for `capacity` samples.
+///
+/// # Panics
+///
+/// Panics if `capacity` is 0.
+pub fn tap_channel(capacity: usize) -> (TapSender, TapReceiver) {
+    assert!(
+        capacity > 0,
+        "A tap must have room for at least one sample."
+    );
+    let ring = Arc::new(TapRing {
+        slots: (0..capacity)
+            .map(|i| Slot {
+                sample: AtomicU32::new(0),
+                sequence: AtomicUsize::new(i),
+            })
+            .collect(),
+        write_count: AtomicUsize::new(0),
+        read_count: AtomicUsize::new(0),
+        dropped_count: AtomicU64::new(0),
+        senders: AtomicUsize::new(1),
+        receiver_dropped: AtomicBool::new(false),
+    });
+    (
+        TapSender {
+            ring: Arc::clone(&ring),
+        },
+        TapReceiver { ring },
+    )
+}
+
+/// The writing end of a tap. Never blocks; samples are dropped if the reader falls behind.
+///
+/// Clones send to the same receiver, and can send at the same time from different threads.
+/// Their samples are then interleaved in the order they claimed their slots in the ring.
+pub struct TapSender {
+    ring: Arc<TapRing>,
+}
+
+impl Clone for TapSender {
+    fn clone(&self) -> Self {
+        self.ring.senders.fetch_add(1, Ordering::Relaxed);
+        TapSender {
+            ring: Arc::clone(&self.ring),
+        }
+    }
+}
+
+impl Drop for TapSender {
+    fn drop(&mut self) {
+        // Releases the samples this sender wrote to the receiver seeing it closed.
+        self.ring.senders.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl TapSender {
+    /// Sends a sample to the receiver. Returns false if the ring was full and the sample was dropped.
+    pub fn send(&self, sample: f32) -> bool {
+        let ring = &self.ring;
+        let mut write_count = ring.write_count.load(Ordering::Relaxed);
+        loop {
+            let slot = &ring.slots[write_count % ring.slots.len()];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            if sequence == write_count {
+                // The slot is free, so try to claim it before another sender does.
+                match ring.write_count.compare_exchange_weak(
+                    write_count,
+                    write_count + 1,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        slot.sample.store(sample.to_bits(), Ordering::Relaxed);
+                        slot.sequence.store(write_count + 1, Ordering::Release);
+                        return true;
+                    }
+                    Err(current) => write_count = current,
+                }
+            } else if sequence < write_count {
+                // The slot still holds the sample from the last time around the ring.
+                ring.dropped_count.fetch_add(1, Ordering::Relaxed);
+                return false;
+            } else {
+                // Another sender claimed the slot first.
+                write_count = ring.write_count.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns true if the next sample sent would be dropped, unless the receiver reads first.
+    pub fn is_full(&self) -> bool {
+        let ring = &self.ring;
+        ring.write_count.load(Ordering::Relaxed) - ring.read_count.load(Ordering::Acquire)
+            >= ring.slots.len()
+    }
+
+    /// Returns true if the receiver has been dropped, so sent samples will never be read.
+    pub fn is_disconnected(&self) -> bool {
+        self.ring.receiver_dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// The reading end of a tap.
+pub struct TapReceiver {
+    ring: Arc<TapRing>,
+}
+
+impl TapReceiver {
+    /// Receives the oldest unread sample, if any.
+    pub fn receive(&self) -> Option<f32> {
+        let ring = &self.ring;
+        let read_count = ring.read_count.load(Ordering::Relaxed);
+        let slot = &ring.slots[read_count % ring.slots.len()];
+        // Claimed slots only hold a sample once their sender has written it.
+        if slot.sequence.load(Ordering::Acquire) != read_count + 1 {
+            return None;
+        }
+        let sample = f32::from_bits(slot.sample.load(Ordering::Relaxed));
+        // The slot can be written again on the next time around the ring.
+        slot.sequence
+            .store(read_count + ring.slots.len(), Ordering::Release);
+        ring.read_count.store(read_count + 1, Ordering::Release);
+        Some(sample)
+    }
+
+    /// Receives all unread samples, oldest first.
+    pub fn receive_all(&self) -> Vec<f32> {
+        std::iter::from_fn(|| self.receive()).collect()
+    }
+
+    /// The number of samples waiting to be read, including any a sender is still writing.
+    pub fn available(&self) -> usize {
+        self.ring.write_count.load(Ordering::Acquire) - self.ring.read_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of samples dropped so far because the ring was full.
+    pub fn dropped(&self) -> u64 {
+        self.ring.dropped_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of samples the ring has room for.
+    pub fn capacity(&self) -> usize {
+        self.ring.slots.len()
+    }
+
+    /// Returns true if every sender has been dropped, so no more samples will arrive
+    /// after the ones waiting to be read.
+    pub fn is_closed(&self) -> bool {
+        self.ring.senders.load(Ordering::Acquire) == 0
+    }
+}
+
+impl Drop for TapReceiver {
+    fn drop(&mut self) {
+        self.ring.receiver_dropped.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_when_full() {
+        let (sender, receiver) = tap_channel(2);
+        assert!(sender.send(1.));
+        assert!(sender.send(2.));
+        assert!(!sender.send(3.));
+        assert_eq!(receiver.receive_all(), vec![1., 2.]);
+        assert_eq!(receiver.dropped(), 1);
+        assert!(sender.send(4.));
+        assert_eq!(receiver.receive(), Some(4.));
+        assert_eq!(receiver.receive(), None);
+    }
+
+    #[test]
+    fn across_threads() {
+        let (sender, receiver) = tap_channel(64);
+        let writer = std::thread::spawn(move || {
+            let mut i = 0;
+            while i < 10000 {
+                if sender.send(i as f32) {
+                    i += 1;
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < 10000 {
+            if let Some(sample) = receiver.receive() {
+                assert_eq!(sample, expected as f32);
+                expected += 1;
+            }
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn closed_once_every_sender_is_dropped() {
+        let (sender, receiver) = tap_channel(2);
+        let clone = sender.clone();
+        sender.send(1.);
+        drop(sender);
+        assert!(!receiver.is_closed());
+        drop(clone);
+        assert!(receiver.is_closed());
+        assert_eq!(receiver.receive(), Some(1.));
+
+        let (sender, receiver) = tap_channel(2);
+        assert!(!sender.is_disconnected());
+        drop(receiver);
+        assert!(sender.is_disconnected());
+    }
+
+    #[test]
+    fn clones_send_at_the_same_time() {
+        let (sender, receiver) = tap_channel(64);
+        // Each writer sends its own increasing sequence, offset so they can be told apart.
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    let mut i = 0;
+                    while i < 2500 {
+                        if sender.send((writer * 10000 + i) as f32) {
+                            i += 1;
+                        }
+                    }
+                })
+            })
+            .collect();
+        let mut next = [0; 4];
+        while next.iter().sum::<usize>() < 10000 {
+            if let Some(sample) = receiver.receive() {
+                let (writer, i) = (sample as usize / 10000, sample as usize % 10000);
+                assert_eq!(
+                    i, next[writer],
+                    "Sample of writer {} lost or reordered",
+                    writer
+                );
+                next[writer] += 1;
+            }
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(receiver.receive(), None);
+    }
+}
@@ -1,10 +1,78 @@
-use std::{path::Path, sync::Arc};
+use crate::utils::interpolation;
+use std::{io::Read, path::Path, sync::Arc};
 
 #[derive(Debug)]
 pub enum AudioLoadError {
     HoundError(hound::Error),
-    InvalidSampleFormat,
-    Not32BitsPerSample,
+    /// The combination of sample format and bits per sample is not supported.
+    UnsupportedFormat(hound::SampleFormat, u16),
+}
+
+impl From<hound::Error> for AudioLoadError {
+    fn from(error: hound::Error) -> Self {
+        AudioLoadError::HoundError(error)
+    }
+}
+
+/// Returns an error if samples of the given spec cannot be converted to floats.
+pub(crate) fn check_spec(spec: &hound::WavSpec) -> Result<(), AudioLoadError> {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, 32) | (hound::SampleFormat::Int, 8 | 16 | 24 | 32) => Ok(()),
+        (sample_format, bits_per_sample) => Err(AudioLoadError::UnsupportedFormat(
+            sample_format,
+            bits_per_sample,
+        )),
+    }
+}
+
+/// Reads the next sample from the reader and converts it to a float between -1 and 1.
+/// Returns None if there are no more samples.
+/// The spec of the reader must have been checked with `check_spec`.
+pub(crate) fn read_sample<R: Read>(
+    reader: &mut hound::WavReader<R>,
+) -> Option<Result<f32, AudioLoadError>> {
+    let spec = reader.spec();
+    let sample = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().next(),
+        hound::SampleFormat::Int => {
+            let scale = 1. / (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .next()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+        }
+    };
+    sample.map(|sample| sample.map_err(AudioLoadError::from))
+}
+
+/// Resamples the signal from one sample rate to another using cubic interpolation.
+///
+/// # Arguments
+///
+/// * `samples` - The signal to resample.
+/// * `from_rate` - The sample rate of `samples`.
+/// * `to_rate` - The sample rate of the result.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from_rate as f64 / to_rate as f64;
+    let result_length = (samples.len() as f64 / step).ceil() as usize;
+    let sample = |index: isize| samples[index.clamp(0, samples.len() as isize - 1) as usize];
+    (0..result_length)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position.floor() as isize;
+            let t = (position - index as f64) as f32;
+            interpolation::cubic(
+                sample(index - 1),
+                sample(index),
+                sample(index + 1),
+                sample(index + 2),
+                t,
+            )
+        })
+        .collect()
 }
 
 pub struct Audio {
@@ -14,37 +82,60 @@ pub struct Audio {
 }
 
 impl Audio {
-    pub fn load<P: AsRef<Path>>(path: P, sample_rate: u32) -> Result<Audio, AudioLoadError> {
-        match hound::WavReader::open(path) {
-            Err(error) => Err(AudioLoadError::HoundError(error)),
-            Ok(wav_reader) => {
-                let spec = wav_reader.spec();
-                if spec.sample_format != hound::SampleFormat::Float {
-                    Err(AudioLoadError::InvalidSampleFormat)
-                } else if spec.bits_per_sample != 32 {
-                    Err(AudioLoadError::Not32BitsPerSample)
-                } else {
-                    let num_channels = spec.channels as usize;
-                    let mut audio: Vec<Vec<f32>> =
-                        vec![Vec::with_capacity(wav_reader.duration() as usize); num_channels];
-                    let mut channel = 0;
-                    for sample in wav_reader.into_samples() {
-                        audio[channel].push(sample.expect(
-                            "This should never fail as the bits per sample are checked above.",
-                        ));
-                        channel += 1;
-                        if channel >= num_channels {
-                            channel = 0;
-                        }
-                    }
-                    Ok(Audio {
-                        audio: audio.into_iter().map(|vec| (*vec).into()).collect(),
-                        num_channels,
-                        sample_rate,
-                    })
-                }
+    /// Loads a WAV file.
+    /// Supports 8, 16, 24 and 32 bit integer samples and 32 bit float samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The WAV file to load.
+    /// * `target_sample_rate` - If given, the audio is resampled to this sample rate.
+    ///   Otherwise the sample rate of the file is kept.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        target_sample_rate: Option<u32>,
+    ) -> Result<Audio, AudioLoadError> {
+        let mut wav_reader = hound::WavReader::open(path)?;
+        let spec = wav_reader.spec();
+        check_spec(&spec)?;
+        let num_channels = spec.channels as usize;
+        let mut audio: Vec<Vec<f32>> =
+            vec![Vec::with_capacity(wav_reader.duration() as usize); num_channels];
+        let mut channel = 0;
+        while let Some(sample) = read_sample(&mut wav_reader) {
+            audio[channel].push(sample?);
+            channel += 1;
+            if channel >= num_channels {
+                channel = 0;
             }
         }
+        let audio = Audio::from_channels(audio, spec.sample_rate);
+        Ok(match target_sample_rate {
+            Some(sample_rate) => audio.resampled(sample_rate),
+            None => audio,
+        })
+    }
+
+    /// Creates audio from separate channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no channels or the channels have different lengths.
+    pub fn from_channels(channels: Vec<Vec<f32>>, sample_rate: u32) -> Audio {
+        assert!(
+            !channels.is_empty(),
+            "Audio must have at least one channel."
+        );
+        assert!(
+            channels
+                .iter()
+                .all(|channel| channel.len() == channels[0].len()),
+            "All channels must have the same length."
+        );
+        Audio {
+            num_channels: channels.len(),
+            audio: channels.into_iter().map(|vec| (*vec).into()).collect(),
+            sample_rate,
+        }
     }
 
     pub fn num_channels(&self) -> usize {
@@ -55,7 +146,120 @@ impl Audio {
         self.sample_rate
     }
 
+    /// The number of samples in each channel.
+    pub fn len(&self) -> usize {
+        self.audio[0].len()
+    }
+
+    /// Returns true if the audio contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The length of the audio in seconds.
+    pub fn duration(&self) -> f32 {
+        self.len() as f32 / self.sample_rate as f32
+    }
+
     pub fn channel(&self, channel_num: usize) -> Arc<[f32]> {
         self.audio[channel_num].clone()
     }
+
+    /// Returns a copy of the audio resampled to the given sample rate.
+    pub fn resampled(&self, sample_rate: u32) -> Audio {
+        if sample_rate == self.sample_rate {
+            return Audio {
+                audio: self.audio.clone(),
+                num_channels: self.num_channels,
+                sample_rate,
+            };
+        }
+        Audio::from_channels(
+            self.audio
+                .iter()
+                .map(|channel| resample(channel, self.sample_rate, sample_rate))
+                .collect(),
+            sample_rate,
+        )
+    }
+
+    /// Returns a single channel copy of the audio where all channels are averaged.
+    pub fn downmixed(&self) -> Audio {
+        let scale = 1. / self.num_channels as f32;
+        let mono = (0..self.len())
+            .map(|i| self.audio.iter().map(|channel| channel[i]).sum::<f32>() * scale)
+            .collect();
+        Audio::from_channels(vec![mono], self.sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(name: &str, spec: hound::WavSpec, samples: &[i32]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.wav", name, std::process::id()));
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn load_16_bit_stereo() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = write_wav("load_16_bit_stereo", spec, &[16384, -32768, 0, 8192]);
+        let audio = Audio::load(&path, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(audio.sample_rate(), 22050);
+        assert_eq!(audio.num_channels(), 2);
+        assert_eq!(&*audio.channel(0), &[0.5, 0.]);
+        assert_eq!(&*audio.channel(1), &[-1., 0.25]);
+        assert_eq!(&*audio.downmixed().channel(0), &[-0.25, 0.125]);
+    }
+
+    #[test]
+    fn load_24_bit_resampled() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 24000,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = write_wav("load_24_bit_resampled", spec, &[1 << 22; 100]);
+        let audio = Audio::load(&path, Some(48000)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(audio.sample_rate(), 48000);
+        assert_eq!(audio.len(), 200);
+        assert!(audio
+            .channel(0)
+            .iter()
+            .all(|&sample| (sample - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 12,
+            sample_format: hound::SampleFormat::Int,
+        };
+        assert!(matches!(
+            check_spec(&spec),
+            Err(AudioLoadError::UnsupportedFormat(
+                hound::SampleFormat::Int,
+                12
+            ))
+        ));
+    }
 }
@@ -0,0 +1,91 @@
+use crate::audio::{check_spec, read_sample, AudioLoadError};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// Reads a WAV file a frame at a time instead of decoding it all up front.
+/// Useful for long files such as music.
+pub struct AudioStream {
+    path: PathBuf,
+    reader: hound::WavReader<BufReader<File>>,
+    num_channels: usize,
+    sample_rate: u32,
+}
+
+impl AudioStream {
+    /// Opens a WAV file for streaming.
+    /// Supports the same formats as `Audio::load`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<AudioStream, AudioLoadError> {
+        let path = path.as_ref().to_path_buf();
+        let reader = hound::WavReader::open(&path)?;
+        let spec = reader.spec();
+        check_spec(&spec)?;
+        Ok(AudioStream {
+            path,
+            reader,
+            num_channels: spec.channels as usize,
+            sample_rate: spec.sample_rate,
+        })
+    }
+
+    /// Opens the same file again.
+    /// The new stream starts from the beginning of the file regardless of how far this stream has read.
+    pub fn try_clone(&self) -> Result<AudioStream, AudioLoadError> {
+        AudioStream::open(&self.path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The total number of frames in the file.
+    pub fn num_frames(&self) -> u32 {
+        self.reader.duration()
+    }
+
+    /// Reads the next frame into `frame` with one sample per channel.
+    /// Returns false if the end of the file has been reached.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is shorter than the number of channels.
+    pub fn next_frame(&mut self, frame: &mut [f32]) -> Result<bool, AudioLoadError> {
+        for sample in frame[0..self.num_channels].iter_mut() {
+            match read_sample(&mut self.reader) {
+                Some(result) => *sample = result?,
+                None => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads the next frame and averages its channels.
+    /// Returns None if the end of the file has been reached.
+    pub fn next_mono(&mut self) -> Result<Option<f32>, AudioLoadError> {
+        let mut sum = 0.;
+        for _ in 0..self.num_channels {
+            match read_sample(&mut self.reader) {
+                Some(result) => sum += result?,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(sum / self.num_channels as f32))
+    }
+
+    /// Moves back to the start of the file.
+    pub fn rewind(&mut self) -> Result<(), AudioLoadError> {
+        self.reader
+            .seek(0)
+            .map_err(|error| AudioLoadError::HoundError(hound::Error::IoError(error)))
+    }
+}
@@ -0,0 +1,351 @@
+//! Dynamics processors that work on frames of any number of channels.
+//! The detection is linked across channels so the stereo image is kept.
+//! The dynamics modules wrap these for use in module graphs, while the processors
+//! themselves can be used directly on a mix.
+use crate::utils::RotatingArray;
+
+/// Converts decibels to a linear gain factor.
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
+
+/// Converts a linear gain factor to decibels.
+/// Gains at or below 0 give a very large negative value instead of negative infinity.
+pub fn gain_to_db(gain: f32) -> f32 {
+    20. * gain.max(1e-9).log10()
+}
+
+/// The coefficient for a one pole smoother that reaches about 63% of a step in the given time.
+fn time_coefficient(time: f32, sample_rate: u32) -> f32 {
+    if time <= 0. {
+        0.
+    } else {
+        (-1. / (time * sample_rate as f32)).exp()
+    }
+}
+
+/// The largest absolute sample in a frame.
+fn frame_peak(frame: &[f32]) -> f32 {
+    frame
+        .iter()
+        .fold(0., |peak: f32, sample| peak.max(sample.abs()))
+}
+
+/// Follows the level of a signal with separate attack and release times.
+#[derive(Clone)]
+pub struct EnvelopeFollower {
+    attack_coefficient: f32,
+    release_coefficient: f32,
+    envelope: f32,
+}
+
+impl EnvelopeFollower {
+    /// Creates a new EnvelopeFollower.
+    ///
+    /// # Arguments
+    ///
+    /// * `attack` - Seconds to follow a rising level.
+    /// * `release` - Seconds to follow a falling level.
+    /// * `sample_rate` - The used sample rate.
+    pub fn new(attack: f32, release: f32, sample_rate: u32) -> EnvelopeFollower {
+        EnvelopeFollower {
+            attack_coefficient: time_coefficient(attack, sample_rate),
+            release_coefficient: time_coefficient(release, sample_rate),
+            envelope: 0.,
+        }
+    }
+
+    /// Feeds the follower the next level and returns the new envelope.
+    pub fn next(&mut self, level: f32) -> f32 {
+        let coefficient = if level > self.envelope {
+            self.attack_coefficient
+        } else {
+            self.release_coefficient
+        };
+        self.envelope = coefficient * self.envelope + (1. - coefficient) * level;
+        self.envelope
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CompressorSettings {
+    /// The level in dB above which the signal is compressed.
+    pub threshold: f32,
+    /// How many dB the input must rise above the threshold for the output to rise 1 dB.
+    pub ratio: f32,
+    /// Seconds to react to a rising level.
+    pub attack: f32,
+    /// Seconds to recover after the level falls.
+    pub release: f32,
+    /// Gain in dB applied after compression.
+    pub makeup: f32,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        CompressorSettings {
+            threshold: -18.,
+            ratio: 4.,
+            attack: 0.005,
+            release: 0.1,
+            makeup: 0.,
+        }
+    }
+}
+
+/// Reduces the gain of signals above a threshold.
+#[derive(Clone)]
+pub struct CompressorProcessor {
+    settings: CompressorSettings,
+    follower: EnvelopeFollower,
+}
+
+impl CompressorProcessor {
+    pub fn new(settings: CompressorSettings, sample_rate: u32) -> CompressorProcessor {
+        CompressorProcessor {
+            settings,
+            follower: EnvelopeFollower::new(settings.attack, settings.release, sample_rate),
+        }
+    }
+
+    /// The gain to apply given the level of the detection signal.
+    fn gain(&mut self, level: f32) -> f32 {
+        let envelope = gain_to_db(self.follower.next(level));
+        let over = (envelope - self.settings.threshold).max(0.);
+        let reduction = over * (1. - 1. / self.settings.ratio);
+        db_to_gain(self.settings.makeup - reduction)
+    }
+
+    /// Compresses the frame based on its own level.
+    pub fn process(&mut self, frame: &mut [f32]) {
+        let gain = self.gain(frame_peak(frame));
+        frame.iter_mut().for_each(|sample| *sample *= gain);
+    }
+
+    /// Compresses the frame based on the level of a separate key signal.
+    /// This can be used for ducking, e.g. lowering music while effects play.
+    pub fn process_sidechained(&mut self, frame: &mut [f32], key: f32) {
+        let gain = self.gain(key.abs());
+        frame.iter_mut().for_each(|sample| *sample *= gain);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LimiterSettings {
+    /// The level in dB the output will never exceed.
+    pub ceiling: f32,
+    /// Seconds the signal is delayed so the gain can be lowered before peaks arrive.
+    pub lookahead: f32,
+    /// Seconds to recover after a peak.
+    pub release: f32,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        LimiterSettings {
+            ceiling: -1.,
+            lookahead: 0.002,
+            release: 0.1,
+        }
+    }
+}
+
+/// A brickwall limiter that guarantees the output never exceeds the ceiling.
+/// The output is delayed by the lookahead time.
+#[derive(Clone)]
+pub struct LimiterProcessor {
+    ceiling: f32,
+    release_coefficient: f32,
+    /// Delay lines for each channel.
+    delays: Vec<RotatingArray<f32>>,
+    /// The peak of each frame within the lookahead window, including the frame being output.
+    peaks: RotatingArray<f32>,
+    gain: f32,
+}
+
+impl LimiterProcessor {
+    pub fn new(
+        settings: LimiterSettings,
+        num_channels: usize,
+        sample_rate: u32,
+    ) -> LimiterProcessor {
+        let lookahead = ((settings.lookahead * sample_rate as f32) as usize).max(1);
+        LimiterProcessor {
+            ceiling: db_to_gain(settings.ceiling),
+            release_coefficient: time_coefficient(settings.release, sample_rate),
+            delays: vec![RotatingArray::new(lookahead, 0.); num_channels],
+            peaks: RotatingArray::new(lookahead + 1, 0.),
+            gain: 1.,
+        }
+    }
+
+    /// Limits the frame in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame does not have the number of channels the limiter was created with.
+    pub fn process(&mut self, frame: &mut [f32]) {
+        assert_eq!(frame.len(), self.delays.len());
+        self.peaks.push(frame_peak(frame));
+        let window_peak = self.peaks.iter().fold(0., |max: f32, &peak| max.max(peak));
+        let target = if window_peak > self.ceiling {
+            self.ceiling / window_peak
+        } else {
+            1.
+        };
+        self.gain = if target < self.gain {
+            target
+        } else {
+            self.release_coefficient * self.gain + (1. - self.release_coefficient) * target
+        };
+        for (sample, delay) in frame.iter_mut().zip(self.delays.iter_mut()) {
+            let delayed = delay.push_pop(*sample);
+            *sample = (delayed * self.gain).clamp(-self.ceiling, self.ceiling);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GateSettings {
+    /// The level in dB below which the gate closes.
+    pub threshold: f32,
+    /// Seconds to open the gate fully.
+    pub attack: f32,
+    /// Seconds to keep the gate open after the level falls below the threshold.
+    pub hold: f32,
+    /// Seconds for the gate to close.
+    pub release: f32,
+}
+
+impl Default for GateSettings {
+    fn default() -> Self {
+        GateSettings {
+            threshold: -50.,
+            attack: 0.001,
+            hold: 0.05,
+            release: 0.1,
+        }
+    }
+}
+
+/// Silences the signal while its level is below a threshold.
+#[derive(Clone)]
+pub struct GateProcessor {
+    threshold: f32,
+    follower: EnvelopeFollower,
+    attack_step: f32,
+    release_coefficient: f32,
+    hold_samples: u64,
+    hold_remaining: u64,
+    gain: f32,
+}
+
+impl GateProcessor {
+    pub fn new(settings: GateSettings, sample_rate: u32) -> GateProcessor {
+        let attack_samples = settings.attack * sample_rate as f32;
+        GateProcessor {
+            threshold: settings.threshold,
+            follower: EnvelopeFollower::new(0., 0.01, sample_rate),
+            attack_step: if attack_samples < 1. {
+                1.
+            } else {
+                1. / attack_samples
+            },
+            release_coefficient: time_coefficient(settings.release, sample_rate),
+            hold_samples: (settings.hold * sample_rate as f32) as u64,
+            hold_remaining: 0,
+            gain: 0.,
+        }
+    }
+
+    /// Gates the frame in place.
+    pub fn process(&mut self, frame: &mut [f32]) {
+        let level = gain_to_db(self.follower.next(frame_peak(frame)));
+        let open = if level > self.threshold {
+            self.hold_remaining = self.hold_samples;
+            true
+        } else if self.hold_remaining > 0 {
+            self.hold_remaining -= 1;
+            true
+        } else {
+            false
+        };
+        self.gain = if open {
+            (self.gain + self.attack_step).min(1.)
+        } else {
+            self.gain * self.release_coefficient
+        };
+        frame.iter_mut().for_each(|sample| *sample *= self.gain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressor_static_gain() {
+        let settings = CompressorSettings {
+            threshold: -20.,
+            ratio: 4.,
+            attack: 0.,
+            release: 0.,
+            makeup: 0.,
+        };
+        let mut compressor = CompressorProcessor::new(settings, 48000);
+        // 0 dB input is 20 dB over the threshold and should come out at -15 dB.
+        let mut frame = [1.];
+        compressor.process(&mut frame);
+        assert!((gain_to_db(frame[0]) + 15.).abs() < 1e-3);
+        // Below the threshold nothing happens.
+        let mut frame = [0.05];
+        compressor.process(&mut frame);
+        assert!((frame[0] - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn limiter_never_exceeds_ceiling() {
+        let mut limiter = LimiterProcessor::new(LimiterSettings::default(), 2, 48000);
+        let ceiling = db_to_gain(LimiterSettings::default().ceiling);
+        for i in 0..4800 {
+            let sample = (i as f32 * 0.05).sin() * (1. + (i % 100) as f32 * 0.05);
+            let mut frame = [sample, -sample * 0.5];
+            limiter.process(&mut frame);
+            assert!(frame.iter().all(|sample| sample.abs() <= ceiling));
+        }
+    }
+
+    #[test]
+    fn limiter_passes_quiet_signal_delayed() {
+        let settings = LimiterSettings {
+            lookahead: 2. / 48000.,
+            ..Default::default()
+        };
+        let mut limiter = LimiterProcessor::new(settings, 1, 48000);
+        let output: Vec<f32> = [0.1, 0.2, 0.3, 0.]
+            .iter()
+            .map(|&sample| {
+                let mut frame = [sample];
+                limiter.process(&mut frame);
+                frame[0]
+            })
+            .collect();
+        assert_eq!(output, vec![0., 0., 0.1, 0.2]);
+    }
+
+    #[test]
+    fn gate_closes_on_silence() {
+        let mut gate = GateProcessor::new(GateSettings::default(), 48000);
+        let mut frame = [0.5];
+        for _ in 0..100 {
+            frame = [0.5];
+            gate.process(&mut frame);
+        }
+        assert!((frame[0] - 0.5).abs() < 1e-6);
+        for _ in 0..48000 {
+            frame = [0.001];
+            gate.process(&mut frame);
+        }
+        assert!(frame[0].abs() < 1e-6);
+    }
+}
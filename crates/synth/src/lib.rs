@@ -1,4 +1,8 @@
+pub mod analysis;
 mod audio;
+mod audio_stream;
+pub use audio_stream::AudioStream;
+pub mod dynamics;
 pub mod modules;
 mod sample_provider;
 pub use sample_provider::{start_stream, SampleProvider};
@@ -0,0 +1,125 @@
+use crate::dynamics::{
+    CompressorProcessor, CompressorSettings, GateProcessor, GateSettings, LimiterProcessor,
+    LimiterSettings,
+};
+use crate::modules::{Module, ModuleTemplate};
+use synth_derive::module;
+
+/// Reduces the gain of the source when it, or an optional key signal, rises above a threshold.
+#[module]
+pub struct Compressor<S: Module, K: Module> {
+    source: S,
+    /// If present, the compression is controlled by this signal instead of the source.
+    key: Option<K>,
+    processor: CompressorProcessor,
+}
+
+impl<S: Module> Compressor<S, f32> {
+    /// Creates a compressor controlled by the level of the source itself.
+    pub fn new(
+        source: ModuleTemplate<S>,
+        settings: CompressorSettings,
+        sample_rate: u32,
+    ) -> ModuleTemplate<Self> {
+        ModuleTemplate {
+            module: Compressor {
+                source: source.module,
+                key: None,
+                processor: CompressorProcessor::new(settings, sample_rate),
+            },
+        }
+    }
+}
+
+impl<S: Module, K: Module> Compressor<S, K> {
+    /// Creates a compressor controlled by the level of the key signal.
+    /// With a low threshold and high ratio this ducks the source whenever the key is audible.
+    pub fn sidechained(
+        source: ModuleTemplate<S>,
+        key: ModuleTemplate<K>,
+        settings: CompressorSettings,
+        sample_rate: u32,
+    ) -> ModuleTemplate<Self> {
+        ModuleTemplate {
+            module: Compressor {
+                source: source.module,
+                key: Some(key.module),
+                processor: CompressorProcessor::new(settings, sample_rate),
+            },
+        }
+    }
+}
+
+impl<S: Module, K: Module> Module for Compressor<S, K> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let mut frame = [self.source.next(sample_num)];
+        match &mut self.key {
+            Some(key) => {
+                let key = key.next(sample_num);
+                self.processor.process_sidechained(&mut frame, key)
+            }
+            None => self.processor.process(&mut frame),
+        }
+        frame[0]
+    }
+}
+
+/// Keeps the source below a ceiling. The output is delayed by the lookahead time.
+#[module]
+pub struct Limiter<S: Module> {
+    source: S,
+    processor: LimiterProcessor,
+}
+
+impl<S: Module> Limiter<S> {
+    pub fn new(
+        source: ModuleTemplate<S>,
+        settings: LimiterSettings,
+        sample_rate: u32,
+    ) -> ModuleTemplate<Self> {
+        ModuleTemplate {
+            module: Limiter {
+                source: source.module,
+                processor: LimiterProcessor::new(settings, 1, sample_rate),
+            },
+        }
+    }
+}
+
+impl<S: Module> Module for Limiter<S> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let mut frame = [self.source.next(sample_num)];
+        self.processor.process(&mut frame);
+        frame[0]
+    }
+}
+
+/// Silences the source while its level is below a threshold.
+#[module]
+pub struct NoiseGate<S: Module> {
+    source: S,
+    processor: GateProcessor,
+}
+
+impl<S: Module> NoiseGate<S> {
+    pub fn new(
+        source: ModuleTemplate<S>,
+        settings: GateSettings,
+        sample_rate: u32,
+    ) -> ModuleTemplate<Self> {
+        ModuleTemplate {
+            module: NoiseGate {
+                source: source.module,
+                processor: GateProcessor::new(settings, sample_rate),
+            },
+        }
+    }
+}
+
+impl<S: Module> Module for NoiseGate<S> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let mut frame = [self.source.next(sample_num)];
+        self.processor.process(&mut frame);
+        frame[0]
+    }
+}
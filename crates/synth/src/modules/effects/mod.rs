@@ -3,3 +3,9 @@ pub use one_pole_filter::OnePoleFilter;
 mod convolution_filter;
 pub use convolution_filter::lowpass_filter;
 pub use convolution_filter::ConvolutionFilter;
+mod modulated_delay;
+pub use modulated_delay::{chorus, flanger, ModulatedDelay};
+mod phaser;
+pub use phaser::{phaser, Phaser};
+mod dynamics;
+pub use dynamics::{Compressor, Limiter, NoiseGate};
@@ -0,0 +1,232 @@
+use crate::modules::{Module, ModuleTemplate, SineOscillator};
+use crate::utils::{interpolation, RotatingArray};
+use synth_derive::module;
+
+/// A delay line whose delay time can change every sample.
+/// Fractional delays are linearly interpolated, so the delay time can be swept smoothly.
+#[module]
+pub struct ModulatedDelay<S: Module, T: Module> {
+    source: S,
+    /// A module that supplies the delay time in seconds each sample.
+    delay_time: T,
+    /// Previous inputs plus feedback, most recent first.
+    buffer: RotatingArray<f32>,
+    /// How much of the delayed signal is fed back into the delay line.
+    feedback: f32,
+    /// How much of the delayed signal is in the output. 0 is only the dry signal and 1 only the delayed.
+    mix: f32,
+    sample_rate: f32,
+}
+
+impl<S: Module, T: Module> ModulatedDelay<S, T> {
+    /// Creates a new ModulatedDelay module.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The signal to delay.
+    /// * `delay_time` - The delay in seconds. Clamped to between one sample and `max_delay_time`.
+    /// * `max_delay_time` - The longest delay in seconds the delay line can hold.
+    /// * `feedback` - How much of the delayed signal is fed back into the delay line.
+    ///   Should be less than 1 in magnitude to avoid the signal growing without bound.
+    /// * `mix` - How much of the delayed signal is in the output.
+    ///   0 is only the dry signal and 1 only the delayed.
+    /// * `sample_rate` - The used sample rate.
+    pub fn new(
+        source: ModuleTemplate<S>,
+        delay_time: ModuleTemplate<T>,
+        max_delay_time: f32,
+        feedback: f32,
+        mix: f32,
+        sample_rate: u32,
+    ) -> ModuleTemplate<ModulatedDelay<S, T>> {
+        let buffer_size = (max_delay_time * sample_rate as f32).ceil() as usize + 2;
+        ModuleTemplate {
+            module: ModulatedDelay {
+                source: source.module,
+                delay_time: delay_time.module,
+                buffer: RotatingArray::new(buffer_size, 0.),
+                feedback,
+                mix,
+                sample_rate: sample_rate as f32,
+            },
+        }
+    }
+}
+
+impl<S: Module, T: Module> Module for ModulatedDelay<S, T> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let input = self.source.next(sample_num);
+        let delay = (self.delay_time.next(sample_num) * self.sample_rate)
+            .clamp(1., (self.buffer.len() - 1) as f32);
+        // The buffer holds the previous inputs, so the sample delayed `k` samples is at index `k - 1`.
+        let index = delay.floor() as usize;
+        let delayed = interpolation::linear(
+            self.buffer.get(index - 1).unwrap(),
+            self.buffer.get(index).unwrap(),
+            delay - index as f32,
+        );
+        self.buffer.push(input + delayed * self.feedback);
+        input * (1. - self.mix) + delayed * self.mix
+    }
+}
+
+/// A delay time that is swept around a center by a sine wave.
+fn sine_sweep(center: f32, depth: f32, rate: f32, sample_rate: u32) -> ModuleTemplate<impl Module> {
+    SineOscillator::new(rate.into(), sample_rate) * depth + center
+}
+
+/// Thickens the signal by mixing it with a copy whose delay is slowly swept around 20 ms.
+///
+/// # Arguments
+///
+/// * `source` - The signal to apply chorus to.
+/// * `rate` - The frequency of the sweep in Hz.
+/// * `depth` - How far in seconds the delay is swept to either side. At most 20 ms.
+/// * `mix` - How much of the delayed signal is in the output.
+/// * `sample_rate` - The used sample rate.
+pub fn chorus<S: Module>(
+    source: ModuleTemplate<S>,
+    rate: f32,
+    depth: f32,
+    mix: f32,
+    sample_rate: u32,
+) -> ModuleTemplate<ModulatedDelay<S, impl Module>> {
+    let center = 0.02;
+    let depth = depth.min(center);
+    ModulatedDelay::new(
+        source,
+        sine_sweep(center, depth, rate, sample_rate),
+        center + depth,
+        0.,
+        mix,
+        sample_rate,
+    )
+}
+
+/// Creates a sweeping comb filter by mixing the signal with a copy whose delay is swept
+/// between 1 and 5 ms and fed back into itself.
+///
+/// # Arguments
+///
+/// * `source` - The signal to flange.
+/// * `rate` - The frequency of the sweep in Hz.
+/// * `feedback` - How much of the delayed signal is fed back. Should be less than 1 in magnitude.
+/// * `mix` - How much of the delayed signal is in the output.
+/// * `sample_rate` - The used sample rate.
+pub fn flanger<S: Module>(
+    source: ModuleTemplate<S>,
+    rate: f32,
+    feedback: f32,
+    mix: f32,
+    sample_rate: u32,
+) -> ModuleTemplate<ModulatedDelay<S, impl Module>> {
+    ModulatedDelay::new(
+        source,
+        sine_sweep(0.003, 0.002, rate, sample_rate),
+        0.005,
+        feedback,
+        mix,
+        sample_rate,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::{NoiseOscillator, Sampler};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    fn impulse() -> ModuleTemplate<Sampler<f32>> {
+        let mut audio = vec![0.; 16];
+        audio[0] = 1.;
+        Sampler::new(audio.into(), false)
+    }
+
+    #[test]
+    fn whole_sample_delay() {
+        let mut delay = ModulatedDelay::new(impulse(), 3.0.into(), 10., 0., 1., 1).module();
+        let output: Vec<f32> = (0..6).map(|i| delay.next(i)).collect();
+        assert_eq!(output, vec![0., 0., 0., 1., 0., 0.]);
+    }
+
+    #[test]
+    fn fractional_delay() {
+        let mut delay = ModulatedDelay::new(impulse(), 1.5.into(), 10., 0., 1., 1).module();
+        let output: Vec<f32> = (0..4).map(|i| delay.next(i)).collect();
+        assert_eq!(output, vec![0., 0.5, 0.5, 0.]);
+    }
+
+    #[test]
+    fn feedback_repeats() {
+        let mut delay = ModulatedDelay::new(impulse(), 2.0.into(), 10., 0.5, 0.5, 1).module();
+        let output: Vec<f32> = (0..7).map(|i| delay.next(i)).collect();
+        assert_eq!(output, vec![0.5, 0., 0.5, 0., 0.25, 0., 0.125]);
+    }
+
+    /// The RMS of the module's output over a second, after letting it settle for a tenth of a second.
+    fn rms(module: &mut impl Module) -> f32 {
+        let settle = SAMPLE_RATE as u64 / 10;
+        let samples: Vec<f32> = (0..settle + SAMPLE_RATE as u64)
+            .map(|i| module.next(i))
+            .skip(settle as usize)
+            .collect();
+        (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn sine(frequency: f32) -> ModuleTemplate<impl Module> {
+        SineOscillator::new(frequency.into(), SAMPLE_RATE)
+    }
+
+    #[test]
+    fn chorus_delays_by_20_ms() {
+        let mut chorus = chorus(impulse(), 1., 0., 1., 1000).module();
+        let output: Vec<f32> = (0..25).map(|i| chorus.next(i)).collect();
+        let mut expected = vec![0.; 25];
+        expected[20] = 1.;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn dry_chorus_passes_signal_through() {
+        let mut chorus = chorus(sine(300.), 1., 0.01, 0., SAMPLE_RATE).module();
+        let mut sine = sine(300.).module();
+        for i in 0..1000 {
+            assert_eq!(chorus.next(i), sine.next(i));
+        }
+    }
+
+    #[test]
+    fn flanger_notches_where_the_delay_inverts_phase() {
+        // Without a sweep the delay stays at 3 ms, which is half a period at 1 / 6 ms,
+        // and a whole period at twice that.
+        let notched = rms(&mut flanger(sine(1000. / 6.), 0., 0., 0.5, SAMPLE_RATE).module());
+        assert!(notched < 0.01, "RMS at the notch: {}", notched);
+        let in_phase = rms(&mut flanger(sine(1000. / 3.), 0., 0., 0.5, SAMPLE_RATE).module());
+        assert!(
+            (in_phase - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01,
+            "RMS: {}",
+            in_phase
+        );
+    }
+
+    #[test]
+    fn flanger_stays_bounded_with_feedback() {
+        let noise = NoiseOscillator::new(SmallRng::seed_from_u64(3));
+        let mut flanger = flanger(noise, 0.5, 0.9, 0.5, SAMPLE_RATE).module();
+        let mut noise = NoiseOscillator::new(SmallRng::seed_from_u64(3)).module();
+        let peak_input = (0..SAMPLE_RATE as u64 * 5)
+            .map(|i| noise.next(i).abs())
+            .fold(0., f32::max);
+        for i in 0..SAMPLE_RATE as u64 * 5 {
+            let sample = flanger.next(i);
+            assert!(
+                sample.is_finite() && sample.abs() < 10. * peak_input,
+                "Sample {} is {}",
+                i,
+                sample
+            );
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::modules::{Module, ModuleTemplate, SineOscillator};
+use synth_derive::module;
+
+/// Sweeps notches through the spectrum by mixing the signal with a copy passed through
+/// a chain of first order all-pass filters whose break frequency is modulated.
+#[module]
+pub struct Phaser<S: Module, M: Module> {
+    source: S,
+    /// A module that supplies where in the sweep range the break frequency is, from 0 to 1.
+    modulation: M,
+    min_frequency: f32,
+    max_frequency: f32,
+    /// How much of the all-pass output is fed back into the chain.
+    feedback: f32,
+    /// How much of the all-pass output is in the result.
+    mix: f32,
+    /// The previous input and output of each all-pass stage.
+    stages: Vec<(f32, f32)>,
+    prev_wet: f32,
+    inverse_sample_rate: f32,
+}
+
+impl<S: Module, M: Module> Phaser<S, M> {
+    /// Creates a new Phaser module.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The signal to phase.
+    /// * `modulation` - Where in the sweep range the break frequency is, from 0 to 1.
+    /// * `min_frequency` - The break frequency when `modulation` is 0.
+    /// * `max_frequency` - The break frequency when `modulation` is 1.
+    /// * `num_stages` - The number of all-pass stages. Each pair of stages adds a notch.
+    /// * `feedback` - How much of the all-pass output is fed back. Should be less than 1 in magnitude.
+    /// * `mix` - How much of the all-pass output is in the result. 0.5 gives the deepest notches.
+    /// * `sample_rate` - The used sample rate.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        source: ModuleTemplate<S>,
+        modulation: ModuleTemplate<M>,
+        min_frequency: f32,
+        max_frequency: f32,
+        num_stages: usize,
+        feedback: f32,
+        mix: f32,
+        sample_rate: u32,
+    ) -> ModuleTemplate<Phaser<S, M>> {
+        ModuleTemplate {
+            module: Phaser {
+                source: source.module,
+                modulation: modulation.module,
+                min_frequency,
+                max_frequency,
+                feedback,
+                mix,
+                stages: vec![(0., 0.); num_stages],
+                prev_wet: 0.,
+                inverse_sample_rate: 1. / sample_rate as f32,
+            },
+        }
+    }
+}
+
+impl<S: Module, M: Module> Module for Phaser<S, M> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let input = self.source.next(sample_num);
+        let modulation = self.modulation.next(sample_num).clamp(0., 1.);
+        // Sweep exponentially so the sweep sounds even across octaves.
+        let frequency =
+            self.min_frequency * (self.max_frequency / self.min_frequency).powf(modulation);
+        let tan = (std::f32::consts::PI * frequency * self.inverse_sample_rate).tan();
+        let coefficient = (tan - 1.) / (tan + 1.);
+
+        let mut signal = input + self.prev_wet * self.feedback;
+        for (prev_input, prev_output) in self.stages.iter_mut() {
+            let output = coefficient * signal + *prev_input - coefficient * *prev_output;
+            *prev_input = signal;
+            *prev_output = output;
+            signal = output;
+        }
+        self.prev_wet = signal;
+        input * (1. - self.mix) + signal * self.mix
+    }
+}
+
+/// A phaser with four stages swept between 200 Hz and 2 kHz by a sine wave.
+///
+/// # Arguments
+///
+/// * `source` - The signal to phase.
+/// * `rate` - The frequency of the sweep in Hz.
+/// * `feedback` - How much of the all-pass output is fed back. Should be less than 1 in magnitude.
+/// * `sample_rate` - The used sample rate.
+pub fn phaser<S: Module>(
+    source: ModuleTemplate<S>,
+    rate: f32,
+    feedback: f32,
+    sample_rate: u32,
+) -> ModuleTemplate<Phaser<S, impl Module>> {
+    let sweep = SineOscillator::new(rate.into(), sample_rate) * 0.5 + 0.5;
+    Phaser::new(source, sweep, 200., 2000., 4, feedback, 0.5, sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::NoiseOscillator;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    const SAMPLE_RATE: u32 = 48000;
+    const BREAK_FREQUENCY: f32 = 1000.;
+
+    /// A four stage phaser of a sine wave, with the break frequency held at `BREAK_FREQUENCY`.
+    fn fixed_phaser(frequency: f32, mix: f32) -> Phaser<impl Module, f32> {
+        let sine = SineOscillator::new(frequency.into(), SAMPLE_RATE);
+        Phaser::new(
+            sine,
+            0.0.into(),
+            BREAK_FREQUENCY,
+            BREAK_FREQUENCY,
+            4,
+            0.,
+            mix,
+            SAMPLE_RATE,
+        )
+        .module()
+    }
+
+    /// The RMS of the module's output over a second, after letting it settle for a tenth of a second.
+    fn rms(module: &mut impl Module) -> f32 {
+        let settle = SAMPLE_RATE as u64 / 10;
+        let samples: Vec<f32> = (0..settle + SAMPLE_RATE as u64)
+            .map(|i| module.next(i))
+            .skip(settle as usize)
+            .collect();
+        (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn notches_where_stages_invert_phase() {
+        // Each stage shifts the phase by 2 atan(f / break frequency), so four stages shift it by
+        // 180 degrees at tan(22.5 degrees) times the break frequency, and by 360 degrees at it.
+        let notch = BREAK_FREQUENCY * std::f32::consts::FRAC_PI_8.tan();
+        let sine_rms = std::f32::consts::FRAC_1_SQRT_2;
+        let notched = rms(&mut fixed_phaser(notch, 0.5));
+        assert!(notched < 0.05 * sine_rms, "RMS at the notch: {}", notched);
+        let in_phase = rms(&mut fixed_phaser(BREAK_FREQUENCY, 0.5));
+        assert!((in_phase - sine_rms).abs() < 0.01, "RMS: {}", in_phase);
+    }
+
+    #[test]
+    fn dry_mix_passes_signal_through() {
+        let mut phaser = fixed_phaser(300., 0.);
+        let mut sine = SineOscillator::new(300.0.into(), SAMPLE_RATE).module();
+        for i in 0..1000 {
+            assert_eq!(phaser.next(i), sine.next(i));
+        }
+    }
+
+    #[test]
+    fn output_stays_bounded_with_feedback() {
+        let noise = NoiseOscillator::new(SmallRng::seed_from_u64(3));
+        let mut phaser = phaser(noise, 5., 0.9, SAMPLE_RATE).module();
+        let mut noise = NoiseOscillator::new(SmallRng::seed_from_u64(3)).module();
+        let peak_input = (0..SAMPLE_RATE as u64 * 5)
+            .map(|i| noise.next(i).abs())
+            .fold(0., f32::max);
+        for i in 0..SAMPLE_RATE as u64 * 5 {
+            let sample = phaser.next(i);
+            assert!(
+                sample.is_finite() && sample.abs() < 10. * peak_input,
+                "Sample {} is {}",
+                i,
+                sample
+            );
+        }
+    }
+}
@@ -10,8 +10,12 @@ mod delay;
 pub use delay::*;
 mod sampler;
 pub use sampler::*;
+mod streamer;
+pub use streamer::*;
 mod input;
 pub use input::*;
+mod tap;
+pub use tap::*;
 
 #[derive(Clone)]
 pub struct ModuleTemplate<M: Module> {
@@ -28,6 +32,12 @@ impl<M: Module> ModuleTemplate<M> {
     }
 }
 
+impl<M: Module + ModuleInfo> ModuleTemplate<M> {
+    pub fn metadata(&self) -> ModuleMetadata {
+        M::METADATA
+    }
+}
+
 impl<L: Module, R: Module> std::ops::Add<ModuleTemplate<R>> for ModuleTemplate<L> {
     type Output = ModuleTemplate<crate::modules::Add<L, R>>;
 
@@ -44,6 +54,22 @@ impl<L: Module, R: Module> std::ops::Mul<ModuleTemplate<R>> for ModuleTemplate<L
     }
 }
 
+impl<L: Module, R: Module> std::ops::Sub<ModuleTemplate<R>> for ModuleTemplate<L> {
+    type Output = ModuleTemplate<crate::modules::Subtract<L, R>>;
+
+    fn sub(self, rhs: ModuleTemplate<R>) -> Self::Output {
+        crate::modules::Subtract::new(self, rhs)
+    }
+}
+
+impl<L: Module> std::ops::Neg for ModuleTemplate<L> {
+    type Output = ModuleTemplate<crate::modules::Multiply<L, f32>>;
+
+    fn neg(self) -> Self::Output {
+        crate::modules::Multiply::new(self, ModuleTemplate { module: -1. })
+    }
+}
+
 impl<L: Module> std::ops::Add<f32> for ModuleTemplate<L> {
     type Output = ModuleTemplate<crate::modules::Add<L, f32>>;
 
@@ -60,6 +86,14 @@ impl<L: Module> std::ops::Mul<f32> for ModuleTemplate<L> {
     }
 }
 
+impl<L: Module> std::ops::Sub<f32> for ModuleTemplate<L> {
+    type Output = ModuleTemplate<crate::modules::Subtract<L, f32>>;
+
+    fn sub(self, rhs: f32) -> Self::Output {
+        crate::modules::Subtract::new(self, ModuleTemplate { module: rhs })
+    }
+}
+
 impl std::convert::From<f32> for ModuleTemplate<f32> {
     fn from(value: f32) -> Self {
         ModuleTemplate { module: value }
@@ -75,3 +109,27 @@ impl Module for f32 {
         *self
     }
 }
+
+/// Describes the structure of a module type, for patch serialization and debugging.
+/// Implemented for every module by the `module` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModuleMetadata {
+    /// The name of the module type.
+    pub name: &'static str,
+    /// The names of all the module's fields.
+    pub parameters: &'static [&'static str],
+    /// The names of the fields that hold other modules.
+    pub sub_modules: &'static [&'static str],
+}
+
+pub trait ModuleInfo {
+    const METADATA: ModuleMetadata;
+}
+
+impl ModuleInfo for f32 {
+    const METADATA: ModuleMetadata = ModuleMetadata {
+        name: "f32",
+        parameters: &[],
+        sub_modules: &[],
+    };
+}
@@ -1,7 +1,14 @@
 mod noise_oscillator;
+mod poly_blep;
 mod saw_oscillator;
 mod sine_oscillator;
+mod square_oscillator;
+mod triangle_oscillator;
+mod wavetable_oscillator;
 
-pub use noise_oscillator::NoiseOscillator;
+pub use noise_oscillator::{NoiseColor, NoiseOscillator};
 pub use saw_oscillator::SawOscillator;
 pub use sine_oscillator::SineOscillator;
+pub use square_oscillator::SquareOscillator;
+pub use triangle_oscillator::TriangleOscillator;
+pub use wavetable_oscillator::{table_from_harmonics, WavetableOscillator};
@@ -3,21 +3,156 @@ use synth_derive::module;
 use rand::Rng;
 use rand_distr::StandardNormal;
 
+/// The spectral shape of noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseColor {
+    /// Equal power at all frequencies.
+    White,
+    /// Power falls by 3 dB per octave.
+    Pink,
+    /// Power falls by 6 dB per octave.
+    Brown,
+}
+
 #[module]
 pub struct NoiseOscillator<R: Rng + Clone> {
     rng: R,
+    color: NoiseColor,
+    /// Filter state used to shape white noise into other colors.
+    state: [f32; 7],
 }
 
 impl<R: Rng + Clone> NoiseOscillator<R> {
+    /// Creates a white noise oscillator.
     pub fn new(rng: R) -> ModuleTemplate<NoiseOscillator<R>> {
+        NoiseOscillator::colored(rng, NoiseColor::White)
+    }
+
+    /// Creates a noise oscillator with the given color.
+    /// All colors have roughly the same power as white noise.
+    pub fn colored(rng: R, color: NoiseColor) -> ModuleTemplate<NoiseOscillator<R>> {
         ModuleTemplate {
-            module: NoiseOscillator { rng },
+            module: NoiseOscillator {
+                rng,
+                color,
+                state: [0.; 7],
+            },
         }
     }
 }
 
 impl<R: Rng + Clone> Module for NoiseOscillator<R> {
     fn next(&mut self, _: u64) -> f32 {
-        self.rng.sample(StandardNormal)
+        let white: f32 = self.rng.sample(StandardNormal);
+        match self.color {
+            NoiseColor::White => white,
+            NoiseColor::Pink => {
+                // Paul Kellet's refined pink noise filter.
+                let b = &mut self.state;
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.969 * b[2] + white * 0.153852;
+                b[3] = 0.8665 * b[3] + white * 0.3104856;
+                b[4] = 0.55 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.0168980;
+                let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+                b[6] = white * 0.115926;
+                pink * 0.33
+            }
+            NoiseColor::Brown => {
+                // A leaky integrator keeps the random walk from drifting away.
+                self.state[0] = (self.state[0] + white * 0.02) / 1.02;
+                self.state[0] * 10.
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SpectrumAnalyzer;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// The average power per bin between the two frequencies, averaged over many windows.
+    fn band_powers(color: NoiseColor, bands: &[(f32, f32)]) -> Vec<f32> {
+        let mut oscillator = NoiseOscillator::colored(SmallRng::seed_from_u64(7), color).module();
+        let mut analyzer = SpectrumAnalyzer::new(4096, SAMPLE_RATE);
+        let mut powers = vec![0.; analyzer.num_bins()];
+        let mut sample_num = 0;
+        for _ in 0..64 {
+            for _ in 0..analyzer.size() {
+                analyzer.push(oscillator.next(sample_num));
+                sample_num += 1;
+            }
+            for (power, magnitude) in powers.iter_mut().zip(analyzer.magnitudes()) {
+                *power += magnitude * magnitude;
+            }
+        }
+        bands
+            .iter()
+            .map(|&(low, high)| {
+                let bins: Vec<f32> = (0..analyzer.num_bins())
+                    .filter(|&bin| (low..high).contains(&analyzer.bin_frequency(bin)))
+                    .map(|bin| powers[bin])
+                    .collect();
+                bins.iter().sum::<f32>() / bins.len() as f32
+            })
+            .collect()
+    }
+
+    /// How much the power changes per octave, from the octave above 400 Hz to the octave above 3200 Hz.
+    fn slope_per_octave(color: NoiseColor) -> f32 {
+        let powers = band_powers(color, &[(400., 800.), (3200., 6400.)]);
+        10. * (powers[1] / powers[0]).log10() / 3.
+    }
+
+    #[test]
+    fn white_noise_is_flat() {
+        let slope = slope_per_octave(NoiseColor::White);
+        assert!(slope.abs() < 0.5, "Slope: {} dB per octave", slope);
+    }
+
+    #[test]
+    fn pink_noise_falls_3_db_per_octave() {
+        let slope = slope_per_octave(NoiseColor::Pink);
+        assert!(
+            (-3.5..-2.5).contains(&slope),
+            "Slope: {} dB per octave",
+            slope
+        );
+    }
+
+    #[test]
+    fn brown_noise_falls_6_db_per_octave() {
+        // The leak keeping brown noise from drifting flattens the slope a little at low frequencies.
+        let slope = slope_per_octave(NoiseColor::Brown);
+        assert!(
+            (-6.5..-5.).contains(&slope),
+            "Slope: {} dB per octave",
+            slope
+        );
+    }
+
+    #[test]
+    fn colors_have_similar_power_and_stay_bounded() {
+        for color in [NoiseColor::White, NoiseColor::Pink, NoiseColor::Brown] {
+            let mut oscillator =
+                NoiseOscillator::colored(SmallRng::seed_from_u64(3), color).module();
+            // 10 seconds is long enough for brown noise to drift if it could.
+            let samples: Vec<f32> = (0..480000).map(|i| oscillator.next(i)).collect();
+            let rms = (samples.iter().map(|sample| sample * sample).sum::<f32>()
+                / samples.len() as f32)
+                .sqrt();
+            let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+            let max = samples
+                .iter()
+                .fold(0., |max: f32, sample| max.max(sample.abs()));
+            assert!((0.8..1.25).contains(&rms), "{:?} RMS: {}", color, rms);
+            assert!(mean.abs() < 0.1, "{:?} mean: {}", color, mean);
+            assert!(max < 8., "{:?} max: {}", color, max);
+        }
     }
 }
@@ -0,0 +1,20 @@
+/// The polynomial band-limited step correction for a discontinuity at phase 0.
+/// Subtracting this from a naive waveform with a step of size 2 at phase 0 removes most of the aliasing.
+///
+/// # Arguments
+///
+/// * `t` - The current phase in the interval [0;1).
+/// * `dt` - The phase increment per sample.
+pub(super) fn poly_blep(t: f32, dt: f32) -> f32 {
+    if dt <= 0. {
+        0.
+    } else if t < dt {
+        let t = t / dt;
+        t + t - t * t - 1.
+    } else if t > 1. - dt {
+        let t = (t - 1.) / dt;
+        t * t + t + t + 1.
+    } else {
+        0.
+    }
+}
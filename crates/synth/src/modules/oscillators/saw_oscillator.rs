@@ -1,6 +1,8 @@
+use super::poly_blep::poly_blep;
 use crate::modules::{Module, ModuleTemplate};
 use synth_derive::module;
 
+/// A band-limited sawtooth wave between -0.5 and 0.5.
 #[module]
 pub struct SawOscillator<F: Module> {
     frequency: F,
@@ -22,8 +24,84 @@ impl<F: Module> SawOscillator<F> {
 
 impl<F: Module> Module for SawOscillator<F> {
     fn next(&mut self, sample_num: u64) -> f32 {
-        self.cur_pos += self.frequency.next(sample_num) * self.inverse_sample_rate;
+        let step = self.frequency.next(sample_num) * self.inverse_sample_rate;
+        self.cur_pos += step;
         self.cur_pos %= 1.;
-        self.cur_pos - 0.5
+        // The naive saw has a step of size 1, so only half the correction is needed.
+        self.cur_pos - 0.5 - 0.5 * poly_blep(self.cur_pos, step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SpectrumAnalyzer;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// The loudest part of the spectrum that isn't a harmonic of the frequency, in dB relative to the fundamental.
+    /// For a saw this is aliasing from the harmonics above the Nyquist frequency.
+    fn worst_alias_db(samples: &[f32], frequency: f32) -> f32 {
+        let mut analyzer = SpectrumAnalyzer::new(samples.len(), SAMPLE_RATE);
+        analyzer.process(samples);
+        let magnitudes = analyzer.magnitudes();
+        let bin_width = analyzer.bin_frequency(1);
+        let fundamental = magnitudes[(frequency / bin_width).round() as usize];
+        let worst = magnitudes
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|&(bin, _)| {
+                let bin_frequency = analyzer.bin_frequency(bin);
+                let harmonic = (bin_frequency / frequency).round().max(1.);
+                // The window spreads each harmonic over a few bins.
+                (bin_frequency - harmonic * frequency).abs() > 4. * bin_width
+            })
+            .fold(0., |worst: f32, (_, &magnitude)| worst.max(magnitude));
+        20. * (worst / fundamental).log10()
+    }
+
+    #[test]
+    fn aliasing_is_suppressed() {
+        let frequency = 2637.;
+        let mut oscillator = SawOscillator::new(frequency.into(), SAMPLE_RATE).module();
+        let samples: Vec<f32> = (0..8192).map(|i| oscillator.next(i)).collect();
+        let naive: Vec<f32> = (1..=8192)
+            .map(|i| (i as f32 * frequency / SAMPLE_RATE as f32) % 1. - 0.5)
+            .collect();
+        let band_limited = worst_alias_db(&samples, frequency);
+        let naive = worst_alias_db(&naive, frequency);
+        assert!(band_limited < -28., "Worst alias: {} dB", band_limited);
+        assert!(
+            band_limited < naive - 6.,
+            "Worst alias: {} dB, naive saw: {} dB",
+            band_limited,
+            naive
+        );
+    }
+
+    #[test]
+    fn stays_within_range() {
+        for frequency in [55., 440., 3001., 9000.] {
+            let mut oscillator = SawOscillator::new(frequency.into(), SAMPLE_RATE).module();
+            let max = (0..48000).fold(0., |max: f32, i| max.max(oscillator.next(i).abs()));
+            assert!(max <= 0.5 + 1e-3, "{} Hz reaches {}", frequency, max);
+        }
+    }
+
+    #[test]
+    fn resets_once_per_period() {
+        let mut oscillator = SawOscillator::new(100.0.into(), SAMPLE_RATE).module();
+        let samples: Vec<f32> = (0..4800).map(|i| oscillator.next(i)).collect();
+        let resets: Vec<usize> = samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[1] < pair[0] - 0.5)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(resets.len(), 10);
+        for pair in resets.windows(2) {
+            assert_eq!(pair[1] - pair[0], 480);
+        }
     }
 }
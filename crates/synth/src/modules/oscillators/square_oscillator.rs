@@ -0,0 +1,71 @@
+use super::poly_blep::poly_blep;
+use crate::modules::{Module, ModuleTemplate};
+use synth_derive::module;
+
+/// A band-limited pulse wave between -1 and 1 with variable pulse width.
+#[module]
+pub struct SquareOscillator<F: Module, W: Module> {
+    /// A module that supplies the frequency each sample.
+    frequency: F,
+    /// A module that supplies the fraction of each period the wave is high.
+    pulse_width: W,
+    inverse_sample_rate: f32,
+    cur_pos: f32,
+}
+
+impl<F: Module, W: Module> SquareOscillator<F, W> {
+    /// Creates a new SquareOscillator.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The frequency of the wave.
+    /// * `pulse_width` - The fraction of each period the wave is high. 0.5 gives a square wave.
+    ///   Values are clamped to between 0 and 1.
+    /// * `sample_rate` - The used sample rate.
+    pub fn new(
+        frequency: ModuleTemplate<F>,
+        pulse_width: ModuleTemplate<W>,
+        sample_rate: u32,
+    ) -> ModuleTemplate<SquareOscillator<F, W>> {
+        ModuleTemplate {
+            module: SquareOscillator {
+                frequency: frequency.module,
+                pulse_width: pulse_width.module,
+                inverse_sample_rate: 1. / (sample_rate as f32),
+                cur_pos: 0.,
+            },
+        }
+    }
+}
+
+impl<F: Module, W: Module> Module for SquareOscillator<F, W> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let step = self.frequency.next(sample_num) * self.inverse_sample_rate;
+        let pulse_width = self.pulse_width.next(sample_num).clamp(0., 1.);
+        self.cur_pos += step;
+        self.cur_pos %= 1.;
+        let naive = if self.cur_pos < pulse_width { 1. } else { -1. };
+        // Correct the rising edge at phase 0 and the falling edge at the pulse width.
+        naive + poly_blep(self.cur_pos, step)
+            - poly_blep((self.cur_pos - pulse_width + 1.) % 1., step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulse_width_sets_duty_cycle() {
+        let mut oscillator = SquareOscillator::new(100.0.into(), 0.25.into(), 48000).module();
+        // Average over exactly 10 periods.
+        let mean = (0..4800).map(|i| oscillator.next(i)).sum::<f32>() / 4800.;
+        assert!((mean + 0.5).abs() < 0.01, "Mean: {}", mean);
+    }
+
+    #[test]
+    fn stays_within_range() {
+        let mut oscillator = SquareOscillator::new(3001.0.into(), 0.5.into(), 48000).module();
+        assert!((0..48000).all(|i| oscillator.next(i).abs() <= 1.));
+    }
+}
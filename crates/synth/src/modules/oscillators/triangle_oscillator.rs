@@ -0,0 +1,87 @@
+use super::poly_blep::poly_blep;
+use crate::modules::{Module, ModuleTemplate};
+use synth_derive::module;
+
+/// How much of the integrated value leaks away each period to keep DC offset from building up.
+const LEAK_PER_PERIOD: f32 = 0.05;
+
+/// A band-limited triangle wave between -1 and 1.
+/// Made by integrating a band-limited square wave.
+#[module]
+pub struct TriangleOscillator<F: Module> {
+    frequency: F,
+    inverse_sample_rate: f32,
+    cur_pos: f32,
+    cur_value: f32,
+}
+
+impl<F: Module> TriangleOscillator<F> {
+    pub fn new(
+        frequency: ModuleTemplate<F>,
+        sample_rate: u32,
+    ) -> ModuleTemplate<TriangleOscillator<F>> {
+        ModuleTemplate {
+            module: TriangleOscillator {
+                frequency: frequency.module,
+                inverse_sample_rate: 1. / (sample_rate as f32),
+                cur_pos: 0.,
+                cur_value: -1.,
+            },
+        }
+    }
+}
+
+impl<F: Module> Module for TriangleOscillator<F> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let step = self.frequency.next(sample_num) * self.inverse_sample_rate;
+        self.cur_pos += step;
+        self.cur_pos %= 1.;
+        let naive = if self.cur_pos < 0.5 { 1. } else { -1. };
+        let square =
+            naive + poly_blep(self.cur_pos, step) - poly_blep((self.cur_pos + 0.5) % 1., step);
+        // Each half period lasts 0.5 / step samples, so this rises or falls by 2 per half period.
+        self.cur_value = self.cur_value * (1. - LEAK_PER_PERIOD * step) + 4. * step * square;
+        self.cur_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 periods of a 100 Hz triangle at 48 kHz after the integrator has settled.
+    fn settled_periods() -> Vec<f32> {
+        let mut oscillator = TriangleOscillator::new(100.0.into(), 48000).module();
+        (0..48000).map(|i| oscillator.next(i)).skip(43200).collect()
+    }
+
+    #[test]
+    fn spans_full_range() {
+        let samples = settled_periods();
+        let max = samples.iter().copied().fold(f32::MIN, f32::max);
+        let min = samples.iter().copied().fold(f32::MAX, f32::min);
+        assert!((0.97..=1.01).contains(&max), "Max: {}", max);
+        assert!((-1.01..-0.97).contains(&min), "Min: {}", min);
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 0.01, "Mean: {}", mean);
+    }
+
+    #[test]
+    fn rises_and_falls_linearly_once_per_period() {
+        let samples = settled_periods();
+        // A triangle between -1 and 1 changes by 4 per period, so by 4 / 480 per sample.
+        for pair in samples.windows(2) {
+            assert!((pair[1] - pair[0]).abs() <= 4. / 480. * 1.02, "{:?}", pair);
+        }
+        let rising_crossings: Vec<usize> = samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0] < 0. && pair[1] >= 0.)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(rising_crossings.len(), 10);
+        for pair in rising_crossings.windows(2) {
+            assert_eq!(pair[1] - pair[0], 480);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::modules::{Module, ModuleTemplate};
+use crate::utils::interpolation;
+use synth_derive::module;
+use std::sync::Arc;
+
+/// Creates a single cycle table by summing sine harmonics.
+/// As long as the highest harmonic times the played frequency is below the Nyquist frequency,
+/// the resulting table plays without aliasing.
+///
+/// # Arguments
+///
+/// * `harmonics` - The amplitude of each harmonic starting with the fundamental.
+/// * `size` - The number of samples in the table.
+pub fn table_from_harmonics(harmonics: &[f32], size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| {
+            let phase = std::f32::consts::TAU * i as f32 / size as f32;
+            harmonics
+                .iter()
+                .enumerate()
+                .map(|(harmonic, amplitude)| amplitude * (phase * (harmonic + 1) as f32).sin())
+                .sum()
+        })
+        .collect()
+}
+
+/// Plays single cycle tables and morphs between them.
+#[module]
+pub struct WavetableOscillator<F: Module, P: Module> {
+    tables: Arc<[Arc<[f32]>]>,
+    /// A module that supplies the frequency each sample.
+    frequency: F,
+    /// A module that supplies which table to play. 0 is the first table and 1 is the last.
+    /// Values in between crossfade between neighbouring tables.
+    position: P,
+    inverse_sample_rate: f32,
+    cur_pos: f32,
+}
+
+impl<F: Module, P: Module> WavetableOscillator<F, P> {
+    /// Creates a new WavetableOscillator.
+    ///
+    /// # Arguments
+    ///
+    /// * `tables` - The single cycle tables to morph between. Tables may have different lengths.
+    /// * `frequency` - The frequency to play the tables at.
+    /// * `position` - Which table to play. 0 is the first table and 1 is the last.
+    /// * `sample_rate` - The used sample rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no tables or any table is empty.
+    pub fn new(
+        tables: Vec<Vec<f32>>,
+        frequency: ModuleTemplate<F>,
+        position: ModuleTemplate<P>,
+        sample_rate: u32,
+    ) -> ModuleTemplate<WavetableOscillator<F, P>> {
+        if tables.is_empty() || tables.iter().any(|table| table.is_empty()) {
+            panic!("A wavetable oscillator needs at least one table and no table may be empty.");
+        }
+        ModuleTemplate {
+            module: WavetableOscillator {
+                tables: tables.into_iter().map(|table| table.into()).collect(),
+                frequency: frequency.module,
+                position: position.module,
+                inverse_sample_rate: 1. / (sample_rate as f32),
+                cur_pos: 0.,
+            },
+        }
+    }
+
+    /// The value of the given table at the current phase.
+    fn table_value(&self, table_index: usize) -> f32 {
+        let table = &self.tables[table_index];
+        let position = self.cur_pos * table.len() as f32;
+        let index = (position as usize).min(table.len() - 1);
+        let next_index = (index + 1) % table.len();
+        interpolation::linear(table[index], table[next_index], position - index as f32)
+    }
+}
+
+impl<F: Module, P: Module> Module for WavetableOscillator<F, P> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        self.cur_pos += self.frequency.next(sample_num) * self.inverse_sample_rate;
+        self.cur_pos %= 1.;
+        let table_position =
+            self.position.next(sample_num).clamp(0., 1.) * (self.tables.len() - 1) as f32;
+        let table_index = (table_position as usize).min(self.tables.len() - 1);
+        let next_table_index = (table_index + 1).min(self.tables.len() - 1);
+        interpolation::linear(
+            self.table_value(table_index),
+            self.table_value(next_table_index),
+            table_position - table_index as f32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn sine_table_plays_sine() {
+        let table = table_from_harmonics(&[1.], 256);
+        let mut oscillator =
+            WavetableOscillator::new(vec![table], 100.0.into(), 0.0.into(), 48000).module();
+        for i in 0..4800 {
+            // The phase is advanced before the table is read.
+            let expected = (TAU * 100. * (i + 1) as f32 / 48000.).sin();
+            let error = (oscillator.next(i) - expected).abs();
+            assert!(error < 1e-3, "Sample {} is off by {}", i, error);
+        }
+    }
+
+    #[test]
+    fn repeats_every_period_within_range() {
+        let table = table_from_harmonics(&[0.6, 0.3, 0.1], 100);
+        let mut oscillator =
+            WavetableOscillator::new(vec![table], 150.0.into(), 0.0.into(), 48000).module();
+        let samples: Vec<f32> = (0..3200).map(|i| oscillator.next(i)).collect();
+        assert!(samples.iter().all(|sample| sample.abs() <= 1.));
+        // 150 Hz at 48 kHz has a period of exactly 320 samples.
+        for (sample, later) in samples.iter().zip(&samples[320..]) {
+            assert!((sample - later).abs() < 1e-3, "{} != {}", sample, later);
+        }
+    }
+
+    #[test]
+    fn position_morphs_between_tables() {
+        let tables = vec![vec![1.; 4], vec![-1.; 8], vec![0.5; 2]];
+        for (position, expected) in [(0., 1.), (0.25, 0.), (0.5, -1.), (0.75, -0.25), (1., 0.5)] {
+            let mut oscillator =
+                WavetableOscillator::new(tables.clone(), 440.0.into(), position.into(), 48000)
+                    .module();
+            assert!((0..100).all(|i| (oscillator.next(i) - expected).abs() < 1e-6));
+        }
+    }
+}
@@ -1,40 +1,369 @@
 use crate::modules::{Module, ModuleTemplate};
+use crate::utils::interpolation;
 use synth_derive::module;
 use std::sync::Arc;
 
+/// How to find the value between two samples when playing at a non-integer position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Use the sample before the position.
+    Nearest,
+    Linear,
+    Cubic,
+}
+
+/// Specifies how a `Sampler` plays its audio.
+#[derive(Clone, Copy, Debug)]
+pub struct SamplerSettings {
+    /// Whether to loop between `loop_start` and `loop_end` instead of stopping at the end.
+    pub repeat: bool,
+    /// The first sample of the loop.
+    pub loop_start: usize,
+    /// The sample after the last sample of the loop. If None the loop ends at the end of the audio.
+    pub loop_end: Option<usize>,
+    /// The number of samples to crossfade over at the loop point.
+    /// The audio just outside the loop is faded in as the loop end approaches,
+    /// so the crossfade is limited by how much audio there is outside the loop.
+    pub crossfade: usize,
+    /// Whether to play the audio backwards, starting from the end.
+    pub reverse: bool,
+    pub interpolation: Interpolation,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings {
+            repeat: false,
+            loop_start: 0,
+            loop_end: None,
+            crossfade: 0,
+            reverse: false,
+            interpolation: Interpolation::Linear,
+        }
+    }
+}
+
+/// Plays recorded audio at a rate given by a module.
+/// A rate of 1 plays the audio at its original pitch, 2 an octave higher and so on.
 #[module]
-pub struct Sampler {
+pub struct Sampler<R: Module> {
     audio: Arc<[f32]>,
+    rate: R,
     repeat: bool,
-    position: usize,
-    end: usize,
+    reverse: bool,
+    interpolation: Interpolation,
+    loop_start: f64,
+    loop_end: f64,
+    crossfade: f64,
+    position: f64,
+    finished: bool,
 }
 
-impl Sampler {
+impl Sampler<f32> {
+    /// Creates a sampler that plays the audio at its original pitch from start to end.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - The samples to play.
+    /// * `repeat` - Whether to start over when the end is reached.
     pub fn new(audio: Arc<[f32]>, repeat: bool) -> ModuleTemplate<Self> {
+        Sampler::pitched(
+            audio,
+            1.0.into(),
+            SamplerSettings {
+                repeat,
+                interpolation: Interpolation::Nearest,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+impl<R: Module> Sampler<R> {
+    /// Creates a sampler with a variable playback rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - The samples to play.
+    /// * `rate` - How many samples of the audio to advance per output sample.
+    ///   Negative values are treated as 0.
+    /// * `settings` - Looping, direction and interpolation settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the audio isn't empty and the loop is empty or extends past the end of the audio.
+    pub fn pitched(
+        audio: Arc<[f32]>,
+        rate: ModuleTemplate<R>,
+        settings: SamplerSettings,
+    ) -> ModuleTemplate<Self> {
         let audio_length = audio.len();
+        // Empty audio is silent whatever the loop is, so there is nothing to check.
+        let (loop_start, loop_end) = if audio_length == 0 {
+            (0, 0)
+        } else {
+            let loop_end = settings.loop_end.unwrap_or(audio_length);
+            if settings.loop_start >= loop_end || loop_end > audio_length {
+                panic!(
+                    "Invalid loop. Loop start: {}, loop end: {}, audio length: {}",
+                    settings.loop_start, loop_end, audio_length
+                );
+            }
+            (settings.loop_start, loop_end)
+        };
+        // The crossfade uses audio from outside the loop, so it cannot be longer than
+        // the audio before the loop (or after it when playing in reverse), or than the loop itself.
+        // In reverse the crossfade reaches one sample further past the loop end.
+        let outside_loop = if settings.reverse {
+            (audio_length - loop_end).saturating_sub(1)
+        } else {
+            loop_start
+        };
+        let crossfade = settings
+            .crossfade
+            .min(outside_loop)
+            .min(loop_end - loop_start);
         ModuleTemplate {
             module: Sampler {
                 audio,
-                repeat,
-                position: 0,
-                end: audio_length,
+                rate: rate.module,
+                repeat: settings.repeat,
+                reverse: settings.reverse,
+                interpolation: settings.interpolation,
+                loop_start: loop_start as f64,
+                loop_end: loop_end as f64,
+                crossfade: crossfade as f64,
+                position: if settings.reverse {
+                    audio_length as f64 - 1.
+                } else {
+                    0.
+                },
+                finished: audio_length == 0,
             },
         }
     }
+
+    /// Returns true if the sampler has played all its audio and does not repeat.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The sample at the given index or 0 if the index is outside the audio.
+    fn sample(&self, index: i64) -> f32 {
+        if index < 0 {
+            0.
+        } else {
+            self.audio.get(index as usize).copied().unwrap_or(0.)
+        }
+    }
+
+    /// The interpolated value at the given position.
+    fn value_at(&self, position: f64) -> f32 {
+        let index = position.floor() as i64;
+        let t = (position - index as f64) as f32;
+        match self.interpolation {
+            Interpolation::Nearest => self.sample(index),
+            Interpolation::Linear => {
+                interpolation::linear(self.sample(index), self.sample(index + 1), t)
+            }
+            Interpolation::Cubic => interpolation::cubic(
+                self.sample(index - 1),
+                self.sample(index),
+                self.sample(index + 1),
+                self.sample(index + 2),
+                t,
+            ),
+        }
+    }
+
+    /// The output at the current position including any loop crossfade.
+    /// The crossfade is complete on the last output before the position moves `step` past the loop point,
+    /// so playback continues seamlessly from the other end of the loop.
+    fn current_value(&self, step: f64) -> f32 {
+        let value = self.value_at(self.position);
+        if !self.repeat || self.crossfade == 0. {
+            return value;
+        }
+        let loop_length = self.loop_end - self.loop_start;
+        // How far into the crossfade region the position is after this step.
+        let (fade_progress, other_position) = if self.reverse {
+            (
+                self.loop_start + self.crossfade - self.position + step,
+                self.position + loop_length,
+            )
+        } else {
+            (
+                self.position + step - (self.loop_end - self.crossfade),
+                self.position - loop_length,
+            )
+        };
+        if fade_progress <= 0. {
+            value
+        } else {
+            let t = (fade_progress / self.crossfade).min(1.) as f32;
+            interpolation::linear(value, self.value_at(other_position), t)
+        }
+    }
 }
 
-impl Module for Sampler {
-    fn next(&mut self, _: u64) -> f32 {
-        if self.position == self.end {
+impl<R: Module> Module for Sampler<R> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        if self.finished {
+            return 0.;
+        }
+        let step = self.rate.next(sample_num).max(0.) as f64;
+        let result = self.current_value(step);
+        let loop_length = self.loop_end - self.loop_start;
+        if self.reverse {
+            self.position -= step;
+            if self.repeat {
+                while self.position < self.loop_start {
+                    self.position += loop_length;
+                }
+            } else if self.position < 0. {
+                self.finished = true;
+            }
+        } else {
+            self.position += step;
             if self.repeat {
-                self.position = 0;
-            } else {
-                return 0.;
+                while self.position >= self.loop_end {
+                    self.position -= loop_length;
+                }
+            } else if self.position >= self.audio.len() as f64 {
+                self.finished = true;
             }
         }
-        let result = self.audio[self.position];
-        self.position += 1;
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(length: usize) -> Arc<[f32]> {
+        (0..length).map(|i| i as f32).collect::<Vec<_>>().into()
+    }
+
+    fn play<R: Module>(mut sampler: Sampler<R>, num_samples: u64) -> Vec<f32> {
+        (0..num_samples).map(|i| sampler.next(i)).collect()
+    }
+
+    #[test]
+    fn repeat() {
+        let sampler = Sampler::new(ramp(3), true).module();
+        assert_eq!(play(sampler, 7), vec![0., 1., 2., 0., 1., 2., 0.]);
+    }
+
+    #[test]
+    fn double_rate() {
+        let sampler = Sampler::pitched(ramp(6), 2.0.into(), SamplerSettings::default()).module();
+        assert_eq!(play(sampler, 4), vec![0., 2., 4., 0.]);
+    }
+
+    #[test]
+    fn half_rate_interpolated() {
+        let sampler = Sampler::pitched(ramp(3), 0.5.into(), SamplerSettings::default()).module();
+        assert_eq!(play(sampler, 5), vec![0., 0.5, 1., 1.5, 2.]);
+    }
+
+    #[test]
+    fn reverse_loop() {
+        let settings = SamplerSettings {
+            repeat: true,
+            loop_start: 1,
+            loop_end: Some(4),
+            reverse: true,
+            interpolation: Interpolation::Nearest,
+            ..Default::default()
+        };
+        let sampler = Sampler::pitched(ramp(6), 1.0.into(), settings).module();
+        assert_eq!(play(sampler, 9), vec![5., 4., 3., 2., 1., 3., 2., 1., 3.]);
+    }
+
+    #[test]
+    fn crossfade() {
+        let settings = SamplerSettings {
+            repeat: true,
+            loop_start: 2,
+            loop_end: Some(6),
+            crossfade: 2,
+            interpolation: Interpolation::Nearest,
+            ..Default::default()
+        };
+        let sampler = Sampler::pitched(ramp(6), 1.0.into(), settings).module();
+        // Sample 4 is halfway through the crossfade and is mixed with sample 0.
+        // Sample 5 is replaced by sample 1, which leads into the start of the loop.
+        assert_eq!(play(sampler, 8), vec![0., 1., 2., 3., 2., 1., 2., 3.]);
+    }
+
+    #[test]
+    fn crossfade_ends_at_loop_point() {
+        let settings = SamplerSettings {
+            repeat: true,
+            loop_start: 2,
+            loop_end: Some(6),
+            crossfade: 2,
+            ..Default::default()
+        };
+        let sampler = Sampler::pitched(ramp(8), 0.5.into(), settings).module();
+        // The last output before the loop point is 1.5, which leads into the loop start at 2.
+        assert_eq!(
+            play(sampler, 16),
+            vec![0., 0.5, 1., 1.5, 2., 2.5, 3., 3.5, 3., 2.5, 2., 1.5, 2., 2.5, 3., 3.5]
+        );
+    }
+
+    #[test]
+    fn reverse_crossfade_ends_at_loop_point() {
+        let settings = SamplerSettings {
+            repeat: true,
+            loop_start: 2,
+            loop_end: Some(5),
+            crossfade: 4,
+            reverse: true,
+            ..Default::default()
+        };
+        let sampler = Sampler::pitched(ramp(8), 0.5.into(), settings).module();
+        // The crossfade is limited by the audio after the loop.
+        // The last output before the loop point is 5, which leads into the loop end at 4.5.
+        assert_eq!(
+            play(sampler, 16),
+            vec![7., 6.5, 6., 5.5, 5., 4.5, 4.75, 5., 5.25, 5.5, 5., 4.5, 4.75, 5., 5.25, 5.5]
+        );
+    }
+
+    #[test]
+    fn crossfade_longer_than_loop() {
+        let settings = SamplerSettings {
+            repeat: true,
+            loop_start: 4,
+            loop_end: Some(6),
+            crossfade: 10,
+            interpolation: Interpolation::Nearest,
+            ..Default::default()
+        };
+        let sampler = Sampler::pitched(ramp(8), 1.0.into(), settings).module();
+        // The crossfade is limited to the length of the loop.
+        assert_eq!(play(sampler, 8), vec![0., 1., 2., 3., 3., 3., 3., 3.]);
+    }
+
+    #[test]
+    fn empty_audio_is_silent() {
+        let empty: Arc<[f32]> = Vec::new().into();
+        let sampler = Sampler::new(empty.clone(), true).module();
+        assert!(sampler.is_finished());
+        assert_eq!(play(sampler, 3), vec![0.; 3]);
+
+        let settings = SamplerSettings {
+            repeat: true,
+            loop_start: 2,
+            loop_end: Some(4),
+            crossfade: 2,
+            reverse: true,
+            ..Default::default()
+        };
+        let sampler = Sampler::pitched(empty, 1.0.into(), settings).module();
+        assert_eq!(play(sampler, 3), vec![0.; 3]);
+    }
+}
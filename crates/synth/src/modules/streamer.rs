@@ -0,0 +1,278 @@
+use crate::analysis::{tap_channel, TapReceiver, TapSender};
+use crate::modules::{Module, ModuleTemplate};
+use crate::utils::interpolation;
+use crate::AudioStream;
+use std::{path::PathBuf, thread, time::Duration};
+use synth_derive::module;
+
+/// How many seconds of the stream are decoded ahead of playback.
+const BUFFER_SECONDS: f32 = 0.5;
+
+/// How long the decoding thread waits before topping up a full buffer.
+const REFILL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Decodes a stream downmixed to mono on a background thread, so reading from it never waits for the file,
+/// and resamples it as it is played. The thread is only started once the stream is played.
+struct BackgroundStream {
+    path: PathBuf,
+    repeat: bool,
+    /// How many decoded samples are buffered ahead of playback.
+    capacity: usize,
+    /// The stream opened by `Streamer::new`, so the first stream to play doesn't have to open the file again.
+    stream: Option<AudioStream>,
+    /// The decoded samples, once playing has started.
+    samples: Option<TapReceiver>,
+    /// How far between `prev_sample` and `next_sample` the current output is.
+    fraction: f32,
+    prev_sample: f32,
+    next_sample: f32,
+    started: bool,
+    finished: bool,
+}
+
+impl BackgroundStream {
+    fn new(stream: Option<AudioStream>, path: PathBuf, repeat: bool, capacity: usize) -> Self {
+        BackgroundStream {
+            path,
+            repeat,
+            capacity,
+            stream,
+            samples: None,
+            fraction: 0.,
+            prev_sample: 0.,
+            next_sample: 0.,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Starts decoding on a new thread, opening the file again if the stream has already been used.
+    fn start_decoding(&mut self) -> &TapReceiver {
+        let (sender, samples) = tap_channel(self.capacity);
+        let stream = self.stream.take();
+        let path = self.path.clone();
+        let repeat = self.repeat;
+        thread::spawn(move || {
+            let stream = match stream {
+                Some(stream) => stream,
+                // If the file can't be opened, the sender is dropped and the stream ends immediately.
+                None => match AudioStream::open(path) {
+                    Ok(stream) => stream,
+                    Err(_) => return,
+                },
+            };
+            Self::decode(stream, repeat, sender);
+        });
+        self.samples.insert(samples)
+    }
+
+    /// Keeps the buffer full until the stream ends or the reading side is dropped.
+    fn decode(mut stream: AudioStream, repeat: bool, sender: TapSender) {
+        let mut rewound = false;
+        while !sender.is_disconnected() {
+            if sender.is_full() {
+                thread::sleep(REFILL_INTERVAL);
+                continue;
+            }
+            match stream.next_mono() {
+                Ok(Some(sample)) => {
+                    rewound = false;
+                    sender.send(sample);
+                }
+                // Reaching the end right after rewinding means the stream is empty.
+                Ok(None) if repeat && !rewound => {
+                    if stream.rewind().is_err() {
+                        return;
+                    }
+                    rewound = true;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Starts decoding and blocks until the buffer is full or the stream has ended.
+    #[cfg(test)]
+    fn wait_until_buffered(&mut self) {
+        let samples = match self.samples {
+            Some(ref samples) => samples,
+            None => self.start_decoding(),
+        };
+        while samples.available() < samples.capacity() && !samples.is_closed() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn read(&mut self) -> f32 {
+        if self.finished {
+            return 0.;
+        }
+        let samples = match self.samples {
+            Some(ref samples) => samples,
+            None => self.start_decoding(),
+        };
+        // Closed is checked first, since samples sent before closing can still be waiting.
+        let closed = samples.is_closed();
+        match samples.receive() {
+            Some(sample) => sample,
+            None if closed => {
+                self.finished = true;
+                0.
+            }
+            // The decoder has fallen behind, so hold the last sample until it catches up.
+            None => self.next_sample,
+        }
+    }
+
+    /// Returns the next output sample, advancing `step` samples through the stream.
+    fn next(&mut self, step: f32) -> f32 {
+        if !self.started {
+            self.started = true;
+            self.prev_sample = self.read();
+            self.next_sample = self.read();
+        }
+        let result = interpolation::linear(self.prev_sample, self.next_sample, self.fraction);
+        self.fraction += step;
+        while self.fraction >= 1. {
+            self.fraction -= 1.;
+            self.prev_sample = self.next_sample;
+            self.next_sample = self.read();
+        }
+        result
+    }
+}
+
+impl Clone for BackgroundStream {
+    /// Plays the same file again from the beginning, decoding it on its own thread once it is played.
+    fn clone(&self) -> Self {
+        BackgroundStream::new(None, self.path.clone(), self.repeat, self.capacity)
+    }
+}
+
+/// Plays an `AudioStream` downmixed to mono, resampling it to the output sample rate on the fly.
+///
+/// The stream is decoded on a background thread, started when the module is first played.
+/// Clones play the file again from the beginning;
+/// if the file can no longer be opened, the clone is silent and finished.
+#[module]
+pub struct Streamer {
+    stream: BackgroundStream,
+    /// How many samples of the stream to advance per output sample.
+    step: f32,
+}
+
+impl Streamer {
+    /// Creates a new Streamer module.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The stream to play.
+    /// * `repeat` - Whether to start over when the end of the stream is reached.
+    /// * `sample_rate` - The used sample rate.
+    pub fn new(stream: AudioStream, repeat: bool, sample_rate: u32) -> ModuleTemplate<Streamer> {
+        let step = stream.sample_rate() as f32 / sample_rate as f32;
+        let capacity = ((stream.sample_rate() as f32 * BUFFER_SECONDS) as usize).max(1);
+        let path = stream.path().to_path_buf();
+        ModuleTemplate {
+            module: Streamer {
+                stream: BackgroundStream::new(Some(stream), path, repeat, capacity),
+                step,
+            },
+        }
+    }
+
+    /// Returns true if the end of the stream has been reached and the stream does not repeat.
+    pub fn is_finished(&self) -> bool {
+        self.stream.finished
+    }
+}
+
+impl Module for Streamer {
+    fn next(&mut self, _: u64) -> f32 {
+        self.stream.next(self.step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_stream(name: &str, samples: &[i16]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn play(streamer: &mut Streamer, num_samples: usize) -> Vec<f32> {
+        streamer.stream.wait_until_buffered();
+        (0..num_samples as u64).map(|i| streamer.next(i)).collect()
+    }
+
+    #[test]
+    fn plays_stream_and_finishes() {
+        let path = write_stream("streamer_finishes", &[8192, 16384, -8192]);
+        let stream = AudioStream::open(&path).unwrap();
+        let mut streamer = Streamer::new(stream, false, 8000).module();
+        let samples = play(&mut streamer, 5);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(samples, [0.25, 0.5, -0.25, 0., 0.]);
+        assert!(streamer.is_finished());
+    }
+
+    #[test]
+    fn repeating_stream_starts_over() {
+        let path = write_stream("streamer_repeats", &[8192, 16384]);
+        let stream = AudioStream::open(&path).unwrap();
+        let mut streamer = Streamer::new(stream, true, 8000).module();
+        let samples = play(&mut streamer, 5);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(samples, [0.25, 0.5, 0.25, 0.5, 0.25]);
+        assert!(!streamer.is_finished());
+    }
+
+    #[test]
+    fn decoding_starts_when_played() {
+        let path = write_stream("streamer_starts", &[8192, 16384]);
+        let stream = AudioStream::open(&path).unwrap();
+        let template = Streamer::new(stream, false, 8000);
+        let mut instance = template.create_instance();
+        assert!(template.module.stream.samples.is_none());
+        assert!(instance.stream.samples.is_none());
+
+        instance.next(0);
+        assert!(instance.stream.samples.is_some());
+        assert!(instance.clone().stream.samples.is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn clones_start_from_the_beginning() {
+        let path = write_stream("streamer_clones", &[8192, 16384, -8192]);
+        let stream = AudioStream::open(&path).unwrap();
+        let template = Streamer::new(stream, false, 8000);
+        let mut first = template.create_instance();
+        play(&mut first, 2);
+        let mut second = first.clone();
+        let samples = play(&mut second, 3);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(samples, [0.25, 0.5, -0.25]);
+
+        // The file is gone, so a new clone can't decode it, but it doesn't panic either.
+        let mut missing = first.clone();
+        assert_eq!(play(&mut missing, 2), [0., 0.]);
+        assert!(missing.is_finished());
+    }
+}
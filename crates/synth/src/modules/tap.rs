@@ -0,0 +1,41 @@
+use crate::analysis::{tap_channel, TapReceiver, TapSender};
+use crate::modules::{Module, ModuleTemplate};
+use synth_derive::module;
+
+/// Passes the source through unchanged while sending every sample to a `TapReceiver`,
+/// so the signal can be metered or analyzed on another thread.
+#[module]
+pub struct Tap<S: Module> {
+    source: S,
+    sender: TapSender,
+}
+
+impl<S: Module> Tap<S> {
+    /// Creates a new Tap module and the receiver its samples are sent to.
+    /// If the receiver falls behind by more than `capacity` samples, new samples are dropped.
+    ///
+    /// All instances of the module send to the same receiver, so their samples are interleaved.
+    pub fn new(
+        source: ModuleTemplate<S>,
+        capacity: usize,
+    ) -> (ModuleTemplate<Tap<S>>, TapReceiver) {
+        let (sender, receiver) = tap_channel(capacity);
+        (
+            ModuleTemplate {
+                module: Tap {
+                    source: source.module,
+                    sender,
+                },
+            },
+            receiver,
+        )
+    }
+}
+
+impl<S: Module> Module for Tap<S> {
+    fn next(&mut self, sample_num: u64) -> f32 {
+        let sample = self.source.next(sample_num);
+        self.sender.send(sample);
+        sample
+    }
+}
@@ -0,0 +1,26 @@
+/// Linearly interpolates between `a` and `b`.
+///
+/// # Arguments
+///
+/// * `a` - The value at `t = 0`.
+/// * `b` - The value at `t = 1`.
+/// * `t` - How far from `a` towards `b` to interpolate.
+pub fn linear(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Interpolates between `y1` and `y2` using a cubic Hermite spline through the four points.
+///
+/// # Arguments
+///
+/// * `y0` - The value before `y1`.
+/// * `y1` - The value at `t = 0`.
+/// * `y2` - The value at `t = 1`.
+/// * `y3` - The value after `y2`.
+/// * `t` - How far from `y1` towards `y2` to interpolate.
+pub fn cubic(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let c1 = 0.5 * (y2 - y0);
+    let c2 = y0 - 2.5 * y1 + 2. * y2 - 0.5 * y3;
+    let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
+    ((c3 * t + c2) * t + c1) * t + y1
+}
@@ -1,2 +1,3 @@
+pub mod interpolation;
 mod rotating_array;
 pub use rotating_array::RotatingArray;
@@ -1 +1,47 @@
-# [derive (Clone)] pub struct Foo { bar : f32 , } impl < RHS : crate :: modules :: Module > std :: ops :: Add < RHS > for Foo { type Output = crate :: modules :: Add < Foo , RHS > ; fn add (self , rhs : RHS) -> Self :: Output { crate :: modules :: Add :: new (self , rhs) } } impl < RHS : crate :: modules :: Module > std :: ops :: Sub < RHS > for Foo { type Output = crate :: modules :: Subtract < Foo , RHS > ; fn sub (self , rhs : RHS) -> Self :: Output { crate :: modules :: Subtract :: new (self , rhs) } } impl < RHS : crate :: modules :: Module > std :: ops :: Mul < RHS > for Foo { type Output = crate :: modules :: Multiply < Foo , RHS > ; fn mul (self , rhs : RHS) -> Self :: Output { crate :: modules :: Multiply :: new (self , rhs) } }
\ No newline at end of file
+#[derive(Clone)]
+pub struct Foo {
+    bar: f32,
+}
+impl<RHS: crate::modules::Module> std::ops::Add<RHS> for Foo {
+    type Output = crate::modules::ModuleTemplate<crate::modules::Add<Foo, RHS>>;
+    fn add(self, rhs: RHS) -> Self::Output {
+        crate::modules::Add::new(
+            crate::modules::ModuleTemplate { module: self },
+            crate::modules::ModuleTemplate { module: rhs },
+        )
+    }
+}
+impl<RHS: crate::modules::Module> std::ops::Sub<RHS> for Foo {
+    type Output = crate::modules::ModuleTemplate<crate::modules::Subtract<Foo, RHS>>;
+    fn sub(self, rhs: RHS) -> Self::Output {
+        crate::modules::Subtract::new(
+            crate::modules::ModuleTemplate { module: self },
+            crate::modules::ModuleTemplate { module: rhs },
+        )
+    }
+}
+impl<RHS: crate::modules::Module> std::ops::Mul<RHS> for Foo {
+    type Output = crate::modules::ModuleTemplate<crate::modules::Multiply<Foo, RHS>>;
+    fn mul(self, rhs: RHS) -> Self::Output {
+        crate::modules::Multiply::new(
+            crate::modules::ModuleTemplate { module: self },
+            crate::modules::ModuleTemplate { module: rhs },
+        )
+    }
+}
+impl std::ops::Neg for Foo {
+    type Output = crate::modules::ModuleTemplate<crate::modules::Multiply<Foo, f32>>;
+    fn neg(self) -> Self::Output {
+        crate::modules::Multiply::new(
+            crate::modules::ModuleTemplate { module: self },
+            crate::modules::ModuleTemplate { module: -1. },
+        )
+    }
+}
+impl crate::modules::ModuleInfo for Foo {
+    const METADATA: crate::modules::ModuleMetadata = crate::modules::ModuleMetadata {
+        name: "Foo",
+        parameters: &["bar"],
+        sub_modules: &[],
+    };
+}
@@ -1,8 +1,14 @@
 use proc_macro::TokenStream;
 use proc_macro2::{self, Ident, Span};
 use quote::quote;
-use syn::{self};
+use syn::{self, parse_quote};
 
+/// Turns a struct into a synth module.
+///
+/// Derives `Clone`, implements `Add`, `Sub`, `Mul` and `Neg` for the struct so modules can be
+/// combined directly, and implements `ModuleInfo` with the struct's name, fields and which of
+/// the fields are sub-modules. A field is considered a sub-module if its type, or the type in
+/// an `Option`, is a type parameter bounded by `Module`.
 #[proc_macro_attribute]
 pub fn module(_: TokenStream, item: TokenStream) -> TokenStream {
     let item = proc_macro2::TokenStream::from(item);
@@ -10,37 +16,223 @@ pub fn module(_: TokenStream, item: TokenStream) -> TokenStream {
     module_inner(item).into()
 }
 
-fn _ident(name: &str) -> Ident {
+fn ident(name: &str) -> Ident {
     Ident::new(name, Span::call_site())
 }
 
 fn module_inner(item: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     let ast: syn::DeriveInput = syn::parse2(item).unwrap();
+    let add = impl_operator(&ast, &ident("Add"), &ident("add"), &ident("Add"));
+    let sub = impl_operator(&ast, &ident("Sub"), &ident("sub"), &ident("Subtract"));
+    let mul = impl_operator(&ast, &ident("Mul"), &ident("mul"), &ident("Multiply"));
+    let neg = impl_neg(&ast);
+    let info = impl_module_info(&ast);
     let gen = quote! {
         #[derive(Clone)]
         #ast
+        #add
+        #sub
+        #mul
+        #neg
+        #info
     };
     gen
 }
 
-fn _impl_operator(
+fn impl_operator(
     ast: &syn::DeriveInput,
     operator: &Ident,
     operator_function: &Ident,
     operator_module: &Ident,
 ) -> proc_macro2::TokenStream {
     let name = &ast.ident;
-    let generics = &ast.generics;
-    let type_params = generics.type_params();
-    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let mut generics = ast.generics.clone();
+    generics
+        .params
+        .push(parse_quote!(RHS: crate::modules::Module));
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
     let gen = quote! {
-        impl <#(#type_params,)* RHS: crate::modules::Module> std::ops::#operator<RHS> for #name #ty_generics #where_clause {
+        impl #impl_generics std::ops::#operator<RHS> for #name #ty_generics #where_clause {
             type Output = crate::modules::ModuleTemplate<crate::modules::#operator_module<#name #ty_generics, RHS>>;
 
             fn #operator_function(self, rhs: RHS) -> Self::Output {
-                crate::modules::#operator_module::new(self, rhs)
+                crate::modules::#operator_module::new(
+                    crate::modules::ModuleTemplate { module: self },
+                    crate::modules::ModuleTemplate { module: rhs },
+                )
             }
         }
     };
     gen
 }
+
+fn impl_neg(ast: &syn::DeriveInput) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let gen = quote! {
+        impl #impl_generics std::ops::Neg for #name #ty_generics #where_clause {
+            type Output = crate::modules::ModuleTemplate<crate::modules::Multiply<#name #ty_generics, f32>>;
+
+            fn neg(self) -> Self::Output {
+                crate::modules::Multiply::new(
+                    crate::modules::ModuleTemplate { module: self },
+                    crate::modules::ModuleTemplate { module: -1. },
+                )
+            }
+        }
+    };
+    gen
+}
+
+/// Returns true if the type parameter has `Module` among its bounds.
+fn is_module_param(param: &syn::TypeParam, where_clause: Option<&syn::WhereClause>) -> bool {
+    let is_module_bound = |bound: &syn::TypeParamBound| match bound {
+        syn::TypeParamBound::Trait(trait_bound) => trait_bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Module"),
+        syn::TypeParamBound::Lifetime(_) => false,
+    };
+    param.bounds.iter().any(is_module_bound)
+        || where_clause.is_some_and(|where_clause| {
+            where_clause.predicates.iter().any(|predicate| match predicate {
+                syn::WherePredicate::Type(predicate_type) => {
+                    matches!(&predicate_type.bounded_ty, syn::Type::Path(type_path) if type_path.path.is_ident(&param.ident))
+                        && predicate_type.bounds.iter().any(is_module_bound)
+                }
+                _ => false,
+            })
+        })
+}
+
+/// If the type is a single identifier, or an `Option` of one, returns that identifier.
+fn inner_type_ident(ty: &syn::Type) -> Option<&Ident> {
+    if let syn::Type::Path(type_path) = ty {
+        if type_path.qself.is_some() || type_path.path.segments.len() != 1 {
+            return None;
+        }
+        let segment = &type_path.path.segments[0];
+        match &segment.arguments {
+            syn::PathArguments::None => Some(&segment.ident),
+            syn::PathArguments::AngleBracketed(arguments) if segment.ident == "Option" => {
+                match arguments.args.first() {
+                    Some(syn::GenericArgument::Type(inner)) if arguments.args.len() == 1 => {
+                        inner_type_ident(inner)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+fn impl_module_info(ast: &syn::DeriveInput) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let name_string = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let module_params: Vec<&Ident> = ast
+        .generics
+        .type_params()
+        .filter(|param| is_module_param(param, ast.generics.where_clause.as_ref()))
+        .map(|param| &param.ident)
+        .collect();
+
+    let fields: Vec<&syn::Field> = match &ast.data {
+        syn::Data::Struct(data_struct) => data_struct.fields.iter().collect(),
+        _ => panic!("Only structs can be modules."),
+    };
+    let parameters: Vec<String> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            field
+                .ident
+                .as_ref()
+                .map_or_else(|| index.to_string(), |ident| ident.to_string())
+        })
+        .collect();
+    let sub_modules: Vec<&String> = fields
+        .iter()
+        .zip(parameters.iter())
+        .filter(|(field, _)| {
+            inner_type_ident(&field.ty).is_some_and(|ident| module_params.contains(&ident))
+        })
+        .map(|(_, parameter)| parameter)
+        .collect();
+
+    let gen = quote! {
+        impl #impl_generics crate::modules::ModuleInfo for #name #ty_generics #where_clause {
+            const METADATA: crate::modules::ModuleMetadata = crate::modules::ModuleMetadata {
+                name: #name_string,
+                parameters: &[#(#parameters),*],
+                sub_modules: &[#(#sub_modules),*],
+            };
+        }
+    };
+    gen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Flattens a token stream into the text of each token, ignoring formatting and
+    /// whether punctuation is joined with the following token.
+    fn tokens(stream: proc_macro2::TokenStream) -> Vec<String> {
+        let mut result = Vec::new();
+        for tree in stream {
+            match tree {
+                proc_macro2::TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        proc_macro2::Delimiter::Parenthesis => ("(", ")"),
+                        proc_macro2::Delimiter::Brace => ("{", "}"),
+                        proc_macro2::Delimiter::Bracket => ("[", "]"),
+                        proc_macro2::Delimiter::None => ("", ""),
+                    };
+                    result.push(open.to_string());
+                    result.extend(tokens(group.stream()));
+                    result.push(close.to_string());
+                }
+                proc_macro2::TokenTree::Punct(punct) => result.push(punct.as_char().to_string()),
+                tree => result.push(tree.to_string()),
+            }
+        }
+        result
+    }
+
+    /// Expands the input and compares the result with the expected output.
+    fn assert_expansion(input: &str, expected_output: &str) {
+        let input = proc_macro2::TokenStream::from_str(input).unwrap();
+        let expected_output = proc_macro2::TokenStream::from_str(expected_output).unwrap();
+        assert_eq!(tokens(module_inner(input)), tokens(expected_output));
+    }
+
+    #[test]
+    fn one_pole_filter_module() {
+        assert_expansion(
+            "pub struct OnePoleFilter<S: Module, C: Module> {
+                source: S,
+                coefficient: C,
+                prev_sample: f32,
+            }",
+            include_str!("../assets/tests/one_pole_filter_module_output.rs"),
+        );
+    }
+
+    #[test]
+    fn no_module_generics() {
+        assert_expansion(
+            "pub struct Foo {
+                bar: f32,
+            }",
+            include_str!("../assets/tests/no_module_generics_output.rs"),
+        );
+    }
+}
@@ -5,10 +5,10 @@ pub use vertex::Locatedf32;
 pub use vertex::Vertex3D;
 
 pub mod png_reader;
-pub use png_reader::read_png;
+pub use png_reader::{read_png, write_png};
 
 mod file_utilities;
-pub use file_utilities::dir_entries;
+pub use file_utilities::{dir_entries, VisitDirError};
 
 mod colors;
 pub use colors::ColorFormat;
@@ -30,7 +30,15 @@ lazy_static! {
                 std::env::current_dir()
                     .expect("Both executable path and working directory are unavailable.")
             },
-            |exe_path| exe_path.join(".."),
+            |exe_path| {
+                // Joining ".." onto the executable itself only works on Windows, so take its directory.
+                let exe_dir = exe_path.parent().unwrap_or(&exe_path);
+                // Test executables are one directory further down, in target/<profile>/deps.
+                match exe_dir.parent() {
+                    Some(parent) if exe_dir.ends_with("deps") => parent.to_path_buf(),
+                    _ => exe_dir.to_path_buf(),
+                }
+            },
         );
         let root_path = path.join("../..");
         let result = if root_path.join("Cargo.toml").is_file() {
@@ -54,6 +54,41 @@ pub fn read_png(path: &Path) -> Result<PngData, PngLoadError> {
     })
 }
 
+#[derive(Debug)]
+pub enum PngSaveError {
+    InvalidFormat,
+    Encoding(png::EncodingError),
+    IO(std::io::Error),
+}
+
+/// Writes RGB or RGBA data to a png file. The first row of the data is the top of the image.
+pub fn write_png(path: &Path, png: &PngData) -> Result<(), PngSaveError> {
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    let color_type = match png.format {
+        ColorFormat::RGB => png::ColorType::RGB,
+        ColorFormat::RGBA => png::ColorType::RGBA,
+        ColorFormat::D => return Err(PngSaveError::InvalidFormat),
+    };
+    let file = match File::create(path) {
+        Ok(f) => f,
+        Err(error) => return Err(PngSaveError::IO(error)),
+    };
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), png.width, png.height);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = match encoder.write_header() {
+        Ok(w) => w,
+        Err(error) => return Err(PngSaveError::Encoding(error)),
+    };
+    match writer.write_image_data(&png.data) {
+        Ok(()) => Ok(()),
+        Err(error) => Err(PngSaveError::Encoding(error)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -1,5 +1,6 @@
 use bytepack::Packed;
 use bytepack_derive::Packed;
+use serde::{Deserialize, Serialize};
 
 use std::ops::Index;
 
@@ -16,7 +17,7 @@ pub trait Locatedf32 {
     fn z(&self) -> f32;
 }
 
-#[derive(Packed, Copy, Clone, Debug, Default)]
+#[derive(Packed, Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct V3C3 {
     pub x: f32,
     pub y: f32,
@@ -26,7 +27,7 @@ pub struct V3C3 {
     pub b: f32,
 }
 
-#[derive(Packed, Copy, Clone, Debug, Default)]
+#[derive(Packed, Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct V3C3UV {
     pub x: f32,
     pub y: f32,
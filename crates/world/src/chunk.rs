@@ -16,7 +16,7 @@ pub const CHUNK_SIZE: u32 = unwrap_ctx!(parse_u32(unwrap_or!(
 pub const CHUNK_SIZE_F: f32 = CHUNK_SIZE as f32;
 
 /// The number of elements in a chunk's voxel vector.
-const CHUNK_LENGTH: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+pub(crate) const CHUNK_LENGTH: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
 
 /// Represents a voxel location in a chunk.
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
@@ -41,6 +41,11 @@ impl ChunkLocation {
             index: (CHUNK_SIZE * CHUNK_SIZE * x + CHUNK_SIZE * y + z) as usize,
         }
     }
+
+    /// The index of the voxel in arrays holding one element per voxel of a chunk.
+    pub(crate) fn index(self) -> usize {
+        self.index
+    }
 }
 
 impl From<Vec3> for ChunkLocation {
@@ -160,17 +165,25 @@ impl Chunk {
     /// Checks if all voxels in the chunk are of a single type and have no voxel object.
     /// If this is the case, change the chunk to a single typed chunk.
     pub fn single_type(&mut self) {
+        if let Some(single_type) = self.uniform_type() {
+            *self = Chunk::SingleType(single_type);
+        }
+    }
+
+    /// Returns the type of every voxel in the chunk if it is stored with multiple types,
+    /// but they are all the same and have no voxel object, so it could be a single typed chunk.
+    pub fn uniform_type(&self) -> Option<VoxelType> {
         if let Chunk::MultiType(voxel_vec, voxel_map) = self {
-            if voxel_map.is_empty() {
-                let single_type = voxel_vec[0];
-                if voxel_vec
+            let single_type = voxel_vec[0];
+            if voxel_map.is_empty()
+                && voxel_vec
                     .iter()
                     .all(|voxel_type| *voxel_type == single_type)
-                {
-                    *self = Chunk::SingleType(single_type);
-                }
+            {
+                return Some(single_type);
             }
         }
+        None
     }
 
     pub fn ignore_voxel(&self, voxel: ChunkLocation) -> bool {
@@ -1,4 +1,5 @@
 pub mod chunk;
+pub mod light;
 mod location;
 pub mod raytrace;
 mod terrain;
@@ -6,8 +7,10 @@ mod voxel;
 
 pub use chunk::chunk_index_to_position;
 pub use chunk::Chunk;
+pub use light::Light;
 pub use location::Location;
 pub use terrain::Terrain;
+pub use voxel::Transparency;
 pub use voxel::Voxel;
 pub use voxel::VoxelType;
 
@@ -0,0 +1,617 @@
+use crate::chunk::{Chunk, ChunkLocation, CHUNK_LENGTH, CHUNK_SIZE};
+use crate::{raytrace, voxel, Location, Transparency, VoxelType};
+use glm::{IVec2, IVec3};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
+use std::sync::Arc;
+
+/// The brightest a voxel can be lit. Voxels open to the sky have this much sky light.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The voxel types that emit block light and how much they emit.
+const LIGHT_SOURCES: [(VoxelType, u8); 1] = [(VoxelType(4), 14)];
+
+/// Whether voxels of the given type block light. Voxels that can be seen through let it pass.
+pub fn is_opaque(voxel_type: VoxelType) -> bool {
+    !raytrace::ignore_voxel_type(voxel_type) && voxel_type.transparency() == Transparency::Opaque
+}
+
+/// The block light emitted by voxels of the given type.
+pub fn emitted_light(voxel_type: VoxelType) -> u8 {
+    LIGHT_SOURCES
+        .iter()
+        .find(|(source, _)| *source == voxel_type)
+        .map_or(0, |(_, light)| *light)
+}
+
+/// The light of a single voxel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Light {
+    /// Light coming from the sky. Voxels with no opaque voxels above them have `MAX_LIGHT`.
+    pub sky: u8,
+    /// Light coming from light emitting voxels.
+    pub block: u8,
+}
+
+impl Light {
+    /// The light of voxels open to the sky and far from any light source.
+    pub const SKY: Light = Light {
+        sky: MAX_LIGHT,
+        block: 0,
+    };
+
+    /// The brightest of the sky and block light.
+    pub fn level(self) -> u8 {
+        self.sky.max(self.block)
+    }
+}
+
+/// The two kinds of light, which spread independently of each other.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Sky,
+    Block,
+}
+
+/// The light of every voxel in a chunk.
+/// Each byte holds the sky light in the high four bits and the block light in the low four bits.
+#[derive(Clone)]
+struct ChunkLight(Vec<u8>);
+
+impl ChunkLight {
+    fn new() -> ChunkLight {
+        ChunkLight(vec![0; CHUNK_LENGTH])
+    }
+
+    fn get(&self, index: usize) -> Light {
+        Light {
+            sky: self.0[index] >> 4,
+            block: self.0[index] & 0xf,
+        }
+    }
+
+    fn level(&self, index: usize, channel: Channel) -> u8 {
+        match channel {
+            Channel::Sky => self.0[index] >> 4,
+            Channel::Block => self.0[index] & 0xf,
+        }
+    }
+
+    fn set_level(&mut self, index: usize, channel: Channel, level: u8) {
+        self.0[index] = match channel {
+            Channel::Sky => (self.0[index] & 0xf) | (level << 4),
+            Channel::Block => (self.0[index] & 0xf0) | level,
+        };
+    }
+}
+
+/// The position of the voxel at the location, in voxels from the origin.
+pub(crate) fn voxel_position(loc: Location) -> IVec3 {
+    loc.chunk * CHUNK_SIZE as i32 + loc.position.map(|x| x.floor() as i32)
+}
+
+/// Splits a position in voxels from the origin into the index of the chunk it is in
+/// and the index of the voxel within that chunk.
+fn split(voxel: IVec3) -> (IVec3, usize) {
+    let size = CHUNK_SIZE as i32;
+    let position = voxel.map(|x| x.rem_euclid(size) as u32);
+    (
+        voxel.map(|x| x.div_euclid(size)),
+        ChunkLocation::new(position.x, position.y, position.z).index(),
+    )
+}
+
+/// The six voxels sharing a face with the voxel.
+fn neighbours(voxel: IVec3) -> [IVec3; 6] {
+    [
+        voxel + IVec3::new(1, 0, 0),
+        voxel - IVec3::new(1, 0, 0),
+        voxel + IVec3::new(0, 1, 0),
+        voxel - IVec3::new(0, 1, 0),
+        voxel + IVec3::new(0, 0, 1),
+        voxel - IVec3::new(0, 0, 1),
+    ]
+}
+
+fn voxel_type(chunks: &HashMap<IVec3, Arc<Chunk>>, voxel: IVec3) -> VoxelType {
+    let (chunk, index) = split(voxel);
+    match chunks.get(&chunk) {
+        Some(chunk) => match &**chunk {
+            Chunk::SingleType(voxel_type) => *voxel_type,
+            Chunk::MultiType(voxel_types, _) => voxel_types[index],
+        },
+        None => voxel::DEFAULT_TYPE,
+    }
+}
+
+/// The light of every voxel in the terrain's chunks.
+///
+/// Sky light comes from every voxel with no opaque voxels above it and block light comes from
+/// light emitting voxels. Both spread to neighbouring non-opaque voxels, losing one level per voxel.
+/// Light only spreads through chunks that exist. Voxels outside all chunks are lit by the sky if
+/// nothing is above them and dark otherwise.
+///
+/// Modified voxels are only recorded when they're modified. `update` then spreads the light
+/// around all modified voxels at once, which is much faster than doing it for every voxel.
+#[derive(Clone, Default)]
+pub(crate) struct LightMap {
+    chunks: HashMap<IVec3, Arc<ChunkLight>>,
+    /// For each column of chunks, the height just above the highest opaque voxel in each of its
+    /// columns of voxels, indexed by `x * CHUNK_SIZE + z`. `i32::MIN` if a column has no opaque voxels.
+    heights: HashMap<IVec2, Arc<Vec<i32>>>,
+    /// The voxels modified since the light was last updated.
+    modified: Vec<IVec3>,
+}
+
+impl LightMap {
+    /// The light of the voxel at the position in voxels from the origin.
+    pub fn get(&self, voxel: IVec3) -> Light {
+        let (chunk, index) = split(voxel);
+        match self.chunks.get(&chunk) {
+            Some(light) => light.get(index),
+            None if voxel.y >= self.height(voxel.x, voxel.z) => Light::SKY,
+            None => Light::default(),
+        }
+    }
+
+    /// The height just above the highest opaque voxel in the column of voxels.
+    fn height(&self, x: i32, z: i32) -> i32 {
+        let size = CHUNK_SIZE as i32;
+        self.heights
+            .get(&IVec2::new(x.div_euclid(size), z.div_euclid(size)))
+            .map_or(i32::MIN, |heights| {
+                heights[(x.rem_euclid(size) * size + z.rem_euclid(size)) as usize]
+            })
+    }
+
+    /// Sets the height of the column of voxels, recording the previous height in `old_heights`
+    /// the first time the column changes.
+    fn set_height(&mut self, x: i32, z: i32, height: i32, old_heights: &mut HashMap<IVec2, i32>) {
+        old_heights
+            .entry(IVec2::new(x, z))
+            .or_insert_with(|| self.height(x, z));
+        let size = CHUNK_SIZE as i32;
+        let heights = self
+            .heights
+            .entry(IVec2::new(x.div_euclid(size), z.div_euclid(size)))
+            .or_insert_with(|| Arc::new(vec![i32::MIN; (size * size) as usize]));
+        Arc::make_mut(heights)[(x.rem_euclid(size) * size + z.rem_euclid(size)) as usize] = height;
+    }
+
+    /// Records that the voxel was modified, so the light around it is updated by the next `update`.
+    pub fn voxel_modified(&mut self, voxel: IVec3) {
+        self.modified.push(voxel);
+    }
+
+    /// Removes the light of chunks the predicate returns false for.
+    pub fn retain_chunks(&mut self, mut predicate: impl FnMut(&IVec3) -> bool) {
+        self.chunks.retain(|chunk, _| predicate(chunk));
+    }
+
+    fn level(&self, voxel: IVec3, channel: Channel) -> Option<u8> {
+        let (chunk, index) = split(voxel);
+        self.chunks
+            .get(&chunk)
+            .map(|light| light.level(index, channel))
+    }
+
+    /// Sets the light of the voxel and records its chunk as changed.
+    /// Does nothing if the voxel's chunk has no light.
+    fn set_level(
+        &mut self,
+        voxel: IVec3,
+        channel: Channel,
+        level: u8,
+        changed: &mut HashSet<IVec3>,
+    ) {
+        let (chunk, index) = split(voxel);
+        if let Some(light) = self.chunks.get_mut(&chunk) {
+            Arc::make_mut(light).set_level(index, channel, level);
+            changed.insert(chunk);
+        }
+    }
+
+    /// The light the voxel gives off by itself.
+    fn source_level(
+        &self,
+        chunks: &HashMap<IVec3, Arc<Chunk>>,
+        voxel: IVec3,
+        channel: Channel,
+    ) -> u8 {
+        match channel {
+            Channel::Sky if voxel.y >= self.height(voxel.x, voxel.z) => MAX_LIGHT,
+            Channel::Sky => 0,
+            Channel::Block => emitted_light(voxel_type(chunks, voxel)),
+        }
+    }
+
+    /// Updates the light around every voxel modified since the last update and lights chunks
+    /// that have been added since.
+    /// Returns the chunks whose light changed.
+    pub fn update(&mut self, chunks: &HashMap<IVec3, Arc<Chunk>>) -> HashSet<IVec3> {
+        let mut changed = HashSet::new();
+        let modified = mem::take(&mut self.modified);
+        let new_chunks: Vec<IVec3> = chunks
+            .keys()
+            .filter(|chunk| !self.chunks.contains_key(chunk))
+            .copied()
+            .collect();
+        if modified.is_empty() && new_chunks.is_empty() {
+            return changed;
+        }
+
+        // Find which columns of voxels are open to the sky further up or down than before.
+        let mut old_heights = HashMap::new();
+        for &chunk in &new_chunks {
+            self.chunks.insert(chunk, Arc::new(ChunkLight::new()));
+            if matches!(*chunks[&chunk], Chunk::SingleType(voxel_type) if !is_opaque(voxel_type)) {
+                continue;
+            }
+            for (voxel_type, position) in chunks[&chunk].iter() {
+                let voxel = chunk * CHUNK_SIZE as i32 + position.map(|x| x as i32);
+                if is_opaque(voxel_type) && voxel.y >= self.height(voxel.x, voxel.z) {
+                    self.set_height(voxel.x, voxel.z, voxel.y + 1, &mut old_heights);
+                }
+            }
+        }
+        // The chunks in each column of chunks, only found if a column needs to be searched.
+        let mut chunk_columns: Option<HashMap<IVec2, Vec<i32>>> = None;
+        for &voxel in &modified {
+            let height = self.height(voxel.x, voxel.z);
+            if is_opaque(voxel_type(chunks, voxel)) {
+                if voxel.y >= height {
+                    self.set_height(voxel.x, voxel.z, voxel.y + 1, &mut old_heights);
+                }
+            } else if voxel.y + 1 == height {
+                // The top voxel of the column was removed, so search down for the next one.
+                let columns = chunk_columns.get_or_insert_with(|| chunk_column_index(chunks));
+                let size = CHUNK_SIZE as i32;
+                let column = IVec2::new(voxel.x.div_euclid(size), voxel.z.div_euclid(size));
+                let bottom = columns
+                    .get(&column)
+                    .and_then(|chunk_ys| chunk_ys.iter().min())
+                    .map_or(voxel.y, |chunk_y| chunk_y * size);
+                let height = (bottom..voxel.y)
+                    .rev()
+                    .find(|&y| is_opaque(voxel_type(chunks, IVec3::new(voxel.x, y, voxel.z))))
+                    .map_or(i32::MIN, |y| y + 1);
+                self.set_height(voxel.x, voxel.z, height, &mut old_heights);
+            }
+        }
+
+        // Remove the light around modified voxels and from voxels no longer open to the sky.
+        // Voxels that are still lit from elsewhere are queued to spread their light back.
+        // New chunks have no light yet and are lit from scratch further down.
+        let mut removals: Vec<(IVec3, Channel)> = Vec::new();
+        let mut sky_sources = Vec::new();
+        for (&column, &old_height) in &old_heights {
+            let height = self.height(column.x, column.y);
+            if height == old_height {
+                continue;
+            }
+            let columns = chunk_columns.get_or_insert_with(|| chunk_column_index(chunks));
+            let size = CHUNK_SIZE as i32;
+            let chunk_column = IVec2::new(column.x.div_euclid(size), column.y.div_euclid(size));
+            let chunk_ys = columns.get(&chunk_column).into_iter().flatten();
+            for &chunk_y in chunk_ys {
+                if new_chunks.contains(&IVec3::new(chunk_column.x, chunk_y, chunk_column.y)) {
+                    continue;
+                }
+                let (low, high) = (height.min(old_height), height.max(old_height));
+                let start = low.max(chunk_y * size);
+                let end = high.min((chunk_y + 1) * size);
+                for y in start..end {
+                    let voxel = IVec3::new(column.x, y, column.y);
+                    if height > old_height {
+                        removals.push((voxel, Channel::Sky));
+                    } else {
+                        sky_sources.push(voxel);
+                    }
+                }
+            }
+        }
+        for &voxel in &modified {
+            removals.push((voxel, Channel::Sky));
+            removals.push((voxel, Channel::Block));
+        }
+        let mut spread = Vec::new();
+        let removed = self.remove_light(&removals, &mut spread, &mut changed);
+
+        // Light the sources of light that were removed or added, and let light spread into
+        // modified voxels and new chunks from their surroundings.
+        let sources = removed
+            .into_iter()
+            .chain(sky_sources.into_iter().map(|voxel| (voxel, Channel::Sky)))
+            .chain(
+                modified
+                    .iter()
+                    .flat_map(|&voxel| [(voxel, Channel::Sky), (voxel, Channel::Block)]),
+            );
+        for (voxel, channel) in sources {
+            let level = self.source_level(chunks, voxel, channel);
+            if level > 0 {
+                self.set_level(voxel, channel, level, &mut changed);
+                spread.push((voxel, channel));
+            }
+        }
+        for &voxel in &modified {
+            for neighbour in neighbours(voxel) {
+                spread.push((neighbour, Channel::Sky));
+                spread.push((neighbour, Channel::Block));
+            }
+        }
+        for &chunk in &new_chunks {
+            self.light_new_chunk(chunks, chunk, &mut spread, &mut changed);
+        }
+        self.spread_light(chunks, spread, &mut changed);
+        changed
+    }
+
+    /// Removes the light that spread from the given voxels, including their own light.
+    /// Voxels next to the removed light that are brighter than it was are added to `spread`,
+    /// since their light has to spread into the removed area again.
+    /// Returns every voxel whose light was removed.
+    fn remove_light(
+        &mut self,
+        removals: &[(IVec3, Channel)],
+        spread: &mut Vec<(IVec3, Channel)>,
+        changed: &mut HashSet<IVec3>,
+    ) -> Vec<(IVec3, Channel)> {
+        let mut removed = Vec::new();
+        let mut queue = VecDeque::new();
+        for &(voxel, channel) in removals {
+            if let Some(level @ 1..) = self.level(voxel, channel) {
+                self.set_level(voxel, channel, 0, changed);
+                removed.push((voxel, channel));
+                queue.push_back((voxel, channel, level));
+            }
+        }
+        while let Some((voxel, channel, level)) = queue.pop_front() {
+            for neighbour in neighbours(voxel) {
+                match self.level(neighbour, channel) {
+                    Some(0) | None => {}
+                    Some(neighbour_level) if neighbour_level < level => {
+                        self.set_level(neighbour, channel, 0, changed);
+                        removed.push((neighbour, channel));
+                        queue.push_back((neighbour, channel, neighbour_level));
+                    }
+                    Some(_) => spread.push((neighbour, channel)),
+                }
+            }
+        }
+        removed
+    }
+
+    /// Lights the sources of light in a chunk that was just added and queues the light
+    /// of the neighbouring chunks to spread into it.
+    fn light_new_chunk(
+        &mut self,
+        chunks: &HashMap<IVec3, Arc<Chunk>>,
+        chunk: IVec3,
+        spread: &mut Vec<(IVec3, Channel)>,
+        changed: &mut HashSet<IVec3>,
+    ) {
+        let size = CHUNK_SIZE as i32;
+        let corner = chunk * size;
+        for x in corner.x..corner.x + size {
+            for z in corner.z..corner.z + size {
+                let height = self.height(x, z);
+                let side_height = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                    .iter()
+                    .map(|(dx, dz)| self.height(x + dx, z + dz))
+                    .max()
+                    .unwrap();
+                for y in height.max(corner.y)..corner.y + size {
+                    let voxel = IVec3::new(x, y, z);
+                    self.set_level(voxel, Channel::Sky, MAX_LIGHT, changed);
+                    // Only sky light at the edge of the sky lit area can spread anywhere.
+                    if y == height || y < side_height {
+                        spread.push((voxel, Channel::Sky));
+                    }
+                }
+            }
+        }
+        if !matches!(*chunks[&chunk], Chunk::SingleType(voxel_type) if emitted_light(voxel_type) == 0)
+        {
+            for (voxel_type, position) in chunks[&chunk].iter() {
+                let level = emitted_light(voxel_type);
+                if level > 0 {
+                    let voxel = corner + position.map(|x| x as i32);
+                    self.set_level(voxel, Channel::Block, level, changed);
+                    spread.push((voxel, Channel::Block));
+                }
+            }
+        }
+        // The voxels bordering the chunk in each neighbouring chunk.
+        for axis in 0..3 {
+            for border in [-1, size] {
+                let mut neighbour = chunk;
+                neighbour[axis] += border.signum();
+                if !self.chunks.contains_key(&neighbour) {
+                    continue;
+                }
+                let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+                for u in 0..size {
+                    for v in 0..size {
+                        let mut voxel = corner;
+                        voxel[axis] += border;
+                        voxel[u_axis] += u;
+                        voxel[v_axis] += v;
+                        spread.push((voxel, Channel::Sky));
+                        spread.push((voxel, Channel::Block));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Spreads the light of the given voxels to their surroundings, until every voxel is at most
+    /// one level darker than its brightest neighbour.
+    fn spread_light(
+        &mut self,
+        chunks: &HashMap<IVec3, Arc<Chunk>>,
+        spread: Vec<(IVec3, Channel)>,
+        changed: &mut HashSet<IVec3>,
+    ) {
+        let mut queue = VecDeque::from(spread);
+        while let Some((voxel, channel)) = queue.pop_front() {
+            let level = match self.level(voxel, channel) {
+                Some(level) if level > 1 => level,
+                _ => continue,
+            };
+            for neighbour in neighbours(voxel) {
+                match self.level(neighbour, channel) {
+                    Some(neighbour_level)
+                        if neighbour_level + 1 < level
+                            && !is_opaque(voxel_type(chunks, neighbour)) =>
+                    {
+                        self.set_level(neighbour, channel, level - 1, changed);
+                        queue.push_back((neighbour, channel));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// The y index of every chunk in each column of chunks.
+fn chunk_column_index(chunks: &HashMap<IVec3, Arc<Chunk>>) -> HashMap<IVec2, Vec<i32>> {
+    let mut columns: HashMap<IVec2, Vec<i32>> = HashMap::new();
+    for chunk in chunks.keys() {
+        columns
+            .entry(IVec2::new(chunk.x, chunk.z))
+            .or_default()
+            .push(chunk.y);
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Terrain;
+
+    fn location(x: i32, y: i32, z: i32) -> Location {
+        Location::from_coords(x as f32, y as f32, z as f32)
+    }
+
+    /// A floor of stone at height 0 with a hole in it, covering a single column of chunks.
+    fn floor_with_hole() -> Terrain {
+        let mut terrain = Terrain::new();
+        let size = CHUNK_SIZE as i32;
+        for x in 0..size {
+            for z in 0..size {
+                terrain.set_voxel_type(location(x, 0, z), VoxelType(1));
+                terrain.set_voxel_type(location(x, -size, z), VoxelType(0));
+            }
+        }
+        terrain.set_voxel_type(location(5, 0, 5), VoxelType(0));
+        terrain.update_light();
+        terrain
+    }
+
+    #[test]
+    fn sky_light_falls_through_holes() {
+        let mut terrain = floor_with_hole();
+        assert_eq!(terrain.light(location(3, 4, 3)), Light::SKY);
+        assert_eq!(terrain.light(location(3, 0, 3)).sky, 0);
+        // Straight below the hole is open to the sky, and the light fades away from it.
+        assert_eq!(terrain.light(location(5, -6, 5)).sky, MAX_LIGHT);
+        assert_eq!(terrain.light(location(7, -6, 5)).sky, MAX_LIGHT - 2);
+        assert_eq!(terrain.light(location(7, -6, 8)).sky, MAX_LIGHT - 5);
+        // Outside all chunks, only voxels with nothing above them are lit.
+        assert_eq!(terrain.light(location(5, -40, 5)), Light::SKY);
+        assert_eq!(terrain.light(location(3, -40, 3)), Light::default());
+
+        let generation = terrain.generation();
+        terrain.set_voxel_type(location(5, 0, 5), VoxelType(1));
+        terrain.update_light();
+        assert_eq!(terrain.light(location(5, -6, 5)).sky, 0);
+        assert_eq!(terrain.light(location(7, -6, 8)).sky, 0);
+        assert_eq!(terrain.light(location(5, -40, 5)), Light::default());
+        assert!(terrain.chunk_generation(IVec3::new(0, -1, 0)) > generation);
+
+        // Glass lets the light through again.
+        terrain.set_voxel_type(location(5, 0, 5), VoxelType(7));
+        terrain.update_light();
+        assert_eq!(terrain.light(location(5, -6, 5)).sky, MAX_LIGHT);
+    }
+
+    #[test]
+    fn light_changes_when_updated() {
+        let mut terrain = floor_with_hole();
+        terrain.set_voxel_type(location(5, 0, 5), VoxelType(1));
+        terrain.set_voxel_type(location(2, -8, 2), VoxelType(4));
+        // Setting voxels leaves the light as it was until it is updated.
+        assert_eq!(terrain.light(location(5, -6, 5)).sky, MAX_LIGHT);
+        assert_eq!(terrain.light(location(2, -8, 5)).block, 0);
+
+        terrain.update_light();
+        assert_eq!(terrain.light(location(5, -6, 5)).sky, 0);
+        assert_eq!(terrain.light(location(2, -8, 5)).block, 11);
+
+        // Nothing changed since, so updating again doesn't start a new generation.
+        let generation = terrain.generation();
+        terrain.update_light();
+        assert_eq!(terrain.generation(), generation);
+    }
+
+    #[test]
+    fn block_light_spreads_and_is_removed() {
+        let mut terrain = floor_with_hole();
+        terrain.set_voxel_type(location(2, -8, 2), VoxelType(4));
+        terrain.update_light();
+        assert_eq!(terrain.light(location(2, -8, 2)).block, 14);
+        assert_eq!(terrain.light(location(2, -8, 5)).block, 11);
+        assert_eq!(terrain.light(location(2, -12, 5)).block, 7);
+        // The floor is opaque, so light spreads around it instead of through it.
+        assert_eq!(terrain.light(location(2, 0, 2)).block, 0);
+
+        terrain.set_voxel_type(location(2, -8, 2), VoxelType(0));
+        terrain.update_light();
+        assert_eq!(terrain.light(location(2, -8, 5)).block, 0);
+        assert_eq!(terrain.light(location(2, -12, 5)).block, 0);
+    }
+
+    #[test]
+    fn incremental_update_matches_full_update() {
+        let changes = [
+            (location(9, -3, 9), VoxelType(4)),
+            (location(8, -2, 5), VoxelType(2)),
+            (location(5, 0, 6), VoxelType(0)),
+            (location(5, 0, 5), VoxelType(1)),
+        ];
+        let mut terrain = floor_with_hole();
+        for (location, voxel_type) in changes {
+            terrain.set_voxel_type(location, voxel_type);
+        }
+        terrain.update_light();
+
+        // Lighting the same voxels from scratch must give the same light.
+        let mut relit = Terrain::new();
+        let size = CHUNK_SIZE as i32;
+        for x in 0..size {
+            for y in -size..size {
+                for z in 0..size {
+                    relit.set_voxel_type(location(x, y, z), terrain.voxel_type(location(x, y, z)));
+                }
+            }
+        }
+        relit.update_light();
+        for x in 0..size {
+            for y in -size..size {
+                for z in 0..size {
+                    assert_eq!(
+                        terrain.light(location(x, y, z)),
+                        relit.light(location(x, y, z)),
+                        "({}, {}, {})",
+                        x,
+                        y,
+                        z
+                    );
+                }
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::mem::swap;
 
 use crate::{
     chunk::{self, Chunk},
+    light::{self, Light, LightMap},
     raytrace,
     voxel::{self, Voxel, VoxelType},
     Location,
@@ -9,6 +10,8 @@ use crate::{
 use glm::{IVec3, Vec3};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use utils::mesh_iterator::MeshIterator;
 
 pub struct VoxelTypeBoxIterator<'a> {
@@ -26,23 +29,79 @@ impl Iterator for VoxelTypeBoxIterator<'_> {
     }
 }
 
+/// The next terrain generation. Shared by all terrains so a generation is never used twice,
+/// not even by a terrain replacing another one, like when a world is loaded.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
 /// Struct that stores all voxels in the world.
+///
+/// Chunks are shared between clones of the terrain and only copied when one of the clones
+/// modifies them, so cloning the terrain is cheap.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Terrain {
-    chunks: HashMap<IVec3, Chunk>,
+    chunks: HashMap<IVec3, Arc<Chunk>>,
+    /// The generation each chunk was last modified in.
+    /// Chunks that haven't been modified since the terrain was created or loaded are not present.
+    #[serde(skip)]
+    chunk_generations: HashMap<IVec3, u64>,
+    /// The generation the terrain was created or loaded in.
+    #[serde(skip, default = "next_generation")]
+    created_generation: u64,
+    /// Set to a new generation every time a voxel is modified.
+    #[serde(skip, default = "next_generation")]
+    generation: u64,
+    /// The light of every voxel. Not saved, since it can be found from the voxels.
+    #[serde(skip)]
+    light: LightMap,
 }
 
 impl Terrain {
     /// Creates a new Terrain with all voxels set to default type.
     pub fn new() -> Terrain {
+        let generation = next_generation();
         Terrain {
             chunks: HashMap::new(),
+            chunk_generations: HashMap::new(),
+            created_generation: generation,
+            generation,
+            light: LightMap::default(),
         }
     }
 
     /// Returns the chunk with the specified index or None if no such chunk exists.
     pub fn chunk(&self, chunk: IVec3) -> Option<&Chunk> {
-        self.chunks.get(&chunk)
+        self.chunks.get(&chunk).map(|chunk| &**chunk)
+    }
+
+    /// Returns the current generation of the terrain.
+    /// The generation is increased every time a voxel is modified, so if the generation
+    /// hasn't changed, neither has the terrain.
+    /// Generations are unique across all terrains, so a new or loaded terrain never has the
+    /// generation of another terrain.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the generation the chunk with the specified index was last modified in.
+    /// This is the generation the terrain was created or loaded in if the chunk hasn't been
+    /// modified since, including if the chunk doesn't exist.
+    pub fn chunk_generation(&self, chunk: IVec3) -> u64 {
+        self.chunk_generations
+            .get(&chunk)
+            .copied()
+            .unwrap_or(self.created_generation)
+    }
+
+    /// Returns the indices of all chunks modified after the given generation.
+    pub fn chunks_modified_since(&self, generation: u64) -> impl Iterator<Item = IVec3> + '_ {
+        self.chunk_generations
+            .iter()
+            .filter(move |(_, &chunk_generation)| chunk_generation > generation)
+            .map(|(&chunk, _)| chunk)
     }
 
     /// Returns the type of voxel at the specified location.
@@ -88,13 +147,41 @@ impl Terrain {
 
     /// Sets the voxel type for the voxel at the given location.
     /// If the location is outside of all chunks, a new chunk is created.
+    ///
+    /// The light around the voxel isn't changed until `update_light` is called.
     pub fn set_voxel_type(&mut self, loc: Location, voxel_type: VoxelType) {
         if let Some(chunk) = self.chunks.get_mut(&loc.chunk) {
-            chunk.set_voxel_type_unchecked(loc.position.into(), voxel_type);
+            Arc::make_mut(chunk).set_voxel_type_unchecked(loc.position.into(), voxel_type);
         } else {
             let mut chunk = Chunk::new();
             chunk.set_voxel_type_unchecked(loc.position.into(), voxel_type);
-            self.chunks.insert(loc.chunk, chunk);
+            self.chunks.insert(loc.chunk, Arc::new(chunk));
+        }
+        self.generation = next_generation();
+        self.chunk_generations.insert(loc.chunk, self.generation);
+        self.light.voxel_modified(light::voxel_position(loc));
+    }
+
+    /// Returns the light of the voxel at the specified location.
+    /// Only includes changes to the terrain made before the last call to `update_light`.
+    pub fn light(&self, loc: Location) -> Light {
+        self.light.get(light::voxel_position(loc))
+    }
+
+    /// Spreads light around every voxel modified since the last update, and lights chunks
+    /// that haven't been lit yet, like the chunks of a terrain that was just loaded.
+    /// Chunks whose light changed count as modified in a new generation.
+    ///
+    /// Light isn't updated by `set_voxel_type` since updating it for many voxels at once is
+    /// much faster, so this should be called once all voxels modified together have been set.
+    pub fn update_light(&mut self) {
+        let changed = self.light.update(&self.chunks);
+        if changed.is_empty() {
+            return;
+        }
+        self.generation = next_generation();
+        for chunk in changed {
+            self.chunk_generations.insert(chunk, self.generation);
         }
     }
 
@@ -106,9 +193,14 @@ impl Terrain {
     /// Removes chunks that contain only the default voxel type and no voxel objects.
     pub fn clean(&mut self) {
         for chunk in self.chunks.values_mut() {
-            chunk.single_type();
+            // Chunks are replaced rather than changed, so chunks shared with clones are never copied.
+            if let Some(single_type) = chunk.uniform_type() {
+                *chunk = Arc::new(Chunk::SingleType(single_type));
+            }
         }
-        self.chunks.retain(|_, chunk| !matches!(chunk, Chunk::SingleType(voxel_type) if *voxel_type == voxel::DEFAULT_TYPE));
+        self.chunks.retain(|_, chunk| !matches!(**chunk, Chunk::SingleType(voxel_type) if voxel_type == voxel::DEFAULT_TYPE));
+        let chunks = &self.chunks;
+        self.light.retain_chunks(|chunk| chunks.contains_key(chunk));
     }
 
     /// Traces the given ray and returns both the voxel it hits and the location where it hits the voxel.
@@ -260,6 +352,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn chunk_generations() {
+        let mut terrain = Terrain::new();
+        terrain.set_voxel_type(Location::from_coords(0., 0., 0.), voxel::VoxelType(1));
+        let snapshot = terrain.clone();
+        let generation = terrain.generation();
+        terrain.set_voxel_type(Location::from_coords(25., 0., 0.), voxel::VoxelType(1));
+        terrain.set_voxel_type(Location::from_coords(1., 0., 0.), voxel::VoxelType(2));
+
+        let last_modified = terrain.chunk_generation(IVec3::new(0, 0, 0));
+        assert_eq!(last_modified, terrain.generation());
+        assert!((generation + 1..last_modified)
+            .contains(&terrain.chunk_generation(IVec3::new(1, 0, 0))));
+        assert!(terrain.chunk_generation(IVec3::new(2, 0, 0)) < generation);
+        let mut modified: Vec<IVec3> = terrain.chunks_modified_since(generation).collect();
+        modified.sort_by_key(|chunk| chunk.x);
+        assert_eq!(modified, vec![IVec3::new(0, 0, 0), IVec3::new(1, 0, 0)]);
+
+        // The clone shares chunks with the terrain, but must not see the later changes.
+        assert_eq!(
+            snapshot.voxel_type(Location::from_coords(1., 0., 0.)),
+            voxel::VoxelType(0)
+        );
+        assert_eq!(snapshot.num_chunks(), 1);
+        assert_eq!(snapshot.generation(), generation);
+    }
+
+    #[test]
+    fn replaced_terrains_never_reuse_generations() {
+        let chunk = IVec3::new(0, 0, 0);
+        let mut terrain = Terrain::new();
+        terrain.set_voxel_type(Location::from_coords(0., 0., 0.), voxel::VoxelType(1));
+        let modified = terrain.chunk_generation(chunk);
+
+        // Meshes made for the old terrain must not look up to date for the loaded one.
+        let loaded: Terrain = bincode::deserialize(&bincode::serialize(&terrain).unwrap()).unwrap();
+        assert!(loaded.chunk_generation(chunk) > modified);
+        assert!(loaded.generation() >= loaded.chunk_generation(chunk));
+
+        let new = Terrain::new();
+        assert!(new.chunk_generation(chunk) > loaded.generation());
+    }
+
     #[test]
     fn ray_trace_2d() {
         let mut terrain = Terrain::new();
@@ -326,4 +461,23 @@ mod tests {
         let loc = Location::from_coords(0.5, 0.5, 0.5);
         terrain.trace_ray(loc, dir).unwrap();
     }
+
+    #[test]
+    fn clean_leaves_shared_chunks_shared() {
+        let mut terrain = Terrain::new();
+        let kept = Location::from_coords(0., 0., 0.);
+        let emptied = Location::from_coords(chunk::CHUNK_SIZE_F, 0., 0.);
+        terrain.set_voxel_type(kept, voxel::VoxelType(1));
+        terrain.set_voxel_type(emptied, voxel::VoxelType(1));
+        terrain.set_voxel_type(emptied, voxel::DEFAULT_TYPE);
+        let snapshot = terrain.clone();
+
+        terrain.clean();
+        assert!(std::ptr::eq(
+            terrain.chunk(kept.chunk).unwrap(),
+            snapshot.chunk(kept.chunk).unwrap()
+        ));
+        assert!(terrain.chunk(emptied.chunk).is_none());
+        assert!(snapshot.chunk(emptied.chunk).is_some());
+    }
 }
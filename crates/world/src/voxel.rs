@@ -6,9 +6,46 @@ pub struct VoxelType(pub u16);
 
 pub const DEFAULT_TYPE: VoxelType = VoxelType(0);
 
+/// How much of what is behind a voxel can be seen through it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transparency {
+    /// Nothing can be seen through the voxel.
+    Opaque,
+    /// Parts of the voxel are fully see-through and the rest is opaque, like leaves.
+    Cutout,
+    /// The voxel tints what is behind it, like water or glass.
+    Translucent,
+}
+
+impl Transparency {
+    pub const ALL: [Transparency; 3] = [
+        Transparency::Opaque,
+        Transparency::Cutout,
+        Transparency::Translucent,
+    ];
+}
+
+/// The voxel types that can be seen through. Every other type is opaque.
+const TRANSPARENT_TYPES: [(VoxelType, Transparency); 3] = [
+    (VoxelType(5), Transparency::Cutout),
+    (VoxelType(6), Transparency::Translucent),
+    (VoxelType(7), Transparency::Translucent),
+];
+
+impl VoxelType {
+    /// How much can be seen through voxels of this type when they are drawn.
+    pub fn transparency(self) -> Transparency {
+        TRANSPARENT_TYPES
+            .iter()
+            .find(|(voxel_type, _)| *voxel_type == self)
+            .map_or(Transparency::Opaque, |(_, transparency)| *transparency)
+    }
+}
+
 /// Defines functionality and extra information for a voxel.
+/// Must be `Sync` since chunks are shared between the logic and packing threads.
 #[typetag::serde(tag = "type")]
-pub trait Voxel: VoxelClone + Send {}
+pub trait Voxel: VoxelClone + Send + Sync {}
 
 pub trait VoxelClone {
     fn clone_box(&self) -> Box<dyn Voxel>;